@@ -1,16 +1,398 @@
 use super::{Artifact, ArtifactCreator};
 use crate::Error;
+use serde::Serialize;
 use serde_json::Value as JsonValue;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+/// Number of hexadecimal digits of the hash of a trace kept in its id.
+const TRACE_ID_LEN: usize = 12;
+
+/// Key of the marker state ending a truncated trace.
+pub(crate) const TRUNCATED_KEY: &str = "#truncated";
+
 /// `modelator`'s artifact containing a test trace encoded as JSON.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
 pub struct JsonTrace {
     pub(crate) states: JsonValue,
 }
 
+impl JsonTrace {
+    /// Returns the states of the trace.
+    pub fn states(&self) -> &[JsonValue] {
+        match &self.states {
+            JsonValue::Array(states) => states,
+            _ => panic!(
+                "[modelator] JsonTrace {:?} should be a serde_json::Value::Array",
+                self
+            ),
+        }
+    }
+
+    /// Returns the number of states of the trace.
+    pub fn len(&self) -> usize {
+        self.states().len()
+    }
+
+    /// Returns true iff the trace has no state.
+    pub fn is_empty(&self) -> bool {
+        self.states().is_empty()
+    }
+
+    /// Returns the state with the given index, if any.
+    pub fn state(&self, index: usize) -> Option<&JsonValue> {
+        self.states().get(index)
+    }
+
+    /// Returns an id of the trace, made of the first digits of the hash of
+    /// its states: the same trace has the same id across runs, and in
+    /// failure messages (see [`crate::TestReport::trace_id`]).
+    pub fn id(&self) -> String {
+        let mut id = self.hash();
+        id.truncate(TRACE_ID_LEN);
+        id
+    }
+
+    /// Returns the hash of the states of the trace, serialized as Json with
+    /// the fields of each object sorted, so that equal traces have the same hash.
+    pub(crate) fn hash(&self) -> String {
+        use sha2::Digest;
+        let mut digest = sha2::Sha256::default();
+        digest.update(self.states.to_string());
+        crate::util::digest::encode(digest)
+    }
+
+    /// Returns the number of states omitted from the trace if it was
+    /// truncated (see
+    /// [`crate::model::checker::ModelCheckerRuntime::max_trace_states`]), in
+    /// which case its last state is a marker
+    /// `{"#truncated": true, "omitted": <n>}`.
+    pub fn omitted_states(&self) -> Option<usize> {
+        let marker = self.states().last()?;
+        if marker.get(TRUNCATED_KEY) != Some(&JsonValue::Bool(true)) {
+            return None;
+        }
+        marker
+            .get("omitted")
+            .and_then(JsonValue::as_u64)
+            .map(|omitted| omitted as usize)
+    }
+
+    /// Returns an iterator over the states of the trace.
+    pub fn iter(&self) -> std::slice::Iter<'_, JsonValue> {
+        self.states().iter()
+    }
+
+    /// Converts the trace to the [Informal Trace Format](https://apalache.informal.systems/docs/adr/015adr-trace.html).
+    ///
+    /// Every state must be a Json object assigning the same variables,
+    /// otherwise [`Error::TraceConversion`] is returned.
+    pub fn to_itf(&self) -> Result<JsonValue, Error> {
+        let error = |reason: String| Error::TraceConversion {
+            format: "ITF".to_owned(),
+            reason,
+        };
+        let mut vars: Option<Vec<&String>> = None;
+        let states = self
+            .iter()
+            .enumerate()
+            .map(|(index, state)| {
+                let state = state
+                    .as_object()
+                    .ok_or_else(|| error(format!("state {} is not a record: {}", index, state)))?;
+                let state_vars: Vec<_> = state.keys().collect();
+                match &vars {
+                    None => vars = Some(state_vars),
+                    Some(vars) if *vars != state_vars => {
+                        return Err(error(format!(
+                            "state {} assigns variables {:?} instead of {:?}",
+                            index, state_vars, vars
+                        )))
+                    }
+                    Some(_) => (),
+                }
+                let mut itf_state = serde_json::Map::new();
+                itf_state.insert("#meta".to_owned(), serde_json::json!({ "index": index }));
+                itf_state.extend(
+                    state
+                        .iter()
+                        .map(|(var, value)| (var.clone(), value.clone())),
+                );
+                Ok(JsonValue::Object(itf_state))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(serde_json::json!({
+            "#meta": {
+                "format": "ITF",
+                "description": "Created by modelator",
+            },
+            "vars": vars.unwrap_or_default(),
+            "states": states,
+        }))
+    }
+
+    /// Reads a trace in the [Informal Trace Format](https://apalache.informal.systems/docs/adr/015adr-trace.html),
+    /// e.g. as returned by [`JsonTrace::to_itf`] or written by Apalache.
+    ///
+    /// The `#meta` fields are dropped, and the ITF encodings of sets, tuples,
+    /// maps and big integers are converted into plain Json values.
+    pub fn from_itf(itf: &JsonValue) -> Result<Self, Error> {
+        let error = |reason: String| {
+            Error::from(<serde_json::Error as serde::de::Error>::custom(format!(
+                "invalid ITF trace: {}",
+                reason
+            )))
+        };
+        let states = itf
+            .get("states")
+            .and_then(JsonValue::as_array)
+            .ok_or_else(|| error("missing the array of states".to_owned()))?;
+        states
+            .iter()
+            .enumerate()
+            .map(|(index, state)| match state {
+                JsonValue::Object(state) => Ok(JsonValue::Object(
+                    state
+                        .iter()
+                        .filter(|(var, _)| *var != "#meta")
+                        .map(|(var, value)| Ok((var.clone(), from_itf_value(value)?)))
+                        .collect::<Result<_, String>>()
+                        .map_err(|reason| error(format!("state {}: {}", index, reason)))?,
+                )),
+                state => Err(error(format!("state {} is not a record: {}", index, state))),
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map(Self::from)
+    }
+
+    /// Reads a trace from a Json file, either an array of states (as written
+    /// by `modelator trace`) or an ITF trace (see [`JsonTrace::from_itf`]).
+    /// Returns [`Error::InvalidTraceFile`] if the file is neither.
+    pub fn read_json(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = crate::util::try_read_file_contents(path)?;
+        let invalid = |reason: String| Error::InvalidTraceFile {
+            path: path.to_path_buf(),
+            reason,
+        };
+        match serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))? {
+            JsonValue::Array(states) => Ok(states.into()),
+            itf @ JsonValue::Object(_) => Self::from_itf(&itf).map_err(|e| invalid(e.to_string())),
+            _ => Err(invalid(
+                "expected an array of states or an ITF trace".to_owned(),
+            )),
+        }
+    }
+
+    /// Converts the trace to a [Graphviz](https://graphviz.org/) graph in the
+    /// DOT language, with a node per state.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph trace {\n    node [shape=box];\n".to_owned();
+        for (index, state) in self.iter().enumerate() {
+            dot.push_str(&format!(
+                "    s{} [label=\"{}\"];\n",
+                index,
+                dot_label(state)
+            ));
+            if index > 0 {
+                dot.push_str(&format!("    s{} -> s{};\n", index - 1, index));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Compares the trace with another one, e.g. a new counterexample with a
+    /// saved baseline, reporting the first state that differs.
+    pub fn diff(&self, other: &Self) -> TraceDiff {
+        let lengths = (self.len(), other.len());
+        let state_index =
+            (0..lengths.0.max(lengths.1)).find(|&index| self.state(index) != other.state(index));
+        let mut deltas = vec![];
+        if let Some(index) = state_index {
+            match (self.state(index), other.state(index)) {
+                (Some(this), Some(other)) => collect_deltas("", this, other, &mut deltas),
+                (this, other) => deltas.push(StateDelta {
+                    path: "<state>".to_owned(),
+                    this: this.cloned(),
+                    other: other.cloned(),
+                }),
+            }
+        }
+        TraceDiff {
+            state_index,
+            deltas,
+            lengths,
+        }
+    }
+}
+
+/// Difference between two traces (see [`JsonTrace::diff`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceDiff {
+    /// Index of the first state that differs, if any.
+    pub state_index: Option<usize>,
+    /// Differences in the first state that differs.
+    pub deltas: Vec<StateDelta>,
+    /// Number of states of both traces.
+    pub lengths: (usize, usize),
+}
+
+impl TraceDiff {
+    /// Returns true iff both traces are identical.
+    pub const fn is_empty(&self) -> bool {
+        self.state_index.is_none()
+    }
+}
+
+/// Difference of a variable (or a nested field of it) in a state of two traces.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StateDelta {
+    /// Path of the variable, e.g. `x.y` for field `y` of variable `x`.
+    pub path: String,
+    /// Value in the compared trace, if defined.
+    pub this: Option<JsonValue>,
+    /// Value in the other trace, if defined.
+    pub other: Option<JsonValue>,
+}
+
+impl std::fmt::Display for TraceDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let index = match self.state_index {
+            None => return write!(f, "traces are identical"),
+            Some(index) => index,
+        };
+        write!(f, "traces differ from state {}", index)?;
+        if self.lengths.0 != self.lengths.1 {
+            write!(f, " (lengths {} and {})", self.lengths.0, self.lengths.1)?;
+        }
+        let value = |value: &Option<JsonValue>| match value {
+            Some(value) => value.to_string(),
+            None => "nothing".to_owned(),
+        };
+        for delta in &self.deltas {
+            write!(
+                f,
+                "\n  {}: {} -> {}",
+                delta.path,
+                value(&delta.this),
+                value(&delta.other)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts an ITF value into a plain Json value.
+fn from_itf_value(value: &JsonValue) -> Result<JsonValue, String> {
+    let values = |values: &JsonValue| match values {
+        JsonValue::Array(values) => values.iter().map(from_itf_value).collect(),
+        values => Err(format!("expected an array, got {}", values)),
+    };
+    let fields = match value {
+        JsonValue::Array(_) => return values(value).map(JsonValue::Array),
+        JsonValue::Object(fields) => fields,
+        value => return Ok(value.clone()),
+    };
+    match fields.iter().next() {
+        Some((key, encoded)) if fields.len() == 1 && (key == "#set" || key == "#tup") => {
+            values(encoded).map(JsonValue::Array)
+        }
+        Some((key, encoded)) if fields.len() == 1 && key == "#bigint" => encoded
+            .as_str()
+            .and_then(|n| serde_json::from_str(n).ok())
+            .map(JsonValue::Number)
+            .ok_or_else(|| format!("invalid big integer {}", encoded)),
+        Some((key, encoded)) if fields.len() == 1 && key == "#map" => match encoded {
+            JsonValue::Array(pairs) => pairs
+                .iter()
+                .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                    Some([key, value]) => {
+                        let key = match from_itf_value(key)? {
+                            JsonValue::String(key) => key,
+                            key => key.to_string(),
+                        };
+                        Ok((key, from_itf_value(value)?))
+                    }
+                    _ => Err(format!("invalid map entry {}", pair)),
+                })
+                .collect::<Result<_, _>>()
+                .map(JsonValue::Object),
+            encoded => Err(format!("expected an array of pairs, got {}", encoded)),
+        },
+        _ => fields
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), from_itf_value(value)?)))
+            .collect::<Result<_, _>>()
+            .map(JsonValue::Object),
+    }
+}
+
+fn collect_deltas(path: &str, this: &JsonValue, other: &JsonValue, deltas: &mut Vec<StateDelta>) {
+    let field = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (this, other) {
+        (JsonValue::Object(this), JsonValue::Object(other)) => {
+            for (key, value) in this {
+                match other.get(key) {
+                    Some(other) => collect_deltas(&field(key), value, other, deltas),
+                    None => deltas.push(StateDelta {
+                        path: field(key),
+                        this: Some(value.clone()),
+                        other: None,
+                    }),
+                }
+            }
+            deltas.extend(
+                other
+                    .iter()
+                    .filter(|(key, _)| !this.contains_key(*key))
+                    .map(|(key, value)| StateDelta {
+                        path: field(key),
+                        this: None,
+                        other: Some(value.clone()),
+                    }),
+            );
+        }
+        (this, other) if this != other => deltas.push(StateDelta {
+            path: if path.is_empty() { "<state>" } else { path }.to_owned(),
+            this: Some(this.clone()),
+            other: Some(other.clone()),
+        }),
+        _ => (),
+    }
+}
+
+impl<'a> IntoIterator for &'a JsonTrace {
+    type Item = &'a JsonValue;
+    type IntoIter = std::slice::Iter<'a, JsonValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Returns the label of the node of a state in a DOT graph, with a
+/// left-justified line per variable.
+pub(crate) fn dot_label(state: &JsonValue) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    match state {
+        JsonValue::Object(state) => state
+            .iter()
+            .map(|(var, value)| format!("{} = {}\\l", escape(var), escape(&value.to_string())))
+            .collect(),
+        state => escape(&state.to_string()),
+    }
+}
+
 impl IntoIterator for JsonTrace {
     type Item = JsonValue;
     type IntoIter = std::vec::IntoIter<Self::Item>;
@@ -34,9 +416,23 @@ impl From<Vec<JsonValue>> for JsonTrace {
     }
 }
 
+/// Prints each state of the trace as pretty JSON, preceded by a
+/// `-- State <index> --` separator.
 impl std::fmt::Display for JsonTrace {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:#}", self.states)
+        match &self.states {
+            JsonValue::Array(states) => {
+                for (index, state) in states.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    writeln!(f, "-- State {} --", index)?;
+                    write!(f, "{:#}", state)?;
+                }
+                Ok(())
+            }
+            states => write!(f, "{:#}", states),
+        }
     }
 }
 
@@ -46,3 +442,182 @@ impl Artifact for JsonTrace {
             .expect("should not fail as it [serde_json::Value] is serializable")
     }
 }
+
+/// Removes the traces whose states are identical to the states of an earlier
+/// trace, preserving the order of the remaining ones.
+pub(crate) fn dedup(traces: Vec<JsonTrace>) -> Vec<JsonTrace> {
+    let mut seen = HashSet::new();
+    traces
+        .into_iter()
+        .filter(|trace| seen.insert(trace.hash()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_id() {
+        let trace_a: JsonTrace = vec![json!({ "x": 0 }), json!({ "x": 1 })].into();
+        let trace_b: JsonTrace = vec![json!({ "x": 0 }), json!({ "x": 2 })].into();
+        assert_eq!(trace_a.id().len(), 12);
+        assert_eq!(trace_a.id(), trace_a.clone().id());
+        assert_ne!(trace_a.id(), trace_b.id());
+    }
+
+    #[test]
+    fn test_dedup() {
+        let trace_a: JsonTrace = vec![json!({ "x": 0 }), json!({ "x": 1 })].into();
+        let trace_b: JsonTrace = vec![json!({ "x": 0 }), json!({ "x": 2 })].into();
+
+        let traces = vec![
+            trace_a.clone(),
+            trace_b.clone(),
+            trace_a.clone(),
+            trace_b.clone(),
+        ];
+        assert_eq!(dedup(traces), vec![trace_a, trace_b]);
+    }
+
+    #[test]
+    fn test_from_itf() {
+        let trace: JsonTrace = vec![json!({ "x": 0, "y": [1] }), json!({ "x": 1, "y": [] })].into();
+        assert_eq!(
+            JsonTrace::from_itf(&trace.to_itf().unwrap()).unwrap(),
+            trace
+        );
+
+        let itf = json!({
+            "vars": ["s", "m", "n"],
+            "states": [{
+                "#meta": { "index": 0 },
+                "s": { "#set": [1, { "#tup": ["a", 2] }] },
+                "m": { "#map": [[1, "x"], ["k", { "#bigint": "12345678901234567890" }]] },
+                "n": { "r": { "#set": [] } },
+            }],
+        });
+        assert_eq!(
+            JsonTrace::from_itf(&itf).unwrap(),
+            vec![json!({
+                "s": [1, ["a", 2]],
+                "m": { "1": "x", "k": 12345678901234567890u64 },
+                "n": { "r": [] },
+            })]
+            .into()
+        );
+        assert!(JsonTrace::from_itf(&json!([{ "x": 0 }])).is_err());
+    }
+
+    #[test]
+    fn test_accessors() {
+        let trace: JsonTrace = vec![json!({ "x": 0 }), json!({ "x": 1 })].into();
+        assert_eq!(trace.len(), 2);
+        assert!(!trace.is_empty());
+        assert_eq!(trace.state(1), Some(&json!({ "x": 1 })));
+        assert_eq!(trace.state(2), None);
+        assert_eq!(
+            (&trace)
+                .into_iter()
+                .map(|state| &state["x"])
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(trace.iter().count(), trace.clone().into_iter().count());
+
+        assert!(JsonTrace::from(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_to_itf() {
+        let trace: JsonTrace =
+            vec![json!({ "x": 0, "y": "a" }), json!({ "x": 1, "y": "b" })].into();
+        assert_eq!(
+            trace.to_itf().unwrap(),
+            json!({
+                "#meta": { "format": "ITF", "description": "Created by modelator" },
+                "vars": ["x", "y"],
+                "states": [
+                    { "#meta": { "index": 0 }, "x": 0, "y": "a" },
+                    { "#meta": { "index": 1 }, "x": 1, "y": "b" },
+                ],
+            })
+        );
+
+        let trace: JsonTrace = vec![json!({ "x": 0 }), json!({ "y": 1 })].into();
+        assert!(matches!(
+            trace.to_itf(),
+            Err(Error::TraceConversion { reason, .. }) if reason.starts_with("state 1 assigns")
+        ));
+        let trace: JsonTrace = vec![json!(0)].into();
+        assert!(matches!(
+            trace.to_itf(),
+            Err(Error::TraceConversion { reason, .. }) if reason == "state 0 is not a record: 0"
+        ));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let trace: JsonTrace = vec![json!({ "x": 0 }), json!({ "x": "a" })].into();
+        assert_eq!(
+            trace.to_dot(),
+            r#"digraph trace {
+    node [shape=box];
+    s0 [label="x = 0\l"];
+    s1 [label="x = \"a\"\l"];
+    s0 -> s1;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let baseline: JsonTrace = vec![
+            json!({ "x": 0, "y": { "a": 0, "b": 0 } }),
+            json!({ "x": 1, "y": { "a": 0, "b": 0 } }),
+        ]
+        .into();
+        assert!(baseline.diff(&baseline).is_empty());
+        assert_eq!(baseline.diff(&baseline).to_string(), "traces are identical");
+
+        let trace: JsonTrace = vec![
+            json!({ "x": 0, "y": { "a": 0, "b": 0 } }),
+            json!({ "x": 2, "y": { "a": 0, "c": 0 } }),
+            json!({ "x": 3, "y": { "a": 0, "c": 0 } }),
+        ]
+        .into();
+        let diff = baseline.diff(&trace);
+        assert_eq!(diff.state_index, Some(1));
+        assert_eq!(diff.lengths, (2, 3));
+        assert_eq!(
+            diff.to_string(),
+            "traces differ from state 1 (lengths 2 and 3)\n  \
+             x: 1 -> 2\n  \
+             y.b: 0 -> nothing\n  \
+             y.c: nothing -> 0"
+        );
+
+        let prefix: JsonTrace = vec![json!({ "x": 0, "y": { "a": 0, "b": 0 } })].into();
+        let diff = baseline.diff(&prefix);
+        assert_eq!(
+            diff.deltas,
+            vec![StateDelta {
+                path: "<state>".to_owned(),
+                this: Some(json!({ "x": 1, "y": { "a": 0, "b": 0 } })),
+                other: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let trace: JsonTrace = vec![json!({ "x": 0 }), json!({ "x": 1 })].into();
+        assert_eq!(
+            trace.to_string(),
+            "-- State 0 --\n{\n  \"x\": 0\n}\n-- State 1 --\n{\n  \"x\": 1\n}"
+        );
+        assert_eq!(JsonTrace::from(vec![]).to_string(), "");
+    }
+}
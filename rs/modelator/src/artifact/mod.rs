@@ -1,5 +1,6 @@
 pub(crate) mod json_trace;
 pub(crate) mod model_checker_stdout;
+pub(crate) mod state_graph;
 pub(crate) mod tla_config_file;
 pub(crate) mod tla_file;
 pub(crate) mod tla_file_suite;
@@ -65,9 +66,10 @@ pub trait ArtifactSaver: Artifact {
 }
 
 // Re-exports.
-pub use json_trace::JsonTrace;
+pub use json_trace::{JsonTrace, StateDelta, TraceDiff};
 pub use model_checker_stdout::ModelCheckerStdout;
-pub use tla_config_file::TlaConfigFile;
+pub use state_graph::StateGraphArtifact;
+pub use tla_config_file::{TlaConfigFile, TlaConfigParts};
 pub use tla_file::TlaFile;
 pub use tla_file_suite::TlaFileSuite;
 pub use tla_trace::TlaTrace;
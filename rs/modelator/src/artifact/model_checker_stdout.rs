@@ -1,18 +1,43 @@
 use super::{Artifact, ArtifactCreator};
+use crate::model::checker::{CheckerStats, Coverage};
 use crate::Error;
+use std::path::{Path, PathBuf};
 
 /// Ultra-basic wrapper around stdout of model checker execution
 /// NOTE: This is a stand in and will be changed soon.
 pub struct ModelCheckerStdout {
     backing_str: String,
+    pub(crate) coverage: Vec<Coverage>,
+    pub(crate) log_path: Option<PathBuf>,
+    pub(crate) stats: CheckerStats,
 }
 
 impl ModelCheckerStdout {
     fn new(s: &str) -> Self {
         Self {
             backing_str: s.to_string(),
+            coverage: Vec::new(),
+            log_path: None,
+            stats: CheckerStats::default(),
         }
     }
+
+    /// Returns the action coverage reported by TLC, if enabled with
+    /// [`crate::model::checker::ModelCheckerRuntime::coverage`].
+    pub fn coverage(&self) -> &[Coverage] {
+        &self.coverage
+    }
+
+    /// Returns the statistics of the run reported by the model checker, e.g.
+    /// the number of states generated, even if the output was discarded.
+    pub fn stats(&self) -> &CheckerStats {
+        &self.stats
+    }
+
+    /// Returns the path of the file the output was logged to, if any.
+    pub fn log_path(&self) -> Option<&Path> {
+        self.log_path.as_deref()
+    }
 }
 
 impl std::fmt::Display for ModelCheckerStdout {
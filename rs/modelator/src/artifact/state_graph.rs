@@ -0,0 +1,136 @@
+use super::json_trace::dot_label;
+use super::Artifact;
+use serde_json::{json, Value as JsonValue};
+
+/// `modelator`'s artifact containing a graph of the states of a spec, as
+/// explored by [`crate::ModelatorRuntime::state_graph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateGraphArtifact {
+    pub(crate) states: Vec<StateGraphNode>,
+    pub(crate) truncated: bool,
+}
+
+/// A state of a [`StateGraphArtifact`] along with its successors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StateGraphNode {
+    pub(crate) state: JsonValue,
+    pub(crate) initial: bool,
+    pub(crate) successors: Vec<usize>,
+}
+
+impl StateGraphArtifact {
+    /// Returns the number of states in the graph.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns whether the graph has no states.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Returns whether the graph was truncated to the maximum number of
+    /// states, in which case some of the successors of its states are missing.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Converts the graph to a [Graphviz](https://graphviz.org/) graph in the
+    /// DOT language, with a node per state. Initial states are drawn in bold.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph states {\n    node [shape=box];\n".to_owned();
+        for (index, node) in self.states.iter().enumerate() {
+            let style = if node.initial { ", style=bold" } else { "" };
+            dot.push_str(&format!(
+                "    s{} [label=\"{}\"{}];\n",
+                index,
+                dot_label(&node.state),
+                style
+            ));
+        }
+        for (index, node) in self.states.iter().enumerate() {
+            for successor in &node.successors {
+                dot.push_str(&format!("    s{} -> s{};\n", index, successor));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Converts the graph to JSON, as an adjacency list: each state has an
+    /// `id`, its value, whether it's `initial`, and the ids of its
+    /// `successors`.
+    pub fn to_json(&self) -> JsonValue {
+        let states: Vec<_> = self
+            .states
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                json!({
+                    "id": index,
+                    "state": node.state,
+                    "initial": node.initial,
+                    "successors": node.successors,
+                })
+            })
+            .collect();
+        json!({
+            "states": states,
+            "truncated": self.truncated,
+        })
+    }
+}
+
+impl Artifact for StateGraphArtifact {
+    fn as_string(&self) -> String {
+        serde_json::to_string_pretty(&self.to_json())
+            .expect("should not fail as it [serde_json::Value] is serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> StateGraphArtifact {
+        StateGraphArtifact {
+            states: vec![
+                StateGraphNode {
+                    state: json!({ "a": 0 }),
+                    initial: true,
+                    successors: vec![1],
+                },
+                StateGraphNode {
+                    state: json!({ "a": 1 }),
+                    initial: false,
+                    successors: vec![0],
+                },
+            ],
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        assert_eq!(
+            graph().to_dot(),
+            "digraph states {\n    node [shape=box];\n    \
+             s0 [label=\"a = 0\\l\", style=bold];\n    s1 [label=\"a = 1\\l\"];\n    \
+             s0 -> s1;\n    s1 -> s0;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            graph().to_json(),
+            json!({
+                "states": [
+                    { "id": 0, "state": { "a": 0 }, "initial": true, "successors": [1] },
+                    { "id": 1, "state": { "a": 1 }, "initial": false, "successors": [0] },
+                ],
+                "truncated": false,
+            })
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use super::{Artifact, ArtifactCreator, ArtifactSaver};
 use crate::Error;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -12,6 +13,25 @@ pub struct TlaConfigFile {
 }
 
 impl TlaConfigFile {
+    /// Returns a builder of a TLA+ config file, e.g.
+    /// `TlaConfigFile::builder().constant("N", "3").init("Init").next("Next").build()`.
+    pub fn builder() -> TlaConfigParts {
+        TlaConfigParts::default()
+    }
+
+    /// Creates a TLA+ config file, without a path, from its parts.
+    pub fn from_parts(parts: &TlaConfigParts) -> Self {
+        Self {
+            path: PathBuf::new(),
+            content: parts.to_string(),
+        }
+    }
+
+    /// Parses the TLA+ config file into its parts.
+    pub fn parts(&self) -> Result<TlaConfigParts, Error> {
+        self.content.parse()
+    }
+
     pub(crate) fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let path = path.as_ref().to_path_buf();
         crate::util::check_file_existence(&path)?;
@@ -26,7 +46,7 @@ impl TlaConfigFile {
         if res.is_none() {
             panic!("TODO: tla config file should not fail to provide filename");
         }
-        return res.unwrap().to_str().unwrap().to_owned();
+        res.unwrap().to_str().unwrap().to_owned()
     }
 
     /// Returns the path to the TLA+ config file.
@@ -43,6 +63,276 @@ impl TlaConfigFile {
     pub fn set_path(&mut self, path: &Path) {
         self.path = path.into();
     }
+
+    /// Returns a copy of the config file in which each constant is assigned
+    /// the given TLA+ value. The assignments are appended, as both TLC and
+    /// Apalache keep the last assignment of a constant.
+    pub fn with_constants(&self, constants: &BTreeMap<String, String>) -> Self {
+        let mut content = self.content.clone();
+        if !constants.is_empty() {
+            content.push_str("\nCONSTANTS\n");
+            for (name, value) in constants {
+                content.push_str(&format!("    {} = {}\n", name, value));
+            }
+        }
+        Self {
+            path: self.path.clone(),
+            content,
+        }
+    }
+}
+
+/// The sections of a TLA+ config file, which can be built one by one with
+/// [`TlaConfigFile::builder`], or parsed with [`TlaConfigFile::parts`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlaConfigParts {
+    /// TLA+ values assigned to constants, e.g. `N = 3`.
+    pub constants: Vec<(String, String)>,
+    /// Operators replacing constants, e.g. `Nodes <- ConstNodes`.
+    pub replacements: Vec<(String, String)>,
+    /// Name of the initial predicate.
+    pub init: Option<String>,
+    /// Name of the next-state relation.
+    pub next: Option<String>,
+    /// Names of the invariants.
+    pub invariants: Vec<String>,
+    /// Names of the temporal properties.
+    pub properties: Vec<String>,
+    /// Names of the state constraints.
+    pub constraints: Vec<String>,
+    /// Name of the set of permutations under which the states are symmetric,
+    /// so that TLC explores a single state of each class of symmetric states.
+    pub symmetry: Option<String>,
+    /// Other directives, e.g. `("VIEW", "View")`, which are kept as they are.
+    pub directives: Vec<(String, String)>,
+}
+
+impl TlaConfigParts {
+    /// Assign a TLA+ value to a constant.
+    pub fn constant(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.constants.push((name.into(), value.into()));
+        self
+    }
+
+    /// Replace a constant by an operator.
+    pub fn replacement(mut self, name: impl Into<String>, operator: impl Into<String>) -> Self {
+        self.replacements.push((name.into(), operator.into()));
+        self
+    }
+
+    /// Set the initial predicate.
+    pub fn init(mut self, init: impl Into<String>) -> Self {
+        self.init = Some(init.into());
+        self
+    }
+
+    /// Set the next-state relation.
+    pub fn next(mut self, next: impl Into<String>) -> Self {
+        self.next = Some(next.into());
+        self
+    }
+
+    /// Add an invariant.
+    pub fn invariant(mut self, invariant: impl Into<String>) -> Self {
+        self.invariants.push(invariant.into());
+        self
+    }
+
+    /// Add a temporal property.
+    pub fn property(mut self, property: impl Into<String>) -> Self {
+        self.properties.push(property.into());
+        self
+    }
+
+    /// Add a state constraint.
+    pub fn constraint(mut self, constraint: impl Into<String>) -> Self {
+        self.constraints.push(constraint.into());
+        self
+    }
+
+    /// Set the symmetry set.
+    pub fn symmetry(mut self, symmetry: impl Into<String>) -> Self {
+        self.symmetry = Some(symmetry.into());
+        self
+    }
+
+    /// Add another directive, e.g. `VIEW View`.
+    pub fn directive(mut self, keyword: impl Into<String>, value: impl Into<String>) -> Self {
+        self.directives.push((keyword.into(), value.into()));
+        self
+    }
+
+    /// Builds the TLA+ config file.
+    pub fn build(&self) -> TlaConfigFile {
+        TlaConfigFile::from_parts(self)
+    }
+}
+
+impl std::fmt::Display for TlaConfigParts {
+    /// Writes the sections in the order of TLC's documentation.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.constants.is_empty() || !self.replacements.is_empty() {
+            writeln!(f, "CONSTANTS")?;
+            for (name, value) in &self.constants {
+                writeln!(f, "    {} = {}", name, value)?;
+            }
+            for (name, operator) in &self.replacements {
+                writeln!(f, "    {} <- {}", name, operator)?;
+            }
+        }
+        if let Some(init) = &self.init {
+            writeln!(f, "INIT {}", init)?;
+        }
+        if let Some(next) = &self.next {
+            writeln!(f, "NEXT {}", next)?;
+        }
+        let sections = [
+            ("INVARIANT", &self.invariants),
+            ("PROPERTY", &self.properties),
+            ("CONSTRAINT", &self.constraints),
+        ];
+        for (keyword, names) in sections {
+            for name in names {
+                writeln!(f, "{} {}", keyword, name)?;
+            }
+        }
+        if let Some(symmetry) = &self.symmetry {
+            writeln!(f, "SYMMETRY {}", symmetry)?;
+        }
+        for (keyword, value) in &self.directives {
+            writeln!(f, "{} {}", keyword, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for TlaConfigParts {
+    type Err = Error;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let tokens = config_tokens(content);
+        let mut parts = Self::default();
+        let mut section = None;
+        let mut index = 0;
+        while index < tokens.len() {
+            let token = tokens[index].as_str();
+            index += 1;
+            if is_config_keyword(token) {
+                section = Some(token);
+                continue;
+            }
+            match section {
+                Some("CONSTANT" | "CONSTANTS") => {
+                    let operator = tokens.get(index).map(String::as_str);
+                    // the value spans the tokens up to the next constant or section
+                    let end = (index + 1..tokens.len())
+                        .find(|&end| {
+                            is_config_keyword(&tokens[end])
+                                || matches!(
+                                    tokens.get(end + 1).map(String::as_str),
+                                    Some("=" | "<-")
+                                )
+                        })
+                        .unwrap_or(tokens.len());
+                    let value = tokens[(index + 1).min(end)..end].join(" ");
+                    match operator {
+                        Some("=") if !value.is_empty() => {
+                            parts.constants.push((token.to_owned(), value))
+                        }
+                        Some("<-") if !value.is_empty() => {
+                            parts.replacements.push((token.to_owned(), value))
+                        }
+                        _ => {
+                            return Err(Error::InvalidTlaConfig(format!(
+                                "constant {} isn't assigned",
+                                token
+                            )))
+                        }
+                    }
+                    index = end;
+                }
+                Some("INIT") if parts.init.is_none() => parts.init = Some(token.to_owned()),
+                Some("NEXT") if parts.next.is_none() => parts.next = Some(token.to_owned()),
+                Some("INVARIANT" | "INVARIANTS") => parts.invariants.push(token.to_owned()),
+                Some("PROPERTY" | "PROPERTIES") => parts.properties.push(token.to_owned()),
+                Some("CONSTRAINT" | "CONSTRAINTS") => parts.constraints.push(token.to_owned()),
+                Some("SYMMETRY") if parts.symmetry.is_none() => {
+                    parts.symmetry = Some(token.to_owned())
+                }
+                Some(
+                    section @ ("SPECIFICATION" | "ACTION_CONSTRAINT" | "ACTION_CONSTRAINTS"
+                    | "VIEW" | "CHECK_DEADLOCK" | "POSTCONDITION" | "ALIAS"),
+                ) => parts
+                    .directives
+                    .push((section.to_owned(), token.to_owned())),
+                Some(section) => {
+                    return Err(Error::InvalidTlaConfig(format!(
+                        "unexpected {} in section {}",
+                        token, section
+                    )))
+                }
+                None => {
+                    return Err(Error::InvalidTlaConfig(format!(
+                        "expected a section, got {}",
+                        token
+                    )))
+                }
+            }
+        }
+        Ok(parts)
+    }
+}
+
+/// Returns whether a token of a TLA+ config file starts a section.
+fn is_config_keyword(token: &str) -> bool {
+    matches!(
+        token,
+        "CONSTANT"
+            | "CONSTANTS"
+            | "INIT"
+            | "NEXT"
+            | "INVARIANT"
+            | "INVARIANTS"
+            | "PROPERTY"
+            | "PROPERTIES"
+            | "CONSTRAINT"
+            | "CONSTRAINTS"
+            | "SYMMETRY"
+            | "SPECIFICATION"
+            | "ACTION_CONSTRAINT"
+            | "ACTION_CONSTRAINTS"
+            | "VIEW"
+            | "CHECK_DEADLOCK"
+            | "POSTCONDITION"
+            | "ALIAS"
+    )
+}
+
+/// Splits the content of a TLA+ config file into tokens, without its
+/// comments, and with `=` and `<-` as tokens of their own.
+fn config_tokens(content: &str) -> Vec<String> {
+    let mut text = String::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("(*") {
+            rest = comment.split_once("*)").map_or("", |(_, rest)| rest);
+            text.push(' ');
+        } else if let Some(comment) = rest.strip_prefix("\\*") {
+            rest = comment.split_once('\n').map_or("", |(_, rest)| rest);
+            text.push(' ');
+        } else if let Some(after) = rest.strip_prefix("<-") {
+            text.push_str(" <- ");
+            rest = after;
+        } else {
+            let c = rest.chars().next().expect("rest isn't empty");
+            match c {
+                '=' => text.push_str(" = "),
+                c => text.push(c),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    text.split_whitespace().map(Into::into).collect()
 }
 
 impl std::fmt::Display for TlaConfigFile {
@@ -74,3 +364,59 @@ impl ArtifactSaver for TlaConfigFile {
         self.filename()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let config = TlaConfigFile::builder()
+            .constant("N", "3")
+            .init("Init")
+            .next("Next")
+            .invariant("Inv")
+            .build();
+        assert_eq!(
+            config.content(),
+            "CONSTANTS\n    N = 3\nINIT Init\nNEXT Next\nINVARIANT Inv\n"
+        );
+    }
+
+    #[test]
+    fn test_parts_round_trip() {
+        let parts = TlaConfigFile::builder()
+            .constant("N", "3")
+            .constant("Nodes", "{\"a\", \"b\"}")
+            .replacement("Values", "ConstValues")
+            .next("Next")
+            .init("Init")
+            .invariant("Inv")
+            .invariant("TypeOK")
+            .property("Liveness")
+            .constraint("Bound")
+            .symmetry("Perms")
+            .directive("VIEW", "View")
+            .directive("CHECK_DEADLOCK", "FALSE");
+        assert_eq!(parts.build().parts().unwrap(), parts);
+    }
+
+    #[test]
+    fn test_parts() {
+        let config = TlaConfigFile::from_string(
+            "\\* a comment\nCONSTANT N=3 M = {1, 2}\n(* another\ncomment *)\nINIT Init NEXT Next\nINVARIANTS A B\n",
+        )
+        .unwrap();
+        let expected = TlaConfigFile::builder()
+            .constant("N", "3")
+            .constant("M", "{1, 2}")
+            .init("Init")
+            .next("Next")
+            .invariant("A")
+            .invariant("B");
+        assert_eq!(config.parts().unwrap(), expected);
+
+        let config = TlaConfigFile::from_string("Spec").unwrap();
+        assert!(matches!(config.parts(), Err(Error::InvalidTlaConfig(_))));
+    }
+}
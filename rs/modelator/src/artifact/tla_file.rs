@@ -11,6 +11,8 @@ pub struct TlaFile {
     file_contents_backing: String,
     /// Module name
     module_name: String,
+    /// Path the file was read from, if any
+    path: Option<PathBuf>,
 }
 
 impl TlaFile {
@@ -19,6 +21,12 @@ impl TlaFile {
         &self.module_name
     }
 
+    /// Returns the path the TLA file was read from, if it was read with
+    /// [`ArtifactCreator::try_read_from_file`].
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
     /// Returns a base filename `{module_name}.tla`
     pub fn file_name(&self) -> String {
         format!("{}.tla", &self.module_name)
@@ -39,9 +47,27 @@ impl ArtifactCreator for TlaFile {
             Ok(name) => Ok(Self {
                 file_contents_backing: s.to_string(),
                 module_name: name,
+                path: None,
             }),
         }
     }
+
+    /// Tries to read a file and initialize from the content.
+    /// Fails if the declared module name doesn't match the file name, as
+    /// the model checkers require.
+    fn try_read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut tla_file = Self::from_string(&crate::util::try_read_file_contents(path)?)?;
+        if path.file_stem() == Some(tla_file.module_name().as_ref()) {
+            tla_file.path = Some(path.to_path_buf());
+            Ok(tla_file)
+        } else {
+            Err(Error::ModuleNameMismatch {
+                filename: path.to_path_buf(),
+                declared: tla_file.module_name,
+            })
+        }
+    }
 }
 
 impl Artifact for TlaFile {
@@ -93,4 +119,22 @@ mod tests {
         let s = "\n---------- MODULE moduleName ----------\n42";
         assert_eq!(module_name(s), Ok("moduleName".into()));
     }
+
+    #[test]
+    fn test_module_name_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "---- MODULE Foo ----\n====";
+
+        let path = dir.path().join("Foo.tla");
+        std::fs::write(&path, content).unwrap();
+        let tla_file = TlaFile::try_read_from_file(&path).unwrap();
+        assert_eq!(tla_file.module_name(), "Foo");
+
+        let path = dir.path().join("foo.tla");
+        std::fs::write(&path, content).unwrap();
+        assert!(matches!(
+            TlaFile::try_read_from_file(&path),
+            Err(Error::ModuleNameMismatch { filename, declared }) if filename == path && declared == "Foo"
+        ));
+    }
 }
@@ -1,5 +1,7 @@
+use lazy_static::lazy_static;
+use regex::Regex;
 use std::collections::BTreeSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use super::tla_config_file::TlaConfigFile;
 use super::tla_file::TlaFile;
@@ -35,7 +37,96 @@ pub struct TlaFileSuite {
     pub dependency_tla_files: Vec<TlaFile>,
 }
 
-fn find_dependencies(tla_module_path: impl AsRef<std::path::Path>) -> Result<Vec<PathBuf>, Error> {
+/// Resolves a module to the `{module_name}.tla` file next to the module
+/// extending it or, if there's none, to the first one found in the library paths.
+fn resolve_module(
+    module_name: &str,
+    current_directory: &Path,
+    tla_library_paths: &[PathBuf],
+) -> Result<PathBuf, Error> {
+    let file_name = format!("{}.tla", module_name);
+    let candidates: Vec<PathBuf> = std::iter::once(current_directory)
+        .chain(tla_library_paths.iter().map(PathBuf::as_path))
+        .map(|dir| dir.join(&file_name))
+        .collect();
+
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .ok_or(Error::FileNotFoundInAny(candidates))
+}
+
+/// Replaces TLA+ comments (`\* ...` and possibly nested `(* ... *)`) and
+/// strings with whitespace, preserving the line breaks.
+pub(crate) fn remove_comments_and_strings(content: &str) -> String {
+    let mut res = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    // nesting depth of block comments
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('(', Some('*')) => {
+                chars.next();
+                depth += 1;
+            }
+            ('*', Some(')')) if depth > 0 => {
+                chars.next();
+                depth -= 1;
+                res.push(' ');
+            }
+            ('\\', Some('*')) if depth == 0 => {
+                // skip the rest of the line
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        res.push(c);
+                        break;
+                    }
+                }
+            }
+            ('"', _) if depth == 0 => {
+                // skip the string, taking care of escaped characters
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            chars.next();
+                        }
+                        _ => {}
+                    }
+                }
+                res.push(' ');
+            }
+            ('\n', _) => res.push(c),
+            _ if depth == 0 => res.push(c),
+            _ => {}
+        }
+    }
+    res
+}
+
+/// Returns the names of the modules extended in the given TLA+ module content.
+/// The `EXTENDS` clause can span several lines and contain comments.
+fn extended_module_names(content: &str) -> Vec<String> {
+    lazy_static! {
+        // Match 'EXTENDS <identifier>, <identifier>, ...'
+        static ref RE: Regex =
+            Regex::new(r"\bEXTENDS\s+(\w+(?:\s*,\s*\w+)*)").unwrap();
+    }
+    RE.captures_iter(&remove_comments_and_strings(content))
+        .flat_map(|caps| {
+            caps[1]
+                .split(',')
+                .map(|module_name| module_name.trim().to_owned())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn find_dependencies(
+    tla_module_path: impl AsRef<Path>,
+    tla_library_paths: &[PathBuf],
+) -> Result<Vec<PathBuf>, Error> {
     let current_directory = tla_module_path
         .as_ref()
         .parent()
@@ -44,19 +135,29 @@ fn find_dependencies(tla_module_path: impl AsRef<std::path::Path>) -> Result<Vec
 
     let content = crate::util::try_read_file_contents(tla_module_path)?;
 
-    Ok(content
-        .lines()
-        .filter(|line| line.starts_with("EXTENDS"))
-        .flat_map(|line| line.trim_start_matches("EXTENDS").split(',').map(str::trim))
-        .filter(|module_name| !STANDARD_MODULES.contains(module_name))
-        .map(|module_name| current_directory.join(format!("{}.tla", module_name)))
-        .collect())
+    extended_module_names(&content)
+        .iter()
+        .filter(|module_name| !STANDARD_MODULES.contains(&module_name.as_str()))
+        .map(|module_name| resolve_module(module_name, &current_directory, tla_library_paths))
+        .collect()
 }
 
 fn gather_dependencies(
-    tla_module_path: impl AsRef<std::path::Path>,
+    tla_module_path: impl AsRef<Path>,
+    tla_library_paths: &[PathBuf],
 ) -> Result<Vec<TlaFile>, Error> {
-    let mut extended_modules = find_dependencies(tla_module_path)?;
+    dependency_paths(tla_module_path, tla_library_paths)?
+        .into_iter()
+        .map(TlaFile::try_read_from_file)
+        .collect()
+}
+
+/// Returns the paths of the modules extended by a module, transitively.
+pub(crate) fn dependency_paths(
+    tla_module_path: impl AsRef<Path>,
+    tla_library_paths: &[PathBuf],
+) -> Result<BTreeSet<PathBuf>, Error> {
+    let mut extended_modules = find_dependencies(tla_module_path, tla_library_paths)?;
 
     let mut explored_set = BTreeSet::new();
 
@@ -64,33 +165,35 @@ fn gather_dependencies(
     while let Some(current_module_path) = extended_modules.pop() {
         if !explored_set.contains(&current_module_path) {
             explored_set.insert(current_module_path.clone());
-            let new_extended_modules = find_dependencies(current_module_path)?;
-            extended_modules.extend(new_extended_modules.into_iter());
+            let new_extended_modules = find_dependencies(current_module_path, tla_library_paths)?;
+            extended_modules.extend(new_extended_modules);
         }
     }
 
-    explored_set
-        .into_iter()
-        .map(|tla_module_path| {
-            let content = crate::util::try_read_file_contents(tla_module_path)?;
-            TlaFile::from_string(&content)
-        })
-        .collect()
+    Ok(explored_set)
 }
 
 impl TlaFileSuite {
     /// Gather all model checking resources from a main .tla and .cfg file
-    pub fn from_tla_and_config_paths<P: AsRef<std::path::Path>>(
+    pub fn from_tla_and_config_paths<P: AsRef<Path>>(
         tla_file_path: P,
         config_file_path: P,
+    ) -> Result<Self, Error> {
+        Self::from_tla_and_config_paths_with_library_paths(tla_file_path, config_file_path, &[])
+    }
+
+    /// Gather all model checking resources from a main .tla and .cfg file,
+    /// looking up the extended modules that are not next to the module
+    /// extending them in each of the given library paths, in order.
+    pub fn from_tla_and_config_paths_with_library_paths<P: AsRef<Path>>(
+        tla_file_path: P,
+        config_file_path: P,
+        tla_library_paths: &[PathBuf],
     ) -> Result<Self, Error> {
         let tla_file = TlaFile::try_read_from_file(&tla_file_path)?;
         let mut tla_config_file = TlaConfigFile::try_read_from_file(config_file_path)?;
-        tla_config_file.set_path(std::path::Path::new(&format!(
-            "{}.cfg",
-            tla_file.module_name()
-        )));
-        let dependency_tla_files = gather_dependencies(tla_file_path)?;
+        tla_config_file.set_path(Path::new(&format!("{}.cfg", tla_file.module_name())));
+        let dependency_tla_files = gather_dependencies(tla_file_path, tla_library_paths)?;
         Ok(Self {
             tla_file,
             tla_config_file,
@@ -99,10 +202,20 @@ impl TlaFileSuite {
     }
 
     /// Gather all resources from a main .tla without .cfg file
-    pub fn from_tla_path<P: AsRef<std::path::Path>>(tla_file_path: P) -> Result<Self, Error> {
+    pub fn from_tla_path<P: AsRef<Path>>(tla_file_path: P) -> Result<Self, Error> {
+        Self::from_tla_path_with_library_paths(tla_file_path, &[])
+    }
+
+    /// Gather all resources from a main .tla without .cfg file, looking up
+    /// the extended modules in the given library paths as
+    /// [`TlaFileSuite::from_tla_and_config_paths_with_library_paths`] does.
+    pub fn from_tla_path_with_library_paths<P: AsRef<Path>>(
+        tla_file_path: P,
+        tla_library_paths: &[PathBuf],
+    ) -> Result<Self, Error> {
         let tla_file = TlaFile::try_read_from_file(&tla_file_path)?;
         let tla_config_file = TlaConfigFile::from_string("")?;
-        let dependency_tla_files = gather_dependencies(tla_file_path)?;
+        let dependency_tla_files = gather_dependencies(tla_file_path, tla_library_paths)?;
         Ok(Self {
             tla_file,
             tla_config_file,
@@ -127,3 +240,84 @@ impl<'a> IntoIterator for &'a TlaFileSuite {
         ret.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_module_names() {
+        // indentation and trailing comment
+        let content = r#"
+---- MODULE Spec ----
+    EXTENDS Naturals, Lib \* a comment
+===="#;
+        assert_eq!(extended_module_names(content), vec!["Naturals", "Lib"]);
+
+        // continuation lines, with comments in between
+        let content = r#"
+---- MODULE Spec ----
+EXTENDS Naturals, \* first line
+        Lib1,
+        (* a block comment *) Lib2
+        ,Lib3
+VARIABLE x
+===="#;
+        assert_eq!(
+            extended_module_names(content),
+            vec!["Naturals", "Lib1", "Lib2", "Lib3"]
+        );
+
+        // `EXTENDS` in comments and strings
+        let content = r#"
+---- MODULE Spec ----
+\* EXTENDS Commented1
+(* EXTENDS Commented2
+   (* nested *) EXTENDS Commented3 *)
+EXTENDS Lib
+Str == "EXTENDS NotAModule"
+===="#;
+        assert_eq!(extended_module_names(content), vec!["Lib"]);
+    }
+
+    #[test]
+    fn test_library_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let specs_dir = dir.path().join("specs");
+        let lib_dir = specs_dir.join("lib");
+        std::fs::create_dir_all(&lib_dir).unwrap();
+
+        let tla_file_path = specs_dir.join("Spec.tla");
+        let config_file_path = specs_dir.join("Spec.cfg");
+        std::fs::write(
+            &tla_file_path,
+            "---- MODULE Spec ----\nEXTENDS Naturals, Lib\nInit == x = 0\n====",
+        )
+        .unwrap();
+        std::fs::write(&config_file_path, "INIT Init").unwrap();
+        std::fs::write(
+            lib_dir.join("Lib.tla"),
+            "---- MODULE Lib ----\nVARIABLE x\n====",
+        )
+        .unwrap();
+
+        // `Lib` is not next to `Spec`
+        assert!(matches!(
+            TlaFileSuite::from_tla_and_config_paths(&tla_file_path, &config_file_path),
+            Err(Error::FileNotFoundInAny(searched)) if searched == vec![specs_dir.join("Lib.tla")]
+        ));
+
+        let suite = TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+            &tla_file_path,
+            &config_file_path,
+            &[dir.path().join("missing"), lib_dir],
+        )
+        .unwrap();
+        let dependencies: Vec<_> = suite
+            .dependency_tla_files
+            .iter()
+            .map(TlaFile::module_name)
+            .collect();
+        assert_eq!(dependencies, vec!["Lib"]);
+    }
+}
@@ -17,7 +17,10 @@ use nom::{
 pub(crate) type TlaState = String;
 
 /// `modelator`'s artifact containing a test trace encoded as TLA+.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Traces compare and hash by their states and the module they extend, which
+/// is what their cached representation holds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TlaTrace {
     /// TODO: file_contents backing strings are to be removed
     file_contents_backing: String,
@@ -43,6 +46,21 @@ impl TlaTrace {
             file_contents_backing: "".to_owned(),
         }
     }
+
+    /// Returns a TLA+ module with the given name, defining an operator
+    /// `State<i>` for each state of the trace (starting from `State1`).
+    /// The module extends the module the trace was generated from, if known.
+    pub fn to_tla_module(&self, module_name: &str) -> String {
+        let mut module = format!("---- MODULE {} ----\n\n", module_name);
+        if let Some(name) = &self.extends_module_name {
+            module.push_str(&format!("EXTENDS {}\n\n", name));
+        }
+        for (index, state) in self.states.iter().enumerate() {
+            module.push_str(&format!("State{} ==\n{}\n\n", index + 1, state.trim_end()));
+        }
+        module.push_str("====\n");
+        module
+    }
 }
 
 impl IntoIterator for TlaTrace {
@@ -79,9 +97,11 @@ impl ArtifactCreator for TlaTrace {
         states.sort_unstable();
         states.dedup_by_key(|(k, _)| *k);
 
-        assert_eq!(
-            (states.first().unwrap().0, states.last().unwrap().0 + 1),
-            (0, states.len()),
+        // Apalache numbers states from `State0`, `TlaTrace::to_tla_module`
+        // from `State1`
+        let first = states.first().unwrap().0;
+        assert!(
+            first <= 1 && states.last().unwrap().0 + 1 - first == states.len(),
             "some consecutive states are missing in .tla trace"
         );
 
@@ -97,12 +117,7 @@ impl Artifact for TlaTrace {
     fn as_string(&self) -> String {
         match &self.extends_module_name {
             None => format!("{}", self),
-            Some(name) => {
-                format!(
-                    "---- MODULE trace ----\n\nEXTENDS {}\n\n{}\n====",
-                    name, self
-                )
-            }
+            Some(_) => self.to_tla_module("trace"),
         }
     }
 }
@@ -199,3 +214,61 @@ fn parse_tla_trace_file_contents(i: &str) -> IResult<&str, TlaTraceFileContent<'
         },
     )(i)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_tla_module() {
+        let mut trace = TlaTrace::new();
+        trace.add("/\\ x = 0\n/\\ y = \"a\"\n".into());
+        trace.add("/\\ x = 1\n/\\ y = \"b\"\n".into());
+        trace.extends_module_name = Some("Spec".into());
+
+        let module = trace.to_tla_module("SpecTrace");
+        assert_eq!(
+            module,
+            r#"---- MODULE SpecTrace ----
+
+EXTENDS Spec
+
+State1 ==
+/\ x = 0
+/\ y = "a"
+
+State2 ==
+/\ x = 1
+/\ y = "b"
+
+====
+"#
+        );
+
+        // the module can be parsed back into the same states
+        let parsed = TlaTrace::from_string(&module).unwrap();
+        let mut expected = TlaTrace::new();
+        expected.add("/\\ x = 0\n/\\ y = \"a\"".into());
+        expected.add("/\\ x = 1\n/\\ y = \"b\"".into());
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn test_dedup() {
+        let module = "---- MODULE trace ----\n\nState0 ==\nx = 0\n\nState1 ==\nx = 1\n\n====\n";
+        let commented = "---- MODULE trace ----\n\n\\* first state\nState0 ==\nx = 0\n\nState1 ==\nx = 1\n\n====\n";
+        let other = "---- MODULE trace ----\n\nState0 ==\nx = 0\n\nState1 ==\nx = 2\n\n====\n";
+
+        let traces: std::collections::HashSet<_> = [module, commented, other, module]
+            .iter()
+            .map(|s| TlaTrace::from_string(s).unwrap())
+            .collect();
+        assert_eq!(traces.len(), 2);
+
+        // traces extending different modules differ
+        let mut extending = TlaTrace::from_string(module).unwrap();
+        extending.extends_module_name = Some("Spec".to_owned());
+        assert!(!traces.contains(&extending));
+        assert!(traces.contains(&TlaTrace::from_string(commented).unwrap()));
+    }
+}
@@ -1,13 +1,24 @@
 // cache for `TlaTrace`s.
 mod tla_trace;
 
+// cache for the states explored by `ModelatorRuntime::next_states`.
+mod next_states;
+
 // Re-exports;
+pub(crate) use next_states::NextStatesCache;
 pub(crate) use tla_trace::TlaTraceCache;
 
+use crate::artifact::{Artifact, ArtifactSaver, TlaFileSuite};
 use crate::{Error, ModelatorRuntime};
-use std::collections::HashSet;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
 use std::path::PathBuf;
 
+/// Prefix of the temporary files values are written to before being moved
+/// into place, which aren't cache entries.
+const TEMP_FILE_PREFIX: &str = ".tmp";
+
 pub(crate) struct Cache {
     cache_dir: PathBuf,
     cached_keys: HashSet<String>,
@@ -19,8 +30,12 @@ impl Cache {
         let cache_dir = runtime.dir.join("cache");
         std::fs::create_dir_all(&cache_dir)?;
 
-        // read files the cache directory
-        let cached_keys = crate::util::read_dir(&cache_dir)?;
+        // read files the cache directory, skipping the temporary files left
+        // by interrupted writes
+        let cached_keys = crate::util::read_dir(&cache_dir)?
+            .into_iter()
+            .filter(|file_name| !file_name.starts_with(TEMP_FILE_PREFIX))
+            .collect();
 
         Ok(Self {
             cache_dir,
@@ -49,21 +64,78 @@ impl Cache {
             "[modelator] trying to cache a key already cached"
         );
 
-        // write the value associated with this key to disk
-        let path = self.key_path(&key);
-        std::fs::write(path, value)?;
+        // write the value associated with this key to a temporary file, then
+        // move it into place, so that an interrupted write leaves no entry
+        // behind instead of a truncated one
+        let mut file = tempfile::Builder::new()
+            .prefix(TEMP_FILE_PREFIX)
+            .tempfile_in(&self.cache_dir)?;
+        file.write_all(value.as_bytes())?;
+        file.persist(self.key_path(&key))
+            .map_err(|e| Error::from(e.error))?;
 
         // mark the key as cached
         self.cached_keys.insert(key);
         Ok(())
     }
 
+    /// Removes a key from the cache, e.g. if its value can't be parsed.
+    #[allow(clippy::ptr_arg)]
+    pub(crate) fn remove(&mut self, key: &String) -> Result<(), Error> {
+        if self.cached_keys.remove(key) {
+            std::fs::remove_file(self.key_path(key))?;
+        }
+        Ok(())
+    }
+
     #[allow(clippy::ptr_arg)]
     fn key_path(&self, key: &String) -> PathBuf {
         self.cache_dir.join(key)
     }
 }
 
+/// Computes the cache key of a [`TlaFileSuite`] from the names and contents
+/// of all the files in it, so that byte-identical suites share the same key.
+/// As the suite holds the modules extended by the test, transitively, editing
+/// one of them changes the key even if it lives in another directory, while
+/// editing an unrelated module next to the test doesn't.
+pub(crate) fn key(tla_file_suite: &TlaFileSuite) -> String {
+    use sha2::Digest;
+    let mut digest = sha2::Sha256::default();
+    for artifact in tla_file_suite {
+        digest.update(artifact.filename());
+        digest.update(artifact.as_string());
+    }
+    crate::util::digest::encode(digest)
+}
+
+/// Runs `f` (in parallel) only once for each distinct key in `keys`, and
+/// shares its result between all the indices having that same key.
+pub(crate) fn single_flight<K, V, F>(keys: &[K], f: F) -> Vec<V>
+where
+    K: Ord,
+    V: Clone + Send,
+    F: Fn(usize) -> V + Sync,
+{
+    // index of the first occurrence of each key
+    let mut first_indices: BTreeMap<&K, usize> = BTreeMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        first_indices.entry(key).or_insert(i);
+    }
+
+    let results: BTreeMap<usize, V> = first_indices
+        .values()
+        .copied()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|i| (i, f(i)))
+        .collect();
+
+    keys.iter()
+        .map(|key| results[&first_indices[key]].clone())
+        .collect()
+}
+
 // TODO: disabling cache for now; see https://github.com/informalsystems/modelator/issues/46
 //#[cfg(test)]
 mod tests {
@@ -100,4 +172,102 @@ mod tests {
         // cleanup
         std::fs::remove_dir_all(modelator_dir).unwrap();
     }
+
+    #[test]
+    fn identical_suites_are_checked_once() {
+        use crate::artifact::{ArtifactCreator, TlaConfigFile, TlaFile};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let suite = || {
+            let mut tla_config_file = TlaConfigFile::from_string("INIT Init").unwrap();
+            tla_config_file.set_path(std::path::Path::new("Spec.cfg"));
+            TlaFileSuite {
+                tla_file: TlaFile::from_string("---- MODULE Spec ----\nInit == TRUE\n====")
+                    .unwrap(),
+                tla_config_file,
+                dependency_tla_files: vec![],
+            }
+        };
+        let suites = [suite(), suite()];
+        let keys: Vec<String> = suites.iter().map(key).collect();
+        assert_eq!(keys[0], keys[1]);
+
+        let invocations = AtomicUsize::new(0);
+        let results = single_flight(&keys, |i| {
+            invocations.fetch_add(1, Ordering::SeqCst);
+            suites[i].tla_file.module_name().to_owned()
+        });
+        assert_eq!(invocations.load(Ordering::SeqCst), 1);
+        assert_eq!(results, vec!["Spec".to_owned(), "Spec".to_owned()]);
+    }
+
+    #[test]
+    fn key_depends_on_extended_modules_only() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let library_dir = tempfile::tempdir().unwrap();
+        let write =
+            |path: std::path::PathBuf, content: &str| std::fs::write(path, content).unwrap();
+        let tla_file_path = test_dir.path().join("Test.tla");
+        let tla_config_file_path = test_dir.path().join("Test.cfg");
+        write(
+            tla_file_path.clone(),
+            "---- MODULE Test ----\nEXTENDS Lib\nInit == x = 0\n====",
+        );
+        write(tla_config_file_path.clone(), "INIT Init");
+        write(
+            library_dir.path().join("Lib.tla"),
+            "---- MODULE Lib ----\nVARIABLE x\n====",
+        );
+        write(
+            test_dir.path().join("Unrelated.tla"),
+            "---- MODULE Unrelated ----\n====",
+        );
+        let library_paths = [library_dir.path().to_path_buf()];
+        let key = || {
+            key(&TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+                &tla_file_path,
+                &tla_config_file_path,
+                &library_paths,
+            )
+            .unwrap())
+        };
+        let initial_key = key();
+
+        // editing a module the test doesn't extend keeps the key
+        write(
+            test_dir.path().join("Unrelated.tla"),
+            "---- MODULE Unrelated ----\nY == 1\n====",
+        );
+        assert_eq!(key(), initial_key);
+
+        // editing an extended module in another directory changes the key
+        write(
+            library_dir.path().join("Lib.tla"),
+            "---- MODULE Lib ----\nVARIABLES x, y\n====",
+        );
+        assert_ne!(key(), initial_key);
+    }
+
+    #[test]
+    fn interrupted_writes_are_ignored() {
+        let modelator_dir = tempfile::tempdir().unwrap();
+        let runtime = ModelatorRuntime::default().dir(modelator_dir.path());
+        let cache_dir = modelator_dir.path().join("cache");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+
+        // a temporary file of an interrupted write isn't an entry
+        std::fs::write(cache_dir.join(format!("{}A", TEMP_FILE_PREFIX)), "[").unwrap();
+        let cache = Cache::new(&runtime).unwrap();
+        assert!(cache.cached_keys.is_empty());
+
+        // an entry that can't be parsed is removed, and can be cached again
+        let key = "A".to_string();
+        std::fs::write(cache_dir.join(&key), "[\"x = ").unwrap();
+        let mut cache = NextStatesCache::new(&runtime).unwrap();
+        assert!(cache.get(&key).unwrap().is_none());
+        assert!(!cache_dir.join(&key).exists());
+        let states = vec!["x = 0".to_string()];
+        cache.insert(key.clone(), &states).unwrap();
+        assert_eq!(cache.get(&key).unwrap(), Some(states));
+    }
 }
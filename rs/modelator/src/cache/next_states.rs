@@ -0,0 +1,68 @@
+use super::Cache;
+use crate::artifact::TlaFileSuite;
+use crate::model::checker::ModelCheckerRuntime;
+use crate::{Error, ModelatorRuntime};
+
+/// Cache of the states explored by [`crate::ModelatorRuntime::next_states`],
+/// as TLA+ formulas.
+pub(crate) struct NextStatesCache {
+    cache: Cache,
+}
+
+impl NextStatesCache {
+    pub(crate) fn new(runtime: &ModelatorRuntime) -> Result<Self, Error> {
+        let cache = Cache::new(runtime)?;
+        Ok(Self { cache })
+    }
+
+    /// Returns the cached states of a key, if any. An entry that can't be
+    /// parsed, e.g. written by an older version, is removed from the cache
+    /// and treated as missing.
+    #[allow(clippy::ptr_arg)]
+    pub(crate) fn get(&mut self, key: &String) -> Result<Option<Vec<String>>, Error> {
+        let value = match self.cache.get(key)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        match serde_json::from_str(&value) {
+            Ok(states) => Ok(Some(states)),
+            Err(e) => {
+                tracing::warn!("removing unparsable cache entry {}: {}", key, e);
+                self.cache.remove(key)?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub(crate) fn insert(&mut self, key: String, states: &[String]) -> Result<(), Error> {
+        let value = serde_json::to_string(states).expect("strings are serializable");
+        self.cache.insert(key, value)
+    }
+
+    /// Computes the key of the states reachable in one step from `state`
+    /// (or of the initial states if `None`) in the spec of a
+    /// [`TlaFileSuite`], with the constants overridden and the constraints
+    /// added by a [`ModelCheckerRuntime`].
+    pub(crate) fn key(
+        tla_file_suite: &TlaFileSuite,
+        state: Option<&str>,
+        checker_runtime: &ModelCheckerRuntime,
+    ) -> String {
+        use sha2::Digest;
+        let mut digest = sha2::Sha256::default();
+        digest.update("next_states");
+        digest.update(super::key(tla_file_suite));
+        // distinguishes the initial states from the successors of a state
+        match state {
+            Some(state) => digest.update(format!("state {}", state)),
+            None => digest.update("initial states"),
+        }
+        for (name, value) in &checker_runtime.constants {
+            digest.update(format!("{} <- {}", name, value));
+        }
+        for (operator, expr) in checker_runtime.constraints() {
+            digest.update(format!("{} == {}", operator, expr));
+        }
+        crate::util::digest::encode(digest)
+    }
+}
@@ -5,10 +5,18 @@ use std::path::PathBuf;
 use std::str::FromStr;
 // CLI output.
 pub(crate) mod output;
+// Show and diff subcommands of the trace subcommand.
+mod show;
+// Watch mode of the trace subcommand.
+mod watch;
 
 #[warn(dead_code, unused)]
-use crate::artifact::{Artifact, JsonTrace, TlaFile, TlaFileSuite, TlaTrace};
-use crate::model::checker::ModelChecker;
+use crate::artifact::{Artifact, ArtifactCreator, JsonTrace, TlaFile, TlaFileSuite, TlaTrace};
+use crate::model::checker::{
+    ApalacheMode, ApalacheParseFormat, CheckerStats, ModelChecker, ModelCheckerRuntime,
+    ModelCheckerWorkers,
+};
+use crate::model::language::TestExpectation;
 use crate::Error;
 use clap::{crate_authors, crate_description, crate_license, crate_name, crate_version};
 use clap::{AppSettings, ArgEnum, ArgSettings, ColorChoice, Parser, Subcommand, ValueHint};
@@ -16,7 +24,7 @@ use serde_json::{json, Value as JsonValue};
 use std::path::Path;
 
 /// Re-exports.
-pub use output::{CliOutput, CliStatus};
+pub use output::{CliOutput, CliStatus, ExitCode};
 
 /// Parse TLA+ files with Apalache.
 #[derive(Debug, Parser)]
@@ -25,23 +33,51 @@ pub struct ParseCli {
     /// TLA+ file with test cases.
     #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
     tla_module: PathBuf,
-    /// Whether or not to write the output file
-    #[clap(long)]
+    /// output format
+    #[clap(short, long, arg_enum, default_value = "tla")]
+    format: ParseFormat,
+    /// File to write the parsed module to, instead of printing it.
+    #[clap(short, long, parse(from_os_str), value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+    /// Whether or not to write the output file, named after the parsed module
+    #[clap(long, conflicts_with = "output")]
     write: bool,
 }
 
 impl ParseCli {
-    fn run(&self) -> Result<JsonValue, Error> {
+    fn run(&self) -> CliOutput {
         let runtime = crate::ModelatorRuntime::default();
-        let tla_file = TlaFileSuite::from_tla_path(&self.tla_module)?;
-        let res = crate::model::checker::Apalache::parse(&tla_file, &runtime)?;
-        tracing::debug!("Apalache::parse output {}", res.0);
-        if self.write {
-            write_parsed_tla_file_to_file(&res.0)
-        } else {
-            Ok(json!({
-                "tla_file_content": res.0.as_string(),
-            }))
+        let format = match self.format {
+            ParseFormat::Tla => ApalacheParseFormat::Tla,
+            ParseFormat::Json => ApalacheParseFormat::Json,
+        };
+        let parsed = TlaFileSuite::from_tla_path(&self.tla_module).and_then(|tla_file| {
+            crate::model::checker::Apalache::parse_with_format(&tla_file, format, &runtime)
+        });
+        let content = match parsed {
+            Ok((content, _)) => content,
+            Err(e) => return CliOutput::with_result(Err(e)),
+        };
+        tracing::debug!("Apalache::parse_with_format output {}", content);
+
+        match (&self.output, &self.format) {
+            (Some(path), _) => CliOutput::with_result(write_parsed_file(path, &content)),
+            (None, ParseFormat::Tla) if self.write => CliOutput::with_result(
+                TlaFile::from_string(&content)
+                    .and_then(|tla_file| write_parsed_tla_file_to_file(&tla_file)),
+            ),
+            (None, ParseFormat::Json) if self.write => {
+                // as Apalache names the parsed module
+                let file_name = format!(
+                    "{}Parsed.json",
+                    self.tla_module
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                );
+                CliOutput::with_result(write_parsed_file(Path::new(&file_name), &content))
+            }
+            (None, _) => CliOutput::with_text(content),
         }
     }
 }
@@ -52,117 +88,263 @@ pub struct TestListCli {
     /// TLA+ file with test cases.
     #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
     tla_module: PathBuf,
+    /// output format
+    #[clap(short, long, arg_enum, default_value = "text")]
+    format: ListFormat,
 }
 
 impl TestListCli {
-    fn run(&self) -> Result<JsonValue, Error> {
-        let tla_file_suite = TlaFileSuite::from_tla_path(&self.tla_module)?;
-        let tests = crate::model::language::Tla::extract_test_names(
-            tla_file_suite.tla_file.file_contents_backing(),
-        )
-        .and_then(|names| {
-            if names.is_empty() {
-                Err(Error::NoTestFound(
-                    tla_file_suite.tla_file.module_name().into(),
-                ))
-            } else {
-                Ok(names)
-            }
-        })?;
-        tracing::debug!("Tla::extract_test_names output {:?}", &tests);
-        Ok(json!(tests))
+    fn run(&self) -> CliOutput {
+        let tests = TlaFile::try_read_from_file(&self.tla_module).and_then(|tla_file| {
+            crate::model::language::Tla::discover_tests(
+                &tla_file,
+                &crate::model::language::TestDiscovery::default(),
+            )
+        });
+        tracing::debug!("Tla::discover_tests output {:?}", &tests);
+        match (tests, &self.format) {
+            (Ok(tests), ListFormat::Text) => CliOutput::with_text(
+                tests
+                    .iter()
+                    .map(|test| format!("{} ({})", test.name, test.rule))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            (tests, _) => CliOutput::with_result(tests.map(|tests| {
+                tests
+                    .iter()
+                    .map(|test| {
+                        json!({
+                            "name": test.name,
+                            "rule": test.rule.to_string(),
+                            "expectation": match test.expectation {
+                                TestExpectation::Counterexample => "counterexample",
+                                TestExpectation::Holds => "holds",
+                            },
+                        })
+                    })
+                    .collect()
+            })),
+        }
+    }
+}
+
+/// List what `modelator` discovers in TLA+ files
+#[derive(Debug, Parser)]
+enum ListCli {
+    /// List the tests discovered in a TLA+ tests module.
+    Tests(TestListCli),
+}
+
+impl ListCli {
+    fn run(&self) -> CliOutput {
+        match self {
+            Self::Tests(test_list_cli) => test_list_cli.run(),
+        }
     }
 }
 
 /// Test models with Apalache/TLC
 #[derive(Debug, Parser)]
-#[clap(color = ColorChoice::Auto)]
+#[clap(color = ColorChoice::Auto, setting = AppSettings::SubcommandsNegateReqs)]
 pub struct TraceCli {
-    /// test name
-    #[clap(short, long, default_value = "@all")]
-    test: String,
+    #[clap(subcommand)]
+    command: Option<TraceCommand>,
+    /// test name [default: @all, or the tests of the config file]
+    #[clap(short, long)]
+    test: Option<String>,
     // TODO: derive ArgEnum for ModelChecker enum
-    /// Checker name
-    #[clap(short, long, possible_values = &["apalache", "tlc"], default_value = "apalache")]
-    model_checker: ModelChecker,
+    /// Checker name [default: apalache]
+    #[clap(short, long, possible_values = &["apalache", "tlc"])]
+    model_checker: Option<ModelChecker>,
     /// output format
     #[clap(short, long, arg_enum, default_value = "json")]
     format: OutputFormat,
-    /// The maximum number of traces to generate for a single test.
-    #[clap(short, long, default_value = "1")]
-    num_traces: usize,
+    /// The maximum number of traces to generate for a single test [default: 1].
+    #[clap(short, long)]
+    num_traces: Option<usize>,
+    /// Number of model checker workers: 'auto' or a number [default: auto].
+    #[clap(short, long)]
+    workers: Option<ModelCheckerWorkers>,
+    /// Apalache command generating the traces: 'simulate' samples random
+    /// executions, which is faster to find shallow violations, but requires
+    /// Apalache 0.30 or later, newer than the Apalache 0.17.5 run by
+    /// modelator [default: check].
+    #[clap(long, arg_enum)]
+    apalache_mode: Option<ApalacheModeName>,
+    /// Number of random executions sampled by `--apalache-mode simulate`.
+    #[clap(long, default_value = "100")]
+    max_run: usize,
+    /// Number of steps of each execution sampled by `--apalache-mode simulate`.
+    #[clap(long, default_value = "10")]
+    length: usize,
+    /// Assign a TLA+ value to a constant, overriding the TLA+ config file.
+    #[clap(
+        long,
+        value_name = "NAME=VALUE",
+        parse(try_from_str = parse_constant),
+        multiple_occurrences = true,
+        number_of_values = 1
+    )]
+    constant: Vec<(String, String)>,
+    /// Project configuration file, whose options are overridden by the flags
+    /// [default: modelator.toml, if it exists]
+    #[clap(long, parse(from_os_str), value_hint = ValueHint::FilePath)]
+    config: Option<PathBuf>,
     /// TLA+ file with test cases.
-    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
-    tla_module: PathBuf,
+    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath, required = true)]
+    tla_module: Option<PathBuf>,
     /// TLA+ config file with CONSTANTS, INIT and NEXT.
-    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
-    tla_config: PathBuf,
+    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath, required = true)]
+    tla_config: Option<PathBuf>,
     /// Whether or not to write output files
     #[clap(long)]
     write: bool,
+    /// Directory to write a file `<test>_<n>.<ext>` per trace into, created if needed.
+    /// The written files are listed by test in the output.
+    #[clap(long, parse(from_os_str), value_hint = ValueHint::DirPath)]
+    output_dir: Option<PathBuf>,
+    /// Whether or not to overwrite existing files in the output directory
+    #[clap(long)]
+    force: bool,
+    /// Re-run the tests whenever the TLA+ files or the config file change,
+    /// reporting the tests whose result changed.
+    #[clap(long, conflicts_with_all = &["write", "output-dir"])]
+    watch: bool,
+    /// Stop running tests after the first failing one; the remaining tests are skipped.
+    #[clap(long, conflicts_with = "watch")]
+    fail_fast: bool,
+    /// Test expected to fail: it's reported as xfailed if no trace is found
+    /// for it, and fails the run as xpassed otherwise.
+    #[clap(
+        long,
+        value_name = "TEST",
+        multiple_occurrences = true,
+        number_of_values = 1
+    )]
+    expect_fail: Vec<String>,
+    /// Print the commands that would model check each test, without running them.
+    #[clap(long, conflicts_with_all = &["watch", "write", "output-dir"])]
+    dry_run: bool,
 }
 
 impl TraceCli {
-    fn run(&self) -> Result<JsonValue, Error> {
-        let runtime = {
-            let mut runtime = crate::ModelatorRuntime::default();
-            runtime.model_checker_runtime.traces_per_test = self.num_traces;
-            runtime
+    fn run(&self) -> CliOutput {
+        if self.watch {
+            return CliOutput::with_result(watch::run(self));
+        }
+        if self.dry_run {
+            return CliOutput::with_result(self.runtime().and_then(|runtime| {
+                let (tla_module, tla_config) = self.tla_paths();
+                Ok(json!(runtime.plan(tla_module, tla_config)?))
+            }));
+        }
+
+        let (runtime, tla_file_suite, test_names) = match self.runtime().and_then(|runtime| {
+            let suite = self.file_suite(&runtime)?;
+            let test_names = self.test_names(&suite, &runtime)?;
+            Ok((runtime, suite, test_names))
+        }) {
+            Ok(loaded) => loaded,
+            Err(e) => return CliOutput::with_result(Err(e)),
         };
 
-        let tla_file_suite =
-            TlaFileSuite::from_tla_and_config_paths(&self.tla_module, &self.tla_config)?;
+        let mut test_traces = Vec::new();
+        let mut test_stats = Vec::new();
+        let mut failures = BTreeMap::new();
+        let mut expected_failures = BTreeMap::new();
+        let mut xpassed = 0;
+        for test_name in test_names {
+            if self.fail_fast && !failures.is_empty() {
+                let error = Error::TestSkipped(test_name.clone());
+                failures.insert(test_name, error);
+                continue;
+            }
+            let expected_failure = runtime.expected_failures.contains(&test_name);
+            match self.generate_traces(&test_name, &tla_file_suite, &runtime) {
+                Ok((_, _, stats)) if expected_failure => {
+                    test_stats.push(output::stats(&test_name, &stats));
+                    xpassed += 1;
+                    let error = Error::UnexpectedPass(test_name.clone());
+                    failures.insert(test_name, error);
+                }
+                Ok((module_name, traces, stats)) => {
+                    test_stats.push(output::stats(&test_name, &stats));
+                    test_traces.push((test_name, module_name, traces));
+                }
+                // only the failures of the test itself are expected, not
+                // those of the environment or of the model checker
+                Err(e) if expected_failure && ExitCode::from(&e) == ExitCode::TestFailed => {
+                    expected_failures.insert(test_name, e);
+                }
+                Err(e) => {
+                    failures.insert(test_name, e);
+                }
+            }
+        }
 
-        let test_names: Vec<String> = crate::model::language::Tla::extract_test_names(
-            tla_file_suite.tla_file.file_contents_backing(),
-        )?
-        .into_iter()
-        .filter(|test_name| allow_test_name(test_name, &self.test))
-        .collect();
+        let skipped = failures
+            .values()
+            .filter(|e| matches!(e, Error::TestSkipped(_)))
+            .count();
+        // the statistics of each test come before the summary line
+        test_stats.push(output::summary(
+            test_traces.len(),
+            failures.len() - skipped - xpassed,
+            expected_failures.len(),
+            xpassed,
+            skipped,
+        ));
+        let summary = test_stats.join("\n");
+        let failure = failures.values().map(ExitCode::from).max();
 
-        if test_names.is_empty() {
-            return Err(Error::NoTestFound(format!(
-                "No test found in {}. [tla module name: {}, test pattern: {}]",
-                tla_file_suite.tla_file.module_name(),
-                tla_file_suite.tla_file.module_name(),
-                &self.test
-            )));
+        let mut result = match self.traces_output(test_traces, &runtime.model_checker_runtime) {
+            Ok(result) => result,
+            Err(e) => {
+                let mut output = CliOutput::with_result(Err(e));
+                output.summary = Some(summary);
+                return output;
+            }
         };
+        if let JsonValue::Object(tests) = &mut result {
+            tests.extend(
+                failures
+                    .into_iter()
+                    .map(|(test_name, e)| (test_name, json!({ "error": e }))),
+            );
+            tests.extend(
+                expected_failures
+                    .into_iter()
+                    .map(|(test_name, e)| (test_name, json!({ "xfail": e }))),
+            );
+        }
+        CliOutput::with_test_results(result, failure, summary)
+    }
 
-        let test_results = test_names
-            .iter()
-            .map(|test_name| {
-                // Create the intermediary file suite to run a single test
-                let input_artifacts =
-                    crate::model::language::Tla::generate_test(test_name, &tla_file_suite)?;
-
-                // Model check the test and collect traces
-                let mut traces = match self.model_checker {
-                    ModelChecker::Apalache => {
-                        crate::model::checker::Apalache::test(&input_artifacts, &runtime)?
-                    }
-                    ModelChecker::Tlc => {
-                        crate::model::checker::Tlc::test(&input_artifacts, &runtime)?
-                    }
-                }
-                .0; // Ignores returned stdout
-
-                traces.iter_mut().for_each(|trace| {
-                    trace.extends_module_name =
-                        Some(input_artifacts.tla_file.module_name().to_string());
-                });
+    /// Writes or converts the traces generated, returning them (or the files
+    /// written) keyed by test.
+    fn traces_output(
+        &self,
+        test_traces: Vec<(String, String, Vec<TlaTrace>)>,
+        checker_runtime: &ModelCheckerRuntime,
+    ) -> Result<JsonValue, Error> {
+        if let Some(output_dir) = &self.output_dir {
+            return self.write_to_output_dir(output_dir, test_traces, checker_runtime);
+        }
 
+        let res = test_traces
+            .into_iter()
+            .map(|(test_name, module_name, traces)| {
                 // Write each trace and get a result containing json information
-                traces
+                let results = traces
                     .into_iter()
                     .enumerate()
                     .map(|(i, trace)| {
-                        let file_name_to_write =
-                            format!("{}{}", input_artifacts.tla_file.module_name(), i);
+                        let file_name_to_write = format!("{}{}", module_name, i);
                         match self.format {
                             OutputFormat::Json => {
                                 let json_trace =
-                                    crate::model::language::Tla::tla_trace_to_json_trace(trace)?;
+                                    json_trace(&file_name_to_write, trace, checker_runtime)?;
                                 tracing::debug!(
                                     "Tla::tla_trace_to_json_trace output {}",
                                     json_trace
@@ -184,19 +366,333 @@ impl TraceCli {
                                     }))
                                 }
                             }
+                            OutputFormat::Itf | OutputFormat::Dot if self.write => {
+                                write_trace_to_file(
+                                    &file_name_to_write,
+                                    &self.format,
+                                    &self.format.convert(
+                                        &file_name_to_write,
+                                        &trace,
+                                        checker_runtime,
+                                    )?,
+                                )
+                            }
+                            OutputFormat::Itf => Ok(json!({
+                                "itf_trace_content":
+                                    json_trace(&file_name_to_write, trace, checker_runtime)?
+                                        .to_itf()?
+                            })),
+                            OutputFormat::Dot => Ok(json!({
+                                "dot_trace_content":
+                                    json_trace(&file_name_to_write, trace, checker_runtime)?
+                                        .to_dot()
+                            })),
                         }
                     })
-                    .collect::<Result<Vec<_>, Error>>()
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok((test_name, results))
+            })
+            .collect::<Result<BTreeMap<String, Vec<JsonValue>>, Error>>()?;
+
+        Ok(json!(res))
+    }
+
+    /// Returns the runtime set by the flags, then by the project
+    /// configuration file, then by the defaults.
+    fn runtime(&self) -> Result<crate::ModelatorRuntime, Error> {
+        let mut runtime = crate::ModelatorRuntime::default();
+        if let Some(config_path) = self.config_path() {
+            let config = crate::config::ModelatorConfig::from_file(&config_path)?;
+            for key in &config.unknown_keys {
+                eprintln!(
+                    "warning: unknown key `{}` in {} is ignored",
+                    key,
+                    config_path.display()
+                );
+            }
+            runtime = runtime.config(&config);
+        }
+
+        let checker_runtime = &mut runtime.model_checker_runtime;
+        if let Some(model_checker) = self.model_checker {
+            checker_runtime.model_checker = model_checker;
+        }
+        if let Some(num_traces) = self.num_traces {
+            checker_runtime.traces_per_test = num_traces;
+        }
+        if let Some(workers) = self.workers {
+            checker_runtime.workers = workers;
+        }
+        if let Some(apalache_mode) = &self.apalache_mode {
+            checker_runtime.apalache_mode = match apalache_mode {
+                ApalacheModeName::Check => ApalacheMode::Check,
+                ApalacheModeName::Simulate => ApalacheMode::Simulate {
+                    max_run: self.max_run,
+                    length: self.length,
+                },
+            };
+        }
+        checker_runtime
+            .constants
+            .extend(self.constant.iter().cloned());
+        runtime
+            .expected_failures
+            .extend(self.expect_fail.iter().cloned());
+        Ok(runtime)
+    }
+
+    /// Returns the path of the project configuration file, if any.
+    fn config_path(&self) -> Option<PathBuf> {
+        match &self.config {
+            Some(config_path) => Some(config_path.clone()),
+            None => {
+                Some(PathBuf::from(crate::config::CONFIG_FILE_NAME)).filter(|path| path.is_file())
+            }
+        }
+    }
+
+    /// Returns the paths of the TLA+ module and config file, which are only
+    /// optional when a subcommand is given.
+    fn tla_paths(&self) -> (&Path, &Path) {
+        match (&self.tla_module, &self.tla_config) {
+            (Some(tla_module), Some(tla_config)) => (tla_module, tla_config),
+            _ => panic!("[modelator] the TLA+ module and config file should be required"),
+        }
+    }
+
+    /// Reads the TLA+ module and config file, looking for the modules they
+    /// extend in the runtime's library paths.
+    fn file_suite(&self, runtime: &crate::ModelatorRuntime) -> Result<TlaFileSuite, Error> {
+        let (tla_module, tla_config) = self.tla_paths();
+        TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+            tla_module,
+            tla_config,
+            &runtime.tla_library_paths,
+        )
+    }
+
+    /// Returns the names of the tests matching the test pattern, or listed
+    /// by the project configuration file if there's no pattern.
+    fn test_names(
+        &self,
+        tla_file_suite: &TlaFileSuite,
+        runtime: &crate::ModelatorRuntime,
+    ) -> Result<Vec<String>, Error> {
+        let pattern = self.test.as_deref().unwrap_or("@all");
+        let listed = match (&self.test, &runtime.test_discovery) {
+            (None, crate::model::language::TestDiscovery::Names(names)) => Some(names),
+            _ => None,
+        };
+        let test_names: Vec<String> = crate::model::language::Tla::extract_test_names(
+            tla_file_suite.tla_file.file_contents_backing(),
+        )?
+        .into_iter()
+        .filter(|test_name| allow_test_name(test_name, pattern))
+        .filter(|test_name| listed.map_or(true, |names| names.contains(test_name)))
+        .collect();
+
+        if test_names.is_empty() {
+            return Err(Error::NoTestFound {
+                file: tla_file_suite.tla_file.path().map(Path::to_path_buf),
+                module: tla_file_suite.tla_file.module_name().to_owned(),
+                details: format!("[test pattern: {}]", pattern),
+            });
+        };
+        Ok(test_names)
+    }
+
+    /// Model checks a test, returning the name of the generated test module,
+    /// the traces found and the statistics of the run.
+    fn generate_traces(
+        &self,
+        test_name: &str,
+        tla_file_suite: &TlaFileSuite,
+        runtime: &crate::ModelatorRuntime,
+    ) -> Result<(String, Vec<TlaTrace>, CheckerStats), Error> {
+        // Create the intermediary file suite to run a single test
+        let input_artifacts =
+            crate::model::language::Tla::generate_test(test_name, tla_file_suite)?;
+
+        // Model check the test and collect traces
+        let (mut traces, stdout) = match runtime.model_checker_runtime.model_checker {
+            ModelChecker::Apalache => {
+                crate::model::checker::Apalache::test(&input_artifacts, runtime)?
+            }
+            ModelChecker::Tlc => crate::model::checker::Tlc::test(&input_artifacts, runtime)?,
+        };
+
+        let module_name = input_artifacts.tla_file.module_name().to_string();
+        traces.iter_mut().for_each(|trace| {
+            trace.extends_module_name = Some(module_name.clone());
+        });
+        Ok((module_name, traces, *stdout.stats()))
+    }
+
+    /// Writes a file `<test>_<n>.<ext>` per trace into the output directory,
+    /// and returns the written files keyed by test.
+    fn write_to_output_dir(
+        &self,
+        output_dir: &Path,
+        test_traces: Vec<(String, String, Vec<TlaTrace>)>,
+        checker_runtime: &ModelCheckerRuntime,
+    ) -> Result<JsonValue, Error> {
+        std::fs::create_dir_all(output_dir)?;
+
+        // convert all traces before writing, so that nothing is written on error
+        let files = test_traces
+            .into_iter()
+            .map(|(test_name, _, traces)| {
+                let files = traces
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, trace)| {
+                        let name = format!("{}_{}", test_name, i);
+                        let path = output_dir.join(format!("{}.{}", name, self.format.extension()));
+                        if path.exists() && !self.force {
+                            return Err(Error::FileAlreadyExists(path));
+                        }
+                        Ok((path, self.format.convert(&name, &trace, checker_runtime)?))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok((test_name, files))
             })
             .collect::<Result<Vec<_>, Error>>()?;
 
-        let res = test_names
-            .iter()
-            .cloned()
-            .zip(test_results)
-            .collect::<BTreeMap<String, Vec<JsonValue>>>();
+        let manifest = files
+            .into_iter()
+            .map(|(test_name, files)| {
+                let paths = files
+                    .into_iter()
+                    .map(|(path, content)| {
+                        std::fs::write(&path, content)?;
+                        Ok(crate::util::absolute_path(&path))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok((test_name, paths))
+            })
+            .collect::<Result<BTreeMap<String, Vec<String>>, Error>>()?;
+        Ok(json!(manifest))
+    }
+}
 
-        Ok(json!(res))
+/// Inspect traces already generated
+#[derive(Debug, Parser)]
+enum TraceCommand {
+    /// Print a trace as a table of its variables at each step.
+    Show(TraceShowCli),
+    /// Show where two traces diverge.
+    Diff(TraceDiffCli),
+}
+
+impl TraceCommand {
+    fn run(&self) -> CliOutput {
+        match self {
+            Self::Show(show_cli) => show_cli.run(),
+            Self::Diff(diff_cli) => diff_cli.run(),
+        }
+    }
+}
+
+/// Print a trace as a table of its variables at each step, highlighting the
+/// values changed by each step. The trace may be a Json array of states, an
+/// ITF trace or an Apalache counterexample `.tla` file.
+#[derive(Debug, Parser)]
+pub struct TraceShowCli {
+    /// Trace file.
+    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
+    trace: PathBuf,
+    /// Don't colorize the output.
+    #[clap(long)]
+    no_color: bool,
+}
+
+impl TraceShowCli {
+    fn run(&self) -> CliOutput {
+        match show::read_trace(&self.trace) {
+            Ok(trace) => CliOutput::with_text(show::render_table(&trace, use_color(self.no_color))),
+            Err(e) => CliOutput::with_result(Err(e)),
+        }
+    }
+}
+
+/// Show the first step where two traces diverge, and the variables that
+/// differ in it. The traces may be in any of the formats read by `trace show`.
+#[derive(Debug, Parser)]
+pub struct TraceDiffCli {
+    /// Trace file.
+    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
+    trace: PathBuf,
+    /// Trace file to compare with.
+    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
+    other_trace: PathBuf,
+    /// Don't colorize the output.
+    #[clap(long)]
+    no_color: bool,
+}
+
+impl TraceDiffCli {
+    fn run(&self) -> CliOutput {
+        match show::read_trace(&self.trace)
+            .and_then(|trace| Ok(trace.diff(&show::read_trace(&self.other_trace)?)))
+        {
+            Ok(diff) => CliOutput::with_text(show::render_diff(&diff, use_color(self.no_color))),
+            Err(e) => CliOutput::with_result(Err(e)),
+        }
+    }
+}
+
+/// Explore the states of a spec up to a depth from its initial states, with
+/// TLC, and print their graph.
+#[derive(Debug, Parser)]
+pub struct ExploreCli {
+    /// TLA+ file with the spec.
+    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
+    tla_module: PathBuf,
+    /// TLA+ config file with CONSTANTS, INIT and NEXT.
+    #[clap(parse(from_os_str), value_hint = ValueHint::FilePath)]
+    tla_config: PathBuf,
+    /// Maximum number of steps from the initial states.
+    #[clap(long, default_value = "5")]
+    depth: usize,
+    /// Maximum number of states printed, numbered breadth-first from the
+    /// initial states; the graph is truncated to them with a warning.
+    #[clap(long, default_value = "1000")]
+    max_states: usize,
+    /// output format
+    #[clap(short, long, arg_enum, default_value = "dot")]
+    format: GraphFormat,
+    /// File to write the graph to, instead of printing it.
+    #[clap(short, long, parse(from_os_str), value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+}
+
+impl ExploreCli {
+    fn run(&self) -> CliOutput {
+        let runtime = crate::ModelatorRuntime::default();
+        let graph = match runtime.state_graph(
+            &self.tla_module,
+            &self.tla_config,
+            self.depth,
+            self.max_states,
+        ) {
+            Ok(graph) => graph,
+            Err(e) => return CliOutput::with_result(Err(e)),
+        };
+        if graph.truncated() {
+            eprintln!(
+                "warning: the state graph is truncated to {} states, some states are missing",
+                self.max_states
+            );
+        }
+
+        let content = match self.format {
+            GraphFormat::Dot => graph.to_dot(),
+            GraphFormat::Json => graph.as_string(),
+        };
+        match &self.output {
+            Some(path) => CliOutput::with_result(write_parsed_file(path, &content)),
+            None => CliOutput::with_text(content),
+        }
     }
 }
 
@@ -204,21 +700,39 @@ impl TraceCli {
 enum Module {
     /// Parse TLA+ files.
     Parse(ParseCli),
-    /// Extract test names from tla file
-    List(TestListCli),
+    /// List the tests of a TLA+ file, without running a model checker.
+    #[clap(subcommand)]
+    List(ListCli),
     /// Generate TLA+ traces using model checker.
     Trace(TraceCli),
+    /// Explore the state graph of a spec.
+    Explore(ExploreCli),
 }
 
 impl Module {
-    fn run(&self) -> Result<JsonValue, Error> {
-        let runtime = crate::ModelatorRuntime::default();
-        runtime.setup()?;
+    fn run(&self) -> CliOutput {
+        let setup = || crate::ModelatorRuntime::default().setup();
 
         match self {
-            Self::Parse(parse_cli) => parse_cli.run(),
-            Self::List(testlist_cli) => testlist_cli.run(),
-            Self::Trace(trace_cli) => trace_cli.run(),
+            Self::Parse(parse_cli) => match setup() {
+                Ok(()) => parse_cli.run(),
+                Err(e) => CliOutput::with_result(Err(e)),
+            },
+            // no model checker is involved, so the jars aren't needed
+            Self::List(list_cli) => list_cli.run(),
+            // traces already generated are read without a model checker
+            Self::Trace(TraceCli {
+                command: Some(command),
+                ..
+            }) => command.run(),
+            Self::Trace(trace_cli) => match setup() {
+                Ok(()) => trace_cli.run(),
+                Err(e) => CliOutput::with_result(Err(e)),
+            },
+            Self::Explore(explore_cli) => match setup() {
+                Ok(()) => explore_cli.run(),
+                Err(e) => CliOutput::with_result(Err(e)),
+            },
         }
     }
 }
@@ -240,7 +754,7 @@ pub struct App {
 impl App {
     /// The top cli arg handler
     pub fn run(&self) -> CliOutput {
-        CliOutput::with_result(self.module.run())
+        self.module.run()
     }
 }
 
@@ -248,10 +762,102 @@ impl App {
 enum OutputFormat {
     Tla,
     Json,
+    Itf,
+    Dot,
+}
+
+impl OutputFormat {
+    const fn extension(&self) -> &'static str {
+        match self {
+            Self::Tla => "tla",
+            Self::Json => "json",
+            Self::Itf => "itf.json",
+            Self::Dot => "dot",
+        }
+    }
+
+    /// Converts a trace to this format, returning the content of the file to
+    /// write. TLA+ traces are written as a module with the given name.
+    fn convert(
+        &self,
+        name: &str,
+        trace: &TlaTrace,
+        checker_runtime: &ModelCheckerRuntime,
+    ) -> Result<String, Error> {
+        let json_trace = || json_trace(name, trace.clone(), checker_runtime);
+        match self {
+            Self::Tla => Ok(trace.to_tla_module(name)),
+            Self::Json => Ok(json_trace()?.as_string()),
+            Self::Itf => Ok(serde_json::to_string_pretty(&json_trace()?.to_itf()?)
+                .expect("[modelator] an ITF trace should be serializable")),
+            Self::Dot => Ok(json_trace()?.to_dot()),
+        }
+    }
+}
+
+/// Converts a trace to JSON, truncated to the limits of the model checker
+/// runtime, warning if it was.
+fn json_trace(
+    name: &str,
+    trace: TlaTrace,
+    checker_runtime: &ModelCheckerRuntime,
+) -> Result<JsonTrace, Error> {
+    let json_trace = crate::model::language::Tla::tla_trace_to_json_trace_with_limits(
+        trace,
+        checker_runtime.max_trace_states,
+        checker_runtime.max_state_bytes,
+    )?;
+    if let Some(omitted) = json_trace.omitted_states() {
+        eprintln!(
+            "warning: trace {} was truncated, {} states are omitted",
+            name, omitted
+        );
+    }
+    Ok(json_trace)
+}
+
+#[derive(Debug, Clone, ArgEnum)]
+enum ParseFormat {
+    Tla,
+    Json,
+}
+
+#[derive(Debug, Clone, ArgEnum)]
+enum ApalacheModeName {
+    Check,
+    Simulate,
+}
+
+#[derive(Debug, Clone, ArgEnum)]
+enum GraphFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Debug, Clone, ArgEnum)]
+enum ListFormat {
+    Text,
+    Json,
+}
+
+/// Whether to colorize the output: unless disabled by a flag or by the
+/// `NO_COLOR` environment variable.
+fn use_color(no_color: bool) -> bool {
+    !no_color && env::var_os("NO_COLOR").is_none()
+}
+
+/// Parses a `NAME=VALUE` constant assignment.
+fn parse_constant(assignment: &str) -> Result<(String, String), String> {
+    match assignment.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected NAME=VALUE, got '{}'", assignment)),
+    }
 }
 
 fn allow_test_name(test_name: &str, pattern: &str) -> bool {
-    if pattern.to_ascii_lowercase() == "@all" {
+    if pattern.eq_ignore_ascii_case("@all") {
         true
     } else {
         // TODO: add regex pattern
@@ -272,6 +878,13 @@ fn json_list_generated_tests(test_files: Vec<(PathBuf, PathBuf)>) -> Result<Json
     Ok(JsonValue::Array(json_array))
 }
 
+fn write_parsed_file(path: &Path, content: &str) -> Result<JsonValue, Error> {
+    std::fs::write(path, content)?;
+    Ok(json!({
+        "output_filepath": crate::util::absolute_path(path),
+    }))
+}
+
 fn write_parsed_tla_file_to_file(tla_file: &TlaFile) -> Result<JsonValue, Error> {
     // Apalache changes the module name in the output file so we use it directly here.
     let file_name = format!("{}.tla", tla_file.module_name());
@@ -287,7 +900,20 @@ fn write_tla_trace_to_file(test_name: &str, tla_trace: &TlaTrace) -> Result<Json
     let path = Path::new(&file_name);
     tla_trace.try_write_to_file(path)?;
     Ok(json!({
-        "tla_trace_filepath": crate::util::absolute_path(&path),
+        "tla_trace_filepath": crate::util::absolute_path(path),
+    }))
+}
+
+fn write_trace_to_file(
+    test_name: &str,
+    format: &OutputFormat,
+    content: &str,
+) -> Result<JsonValue, Error> {
+    let file_name = format!("trace_{}.{}", test_name, format.extension());
+    let path = Path::new(&file_name);
+    std::fs::write(path, content)?;
+    Ok(json!({
+        format!("{:?}_trace_filepath", format).to_lowercase(): crate::util::absolute_path(path),
     }))
 }
 
@@ -296,6 +922,95 @@ fn write_json_trace_to_file(test_name: &str, json_trace: &JsonTrace) -> Result<J
     let path = Path::new(&file_name);
     json_trace.try_write_to_file(path)?;
     Ok(json!({
-        "json_trace_filepath": crate::util::absolute_path(&path),
+        "json_trace_filepath": crate::util::absolute_path(path),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace_cli(args: &str) -> TraceCli {
+        let args = format!(
+            "trace {} tests/integration/resource/Numbers.tla tests/integration/resource/Numbers.cfg",
+            args
+        );
+        match Module::try_parse_from(std::iter::once("modelator").chain(args.split_whitespace()))
+            .unwrap()
+        {
+            Module::Trace(trace_cli) => trace_cli,
+            module => panic!("expected the trace subcommand, got {:?}", module),
+        }
+    }
+
+    #[test]
+    fn test_runtime_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join(crate::config::CONFIG_FILE_NAME);
+        std::fs::write(
+            &config_path,
+            "model_checker = \"tlc\"\ntraces_per_test = 3\nworkers = 2\n\n\
+             [constants]\nMaxNumber = 4\nMinNumber = 0\n",
+        )
+        .unwrap();
+        let config = format!("--config {}", config_path.display());
+
+        // the config file takes precedence over the defaults
+        let runtime = trace_cli(&config).runtime().unwrap();
+        let checker_runtime = &runtime.model_checker_runtime;
+        assert_eq!(checker_runtime.model_checker, ModelChecker::Tlc);
+        assert_eq!(checker_runtime.traces_per_test, 3);
+        assert_eq!(checker_runtime.workers, ModelCheckerWorkers::Count(2));
+        assert_eq!(checker_runtime.constants["MaxNumber"], "4");
+
+        // the flags take precedence over the config file
+        let runtime = trace_cli(&format!(
+            "{} -m apalache -n 1 --constant MaxNumber=2",
+            config
+        ))
+        .runtime()
+        .unwrap();
+        let checker_runtime = &runtime.model_checker_runtime;
+        assert_eq!(checker_runtime.model_checker, ModelChecker::Apalache);
+        assert_eq!(checker_runtime.traces_per_test, 1);
+        assert_eq!(checker_runtime.workers, ModelCheckerWorkers::Count(2));
+        assert_eq!(checker_runtime.constants["MaxNumber"], "2");
+        assert_eq!(checker_runtime.constants["MinNumber"], "0");
+
+        assert!(parse_constant("MaxNumber").is_err());
+    }
+
+    #[test]
+    fn test_apalache_mode() {
+        let runtime = trace_cli("").runtime().unwrap();
+        assert_eq!(
+            runtime.model_checker_runtime.apalache_mode,
+            ApalacheMode::Check
+        );
+
+        let runtime = trace_cli("--apalache-mode simulate --max-run 20")
+            .runtime()
+            .unwrap();
+        assert_eq!(
+            runtime.model_checker_runtime.apalache_mode,
+            ApalacheMode::Simulate {
+                max_run: 20,
+                length: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_expect_fail() {
+        let runtime = trace_cli("--expect-fail AMaxTest --expect-fail BMaxTest")
+            .runtime()
+            .unwrap();
+        assert_eq!(
+            runtime.expected_failures,
+            ["AMaxTest", "BMaxTest"]
+                .iter()
+                .map(|test| test.to_string())
+                .collect()
+        );
+    }
+}
@@ -1,5 +1,6 @@
 // This module is inspired by what's in:
 // https://github.com/informalsystems/ibc-rs/blob/ad827a94e5c84ceb1af764a255dd1821d7852fef/relayer-cli/src/conclude.rs
+use crate::model::checker::CheckerStats;
 use crate::Error;
 use serde::Deserialize;
 use serde::Serialize;
@@ -13,43 +14,194 @@ pub struct CliOutput {
 
     /// The result of a command.
     pub result: JsonValue,
+
+    /// The result of a command, when it's printed as plain text instead of JSON.
+    #[serde(skip)]
+    pub text: Option<String>,
+
+    /// The code the process exits with.
+    #[serde(skip)]
+    pub exit_code: ExitCode,
+
+    /// Lines summarizing the tests run, printed last to stderr: the
+    /// statistics of each test, and the number of tests passed and failed.
+    #[serde(skip)]
+    pub summary: Option<String>,
 }
 
 impl CliOutput {
     pub(crate) fn with_result(result: Result<JsonValue, Error>) -> Self {
-        let (status, result) = match result {
-            Ok(result) => (CliStatus::Success, result),
+        let (status, result, exit_code) = match result {
+            Ok(result) => (CliStatus::Success, result, ExitCode::Success),
             Err(err) => {
+                let exit_code = ExitCode::from(&err);
                 let result = match serde_json::to_value(err) {
                     Ok(json_val) => json_val,
                     Err(e) => {
                         panic!("[modelator] CLI error serialization failed: {:?}", e)
                     }
                 };
-                (CliStatus::Error, result)
+                (CliStatus::Error, result, exit_code)
             }
         };
-        Self { status, result }
+        Self {
+            status,
+            result,
+            text: None,
+            exit_code,
+            summary: None,
+        }
+    }
+
+    pub(crate) fn with_text(text: String) -> Self {
+        Self {
+            status: CliStatus::Success,
+            result: JsonValue::String(text.clone()),
+            text: Some(text),
+            exit_code: ExitCode::Success,
+            summary: None,
+        }
+    }
+
+    /// Output of tests run, given their results keyed by test, the exit code
+    /// of the most severe failure, if any, and the summary line.
+    pub(crate) fn with_test_results(
+        result: JsonValue,
+        failure: Option<ExitCode>,
+        summary: String,
+    ) -> Self {
+        Self {
+            status: match failure {
+                Some(_) => CliStatus::Error,
+                None => CliStatus::Success,
+            },
+            result,
+            text: None,
+            exit_code: failure.unwrap_or(ExitCode::Success),
+            summary: Some(summary),
+        }
     }
 
     /// Function that exits the program with a proper error code given the
     /// the [`CliOutput`].
     pub fn exit(self) {
-        let pretty = match serde_json::to_string_pretty(&self) {
-            Ok(pretty) => pretty,
-            Err(e) => panic!("[modelator] CLI output serialization failed: {:?}", e),
-        };
-        println!("{}", pretty);
+        match &self.text {
+            Some(text) => println!("{}", text),
+            None => {
+                let pretty = match serde_json::to_string_pretty(&self) {
+                    Ok(pretty) => pretty,
+                    Err(e) => panic!("[modelator] CLI output serialization failed: {:?}", e),
+                };
+                println!("{}", pretty);
+            }
+        }
+
+        if let Some(summary) = &self.summary {
+            eprintln!("{}", summary);
+        }
 
         // the return code
-        if self.status == CliStatus::Error {
-            std::process::exit(1);
-        } else {
-            std::process::exit(0);
+        let exit_code = match (self.status, self.exit_code) {
+            // an error always exits with a non-zero code
+            (CliStatus::Error, ExitCode::Success) => ExitCode::TestFailed,
+            (_, exit_code) => exit_code,
+        };
+        std::process::exit(exit_code as i32);
+    }
+}
+
+/// Exit codes of the `modelator` CLI. When several tests fail, the process
+/// exits with the code of the most severe failure, the highest one.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ExitCode {
+    /// All tests passed.
+    Success = 0,
+    /// At least one test failed: no trace was found for it, or the
+    /// property it expects to hold is violated.
+    TestFailed = 1,
+    /// The command line or the files given to it are invalid.
+    Usage = 2,
+    /// Java, the model checker jars or the file system are unavailable.
+    Environment = 3,
+    /// The model checker crashed, or its output can't be understood.
+    CheckerCrash = 4,
+}
+
+impl Default for ExitCode {
+    fn default() -> Self {
+        Self::Success
+    }
+}
+
+impl From<&Error> for ExitCode {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::NoTestTraceFound(_)
+            | Error::HoldsTestViolated { .. }
+            | Error::TestSkipped(_)
+            | Error::UnexpectedPass(_) => Self::TestFailed,
+            Error::InvalidUnicode(_)
+            | Error::MissingTlaFileModuleName(_)
+            | Error::ModuleNameMismatch { .. }
+            | Error::FileNotFound(_)
+            | Error::FileNotFoundInAny(_)
+            | Error::NoTestFound { .. }
+            | Error::TlaOperatorNameParseError { .. }
+            | Error::ApalacheTypeError { .. }
+            | Error::ApalacheParseError { .. }
+            | Error::UnrecognizedChecker(_)
+            | Error::UnsupportedOutputFormat(_)
+            | Error::UnsupportedApalacheMode { .. }
+            | Error::TraceConversion { .. }
+            | Error::FileAlreadyExists(_)
+            | Error::InvalidConfigFile { .. }
+            | Error::InvalidEnvVar { .. }
+            | Error::InvalidTlaConfig(_)
+            | Error::InvalidTraceFile { .. }
+            | Error::DryRun(_) => Self::Usage,
+            Error::IO(_)
+            | Error::MissingJava
+            | Error::MinimumJavaVersion(_, _)
+            | Error::Ureq(_)
+            | Error::Notify(_) => Self::Environment,
+            Error::InvalidTLCOutput(_)
+            | Error::TLCFailure { .. }
+            | Error::UnexpectedModelCheckerOutput { .. }
+            | Error::ApalacheFailure(_)
+            | Error::InvalidApalacheCounterexample(_)
+            | Error::Nom(_)
+            | Error::JsonParseError(_) => Self::CheckerCrash,
         }
     }
 }
 
+/// Returns the summary line of a run: `modelator: <n> tests, <n> passed,
+/// <n> failed, <n> xfailed, <n> xpassed, <n> skipped`, where the tests
+/// expected to fail are either xfailed or xpassed.
+pub(crate) fn summary(
+    passed: usize,
+    failed: usize,
+    xfailed: usize,
+    xpassed: usize,
+    skipped: usize,
+) -> String {
+    format!(
+        "modelator: {} tests, {} passed, {} failed, {} xfailed, {} xpassed, {} skipped",
+        passed + failed + xfailed + xpassed + skipped,
+        passed,
+        failed,
+        xfailed,
+        xpassed,
+        skipped
+    )
+}
+
+/// Returns the line reporting the statistics of a test: `modelator: <test>:
+/// <n> states generated, <n> distinct states, depth <n>, <duration>`.
+pub(crate) fn stats(test_name: &str, stats: &CheckerStats) -> String {
+    format!("modelator: {}: {}", test_name, stats)
+}
+
 /// Represents the exit status of any CLI command
 #[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -59,3 +211,59 @@ pub enum CliStatus {
     /// An exit status representing an error.
     Error,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let output = CliOutput::with_result(Err(Error::MissingJava));
+        assert_eq!(output.exit_code, ExitCode::Environment);
+        let output = CliOutput::with_result(Err(Error::NoTestFound {
+            file: None,
+            module: "Test".to_string(),
+            details: String::new(),
+        }));
+        assert_eq!(output.exit_code, ExitCode::Usage);
+
+        // the most severe failure wins
+        let failure = [
+            Error::NoTestTraceFound("Test.tla".into()),
+            Error::TLCFailure {
+                message: "crash".to_string(),
+                kind: Box::new(crate::model::checker::TlcErrorKind::Other { code: None }),
+                stderr: String::new(),
+            },
+            Error::TestSkipped("Test".to_string()),
+        ]
+        .iter()
+        .map(ExitCode::from)
+        .max();
+        assert_eq!(failure, Some(ExitCode::CheckerCrash));
+        let output = CliOutput::with_test_results(JsonValue::Null, failure, summary(1, 2, 1, 1, 1));
+        assert_eq!(output.status, CliStatus::Error);
+        assert_eq!(
+            output.summary.unwrap(),
+            "modelator: 6 tests, 1 passed, 2 failed, 1 xfailed, 1 xpassed, 1 skipped"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let test_stats = CheckerStats {
+            states_generated: Some(90),
+            distinct_states: Some(43),
+            depth: Some(7),
+            duration: Some(std::time::Duration::from_millis(1612)),
+        };
+        assert_eq!(
+            stats("AMaxBMinTest", &test_stats),
+            "modelator: AMaxBMinTest: 90 states generated, 43 distinct states, depth 7, 1.6s"
+        );
+        assert_eq!(
+            stats("AMaxBMinTest", &CheckerStats::default()),
+            "modelator: AMaxBMinTest: no statistics reported"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::artifact::{JsonTrace, TraceDiff};
+use crate::Error;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// ANSI escape codes of the colors used.
+const CHANGED: &str = "\x1b[1;33m";
+const REMOVED: &str = "\x1b[31m";
+const ADDED: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// Column of the states that aren't Json objects.
+const STATE_COLUMN: &str = "<state>";
+
+/// Reads a trace, detecting its format: an Apalache counterexample if the
+/// file has the `.tla` extension, otherwise an ITF trace or a Json array of
+/// states (as written by `modelator trace`).
+pub(super) fn read_trace(path: &Path) -> Result<JsonTrace, Error> {
+    let invalid = |reason: String| Error::InvalidTraceFile {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let trace = if path
+        .extension()
+        .map_or(false, |extension| extension == "tla")
+    {
+        let content = crate::util::try_read_file_contents(path)?;
+        crate::model::checker::parse_apalache_counterexample(&content)
+            .and_then(crate::model::language::Tla::tla_trace_to_json_trace)
+            .map_err(|e| invalid(e.to_string()))?
+    } else {
+        JsonTrace::read_json(path)?
+    };
+    if trace.is_empty() {
+        return Err(invalid("the trace has no state".to_owned()));
+    }
+    Ok(trace)
+}
+
+/// Renders the trace as a table with a row per step and a column per
+/// variable, highlighting the values changed by each step.
+pub(super) fn render_table(trace: &JsonTrace, color: bool) -> String {
+    let states: Vec<BTreeMap<&str, String>> = trace
+        .iter()
+        .map(|state| match state {
+            JsonValue::Object(vars) => vars
+                .iter()
+                .map(|(var, value)| (var.as_str(), value.to_string()))
+                .collect(),
+            state => std::iter::once((STATE_COLUMN, state.to_string())).collect(),
+        })
+        .collect();
+    let vars: BTreeSet<&str> = states
+        .iter()
+        .flat_map(|state| state.keys().copied())
+        .collect();
+
+    // cells of each row, along with whether they changed
+    let mut rows = vec![std::iter::once("step")
+        .chain(vars.iter().copied())
+        .map(|header| (header.to_owned(), false))
+        .collect::<Vec<_>>()];
+    for (index, state) in states.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|previous| &states[previous]);
+        rows.push(
+            std::iter::once((index.to_string(), false))
+                .chain(vars.iter().map(|var| {
+                    let value = state.get(var);
+                    let changed = previous.map_or(false, |previous| previous.get(var) != value);
+                    (value.cloned().unwrap_or_default(), changed)
+                }))
+                .collect(),
+        );
+    }
+
+    let widths: Vec<usize> = (0..=vars.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].0.chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let render_row = |row: &[(String, bool)]| {
+        row.iter()
+            .zip(&widths)
+            .map(|((cell, changed), &width)| {
+                let cell = format!("{:<width$}", cell, width = width);
+                if color && *changed {
+                    format!("{}{}{}", CHANGED, cell, RESET)
+                } else {
+                    cell
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_owned()
+    };
+    let separator = widths
+        .iter()
+        .map(|&width| "-".repeat(width))
+        .collect::<Vec<_>>()
+        .join("-+-");
+    let mut lines = vec![render_row(&rows[0]), separator];
+    lines.extend(rows[1..].iter().map(|row| render_row(row)));
+    lines.join("\n")
+}
+
+/// Renders the difference between two traces, with the values of the first
+/// trace in red and those of the second one in green.
+pub(super) fn render_diff(diff: &TraceDiff, color: bool) -> String {
+    if !color {
+        return diff.to_string();
+    }
+    let value = |value: &Option<JsonValue>, color: &str| match value {
+        Some(value) => format!("{}{}{}", color, value, RESET),
+        None => "nothing".to_owned(),
+    };
+    let header = diff
+        .to_string()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+    std::iter::once(header)
+        .chain(diff.deltas.iter().map(|delta| {
+            format!(
+                "  {}: {} -> {}",
+                delta.path,
+                value(&delta.this, REMOVED),
+                value(&delta.other, ADDED)
+            )
+        }))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_table() {
+        let trace: JsonTrace = vec![
+            json!({ "x": 0, "name": "a" }),
+            json!({ "x": 10, "name": "a" }),
+        ]
+        .into();
+        assert_eq!(
+            render_table(&trace, false),
+            "step | name | x\n\
+             -----+------+---\n\
+             0    | \"a\"  | 0\n\
+             1    | \"a\"  | 10"
+        );
+        assert_eq!(
+            render_table(&trace, true).lines().last().unwrap(),
+            format!("1    | \"a\"  | {}10{}", CHANGED, RESET)
+        );
+    }
+
+    #[test]
+    fn test_read_trace() {
+        let dir = tempfile::tempdir().unwrap();
+        let trace: JsonTrace = vec![json!({ "x": 0 }), json!({ "x": 1 })].into();
+
+        let json_path = dir.path().join("trace.json");
+        std::fs::write(&json_path, serde_json::to_string(&trace).unwrap()).unwrap();
+        assert_eq!(read_trace(&json_path).unwrap(), trace);
+
+        let itf_path = dir.path().join("trace.itf.json");
+        std::fs::write(&itf_path, trace.to_itf().unwrap().to_string()).unwrap();
+        assert_eq!(read_trace(&itf_path).unwrap(), trace);
+
+        let counterexample_path = dir.path().join("counterexample.tla");
+        std::fs::write(
+            &counterexample_path,
+            "---- MODULE counterexample ----\n\
+             (* Initial state *)\n\
+             State0 ==\n/\\ x = 0\n\n\
+             (* Transition 0 to State1 *)\n\
+             State1 ==\n/\\ x = 1\n\n\
+             InvariantViolation == x = 1\n\n\
+             ================================================================================\n",
+        )
+        .unwrap();
+        assert_eq!(read_trace(&counterexample_path).unwrap(), trace);
+    }
+}
@@ -0,0 +1,228 @@
+use super::TraceCli;
+use crate::artifact::TlaFileSuite;
+use crate::Error;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+/// Delay during which successive file system events are merged into one.
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
+/// Interval at which Ctrl-C is checked for while waiting for changes.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Result of a test in a run: the number of traces generated, or the error.
+type TestStatus = Result<usize, String>;
+
+/// Runs the tests, then re-runs them whenever the TLA+ module, its config or
+/// any of the modules it extends change, until Ctrl-C is pressed.
+/// Returns the results of the last run.
+pub(super) fn run(trace_cli: &TraceCli) -> Result<JsonValue, Error> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = interrupted.clone();
+        ctrlc::set_handler(move || interrupted.store(true, Ordering::SeqCst))
+            .map_err(|e| Error::io(e.to_string()))?;
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::watcher(tx, DEBOUNCE_DELAY)?;
+    let mut watched_dirs = BTreeSet::new();
+
+    let mut previous: Option<BTreeMap<String, TestStatus>> = None;
+    for run in 1.. {
+        // the files are read again on each run, so that changed files
+        // produce different test suites (and thus cache misses)
+        // the project configuration file is read again on each run too
+        match run_tests(trace_cli) {
+            Ok(results) => {
+                println!("{}", report(run, previous.as_ref(), &results));
+                previous = Some(results);
+            }
+            Err(e) => println!("run {}: {}", run, e),
+        }
+
+        // the modules extended may have changed
+        let watched_files = watched_files(trace_cli);
+        for file in &watched_files {
+            if let Some(dir) = file.parent() {
+                if watched_dirs.insert(dir.to_path_buf()) {
+                    // editors often replace files instead of writing them,
+                    // so their directory is watched
+                    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                }
+            }
+        }
+
+        if !wait_for_change(&rx, &watched_files, &interrupted) {
+            break;
+        }
+    }
+
+    Ok(json!(previous
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(test_name, status)| (test_name, status_name(&status)))
+        .collect::<BTreeMap<_, _>>()))
+}
+
+fn run_tests(trace_cli: &TraceCli) -> Result<BTreeMap<String, TestStatus>, Error> {
+    let runtime = trace_cli.runtime()?;
+    let tla_file_suite = trace_cli.file_suite(&runtime)?;
+    Ok(trace_cli
+        .test_names(&tla_file_suite, &runtime)?
+        .into_iter()
+        .map(|test_name| {
+            let status = trace_cli
+                .generate_traces(&test_name, &tla_file_suite, &runtime)
+                .map(|(_, traces, _)| traces.len())
+                .map_err(|e| e.to_string());
+            (test_name, status)
+        })
+        .collect())
+}
+
+/// Returns the absolute paths of the module, its config, the project
+/// configuration file and the modules the module extends, transitively.
+fn watched_files(trace_cli: &TraceCli) -> BTreeSet<PathBuf> {
+    let lib_paths = trace_cli
+        .runtime()
+        .map(|runtime| runtime.tla_library_paths)
+        .unwrap_or_default();
+    let (tla_module, tla_config) = trace_cli.tla_paths();
+    let dependencies = crate::artifact::tla_file_suite::dependency_paths(tla_module, &lib_paths)
+        .unwrap_or_default();
+    [tla_module, tla_config]
+        .iter()
+        .map(|path| path.to_path_buf())
+        .chain(trace_cli.config_path())
+        .chain(dependencies)
+        .filter_map(|path| absolute_path(&path))
+        .collect()
+}
+
+/// Returns the absolute path of a file, even if it was (temporarily) removed.
+fn absolute_path(path: &Path) -> Option<PathBuf> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Some(dir.canonicalize().ok()?.join(path.file_name()?))
+}
+
+/// Blocks until one of the files changes, returning false if interrupted.
+fn wait_for_change(
+    rx: &mpsc::Receiver<DebouncedEvent>,
+    watched_files: &BTreeSet<PathBuf>,
+    interrupted: &AtomicBool,
+) -> bool {
+    loop {
+        if interrupted.load(Ordering::SeqCst) {
+            return false;
+        }
+        let paths = match rx.recv_timeout(INTERRUPT_POLL_INTERVAL) {
+            Ok(DebouncedEvent::Create(path))
+            | Ok(DebouncedEvent::Write(path))
+            | Ok(DebouncedEvent::Remove(path)) => vec![path],
+            Ok(DebouncedEvent::Rename(from, to)) => vec![from, to],
+            Ok(DebouncedEvent::Rescan) => return true,
+            Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return false,
+        };
+        if paths.iter().any(|path| watched_files.contains(path)) {
+            // the other events of the same change are dropped
+            while rx.recv_timeout(DEBOUNCE_DELAY).is_ok() {}
+            return true;
+        }
+    }
+}
+
+const fn status_name(status: &TestStatus) -> &'static str {
+    match status {
+        Ok(_) => "passed",
+        Err(_) => "failed",
+    }
+}
+
+/// Reports the number of passed and failed tests, and the tests that are new,
+/// removed, or whose status changed since the previous run.
+fn report(
+    run: usize,
+    previous: Option<&BTreeMap<String, TestStatus>>,
+    results: &BTreeMap<String, TestStatus>,
+) -> String {
+    let failed = results.values().filter(|status| status.is_err()).count();
+    let mut report = format!(
+        "run {}: {} passed, {} failed",
+        run,
+        results.len() - failed,
+        failed
+    );
+    let describe = |status: &TestStatus| match status {
+        Ok(trace_count) => format!("passed ({} traces)", trace_count),
+        Err(e) => format!("failed ({})", e),
+    };
+    for (test_name, status) in results {
+        match previous.map(|previous| previous.get(test_name)) {
+            Some(Some(previous_status)) if previous_status.is_ok() == status.is_ok() => {}
+            Some(Some(previous_status)) => report.push_str(&format!(
+                "\n  {}: {} -> {}",
+                test_name,
+                status_name(previous_status),
+                describe(status)
+            )),
+            Some(None) => report.push_str(&format!("\n  + {}: {}", test_name, describe(status))),
+            None => report.push_str(&format!("\n  {}: {}", test_name, describe(status))),
+        }
+    }
+    for test_name in previous
+        .into_iter()
+        .flat_map(BTreeMap::keys)
+        .filter(|test_name| !results.contains_key(*test_name))
+    {
+        report.push_str(&format!("\n  - {}", test_name));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let results = |statuses: &[(&str, TestStatus)]| {
+            statuses
+                .iter()
+                .map(|(test_name, status)| (test_name.to_string(), status.clone()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let first = results(&[("ATest", Ok(1)), ("BTest", Ok(2))]);
+        assert_eq!(
+            report(1, None, &first),
+            "run 1: 2 passed, 0 failed\n  ATest: passed (1 traces)\n  BTest: passed (2 traces)"
+        );
+
+        let second = results(&[
+            ("ATest", Ok(2)),
+            ("BTest", Err("No trace found".to_string())),
+            ("CTest", Ok(1)),
+        ]);
+        assert_eq!(
+            report(2, Some(&first), &second),
+            "run 2: 2 passed, 1 failed\n  \
+             BTest: passed -> failed (No trace found)\n  \
+             + CTest: passed (1 traces)"
+        );
+
+        let third = results(&[("ATest", Ok(2)), ("BTest", Ok(1))]);
+        assert_eq!(
+            report(3, Some(&second), &third),
+            "run 3: 2 passed, 0 failed\n  BTest: failed -> passed (1 traces)\n  - CTest"
+        );
+    }
+}
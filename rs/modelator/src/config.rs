@@ -0,0 +1,247 @@
+use crate::model::checker::{ModelChecker, ModelCheckerWorkers};
+use crate::Error;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml::Value as TomlValue;
+
+/// Name of the project configuration file read by the CLI from its working
+/// directory.
+pub const CONFIG_FILE_NAME: &str = "modelator.toml";
+
+/// Options set by a `modelator.toml` project configuration file, e.g.
+///
+/// ```toml
+/// model_checker = "tlc"
+/// workers = "auto"
+/// traces_per_test = 2
+/// jvm_args = ["-Xss16M"]
+/// tla_library_paths = ["specs/lib"]
+/// tests = ["AMaxBMinTest"]
+///
+/// [constants]
+/// MaxNumber = 2
+/// Names = '{"a", "b"}'
+/// ```
+///
+/// The options that aren't set leave the defaults in place.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModelatorConfig {
+    /// Which model checker to use.
+    pub model_checker: Option<ModelChecker>,
+
+    /// Number of model checker worker threads: `"auto"` or a number.
+    pub workers: Option<ModelCheckerWorkers>,
+
+    /// The maximum number of traces to try to generate for a single test.
+    pub traces_per_test: Option<usize>,
+
+    /// Arguments passed to the JVM running the model checker.
+    pub jvm_args: Option<Vec<String>>,
+
+    /// Directories where to look for extended TLA+ modules, relative to the
+    /// directory of the configuration file.
+    pub tla_library_paths: Option<Vec<PathBuf>>,
+
+    /// Names of the test operators; the other operators aren't tests.
+    pub tests: Option<Vec<String>>,
+
+    /// TLA+ values assigned to constants, overriding the TLA+ config files.
+    /// Numbers and booleans are converted to TLA+, strings are TLA+ expressions.
+    pub constants: BTreeMap<String, String>,
+
+    /// Keys of the file that aren't options, and are thus ignored.
+    pub unknown_keys: Vec<String>,
+}
+
+impl ModelatorConfig {
+    /// Read a project configuration file. Unknown keys are listed in
+    /// [`ModelatorConfig::unknown_keys`], for the caller to report.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        crate::util::check_file_existence(path)?;
+        let content = std::fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        parse(&content, dir).map_err(|reason| Error::InvalidConfigFile {
+            path: path.to_path_buf(),
+            reason,
+        })
+    }
+}
+
+/// Parses the content of a configuration file; relative paths are relative to `dir`.
+fn parse(content: &str, dir: &Path) -> Result<ModelatorConfig, String> {
+    let table = match content.parse::<TomlValue>().map_err(|e| e.to_string())? {
+        TomlValue::Table(table) => table,
+        _ => return Err("expected a table".to_string()),
+    };
+
+    let mut config = ModelatorConfig::default();
+    for (key, value) in table {
+        match key.as_str() {
+            "model_checker" => {
+                config.model_checker =
+                    Some(ModelChecker::from_str(string(&key, &value)?).map_err(|e| e.to_string())?);
+            }
+            "workers" => {
+                let workers = match &value {
+                    TomlValue::Integer(count) => count.to_string(),
+                    value => string(&key, value)?.to_string(),
+                };
+                config.workers = Some(ModelCheckerWorkers::from_str(&workers)?);
+            }
+            "traces_per_test" => match value {
+                TomlValue::Integer(n) if n > 0 => config.traces_per_test = Some(n as usize),
+                _ => return Err(format!("`{}` should be a positive integer", key)),
+            },
+            "jvm_args" => config.jvm_args = Some(strings(&key, &value)?),
+            "tla_library_paths" => {
+                config.tla_library_paths = Some(
+                    strings(&key, &value)?
+                        .into_iter()
+                        .map(|path| dir.join(path))
+                        .collect(),
+                );
+            }
+            "tests" => config.tests = Some(strings(&key, &value)?),
+            "constants" => {
+                let constants = match value {
+                    TomlValue::Table(constants) => constants,
+                    _ => return Err(format!("`{}` should be a table", key)),
+                };
+                for (name, value) in constants {
+                    let value = match value {
+                        TomlValue::String(value) => value,
+                        TomlValue::Integer(value) => value.to_string(),
+                        TomlValue::Boolean(value) => value.to_string().to_uppercase(),
+                        _ => {
+                            return Err(format!(
+                                "constant `{}` should be a number, a boolean or a string",
+                                name
+                            ))
+                        }
+                    };
+                    config.constants.insert(name, value);
+                }
+            }
+            _ => config.unknown_keys.push(key),
+        }
+    }
+    Ok(config)
+}
+
+fn string<'a>(key: &str, value: &'a TomlValue) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("`{}` should be a string", key))
+}
+
+fn strings(key: &str, value: &TomlValue) -> Result<Vec<String>, String> {
+    value
+        .as_array()
+        .and_then(|values| {
+            values
+                .iter()
+                .map(|value| value.as_str().map(str::to_string))
+                .collect()
+        })
+        .ok_or_else(|| format!("`{}` should be an array of strings", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::language::TestDiscovery;
+    use crate::ModelatorRuntime;
+
+    #[test]
+    fn test_minimal_config() {
+        let config = parse("", Path::new("")).unwrap();
+        assert_eq!(config, ModelatorConfig::default());
+
+        // the defaults are left in place
+        let runtime = ModelatorRuntime::default().config(&config);
+        let default = ModelatorRuntime::default();
+        assert_eq!(
+            runtime.model_checker_runtime.model_checker,
+            default.model_checker_runtime.model_checker
+        );
+        assert_eq!(runtime.test_discovery, default.test_discovery);
+        assert!(runtime.model_checker_runtime.constants.is_empty());
+    }
+
+    #[test]
+    fn test_maximal_config() {
+        let content = r#"
+model_checker = "tlc"
+workers = 4
+traces_per_test = 2
+jvm_args = ["-Xss16M"]
+tla_library_paths = ["lib"]
+tests = ["AMaxBMinTest"]
+checker = "apalache"
+
+[constants]
+MaxNumber = 2
+Enabled = true
+Names = '{"a", "b"}'
+"#;
+        let config = parse(content, Path::new("specs")).unwrap();
+        assert_eq!(
+            config,
+            ModelatorConfig {
+                model_checker: Some(ModelChecker::Tlc),
+                workers: Some(ModelCheckerWorkers::Count(4)),
+                traces_per_test: Some(2),
+                jvm_args: Some(vec!["-Xss16M".to_string()]),
+                tla_library_paths: Some(vec![Path::new("specs").join("lib")]),
+                tests: Some(vec!["AMaxBMinTest".to_string()]),
+                constants: [
+                    ("MaxNumber", "2"),
+                    ("Enabled", "TRUE"),
+                    ("Names", r#"{"a", "b"}"#)
+                ]
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+                unknown_keys: vec!["checker".to_string()],
+            }
+        );
+
+        let runtime = ModelatorRuntime::default().config(&config);
+        let checker_runtime = &runtime.model_checker_runtime;
+        assert_eq!(checker_runtime.model_checker, ModelChecker::Tlc);
+        assert_eq!(checker_runtime.workers, ModelCheckerWorkers::Count(4));
+        assert_eq!(checker_runtime.traces_per_test, 2);
+        assert_eq!(checker_runtime.jvm_args, vec!["-Xss16M"]);
+        assert_eq!(checker_runtime.constants, config.constants);
+        assert_eq!(runtime.tla_library_paths, vec![Path::new("specs/lib")]);
+        assert_eq!(
+            runtime.test_discovery,
+            TestDiscovery::Names(vec!["AMaxBMinTest".to_string()])
+        );
+
+        assert!(parse("traces_per_test = 0", Path::new("")).is_err());
+        assert!(parse("model_checker = \"nusmv\"", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_from_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "model_checker = \"tlc\"\ntraces_per_test = 3\n").unwrap();
+
+        let runtime = ModelatorRuntime::from_config_file(&path).unwrap();
+        assert_eq!(
+            runtime.model_checker_runtime.model_checker,
+            ModelChecker::Tlc
+        );
+        assert_eq!(runtime.model_checker_runtime.traces_per_test, 3);
+
+        std::fs::write(&path, "traces_per_test = \"3\"\n").unwrap();
+        assert!(matches!(
+            ModelatorRuntime::from_config_file(&path),
+            Err(Error::InvalidConfigFile { .. })
+        ));
+    }
+}
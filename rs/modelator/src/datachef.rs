@@ -1,5 +1,7 @@
 use std::any::{type_name, Any, TypeId};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::Hash;
 
 /// Recipe describes how a set of data structures can be produced from
 /// the set of ingredients (other data structures).
@@ -19,14 +21,14 @@ use std::collections::BTreeMap;
 ///     pub landline: Phone,
 ///     pub mobile: Phone,
 /// }
-
+///
 /// pub struct Address {
 ///     pub postal_code: u32,
 ///     pub city: String,
 ///     pub street: String,
 ///     pub door: u32,
 /// }
-
+///
 /// pub struct Phone {
 ///     pub area_code: u32,
 ///     pub number: u32,
@@ -137,6 +139,29 @@ use std::collections::BTreeMap;
 /// });
 /// test_phone(&r); // tests mobile phone
 /// ```
+///
+/// Conversions that may fail, e.g. from an arbitrary string to an address
+/// of a fixed size, are added with [`try_add()`](Recipe::try_add) and applied
+/// with [`try_make()`](Recipe::try_make), which returns a [`CookError`]
+/// locating the failure in the structure being made:
+/// ```ignore
+/// r.try_add(|_, address: String| match address.len() {
+///     20 => Ok(Address(address.into_bytes())),
+///     len => Err(CookError::new::<String, Address>(format!("{} bytes instead of 20", len))),
+/// });
+/// r.try_add(|r, tx: AbstractTransaction| {
+///     Ok(Transaction {
+///         sender: r.try_make(tx.sender).map_err(|e| e.at("sender"))?,
+///     })
+/// });
+/// // fails with: cannot make Address from String at Transaction.sender: 5 bytes instead of 20
+/// let tx: Result<Transaction, CookError> = r.try_make(AbstractTransaction { sender: "alice".to_string() });
+/// ```
+///
+/// Conversions that depend on ambient data, e.g. the keys of the nodes of
+/// a model, get it from a context inserted in the recipe with
+/// [`insert_context()`](Recipe::insert_context), instead of capturing it:
+/// see [`put_with_context()`](Recipe::put_with_context).
 
 #[derive(Debug)]
 pub struct Recipe {
@@ -146,8 +171,13 @@ pub struct Recipe {
     // TODO get rid of those by redirecting default -> convert with From == ()
     defaults: BTreeMap<TypeId, Box<dyn Any>>,
     named_defaults: BTreeMap<(String, TypeId), Box<dyn Any>>,
+
+    contexts: BTreeMap<TypeId, Box<dyn Any>>,
 }
 
+/// A conversion, as stored by a [`Recipe`].
+type Converter<From, To> = Box<dyn Fn(&Recipe, From) -> Result<To, CookError>>;
+
 impl Default for Recipe {
     fn default() -> Self {
         Self::new()
@@ -164,6 +194,8 @@ impl Recipe {
             // TODO: get rid of those by instead redirecting default -> convert with From == ()
             defaults: BTreeMap::new(),
             named_defaults: BTreeMap::new(),
+
+            contexts: BTreeMap::new(),
         }
     }
 
@@ -174,6 +206,19 @@ impl Recipe {
         From: Sized + Any,
         To: Sized + Any,
     {
+        let converter: Converter<From, To> = Box::new(move |r, x| Ok(converter(r, x)));
+        let type_ids = (TypeId::of::<From>(), TypeId::of::<To>());
+        self.converts.insert(type_ids, Box::new(converter));
+    }
+
+    /// Add fallible conversion from From into To.
+    /// Use [`try_make()`](Recipe::try_make) to apply the conversion.
+    pub fn try_add<From, To>(&mut self, converter: fn(&Self, From) -> Result<To, CookError>)
+    where
+        From: Sized + Any,
+        To: Sized + Any,
+    {
+        let converter: Converter<From, To> = Box::new(converter);
         let type_ids = (TypeId::of::<From>(), TypeId::of::<To>());
         self.converts.insert(type_ids, Box::new(converter));
     }
@@ -189,6 +234,77 @@ impl Recipe {
         self.named_converts.insert(type_ids, Box::new(converter));
     }
 
+    /// Add conversion from From into To, which gets the context of type
+    /// Context inserted in the recipe with [`insert_context()`](Recipe::insert_context).
+    /// The conversion is applied like the ones added with [`add()`](Recipe::add),
+    /// also by nested and derived conversions, and fails if the recipe
+    /// has no such context.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use modelator::artifact::JsonTrace;
+    /// use modelator::{ingredient, Recipe};
+    /// use std::collections::BTreeMap;
+    ///
+    /// struct Keyring(BTreeMap<String, [u8; 4]>);
+    /// struct ValidatorName(String);
+    /// #[derive(Debug, PartialEq)]
+    /// struct SigningKey([u8; 4]);
+    /// ingredient!(ValidatorName => SigningKey);
+    ///
+    /// let mut r = Recipe::new();
+    /// r.insert_context(Keyring(
+    ///     vec![("alice".to_owned(), [1; 4]), ("bob".to_owned(), [2; 4])]
+    ///         .into_iter()
+    ///         .collect(),
+    /// ));
+    /// r.put_with_context(|_, keyring: &Keyring, name: ValidatorName| {
+    ///     SigningKey(keyring.0[&name.0])
+    /// });
+    ///
+    /// let trace: JsonTrace = vec![serde_json::json!({ "validators": ["bob", "alice"] })].into();
+    /// for state in trace {
+    ///     let names = state["validators"].as_array().unwrap().iter();
+    ///     let names: Vec<_> = names
+    ///         .map(|name| ValidatorName(name.as_str().unwrap().to_owned()))
+    ///         .collect();
+    ///     let keys: Vec<SigningKey> = r.cook(names);
+    ///     assert_eq!(keys, vec![SigningKey([2; 4]), SigningKey([1; 4])]);
+    /// }
+    /// ```
+    pub fn put_with_context<Context, From, To>(
+        &mut self,
+        converter: fn(&Self, &Context, From) -> To,
+    ) where
+        Context: Sized + Any,
+        From: Sized + Any,
+        To: Sized + Any,
+    {
+        let converter: Converter<From, To> = Box::new(move |r, x| match r.context::<Context>() {
+            Some(context) => Ok(converter(r, context, x)),
+            None => Err(CookError::new::<From, To>(format!(
+                "missing context {}",
+                short_type_name(type_name::<Context>())
+            ))),
+        });
+        let type_ids = (TypeId::of::<From>(), TypeId::of::<To>());
+        self.converts.insert(type_ids, Box::new(converter));
+    }
+
+    /// Insert the context of type C, replacing the previous one.
+    /// Use [`context()`](Recipe::context) to retrieve it.
+    pub fn insert_context<C: Sized + Any>(&mut self, context: C) {
+        self.contexts.insert(TypeId::of::<C>(), Box::new(context));
+    }
+
+    /// Get the context of type C, if inserted.
+    pub fn context<C: Sized + Any>(&self) -> Option<&C> {
+        self.contexts
+            .get(&TypeId::of::<C>())
+            .and_then(|context| context.downcast_ref::<C>())
+    }
+
     /// Put default value for type T.
     /// Use [`take()`](Recipe::take) to retrieve the default.
     pub fn put<T: Sized + Any>(&mut self, default: fn(&Self) -> T) {
@@ -205,19 +321,29 @@ impl Recipe {
     }
 
     /// Makes from From a To, applying a previously defined conversion.
+    ///
+    /// Panics if the conversion is undefined or fails: use
+    /// [`try_make()`](Recipe::try_make) to handle these cases.
     pub fn make<From, To>(&self, x: From) -> To
     where
         From: Sized + Any,
         To: Sized + Any,
     {
-        match self.get::<From, To>() {
-            Some(f) => f(x),
-            None => panic!(
-                "Undefined conversion from {:?} to {:?}",
-                type_name::<From>(),
-                type_name::<To>()
-            ),
-        }
+        self.try_make(x).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Makes from From a To, applying a previously defined conversion,
+    /// possibly fallible.
+    ///
+    /// Returns a [`CookError`] if the conversion is undefined or fails,
+    /// also in a nested conversion.
+    pub fn try_make<From, To>(&self, x: From) -> Result<To, CookError>
+    where
+        From: Sized + Any,
+        To: Sized + Any,
+    {
+        self.try_make_explicit(x)
+            .unwrap_or_else(|_| Err(CookError::new::<From, To>("undefined conversion")))
     }
 
     /// Makes from From a To, applying a previously defined named conversion
@@ -237,6 +363,27 @@ impl Recipe {
         }
     }
 
+    /// Cooks from From a To, applying a previously defined conversion, or
+    /// the conversion derived from the conversions of their elements if From
+    /// and To are containers (see [`Ingredient`]).
+    pub fn cook<From, To>(&self, x: From) -> To
+    where
+        From: Ingredient<To>,
+        To: Sized + Any,
+    {
+        x.cook(self)
+    }
+
+    /// Same as [`cook()`](Recipe::cook), but returns a [`CookError`] instead
+    /// of panicking if a conversion is undefined or fails.
+    pub fn try_cook<From, To>(&self, x: From) -> Result<To, CookError>
+    where
+        From: Ingredient<To>,
+        To: Sized + Any,
+    {
+        x.try_cook(self)
+    }
+
     /// Take default value of type T.
     pub fn take<T: Sized + Any>(&self) -> T {
         match self.get_default::<T>() {
@@ -257,16 +404,22 @@ impl Recipe {
         }
     }
 
-    fn get<From, To>(&self) -> Option<Box<dyn Fn(From) -> To + '_>>
+    /// Makes from From a To if a conversion was defined, otherwise returns
+    /// x back. The failures of the conversion are located in To.
+    fn try_make_explicit<From, To>(&self, x: From) -> Result<Result<To, CookError>, From>
     where
         From: Sized + Any,
         To: Sized + Any,
     {
         let type_ids = (TypeId::of::<From>(), TypeId::of::<To>());
-        self.converts.get(&type_ids).and_then(|f| {
-            f.downcast_ref::<fn(&Self, From) -> To>()
-                .map(|f| Box::new(move |x: From| f(self, x)) as Box<dyn Fn(From) -> To>)
-        })
+        match self
+            .converts
+            .get(&type_ids)
+            .and_then(|f| f.downcast_ref::<Converter<From, To>>())
+        {
+            Some(f) => Ok(f(self, x).map_err(CookError::within::<To>)),
+            None => Err(x),
+        }
     }
 
     fn get_as<From, To>(&self, name: &str) -> Option<Box<dyn Fn(From) -> To + '_>>
@@ -300,6 +453,247 @@ impl Recipe {
     }
 }
 
+/// A type that can be cooked into `To` with a [`Recipe`], via
+/// [`Recipe::cook`] or [`Recipe::try_cook`].
+///
+/// Conversions of containers are derived from the conversions of their
+/// elements: if `A` is an `Ingredient<C>`, then `Vec<A>` is an
+/// `Ingredient<Vec<C>>`, and likewise for `Option`, `BTreeMap` and `HashMap`
+/// values, and tuples of up to 4 elements. Derived conversions nest, e.g.
+/// `Vec<Option<A>>` is an `Ingredient<Vec<Option<C>>>`. The failure of the
+/// conversion of an element is located by its index (or key) in the
+/// container, e.g. `[3]`.
+///
+/// A conversion explicitly added to the recipe always takes precedence over
+/// the derived one: e.g. if a conversion from `Vec<A>` to `Vec<C>` was added
+/// with [`Recipe::add`], it's used instead of converting each element. This
+/// holds at every level of nesting.
+///
+/// Implement it for the types of your recipe with [`ingredient!`](crate::ingredient),
+/// which delegates to the conversion added with [`Recipe::add`] or
+/// [`Recipe::try_add`].
+pub trait Ingredient<To: Any>: Sized + Any {
+    /// Cook the ingredient into `To`, or return why it can't be.
+    fn try_cook(self, recipe: &Recipe) -> Result<To, CookError>;
+
+    /// Cook the ingredient into `To`, panicking if it can't be.
+    fn cook(self, recipe: &Recipe) -> To {
+        self.try_cook(recipe).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+/// Implement [`Ingredient`] for the given conversions, applying the
+/// conversion added to the [`Recipe`] with [`Recipe::add`] or
+/// [`Recipe::try_add`].
+///
+/// ```
+/// use modelator::{ingredient, Recipe};
+///
+/// struct AbstractPhone(u32);
+/// struct Phone(String);
+/// ingredient!(AbstractPhone => Phone);
+///
+/// let mut r = Recipe::new();
+/// r.add(|_, phone: AbstractPhone| Phone(format!("+{}", phone.0)));
+/// let phones: Vec<Option<Phone>> = r.cook(vec![Some(AbstractPhone(49)), None]);
+/// assert_eq!(phones[0].as_ref().unwrap().0, "+49");
+/// ```
+#[macro_export]
+macro_rules! ingredient {
+    ($($from:ty => $to:ty),+ $(,)?) => {
+        $(
+            impl $crate::datachef::Ingredient<$to> for $from {
+                fn try_cook(
+                    self,
+                    recipe: &$crate::Recipe,
+                ) -> Result<$to, $crate::datachef::CookError> {
+                    recipe.try_make(self)
+                }
+            }
+        )+
+    };
+}
+
+impl<A, C> Ingredient<Vec<C>> for Vec<A>
+where
+    A: Ingredient<C>,
+    C: Any,
+{
+    fn try_cook(self, recipe: &Recipe) -> Result<Vec<C>, CookError> {
+        recipe.try_make_explicit(self).unwrap_or_else(|values| {
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    value
+                        .try_cook(recipe)
+                        .map_err(|e| e.at(&format!("[{}]", index)))
+                })
+                .collect()
+        })
+    }
+}
+
+impl<A, C> Ingredient<Option<C>> for Option<A>
+where
+    A: Ingredient<C>,
+    C: Any,
+{
+    fn try_cook(self, recipe: &Recipe) -> Result<Option<C>, CookError> {
+        recipe
+            .try_make_explicit(self)
+            .unwrap_or_else(|value| value.map(|value| value.try_cook(recipe)).transpose())
+    }
+}
+
+impl<K, A, C> Ingredient<BTreeMap<K, C>> for BTreeMap<K, A>
+where
+    K: Ord + Debug + Any,
+    A: Ingredient<C>,
+    C: Any,
+{
+    fn try_cook(self, recipe: &Recipe) -> Result<BTreeMap<K, C>, CookError> {
+        recipe.try_make_explicit(self).unwrap_or_else(|map| {
+            map.into_iter()
+                .map(|(key, value)| match value.try_cook(recipe) {
+                    Ok(value) => Ok((key, value)),
+                    Err(e) => Err(e.at(&format!("[{:?}]", key))),
+                })
+                .collect()
+        })
+    }
+}
+
+impl<K, A, C> Ingredient<HashMap<K, C>> for HashMap<K, A>
+where
+    K: Eq + Hash + Debug + Any,
+    A: Ingredient<C>,
+    C: Any,
+{
+    fn try_cook(self, recipe: &Recipe) -> Result<HashMap<K, C>, CookError> {
+        recipe.try_make_explicit(self).unwrap_or_else(|map| {
+            map.into_iter()
+                .map(|(key, value)| match value.try_cook(recipe) {
+                    Ok(value) => Ok((key, value)),
+                    Err(e) => Err(e.at(&format!("[{:?}]", key))),
+                })
+                .collect()
+        })
+    }
+}
+
+macro_rules! tuple_ingredient {
+    ($($from:ident => $to:ident: $index:tt),+) => {
+        impl<$($from, $to),+> Ingredient<($($to,)+)> for ($($from,)+)
+        where
+            $($from: Ingredient<$to>, $to: Any),+
+        {
+            fn try_cook(self, recipe: &Recipe) -> Result<($($to,)+), CookError> {
+                recipe.try_make_explicit(self).unwrap_or_else(|values| {
+                    Ok(($(
+                        values
+                            .$index
+                            .try_cook(recipe)
+                            .map_err(|e| e.at(stringify!($index)))?,
+                    )+))
+                })
+            }
+        }
+    };
+}
+
+tuple_ingredient!(A1 => C1: 0);
+tuple_ingredient!(A1 => C1: 0, A2 => C2: 1);
+tuple_ingredient!(A1 => C1: 0, A2 => C2: 1, A3 => C3: 2);
+tuple_ingredient!(A1 => C1: 0, A2 => C2: 1, A3 => C3: 2, A4 => C4: 3);
+
+/// The failure of a conversion of a [`Recipe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookError {
+    /// Name of the type converted from.
+    pub from: &'static str,
+    /// Name of the type converted into.
+    pub to: &'static str,
+    /// Why the conversion failed.
+    pub message: String,
+    /// Name of the outermost type being made when the conversion failed.
+    pub root: &'static str,
+    /// Location of the failed conversion in the outermost type, from the
+    /// outside in: field names and indices such as `[3]`.
+    pub path: Vec<String>,
+}
+
+impl CookError {
+    /// Create the error of a conversion from From into To.
+    pub fn new<From: Any, To: Any>(message: impl Into<String>) -> Self {
+        Self {
+            from: type_name::<From>(),
+            to: type_name::<To>(),
+            message: message.into(),
+            root: type_name::<To>(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Locate the failure in a field (e.g. `sender`) or at an index (e.g.
+    /// `[3]`) of the type being made.
+    pub fn at(mut self, segment: &str) -> Self {
+        self.path.insert(0, segment.to_owned());
+        self
+    }
+
+    /// Locate the failure in a value of type T.
+    fn within<T: Any>(mut self) -> Self {
+        self.root = type_name::<T>();
+        self
+    }
+}
+
+impl std::fmt::Display for CookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cannot make {} from {}",
+            short_type_name(self.to),
+            short_type_name(self.from)
+        )?;
+        if !self.path.is_empty() {
+            write!(f, " at {}", short_type_name(self.root))?;
+            for segment in &self.path {
+                match segment.starts_with('[') {
+                    true => write!(f, "{}", segment)?,
+                    false => write!(f, ".{}", segment)?,
+                }
+            }
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for CookError {}
+
+/// Removes the module paths from a type name, e.g.
+/// `alloc::vec::Vec<my_crate::Block>` becomes `Vec<Block>`.
+fn short_type_name(type_name: &str) -> String {
+    let mut short = String::new();
+    let mut segment = String::new();
+    let mut chars = type_name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            short.push_str(&segment);
+            segment.clear();
+            short.push(c);
+        }
+    }
+    short.push_str(&segment);
+    short
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -468,4 +862,197 @@ mod tests {
 
         assert_eq!(block, expected);
     }
+
+    #[derive(Debug, PartialEq)]
+    struct AbstractAccount(u8);
+
+    #[derive(Debug, PartialEq)]
+    struct Account(String);
+
+    crate::ingredient!(AbstractAccount => Account);
+
+    #[test]
+    fn test_derived_conversions() {
+        let mut r = Recipe::new();
+        r.add(|_, account: AbstractAccount| Account(format!("account{}", account.0)));
+        let account = |name: &str| Account(name.to_string());
+
+        let accounts: Vec<Option<Account>> = r.cook(vec![
+            Some(AbstractAccount(1)),
+            None,
+            Some(AbstractAccount(2)),
+        ]);
+        assert_eq!(
+            accounts,
+            vec![Some(account("account1")), None, Some(account("account2"))]
+        );
+
+        let balances: HashMap<u64, (Account, Vec<Account>)> = r.cook(
+            vec![(7, (AbstractAccount(1), vec![AbstractAccount(2)]))]
+                .into_iter()
+                .collect::<HashMap<_, _>>(),
+        );
+        assert_eq!(
+            balances[&7],
+            (account("account1"), vec![account("account2")])
+        );
+
+        let tuple: (
+            Account,
+            Option<Account>,
+            Vec<Account>,
+            BTreeMap<u8, Account>,
+        ) = r.cook((
+            AbstractAccount(1),
+            None::<AbstractAccount>,
+            Vec::<AbstractAccount>::new(),
+            std::iter::once((0, AbstractAccount(3))).collect::<BTreeMap<_, _>>(),
+        ));
+        assert_eq!(tuple.0, account("account1"));
+        assert_eq!(tuple.3[&0], account("account3"));
+
+        // an explicit conversion beats the derived one, also when nested
+        r.add(|_, accounts: Vec<AbstractAccount>| {
+            vec![Account(format!("{} accounts", accounts.len()))]
+        });
+        let accounts: Vec<Account> = r.cook(vec![AbstractAccount(1), AbstractAccount(2)]);
+        assert_eq!(accounts, vec![account("2 accounts")]);
+        let accounts: Option<Vec<Account>> = r.cook(Some(vec![AbstractAccount(1)]));
+        assert_eq!(accounts, Some(vec![account("1 accounts")]));
+        let accounts: Vec<Option<Account>> = r.cook(vec![Some(AbstractAccount(1))]);
+        assert_eq!(accounts, vec![Some(account("account1"))]);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct ChainAddress([u8; 20]);
+
+    #[derive(Debug, PartialEq)]
+    struct AbstractTransaction {
+        sender: String,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Transaction {
+        sender: ChainAddress,
+    }
+
+    crate::ingredient!(AbstractTransaction => Transaction);
+
+    #[derive(Debug, PartialEq)]
+    struct AbstractChainBlock {
+        transactions: Vec<AbstractTransaction>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct ChainBlock {
+        transactions: Vec<Transaction>,
+    }
+
+    #[test]
+    fn test_try_make() {
+        let mut r = Recipe::new();
+        r.try_add(|_, address: String| {
+            let mut bytes = [0; 20];
+            if address.len() != bytes.len() {
+                return Err(CookError::new::<String, ChainAddress>(format!(
+                    "{} bytes instead of 20",
+                    address.len()
+                )));
+            }
+            bytes.copy_from_slice(address.as_bytes());
+            Ok(ChainAddress(bytes))
+        });
+        r.try_add(|r, tx: AbstractTransaction| {
+            Ok(Transaction {
+                sender: r.try_make(tx.sender).map_err(|e| e.at("sender"))?,
+            })
+        });
+        r.try_add(|r, block: AbstractChainBlock| {
+            Ok(ChainBlock {
+                transactions: r
+                    .try_cook(block.transactions)
+                    .map_err(|e| e.at("transactions"))?,
+            })
+        });
+
+        let block = |senders: &[&str]| AbstractChainBlock {
+            transactions: senders
+                .iter()
+                .map(|sender| AbstractTransaction {
+                    sender: sender.to_string(),
+                })
+                .collect(),
+        };
+        let alice = "alice_______________";
+        let made: ChainBlock = r.make(block(&[alice]));
+        assert_eq!(made.transactions[0].sender.0, *alice.as_bytes());
+
+        let e = r
+            .try_make::<_, ChainBlock>(block(&[alice, alice, alice, "bob"]))
+            .unwrap_err();
+        assert_eq!(e.path, vec!["transactions", "[3]", "sender"]);
+        assert_eq!(
+            e.to_string(),
+            "cannot make ChainAddress from String at ChainBlock.transactions[3].sender: \
+             3 bytes instead of 20"
+        );
+
+        let e = r.try_make::<_, Transaction>(1_u8).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "cannot make Transaction from u8: undefined conversion"
+        );
+        assert_eq!(
+            short_type_name("alloc::vec::Vec<core::option::Option<a::B>>"),
+            "Vec<Option<B>>"
+        );
+    }
+
+    struct ChainId(u32);
+
+    struct NodeName(String);
+
+    #[derive(Debug, PartialEq)]
+    struct NodeId(String);
+
+    crate::ingredient!(NodeName => NodeId);
+
+    #[derive(Debug, PartialEq)]
+    struct Validators {
+        nodes: Vec<NodeId>,
+    }
+
+    #[test]
+    fn test_context() {
+        let mut r = Recipe::new();
+        r.put_with_context(|_, chain_id: &ChainId, name: NodeName| {
+            NodeId(format!("{}@{}", name.0, chain_id.0))
+        });
+        r.add(|r, names: Vec<&str>| Validators {
+            nodes: r.cook(
+                names
+                    .into_iter()
+                    .map(|name| NodeName(name.to_owned()))
+                    .collect::<Vec<_>>(),
+            ),
+        });
+
+        let e = r
+            .try_make::<_, NodeId>(NodeName("alice".to_owned()))
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "cannot make NodeId from NodeName: missing context ChainId"
+        );
+
+        r.insert_context(ChainId(1));
+        r.insert_context(ChainId(2));
+        assert_eq!(r.context::<ChainId>().unwrap().0, 2);
+        assert!(r.context::<String>().is_none());
+        let validators: Validators = r.make(vec!["alice", "bob"]);
+        assert_eq!(
+            validators.nodes,
+            vec![NodeId("alice@2".to_owned()), NodeId("bob@2".to_owned())]
+        );
+    }
 }
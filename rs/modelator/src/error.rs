@@ -1,16 +1,24 @@
-use serde::Serialize;
-use std::fmt::Debug;
+use serde::{Serialize, Serializer};
+use serde_json::Value as JsonValue;
+use std::fmt::{Debug, Display};
+use std::sync::Arc;
 use thiserror::Error;
 
-use crate::model::checker::ApalacheError;
+use crate::artifact::JsonTrace;
+use crate::execution_log::ExecutionLog;
+use crate::model::checker::{ApalacheError, TlcErrorKind};
 
 /// Set of possible errors that can occur when running `modelator`.
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Error, Debug, Serialize)]
+#[derive(Error, Debug, Clone, Serialize)]
 pub enum Error {
     /// An error that occurs when there's an IO error.
     #[error("IO error: {0}")]
-    IO(String),
+    IO(
+        #[source]
+        #[serde(serialize_with = "serialize_display")]
+        Arc<std::io::Error>,
+    ),
 
     /// An error that occurs when invalid unicode is encountered.
     #[error("Invalid unicode: {0:?}")]
@@ -20,10 +28,23 @@ pub enum Error {
     #[error("Unable to parse module name of: {0}")]
     MissingTlaFileModuleName(String),
 
+    /// An error that occurs when the module declared in a TLA file doesn't match the file name.
+    #[error("Module '{declared}' declared in {filename:?} doesn't match the file name")]
+    ModuleNameMismatch {
+        /// Path of the TLA file.
+        filename: std::path::PathBuf,
+        /// Module name declared in the TLA file.
+        declared: String,
+    },
+
     /// An error that occurs when a file is not found.
     #[error("File not found: {0}")]
     FileNotFound(std::path::PathBuf),
 
+    /// An error that occurs when a file is not found in any of the searched locations.
+    #[error("File not found in any of: {0:?}")]
+    FileNotFoundInAny(Vec<std::path::PathBuf>),
+
     /// An error that occurs when `Java` is not installed.
     #[error("Missing Java. Please install it.")]
     MissingJava,
@@ -33,29 +54,113 @@ pub enum Error {
     MinimumJavaVersion(usize, usize),
 
     /// An error that occurs when a TLA+ file representing a set of tests contains no test.
-    #[error("No test found in {0}")]
-    NoTestFound(String),
+    #[error(
+        "No test found in module {module}{}: {details}",
+        file.as_ref().map(|file| format!(" ({})", file.display())).unwrap_or_default()
+    )]
+    NoTestFound {
+        /// Path of the TLA+ file, if it was read from one.
+        file: Option<std::path::PathBuf>,
+        /// Name of the TLA+ module.
+        module: String,
+        /// How the tests were looked for.
+        details: String,
+    },
+
+    /// An error that occurs when a TLA+ config file can't be parsed.
+    #[error("Invalid TLA+ config file: {0}")]
+    InvalidTlaConfig(String),
 
-    /// Tla operator name parse error
-    #[error("Unable to parse all operator names in tla module with content: {0}")]
-    TlaOperatorNameParseError(String),
+    /// An error that occurs when the name of an operator defined in a TLA+
+    /// module can't be parsed.
+    #[error(
+        "Unable to parse the name of the operator defined at line {line}{}: {snippet}",
+        file.as_ref().map(|file| format!(" of {}", file.display())).unwrap_or_default()
+    )]
+    TlaOperatorNameParseError {
+        /// Path of the TLA+ file, if it was read from one.
+        file: Option<std::path::PathBuf>,
+        /// Line of the operator definition, starting at 1.
+        line: usize,
+        /// Line of the operator definition, trimmed.
+        snippet: String,
+    },
 
     /// An error that occurs when the model checker isn't able to generate a test trace.
     #[error("No trace found in {0}")]
     NoTestTraceFound(std::path::PathBuf),
 
+    /// An error that occurs when the model checker finds a counterexample to
+    /// a test that is expected to hold.
+    #[error("Test '{test}' is expected to hold, but is violated by trace: {trace}")]
+    HoldsTestViolated {
+        /// Test name.
+        test: String,
+        /// Trace violating the test.
+        trace: JsonTrace,
+    },
+
     /// An error that occurs when the output of TLC is unexpected.
     #[error("Invalid TLC output: {0}")]
     InvalidTLCOutput(std::path::PathBuf),
 
     /// An error that occurs when the output of TLC returns an error.
-    #[error("TLC failure: {0}")]
-    TLCFailure(String),
+    #[error(
+        "TLC failure: {message}{}{}",
+        kind.hint().map_or_else(String::new, |hint| format!("\nhint: {}", hint)),
+        if stderr.is_empty() { String::new() } else { format!("\nstderr:\n{}", stderr) }
+    )]
+    TLCFailure {
+        /// Errors reported by TLC.
+        message: String,
+        /// Kind of the first error reported by TLC.
+        // boxed to keep `Error` small
+        kind: Box<TlcErrorKind>,
+        /// TLC stderr, e.g. with the errors of the JVM.
+        stderr: String,
+    },
+
+    /// An error that occurs when the output of a model checker can't be interpreted.
+    #[error("Unexpected model checker output:\nstdout:\n{stdout}\nstderr:\n{stderr}")]
+    UnexpectedModelCheckerOutput {
+        /// Model checker stdout.
+        stdout: String,
+        /// Model checker stderr.
+        stderr: String,
+    },
 
     /// An error that occurs when the output of Apalache returns an error.
     #[error("Apalache failure: {0}")]
     ApalacheFailure(ApalacheError),
 
+    /// An error that occurs when Apalache's type checker rejects a TLA+ module.
+    #[error("Apalache type error at {location}: {message}")]
+    ApalacheTypeError {
+        /// Location of the type error, as reported by Apalache.
+        location: String,
+        /// Type error message.
+        message: String,
+    },
+
+    /// An error that occurs when Apalache's parser rejects a TLA+ module.
+    #[error(
+        "Apalache parse error in module {module}{}: {message}",
+        match (line, column) {
+            (Some(line), Some(column)) => format!(" at line {}, column {}", line, column),
+            _ => String::new(),
+        }
+    )]
+    ApalacheParseError {
+        /// Module that can't be parsed.
+        module: String,
+        /// Line of the error, if reported.
+        line: Option<usize>,
+        /// Column of the error, if reported.
+        column: Option<usize>,
+        /// Parse error message, with its location.
+        message: String,
+    },
+
     /// An error that occurs when the counterexample produced by Apalache is unexpected.
     #[error("Invalid Apalache counterexample: {0}")]
     InvalidApalacheCounterexample(String),
@@ -68,22 +173,126 @@ pub enum Error {
     #[error("Nom error: {0}")]
     Nom(String),
 
+    /// An error that occurs when using the `notify` crate.
+    #[error("Notify error: {0}")]
+    Notify(String),
+
     /// An error that occurs when parsing a JSON value.
     #[error("JSON parse error: {0}")]
-    JsonParseError(String),
+    JsonParseError(
+        #[source]
+        #[serde(serialize_with = "serialize_display")]
+        Arc<serde_json::Error>,
+    ),
 
     /// An error for unrecognized checker name.
     #[error("Unrecognized checker: {0}")]
     UnrecognizedChecker(String),
 
+    /// An error that occurs when the Apalache command selected (see
+    /// [`crate::model::checker::ApalacheMode`]) isn't supported by the
+    /// version of Apalache run by modelator.
+    #[error("`apalache {command}` requires Apalache {min_version} or later, but modelator runs Apalache {version}")]
+    UnsupportedApalacheMode {
+        /// The Apalache command.
+        command: String,
+        /// The first version of Apalache with the command.
+        min_version: String,
+        /// The version of Apalache run by modelator.
+        version: String,
+    },
+
     /// An error for unsupported output format.
     #[error("Unsupported output format: {0}")]
     UnsupportedOutputFormat(String),
+
+    /// An error that occurs when a trace can't be converted to another format.
+    #[error("Unable to convert trace to {format}: {reason}")]
+    TraceConversion {
+        /// Format the trace is converted to.
+        format: String,
+        /// Why the conversion isn't possible.
+        reason: String,
+    },
+
+    /// An error that occurs when a file that would be overwritten already exists.
+    #[error("File already exists: {0}")]
+    FileAlreadyExists(std::path::PathBuf),
+
+    /// An error that occurs when a test isn't run because a previous test
+    /// failed, with [`crate::ModelatorRuntime::fail_fast`] set.
+    #[error("Test '{0}' was skipped after a previous test failed")]
+    TestSkipped(String),
+
+    /// An error that occurs when a test expected to fail passes (see
+    /// [`crate::ModelatorRuntime::expect_fail`]).
+    #[error("Test '{0}' was expected to fail, but passed")]
+    UnexpectedPass(String),
+
+    /// An error that occurs in place of running a model checker when
+    /// [`crate::ModelatorRuntime::dry_run`] is set, with the command that
+    /// would have been run.
+    #[error("Dry run: {0}")]
+    DryRun(String),
+
+    /// An error that occurs when a `MODELATOR_*` environment variable read by
+    /// [`crate::ModelatorRuntime::from_env`] has an invalid value.
+    #[error("Invalid value {value:?} of environment variable {name}: {reason}")]
+    InvalidEnvVar {
+        /// Name of the environment variable.
+        name: String,
+        /// Value of the environment variable.
+        value: String,
+        /// Why the value is invalid.
+        reason: String,
+    },
+
+    /// An error that occurs when a project configuration file is invalid.
+    #[error("Invalid configuration file {path}: {reason}")]
+    InvalidConfigFile {
+        /// Path of the configuration file.
+        path: std::path::PathBuf,
+        /// Why the configuration file is invalid.
+        reason: String,
+    },
+
+    /// An error that occurs when a trace file can't be read as a trace.
+    #[error("Invalid trace file {path}: {reason}")]
+    InvalidTraceFile {
+        /// Path of the trace file.
+        path: std::path::PathBuf,
+        /// Why the trace file is invalid.
+        reason: String,
+    },
+}
+
+impl Error {
+    /// An [`Error::IO`] with the given message.
+    pub(crate) fn io(message: impl Into<String>) -> Self {
+        Self::IO(Arc::new(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            message.into(),
+        )))
+    }
+}
+
+/// Serializes the errors kept as sources by their message.
+fn serialize_display<S: Serializer>(
+    value: &impl Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
 }
 
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
-        Self::IO(err.to_string())
+        Self::IO(Arc::new(err))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::JsonParseError(Arc::new(err))
     }
 }
 
@@ -93,6 +302,12 @@ impl From<ureq::Error> for Error {
     }
 }
 
+impl From<notify::Error> for Error {
+    fn from(err: notify::Error) -> Self {
+        Self::Notify(err.to_string())
+    }
+}
+
 impl From<nom::Err<nom::error::Error<&str>>> for Error {
     fn from(err: nom::Err<nom::error::Error<&str>>) -> Self {
         Self::Nom(err.to_string())
@@ -100,11 +315,11 @@ impl From<nom::Err<nom::error::Error<&str>>> for Error {
 }
 
 /// Set of possible errors that can occur when running a test using `modelator`.
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum TestError {
     /// A `modelator` [enum@Error].
     #[error("Error while running modelator: {0}")]
-    Modelator(Error),
+    Modelator(#[source] Error),
 
     /// A error that occurs when a test fails.
     #[error("Unhandled test: {test}")]
@@ -116,15 +331,207 @@ pub enum TestError {
     },
 
     /// A error that occurs when a test fails.
-    #[error("Test failed: {message}\n   {location}")]
-    FailedTest {
+    #[error("Test failed: {0}")]
+    FailedTest(Box<FailedTest>),
+
+    /// A error that occurs when finalizing the system under test, after all
+    /// steps of a test succeeded.
+    #[error("Test finalization failed after {step_count} successful steps: {message}")]
+    FinalizationFailed {
         /// Failure message.
         message: String,
-        /// Failure location.
-        location: String,
+        /// Number of steps that succeeded.
+        step_count: usize,
         /// Test content
         test: String,
-        /// System under test
-        system: String,
     },
+
+    /// A error that occurs when observed states of a system don't refine a
+    /// trace (see [`crate::TraceMonitor`]).
+    #[error(
+        "Observed state {observed_index} diverges from state {trace_index} of the trace: {}",
+        mismatches.join(", ")
+    )]
+    TraceDivergence {
+        /// Index of the diverging observed state.
+        observed_index: usize,
+        /// The diverging observed state, if the observed states didn't end.
+        observed: Option<JsonValue>,
+        /// Index of the state of the trace that was expected instead.
+        trace_index: usize,
+        /// The state of the trace that was expected instead.
+        expected: JsonValue,
+        /// Differences between the observed and the expected states.
+        mismatches: Vec<String>,
+    },
+
+    /// A test failure, along with the shortest prefix of its trace on which
+    /// the system under test still fails (see [`crate::ModelatorRuntime::shrink`]).
+    #[error("{error}\n   shortest failing prefix: {prefix_length} of {trace_length} steps")]
+    ShrunkFailedTest {
+        /// Failure on the whole trace.
+        error: Box<TestError>,
+        /// Number of steps of the shortest failing prefix.
+        prefix_length: usize,
+        /// Number of steps of the whole trace.
+        trace_length: usize,
+        /// Shortest failing prefix of the trace.
+        prefix: JsonTrace,
+    },
+
+    /// A error that occurs when the states of a trace have variables which
+    /// the steps don't deserialize (see [`crate::ModelatorRuntime::strict_fields`]).
+    #[error("Step type {step_type} has no field for the TLA+ variables: {}", variables.join(", "))]
+    MissingStepFields {
+        /// Name of the step type.
+        step_type: String,
+        /// Variables of the trace states ignored when deserializing the steps.
+        variables: Vec<String>,
+    },
+}
+
+/// A test failure (see [`TestError::FailedTest`]).
+#[derive(Debug, Clone)]
+pub struct FailedTest {
+    /// Failure message.
+    pub message: String,
+    /// Failure location.
+    pub location: String,
+    /// The failing step, if the failure occurred while running one.
+    pub step: Option<FailedStep>,
+    /// Test content
+    pub test: String,
+    /// System under test
+    pub system: String,
+    /// Steps executed before the failure, if recorded
+    /// (see [`crate::ModelatorRuntime::record_execution`]).
+    pub execution_log: Option<ExecutionLog>,
+    /// Id of the failing trace, if known (see [`JsonTrace::id`]).
+    pub trace_id: Option<String>,
+}
+
+impl std::fmt::Display for FailedTest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n   {}", self.message, self.location)?;
+        if let Some(id) = &self.trace_id {
+            write!(f, " (trace {})", id)?;
+        }
+        if !self.system.is_empty() {
+            write!(f, "\n   system: {}", self.system)?;
+        }
+        Ok(())
+    }
+}
+
+/// The step on which a test failed (see [`TestError::FailedTest`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedStep {
+    /// Zero-based index of the step in the trace.
+    pub index: usize,
+    /// The step, as in the trace, if known.
+    pub value: Option<JsonValue>,
+    /// Expected and actual values, if the step failed because they differ.
+    pub diff: Option<StepDiff>,
+}
+
+/// Expected and actual values of a failed step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepDiff {
+    /// The expected value.
+    pub expected: String,
+    /// The actual value.
+    pub actual: String,
+}
+
+impl TestError {
+    /// Returns the step on which the test failed, if known.
+    pub fn failed_step(&self) -> Option<&FailedStep> {
+        match self {
+            Self::FailedTest(failure) => failure.step.as_ref(),
+            Self::ShrunkFailedTest { error, .. } => error.failed_step(),
+            _ => None,
+        }
+    }
+
+    /// Attaches the id of the failing trace to a failed test.
+    pub(crate) fn with_trace_id(mut self, id: String) -> Self {
+        self.set_trace_id(id);
+        self
+    }
+
+    fn set_trace_id(&mut self, id: String) {
+        match self {
+            Self::FailedTest(failure) => failure.trace_id = Some(id),
+            Self::ShrunkFailedTest { error, .. } => error.set_trace_id(id),
+            _ => {}
+        }
+    }
+
+    /// Attaches the state of the system under test to a failed test, unless
+    /// it already has one.
+    pub(crate) fn with_system(mut self, state: &str) -> Self {
+        self.set_system(state);
+        self
+    }
+
+    fn set_system(&mut self, state: &str) {
+        match self {
+            Self::FailedTest(failure) if failure.system.is_empty() => {
+                failure.system = state.to_owned();
+            }
+            Self::ShrunkFailedTest { error, .. } => error.set_system(state),
+            _ => {}
+        }
+    }
+
+    /// Attaches the given execution log to a failed test.
+    pub(crate) fn with_execution_log(mut self, log: Option<&ExecutionLog>) -> Self {
+        if let Self::FailedTest(failure) = &mut self {
+            failure.execution_log = log.cloned();
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_source_chain() {
+        let sources = |error: &dyn std::error::Error| {
+            let mut sources = Vec::new();
+            let mut source = error.source();
+            while let Some(error) = source {
+                sources.push(error.to_string());
+                source = error.source();
+            }
+            sources
+        };
+
+        let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let error = TestError::Modelator(io_error.into());
+        assert_eq!(sources(&error), ["IO error: no such file", "no such file"]);
+        assert!(error
+            .source()
+            .and_then(|source| source.source())
+            .and_then(|source| source.downcast_ref::<Arc<std::io::Error>>())
+            .map_or(false, |io_error| io_error.kind()
+                == std::io::ErrorKind::NotFound));
+
+        let json_error = serde_json::from_str::<JsonValue>("{").unwrap_err();
+        let message = json_error.to_string();
+        let error = TestError::Modelator(json_error.into());
+        assert_eq!(
+            sources(&error),
+            [format!("JSON parse error: {}", message), message]
+        );
+
+        // the errors are serialized by their message
+        assert_eq!(
+            serde_json::to_value(Error::io("no such file")).unwrap(),
+            serde_json::json!({ "IO": "no such file" })
+        );
+    }
 }
@@ -1,8 +1,12 @@
+use crate::execution_log::ExecutionLog;
+use crate::step_runner::{Invariant, StepRunner};
 use crate::tester::*;
-use crate::{artifact::JsonTrace, TestError};
+use crate::{artifact::JsonTrace, FailedStep, FailedTest, StepDiff, TestError};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value as JsonValue;
+use std::cell::RefCell;
 use std::iter::Iterator;
+use std::rc::Rc;
 use std::{any::Any, fmt::Debug, panic::UnwindSafe};
 
 /// A trait for handling the mapping between abstract and concrete system states
@@ -23,6 +27,112 @@ pub trait StateHandler<State> {
     fn read(&self) -> State;
 }
 
+/// A trait for comparing the expected abstract state with the one read from the
+/// concrete system, see [`EventRunner::with_state_matching`].
+pub trait StateMatcher<State> {
+    /// Describe every difference between the `expected` and the `actual` state.
+    /// The states match if the returned list is empty.
+    fn mismatches(expected: &State, actual: &State) -> Vec<String>;
+}
+
+/// A [`StateMatcher`] requiring all fields of the abstract state to be equal.
+#[derive(Clone, Copy, Debug)]
+pub struct FullEquality;
+
+impl<State: Serialize> StateMatcher<State> for FullEquality {
+    fn mismatches(expected: &State, actual: &State) -> Vec<String> {
+        json_mismatches(expected, actual, false)
+    }
+}
+
+/// A [`StateMatcher`] comparing only the fields present in the serialized
+/// expected state; fields that the model leaves unconstrained can be omitted
+/// from it, e.g. with `#[serde(skip_serializing_if = "Option::is_none")]`.
+#[derive(Clone, Copy, Debug)]
+pub struct ExpectedFieldsOnly;
+
+impl<State: Serialize> StateMatcher<State> for ExpectedFieldsOnly {
+    fn mismatches(expected: &State, actual: &State) -> Vec<String> {
+        json_mismatches(expected, actual, true)
+    }
+}
+
+/// Compares the Json representations of two states field by field.
+fn json_mismatches<State: Serialize>(
+    expected: &State,
+    actual: &State,
+    expected_fields_only: bool,
+) -> Vec<String> {
+    let (expected, actual) = match (serde_json::to_value(expected), serde_json::to_value(actual)) {
+        (Ok(expected), Ok(actual)) => (expected, actual),
+        (Err(e), _) | (_, Err(e)) => return vec![format!("cannot serialize state: {}", e)],
+    };
+    json_value_mismatches(&expected, &actual, expected_fields_only)
+}
+
+/// Describes every difference between two Json states, field by field.
+pub(crate) fn json_value_mismatches(
+    expected: &JsonValue,
+    actual: &JsonValue,
+    expected_fields_only: bool,
+) -> Vec<String> {
+    let mut mismatches = vec![];
+    collect_mismatches("", expected, actual, expected_fields_only, &mut mismatches);
+    mismatches
+}
+
+fn collect_mismatches(
+    path: &str,
+    expected: &JsonValue,
+    actual: &JsonValue,
+    expected_fields_only: bool,
+    mismatches: &mut Vec<String>,
+) {
+    let field = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (expected, actual) {
+        (JsonValue::Object(expected), JsonValue::Object(actual)) => {
+            for (key, expected) in expected {
+                match actual.get(key) {
+                    Some(actual) => collect_mismatches(
+                        &field(key),
+                        expected,
+                        actual,
+                        expected_fields_only,
+                        mismatches,
+                    ),
+                    None => mismatches.push(format!(
+                        "{}: expected {}, got nothing",
+                        field(key),
+                        expected
+                    )),
+                }
+            }
+            if !expected_fields_only {
+                for (key, actual) in actual {
+                    if !expected.contains_key(key) {
+                        mismatches.push(format!(
+                            "{}: expected nothing, got {}",
+                            field(key),
+                            actual
+                        ));
+                    }
+                }
+            }
+        }
+        (expected, actual) if expected != actual => {
+            let path = if path.is_empty() { "<state>" } else { path };
+            mismatches.push(format!("{}: expected {}, got {}", path, expected, actual));
+        }
+        _ => (),
+    }
+}
+
 /// A trait for handling abstract test actions (messages).
 pub trait ActionHandler<Action> {
     /// Type of action outcome. Set to () if none.
@@ -70,6 +180,25 @@ impl EventStream {
         Self { events: vec![] }
     }
 
+    /// Create an event stream from the given events, in order.
+    pub fn from_events(events: Vec<Event>) -> Self {
+        Self { events }
+    }
+
+    /// Add an event to the end of the event stream.
+    /// Modifies the caller.
+    pub fn push(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// Add an event to the end of the event stream.
+    /// Produces the modified version of the caller,
+    /// allowing to chain the events.
+    pub fn event(mut self, event: Event) -> Self {
+        self.push(event);
+        self
+    }
+
     /// Add an initial abstract state to the event stream.
     /// [`StateHandler::init`] should handle this event and
     /// initialize the concrete system state from it.
@@ -190,6 +319,18 @@ impl EventStream {
     }
 }
 
+impl From<Vec<Event>> for EventStream {
+    fn from(events: Vec<Event>) -> Self {
+        Self::from_events(events)
+    }
+}
+
+impl Extend<Event> for EventStream {
+    fn extend<T: IntoIterator<Item = Event>>(&mut self, events: T) {
+        self.events.extend(events);
+    }
+}
+
 impl IntoIterator for EventStream {
     type Item = Event;
     type IntoIter = std::vec::IntoIter<Self::Item>;
@@ -199,26 +340,116 @@ impl IntoIterator for EventStream {
     }
 }
 
-impl From<JsonTrace> for EventStream {
-    fn from(trace: JsonTrace) -> Self {
+/// Default trace field holding the expected outcome of an action.
+pub const DEFAULT_OUTCOME_FIELD: &str = "actionOutcome";
+
+impl EventStream {
+    /// Create an event stream from a Json trace, where the expected outcome of
+    /// each action is read from the given field of the states.
+    pub fn from_json_trace(trace: JsonTrace, outcome_field: &str) -> Self {
         let mut events = Self::new();
         for (index, value) in trace.into_iter().enumerate() {
             if index == 0 {
                 events.add_init(value);
             } else {
-                if let JsonValue::Object(value) = value.clone() {
-                    if let Some(action) = value.get("action") {
-                        events.add_action(action.clone());
-                    };
-                    if let Some(outcome) = value.get("actionOutcome") {
-                        events.add_expect(outcome.clone());
-                    }
-                }
-                events.add_equal(value);
+                events.add_json_step(value, outcome_field);
             }
         }
         events
     }
+
+    /// Add the events of a Json trace state following the initial one: its
+    /// action and expected outcome, if any, and the expectation of the state.
+    fn add_json_step(&mut self, value: JsonValue, outcome_field: &str) {
+        if let JsonValue::Object(value) = &value {
+            if let Some(action) = value.get("action") {
+                self.add_action(action.clone());
+            };
+            if let Some(outcome) = value.get(outcome_field) {
+                self.add_expect(outcome.clone());
+            }
+        }
+        self.add_equal(value);
+    }
+}
+
+impl From<JsonTrace> for EventStream {
+    fn from(trace: JsonTrace) -> Self {
+        Self::from_json_trace(trace, DEFAULT_OUTCOME_FIELD)
+    }
+}
+
+/// Describes an abstract action, for error messages.
+fn describe_action(action: &dyn Any) -> String {
+    if let Some(action) = action.downcast_ref::<JsonValue>() {
+        action.to_string()
+    } else if let Some(action) = action.downcast_ref::<String>() {
+        action.clone()
+    } else {
+        "<action>".to_string()
+    }
+}
+
+/// Describes an event as Json, for execution logs.
+fn describe_event(event: &Event) -> JsonValue {
+    let input = |input: &dyn Any| {
+        if let Some(input) = input.downcast_ref::<JsonValue>() {
+            input.clone()
+        } else if let Some(input) = input.downcast_ref::<String>() {
+            input.clone().into()
+        } else {
+            "<typed input>".into()
+        }
+    };
+    match event {
+        Event::Init(state) => serde_json::json!({ "init": input(state.as_ref()) }),
+        Event::Action(action) => serde_json::json!({ "action": input(action.as_ref()) }),
+        Event::Expect(outcome) => serde_json::json!({
+            "expect": serde_json::from_str::<JsonValue>(outcome)
+                .unwrap_or_else(|_| outcome.clone().into())
+        }),
+        Event::Check(_) => serde_json::json!({ "check": "<assertion>" }),
+        Event::Equal(state) => serde_json::json!({ "equal": input(state.as_ref()) }),
+    }
+}
+
+/// Compacts an outcome serialized as Json, so that its formatting doesn't matter.
+fn compact_outcome(outcome: &str) -> String {
+    serde_json::from_str::<JsonValue>(outcome)
+        .map_or_else(|_| outcome.to_string(), |outcome| outcome.to_string())
+}
+
+/// Compares the expected outcome of an action with the one it produced, both
+/// serialized as Json, once deserialized as the outcome type of the action.
+type OutcomeCheck = fn(&str, &str) -> Result<bool, String>;
+
+/// Deserializes two outcomes as `Outcome`, and compares them.
+fn outcomes_equal<Outcome>(expected: &str, actual: &str) -> Result<bool, String>
+where
+    Outcome: DeserializeOwned + PartialEq,
+{
+    let parse = |outcome: &str| {
+        serde_json::from_str::<Outcome>(outcome).map_err(|e| {
+            format!(
+                "outcome {} is not a {}: {}",
+                compact_outcome(outcome),
+                std::any::type_name::<Outcome>(),
+                e
+            )
+        })
+    };
+    Ok(parse(expected)? == parse(actual)?)
+}
+
+/// Adds the abstract states whose handlers ran to the message of a failure.
+fn with_handlers((result, handlers): (TestResult, Vec<&'static str>)) -> TestResult {
+    match result {
+        TestResult::Failure { message, location } => TestResult::Failure {
+            message: format!("{}\n(state handlers run: {})", message, handlers.join(", ")),
+            location,
+        },
+        result => result,
+    }
 }
 
 /// A runner that allows to run tests specified as event streams
@@ -226,12 +457,21 @@ impl From<JsonTrace> for EventStream {
 /// You can implement several instances of [StateHandler]s
 /// and [ActionHandler]s for the `System`, thus allowing your system
 /// to handle several kinds of abstract states or actions.
+/// Every registered abstract state that can be read from a state of the test
+/// is initialized and checked, regardless of the registration order; an event
+/// is unhandled only if no registered state or action applies to it.
 pub struct EventRunner<System: Debug> {
     inits: SystemTester<System>,
     actions: SystemTester<System>,
     checks: SystemTester<System>,
     equals: SystemTester<System>,
     outcome: String,
+    action: String,
+    outcome_field: String,
+    outcome_checks: Vec<(&'static str, OutcomeCheck)>,
+    outcome_check: Option<OutcomeCheck>,
+    pub(crate) execution_log: Option<ExecutionLog>,
+    invariants: Vec<(String, Invariant<System>)>,
 }
 
 impl<System: Debug> Default for EventRunner<System> {
@@ -249,9 +489,61 @@ impl<System: Debug> EventRunner<System> {
             checks: SystemTester::new(),
             equals: SystemTester::new(),
             outcome: String::new(),
+            action: String::new(),
+            outcome_field: DEFAULT_OUTCOME_FIELD.to_string(),
+            outcome_checks: Vec::new(),
+            outcome_check: None,
+            execution_log: None,
+            invariants: Vec::new(),
+        }
+    }
+
+    /// Check the given named invariant of the concrete system after each
+    /// event is processed; a violation fails the test at that event.
+    pub fn with_invariant(mut self, name: &str, invariant: Invariant<System>) -> Self {
+        self.invariants.push((name.to_string(), invariant));
+        self
+    }
+
+    /// Record the events of each run, along with the system after each of
+    /// them, keeping at most the last `cap` ones.
+    /// The record is attached to [`TestError::FailedTest`].
+    pub fn with_execution_log(mut self, cap: usize) -> Self {
+        self.execution_log = Some(ExecutionLog::new(cap));
+        self
+    }
+
+    /// The events recorded during the last run, if recording.
+    pub fn execution_log(&self) -> Option<&ExecutionLog> {
+        self.execution_log.as_ref()
+    }
+
+    /// Set the field of the Json trace states holding the expected outcome of
+    /// an action (`actionOutcome` by default). The outcome returned by
+    /// [`ActionHandler::handle`] is compared against it.
+    pub fn with_outcome_field(mut self, outcome_field: &str) -> Self {
+        self.outcome_field = outcome_field.to_string();
+        self
+    }
+
+    /// Turn the runner into a [`StepRunner`] on Json steps, driving a default
+    /// `System` (see [`EventStepRunner`]), so that it can be run by
+    /// [`crate::ModelatorRuntime::run_tla_steps`].
+    pub fn into_step_runner(self) -> EventStepRunner<System>
+    where
+        System: Default,
+    {
+        EventStepRunner {
+            system: System::default(),
+            runner: Rc::new(RefCell::new(self)),
         }
     }
 
+    /// The field of the Json trace states holding the expected outcome of an action.
+    pub fn outcome_field(&self) -> &str {
+        &self.outcome_field
+    }
+
     /// Equip the runner with the ability to handle given abstract `State`.
     pub fn with_state<State>(mut self) -> Self
     where
@@ -266,6 +558,28 @@ impl<System: Debug> EventRunner<System> {
         self
     }
 
+    /// Equip the runner with the ability to handle given abstract `State`,
+    /// comparing the expected and the actual states with the [`StateMatcher`]
+    /// `Matcher` instead of requiring them to be equal.
+    /// On a mismatch, each differing field is reported.
+    pub fn with_state_matching<State, Matcher>(mut self) -> Self
+    where
+        State: 'static + DeserializeOwned + UnwindSafe + Clone + Debug,
+        System: 'static + StateHandler<State>,
+        Matcher: 'static + StateMatcher<State>,
+    {
+        self.inits.add(StateHandler::<State>::init);
+        self.checks
+            .add_fn(|system, assertion: fn(State)| assertion(system.read()));
+        self.equals.add(|system, state: State| {
+            let mismatches = Matcher::mismatches(&state, &system.read());
+            if !mismatches.is_empty() {
+                panic!("state mismatch:\n  {}", mismatches.join("\n  "));
+            }
+        });
+        self
+    }
+
     /// Equip the runner with the ability to handle given abstract `Action`.
     pub fn with_action<Action>(mut self) -> Self
     where
@@ -277,6 +591,28 @@ impl<System: Debug> EventRunner<System> {
         self
     }
 
+    /// Equip the runner with the ability to handle given abstract `Action`,
+    /// like [`EventRunner::with_action`], but comparing the outcome returned
+    /// by [`ActionHandler::handle`] with the expected one as values of the
+    /// outcome type, with its [`PartialEq`], instead of comparing their Json
+    /// serializations. Both outcomes are deserialized as the outcome type,
+    /// so that e.g. the outcome `1` of the trace equals the outcome `1.0` of
+    /// a float; an expected outcome that can't be deserialized fails the test.
+    pub fn with_outcome_check<Action>(mut self) -> Self
+    where
+        Action: 'static + DeserializeOwned + UnwindSafe + Clone,
+        System: 'static + ActionHandler<Action>,
+        <System as ActionHandler<Action>>::Outcome:
+            'static + Serialize + DeserializeOwned + PartialEq,
+    {
+        self.actions.add(ActionHandler::<Action>::handle);
+        self.outcome_checks.push((
+            std::any::type_name::<Action>(),
+            outcomes_equal::<<System as ActionHandler<Action>>::Outcome>,
+        ));
+        self
+    }
+
     /// Run the runner on:
     /// - the given concrete `system`,
     ///   which provides storage of concrete system states,
@@ -291,35 +627,112 @@ impl<System: Debug> EventRunner<System> {
     ) -> Result<(), TestError> {
         // TODO: check that all inits for states are called
         // TODO: call inits for all actions
-        for event in stream {
+        if let Some(log) = self.execution_log.as_mut() {
+            log.clear();
+        }
+        // index of the trace step of the events, as in `EventStream::from_json_trace`:
+        // a step starts with its action or outcome, if any, and ends with its state
+        let (mut step_index, mut in_step) = (0, false);
+        for (index, event) in stream.enumerate() {
+            let described = self.execution_log.is_some().then(|| describe_event(&event));
+            let starts_step = match event {
+                Event::Init(_) => {
+                    step_index = 0;
+                    false
+                }
+                Event::Action(_) | Event::Expect(_) | Event::Equal(_) => !in_step,
+                Event::Check(_) => false,
+            };
+            if starts_step {
+                step_index += 1;
+            }
+            in_step = matches!(event, Event::Action(_) | Event::Expect(_));
+            let mut diff = None;
             let result = match event {
                 Event::Init(input) => self.inits.test(system, &input),
-                Event::Action(input) => self.actions.test(system, &input),
+                Event::Action(input) => {
+                    self.action = describe_action(input.as_ref());
+                    let (result, handlers) = self.actions.test_reporting_handlers(system, &input);
+                    self.outcome_check = handlers.iter().find_map(|handler| {
+                        self.outcome_checks
+                            .iter()
+                            .find(|(action, _)| action == handler)
+                            .map(|(_, check)| *check)
+                    });
+                    result
+                }
                 Event::Expect(expected) => {
-                    if self.outcome == expected {
-                        TestResult::Success(self.outcome.clone())
-                    } else {
-                        TestResult::Failure {
-                            message: format!(
-                                "Expected action outcome '{}', got '{}'",
-                                expected, self.outcome
-                            ),
-                            location: String::new(),
+                    let (expected_json, actual_json) =
+                        (compact_outcome(&expected), compact_outcome(&self.outcome));
+                    let equal = match self.outcome_check {
+                        Some(check) => check(&expected, &self.outcome),
+                        None => Ok(expected_json == actual_json),
+                    };
+                    match equal {
+                        Ok(true) => TestResult::Success(self.outcome.clone()),
+                        Ok(false) => {
+                            diff = Some(StepDiff {
+                                expected: expected_json.clone(),
+                                actual: actual_json.clone(),
+                            });
+                            TestResult::Failure {
+                                message: format!(
+                                    "Action {} produced outcome {}, but {} was expected",
+                                    self.action, actual_json, expected_json
+                                ),
+                                location: String::new(),
+                            }
                         }
+                        Err(message) => TestResult::Failure {
+                            message: format!("Action {}: {}", self.action, message),
+                            location: String::new(),
+                        },
                     }
                 }
-                Event::Check(assertion) => self.checks.test(system, &assertion),
-                Event::Equal(state) => self.equals.test(system, &state),
+                Event::Check(assertion) => {
+                    with_handlers(self.checks.test_reporting_handlers(system, &assertion))
+                }
+                Event::Equal(state) => {
+                    with_handlers(self.equals.test_reporting_handlers(system, &state))
+                }
             };
+            let result = match result {
+                TestResult::Success(res) => self
+                    .invariants
+                    .iter()
+                    .find_map(|(name, invariant)| {
+                        invariant(system).err().map(|message| TestResult::Failure {
+                            message: format!("invariant '{}' violated: {}", name, message),
+                            location: format!("event {}", index),
+                        })
+                    })
+                    .unwrap_or(TestResult::Success(res)),
+                result => result,
+            };
+            if let (Some(log), Some(event)) = (self.execution_log.as_mut(), described) {
+                let outcome = match &result {
+                    TestResult::Success(res) => res.clone(),
+                    TestResult::Failure { message, .. } => message.clone(),
+                    TestResult::Unhandled => "unhandled".to_string(),
+                };
+                log.record(event, format!("{:?}", system), outcome);
+            }
             match result {
                 TestResult::Success(res) => self.outcome = res,
                 TestResult::Failure { message, location } => {
-                    return Err(TestError::FailedTest {
+                    return Err(TestError::FailedTest(Box::new(FailedTest {
                         message,
                         location,
+                        step: Some(FailedStep {
+                            index: step_index,
+                            value: None, // we don't know the trace at that point
+                            diff,
+                        }),
                         test: "".to_string(), // we don't know the test at that point
                         system: format!("{:?}", system),
-                    });
+                        execution_log: self.execution_log.clone(),
+                        trace_id: None,
+                    })));
                 }
                 TestResult::Unhandled => {
                     return Err(TestError::UnhandledTest {
@@ -333,6 +746,58 @@ impl<System: Debug> EventRunner<System> {
     }
 }
 
+/// A [`StepRunner`] on Json steps, created by [`EventRunner::into_step_runner`],
+/// which runs the events of each step on its system, as in
+/// [`EventStream::from_json_trace`]: the initial step initializes the system,
+/// and each next step runs its action, checks its outcome and then its state.
+/// A failing event fails the step with the message of the failure.
+///
+/// Clones of the step runner share the same [`EventRunner`], but each one has
+/// its own copy of the system.
+pub struct EventStepRunner<System: Debug> {
+    /// The system driven by the events.
+    pub system: System,
+    runner: Rc<RefCell<EventRunner<System>>>,
+}
+
+impl<System: Debug> EventStepRunner<System> {
+    /// Runs the given events on the system.
+    fn run_events(&mut self, events: EventStream) -> Result<(), String> {
+        let mut runner = self.runner.borrow_mut();
+        runner
+            .run(&mut self.system, &mut events.into_iter())
+            .map_err(|error| error.to_string())
+    }
+}
+
+impl<System: Debug + Clone> Clone for EventStepRunner<System> {
+    fn clone(&self) -> Self {
+        Self {
+            system: self.system.clone(),
+            runner: Rc::clone(&self.runner),
+        }
+    }
+}
+
+impl<System: Debug> Debug for EventStepRunner<System> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.system.fmt(f)
+    }
+}
+
+impl<System: Debug> StepRunner<JsonValue> for EventStepRunner<System> {
+    fn initial_step(&mut self, step: JsonValue) -> Result<(), String> {
+        self.run_events(EventStream::new().init(step))
+    }
+
+    fn next_step(&mut self, step: JsonValue) -> Result<(), String> {
+        let outcome_field = self.runner.borrow().outcome_field.clone();
+        let mut events = EventStream::new();
+        events.add_json_step(step, &outcome_field);
+        self.run_events(events)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -366,7 +831,7 @@ mod tests {
         value2: String,
     }
 
-    #[derive(Debug, Default)]
+    #[derive(Debug, Default, Clone)]
     struct MySystem {
         state1: String,
         state2: String,
@@ -447,6 +912,43 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_explicit_events() {
+        let mut events = EventStream::from_events(vec![
+            Event::Init(Box::new(State1 {
+                state1: "init state 1".to_string(),
+            })),
+            Event::Action(Box::new(Action1 {
+                value1: "action1 state".to_string(),
+            })),
+        ]);
+        events.push(Event::Expect(
+            serde_json::to_string(&Outcome::Success("OK".to_string())).unwrap(),
+        ));
+        let events = events.event(Event::Equal(Box::new(State1 {
+            state1: "action1 state".to_string(),
+        })));
+
+        let mut runner = EventRunner::new()
+            .with_state::<State1>()
+            .with_action::<Action1>();
+        let mut system = MySystem::default();
+        let result = runner.run(&mut system, &mut events.into_iter());
+        assert!(result.is_ok());
+        assert_eq!(system.state1, "action1 state");
+
+        // the same events, expecting the wrong state
+        let mut events: EventStream = vec![Event::Init(Box::new(State1 {
+            state1: "init state 1".to_string(),
+        }))]
+        .into();
+        events.extend([Event::Equal(Box::new(State1 {
+            state1: "other state".to_string(),
+        }))]);
+        let result = runner.run(&mut system, &mut events.into_iter());
+        assert!(matches!(result, Err(TestError::FailedTest(_))));
+    }
+
     #[test]
     fn test_json_trace() {
         let mut system = MySystem::default();
@@ -489,4 +991,449 @@ mod tests {
         let result = runner.run(&mut system, &mut events.into_iter());
         assert!(result.is_ok());
     }
+
+    #[derive(Deserialize, Serialize, Clone)]
+    struct Deposit {
+        amount: f64,
+    }
+
+    impl ActionHandler<Deposit> for MySystem {
+        type Outcome = f64;
+
+        fn handle(&mut self, action: Deposit) -> f64 {
+            action.amount
+        }
+    }
+
+    #[test]
+    fn test_outcome_check() {
+        let trace = |outcome: &str| -> JsonTrace {
+            vec![
+                r#"{ "state1": "init state 1" }"#.to_string(),
+                format!(
+                    r#"{{ "action": {{ "amount": 1 }}, "actionOutcome": {}, "state1": "init state 1" }}"#,
+                    outcome
+                ),
+            ]
+            .into_iter()
+            .map(|x| serde_json::from_str(&x).unwrap())
+            .collect::<Vec<Value>>()
+            .into()
+        };
+        let mut system = MySystem::default();
+
+        // the Json serializations `1` and `1.0` differ
+        let mut runner = EventRunner::new()
+            .with_state::<State1>()
+            .with_action::<Deposit>();
+        let events: EventStream = trace("1").into();
+        let result = runner.run(&mut system, &mut events.into_iter());
+        assert!(matches!(result, Err(TestError::FailedTest(_))));
+
+        // but the outcomes are equal
+        let mut runner = EventRunner::new()
+            .with_state::<State1>()
+            .with_outcome_check::<Deposit>();
+        let events: EventStream = trace("1").into();
+        assert!(runner.run(&mut system, &mut events.into_iter()).is_ok());
+
+        let events: EventStream = trace("2").into();
+        match runner.run(&mut system, &mut events.into_iter()) {
+            Err(error @ TestError::FailedTest(_)) => {
+                assert!(error.to_string().starts_with(
+                    r#"Test failed: Action {"amount":1} produced outcome 1.0, but 2 was expected"#
+                ));
+                assert_eq!(
+                    error.failed_step().unwrap().diff,
+                    Some(StepDiff {
+                        expected: "2".to_string(),
+                        actual: "1.0".to_string(),
+                    })
+                );
+            }
+            result => panic!("expected the outcome to differ, got {:?}", result),
+        }
+
+        let events: EventStream = trace(r#""OK""#).into();
+        match runner.run(&mut system, &mut events.into_iter()) {
+            Err(error @ TestError::FailedTest(_)) => {
+                assert!(error
+                    .to_string()
+                    .starts_with(r#"Test failed: Action {"amount":1}: outcome "OK" is not a f64"#));
+            }
+            result => panic!("expected the outcome to be invalid, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_step_runner() {
+        // the bounds of `ModelatorRuntime::run_tla_steps`
+        fn assert_bounds<S: StepRunner<Value> + Debug + Clone>(_: &S) {}
+
+        let mut runner = EventRunner::<MySystem>::new()
+            .with_state::<State1>()
+            .with_state::<State2>()
+            .with_action::<Action1>()
+            .into_step_runner();
+        assert_bounds(&runner);
+
+        let trace = |state1: &str| -> JsonTrace {
+            vec![
+                r#"{ "state1": "init state 1", "state2": "init state 2" }"#.to_string(),
+                format!(
+                    r#"{{ "action": {{ "value1": "action1 state" }},
+                          "actionOutcome": {{ "Success": "OK" }},
+                          "state1": "{}", "state2": "init state 2" }}"#,
+                    state1
+                ),
+            ]
+            .into_iter()
+            .map(|x| serde_json::from_str(&x).unwrap())
+            .collect::<Vec<Value>>()
+            .into()
+        };
+
+        assert!(runner.run(trace("action1 state")).is_ok());
+        assert_eq!(runner.system.state1, "action1 state");
+        assert_eq!(runner.system.state2, "init state 2");
+
+        match runner.clone().run(trace("other state")) {
+            Err(error @ TestError::FailedTest(_)) => {
+                assert_eq!(error.failed_step().unwrap().index, 1);
+            }
+            result => panic!("expected the state to differ, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_outcome_field() {
+        let mut system = MySystem::default();
+        let mut runner = EventRunner::new()
+            .with_state::<State1>()
+            .with_state::<State2>()
+            .with_action::<Action1>()
+            .with_outcome_field("result");
+
+        let trace = |outcome: &str| -> JsonTrace {
+            vec![
+                r#"{ "state1": "init state 1", "state2": "init state 2" }"#.to_string(),
+                format!(
+                    r#"{{ "action": {{ "value1": "action1 state" }},
+                          "result": {},
+                          "state1": "action1 state", "state2": "init state 2" }}"#,
+                    outcome
+                ),
+            ]
+            .into_iter()
+            .map(|x| serde_json::from_str(&x).unwrap())
+            .collect::<Vec<Value>>()
+            .into()
+        };
+
+        let events =
+            EventStream::from_json_trace(trace(r#"{ "Success": "OK" }"#), runner.outcome_field());
+        let result = runner.run(&mut system, &mut events.into_iter());
+        assert!(result.is_ok());
+
+        let events = EventStream::from_json_trace(
+            trace(r#"{ "Failure": "NOT OK" }"#),
+            runner.outcome_field(),
+        );
+        match runner.run(&mut system, &mut events.into_iter()) {
+            Err(error @ TestError::FailedTest(_)) => {
+                assert!(error.to_string().starts_with(
+                    r#"Test failed: Action {"value1":"action1 state"} produced outcome {"Success":"OK"}, but {"Failure":"NOT OK"} was expected"#
+                ));
+                assert_eq!(
+                    error.failed_step(),
+                    Some(&FailedStep {
+                        index: 1,
+                        value: None,
+                        diff: Some(StepDiff {
+                            expected: r#"{"Failure":"NOT OK"}"#.to_string(),
+                            actual: r#"{"Success":"OK"}"#.to_string(),
+                        }),
+                    })
+                );
+            }
+            result => panic!("expected the outcome to differ, got {:?}", result),
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Clone, Debug)]
+    struct Counter {
+        count: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    }
+
+    #[derive(Debug)]
+    struct CounterSystem {
+        count: u64,
+        label: String,
+    }
+
+    impl StateHandler<Counter> for CounterSystem {
+        fn init(&mut self, state: Counter) {
+            self.count = state.count;
+        }
+
+        fn read(&self) -> Counter {
+            Counter {
+                count: self.count,
+                label: Some(self.label.clone()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_state_matching() {
+        let mut system = CounterSystem {
+            count: 0,
+            label: "concrete".to_string(),
+        };
+        let events = || {
+            EventStream::new()
+                .init(Counter {
+                    count: 1,
+                    label: None,
+                })
+                .equal(Counter {
+                    count: 2,
+                    label: None,
+                })
+        };
+
+        // the label is absent from the expected state, so only the count differs
+        let mut runner = EventRunner::new().with_state_matching::<Counter, ExpectedFieldsOnly>();
+        match runner.run(&mut system, &mut events().into_iter()) {
+            Err(TestError::FailedTest(failure)) => assert!(failure
+                .message
+                .starts_with("state mismatch:\n  count: expected 2, got 1\n(")),
+            result => panic!("expected the states to differ, got {:?}", result),
+        }
+
+        let mut runner = EventRunner::new().with_state_matching::<Counter, FullEquality>();
+        match runner.run(&mut system, &mut events().into_iter()) {
+            Err(TestError::FailedTest(failure)) => assert!(failure.message.starts_with(
+                "state mismatch:\n  count: expected 2, got 1\n  label: expected nothing, got \"concrete\"\n("
+            )),
+            result => panic!("expected the states to differ, got {:?}", result),
+        }
+
+        let events = EventStream::new()
+            .init(Counter {
+                count: 1,
+                label: None,
+            })
+            .equal(Counter {
+                count: 1,
+                label: None,
+            });
+        let mut runner = EventRunner::new().with_state_matching::<Counter, ExpectedFieldsOnly>();
+        assert!(runner.run(&mut system, &mut events.into_iter()).is_ok());
+    }
+
+    #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+    struct A {
+        a: u64,
+    }
+
+    #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+    struct AB {
+        a: u64,
+        b: u64,
+    }
+
+    #[derive(Debug, Default)]
+    struct NumberSystem {
+        a: u64,
+        b: u64,
+        // added to `b` when read as `AB`
+        b_error: u64,
+    }
+
+    impl StateHandler<A> for NumberSystem {
+        fn init(&mut self, state: A) {
+            self.a = state.a;
+        }
+
+        fn read(&self) -> A {
+            A { a: self.a }
+        }
+    }
+
+    impl StateHandler<AB> for NumberSystem {
+        fn init(&mut self, state: AB) {
+            self.a = state.a;
+            self.b = state.b;
+        }
+
+        fn read(&self) -> AB {
+            AB {
+                a: self.a,
+                b: self.b + self.b_error,
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlapping_states() {
+        let trace = |states: &[&str]| -> EventStream {
+            let trace: JsonTrace = states
+                .iter()
+                .map(|x| serde_json::from_str(x).unwrap())
+                .collect::<Vec<Value>>()
+                .into();
+            trace.into()
+        };
+        let numbers = [r#"{ "a": 0, "b": 0 }"#, r#"{ "a": 0, "b": 0 }"#];
+
+        let (a, ab) = (std::any::type_name::<A>(), std::any::type_name::<AB>());
+        // both states are checked, whatever the registration order;
+        // handlers stop running at the first failure
+        let runners = vec![
+            (
+                EventRunner::new().with_state::<A>().with_state::<AB>(),
+                format!("{}, {}", a, ab),
+            ),
+            (
+                EventRunner::new().with_state::<AB>().with_state::<A>(),
+                ab.to_string(),
+            ),
+        ];
+        for (mut runner, handlers_run) in runners {
+            let mut system = NumberSystem::default();
+            assert!(runner
+                .run(&mut system, &mut trace(&numbers).into_iter())
+                .is_ok());
+
+            let mut system = NumberSystem {
+                b_error: 1,
+                ..NumberSystem::default()
+            };
+            match runner.run(&mut system, &mut trace(&numbers).into_iter()) {
+                Err(TestError::FailedTest(failure)) => assert!(
+                    failure
+                        .message
+                        .ends_with(&format!("(state handlers run: {})", handlers_run)),
+                    "{}",
+                    failure.message
+                ),
+                result => panic!("expected AB to differ, got {:?}", result),
+            }
+        }
+
+        // only A applies to a state without `b`
+        let mut runner = EventRunner::new().with_state::<AB>().with_state::<A>();
+        let mut system = NumberSystem {
+            b_error: 1,
+            ..NumberSystem::default()
+        };
+        assert!(runner
+            .run(
+                &mut system,
+                &mut trace(&[r#"{ "a": 0 }"#, r#"{ "a": 0 }"#]).into_iter()
+            )
+            .is_ok());
+
+        // none applies to a state without `a`
+        assert!(matches!(
+            runner.run(
+                &mut system,
+                &mut trace(&[r#"{ "b": 0 }"#, r#"{ "b": 0 }"#]).into_iter()
+            ),
+            Err(TestError::UnhandledTest { .. })
+        ));
+    }
+
+    #[test]
+    fn test_execution_log() {
+        let mut system = MySystem::default();
+        let mut runner = EventRunner::new()
+            .with_state::<State1>()
+            .with_state::<State2>()
+            .with_action::<Action1>()
+            .with_execution_log(10);
+
+        let trace: JsonTrace = vec![
+            r#"{ "state1": "init state 1", "state2": "init state 2" }"#,
+            r#"{ "action": { "value1": "action1 state" },
+                 "state1": "other state", "state2": "init state 2" }"#,
+        ]
+        .into_iter()
+        .map(|x| serde_json::from_str(x).unwrap())
+        .collect::<Vec<Value>>()
+        .into();
+
+        let events: EventStream = trace.into();
+        let execution_log = match runner.run(&mut system, &mut events.into_iter()) {
+            Err(TestError::FailedTest(failure)) if failure.execution_log.is_some() => {
+                failure.execution_log.unwrap()
+            }
+            result => panic!("expected a recorded failure, got {:?}", result),
+        };
+        let steps: Vec<_> = execution_log
+            .entries
+            .iter()
+            .map(|entry| {
+                entry
+                    .step
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .next()
+                    .unwrap()
+                    .clone()
+            })
+            .collect();
+        assert_eq!(steps, vec!["init", "action", "equal"]);
+        let last = execution_log.entries.last().unwrap();
+        assert!(last.system.contains("action1 state"));
+        assert!(last.outcome.contains("other state"));
+        assert_eq!(runner.execution_log(), Some(&execution_log));
+    }
+
+    #[test]
+    fn test_invariants() {
+        let mut runner = EventRunner::new()
+            .with_state::<State1>()
+            .with_state::<State2>()
+            .with_action::<Action1>()
+            .with_invariant("State2Unchanged", |system: &MySystem| {
+                (system.state2 == "init state 2")
+                    .then(|| ())
+                    .ok_or_else(|| format!("state2 is {}", system.state2))
+            })
+            .with_invariant("State1Initial", |system: &MySystem| {
+                if system.state1 == "init state 1" {
+                    Ok(())
+                } else {
+                    Err(format!("state1 is {}", system.state1))
+                }
+            });
+
+        let trace: JsonTrace = vec![
+            r#"{ "state1": "init state 1", "state2": "init state 2" }"#,
+            r#"{ "action": { "value1": "action1 state" },
+                 "state1": "action1 state", "state2": "init state 2" }"#,
+        ]
+        .into_iter()
+        .map(|x| serde_json::from_str(x).unwrap())
+        .collect::<Vec<Value>>()
+        .into();
+
+        let events: EventStream = trace.into();
+        match runner.run(&mut MySystem::default(), &mut events.into_iter()) {
+            Err(TestError::FailedTest(failure)) => {
+                assert_eq!(
+                    failure.message,
+                    "invariant 'State1Initial' violated: state1 is action1 state"
+                );
+                // the events are: init, action, equal
+                assert_eq!(failure.location, "event 1");
+            }
+            result => panic!("expected State1Initial to be violated, got {:?}", result),
+        }
+    }
 }
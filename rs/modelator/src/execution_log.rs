@@ -0,0 +1,107 @@
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+/// Default maximum number of entries kept by an [`ExecutionLog`].
+pub const DEFAULT_EXECUTION_LOG_CAP: usize = 1000;
+
+/// A step executed against the system under test.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ExecutionLogEntry {
+    /// The step (or event) that was executed.
+    pub step: JsonValue,
+    /// The `Debug` representation of the system after executing the step.
+    pub system: String,
+    /// The outcome of executing the step.
+    pub outcome: String,
+}
+
+/// History of the steps executed against the system under test while
+/// running a trace, kept for post-mortem debugging.
+///
+/// Only the last `cap` entries are kept: older ones are dropped and counted.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ExecutionLog {
+    /// Recorded entries, oldest first.
+    pub entries: Vec<ExecutionLogEntry>,
+    /// Number of older entries that were dropped.
+    pub dropped: usize,
+    #[serde(skip)]
+    cap: usize,
+}
+
+impl Default for ExecutionLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_EXECUTION_LOG_CAP)
+    }
+}
+
+impl ExecutionLog {
+    /// Create an empty log keeping at most `cap` entries.
+    pub const fn new(cap: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            dropped: 0,
+            cap,
+        }
+    }
+
+    /// Record an executed step, dropping the oldest entry if the log is full.
+    pub fn record(&mut self, step: JsonValue, system: String, outcome: String) {
+        if self.cap == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.entries.len() == self.cap {
+            self.entries.remove(0);
+            self.dropped += 1;
+        }
+        self.entries.push(ExecutionLogEntry {
+            step,
+            system,
+            outcome,
+        });
+    }
+
+    /// Remove all entries, e.g. before running another trace.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    /// Serialize the log to pretty-printed JSON, e.g. to store it as a CI artifact.
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .expect("[modelator] an execution log should be serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cap() {
+        let mut log = ExecutionLog::new(2);
+        for i in 0..3 {
+            log.record(i.into(), format!("system {}", i), "OK".to_string());
+        }
+        assert_eq!(log.dropped, 1);
+        assert_eq!(
+            log.entries
+                .iter()
+                .map(|e| e.step.clone())
+                .collect::<Vec<_>>(),
+            vec![JsonValue::from(1), JsonValue::from(2)]
+        );
+        assert_eq!(
+            serde_json::from_str::<JsonValue>(&log.to_json_string()).unwrap(),
+            serde_json::json!({
+                "entries": [
+                    { "step": 1, "system": "system 1", "outcome": "OK" },
+                    { "step": 2, "system": "system 2", "outcome": "OK" },
+                ],
+                "dropped": 1,
+            })
+        );
+    }
+}
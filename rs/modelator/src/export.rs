@@ -0,0 +1,231 @@
+use crate::artifact::{try_write_to_dir, Artifact, ArtifactSaver};
+use crate::model::checker::{Apalache, ModelChecker, Tlc};
+use crate::model::language::{TestExpectation, Tla, TlaTest};
+use crate::{Error, ModelatorRuntime};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the file describing the exported artifacts of a run.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Name of the file the output of the model checker is exported to.
+const LOG_FILE_NAME: &str = "checker.log";
+
+/// Description of the artifacts of a run exported with
+/// [`ModelatorRuntime::export_run`], also written to its `manifest.json`.
+/// All paths are relative to the export directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RunManifest {
+    /// The exported artifacts of each test, by name.
+    pub tests: BTreeMap<String, TestManifest>,
+}
+
+/// The exported artifacts of a single test, in a directory named after it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TestManifest {
+    /// The TLA+ module generated for the test.
+    pub module: PathBuf,
+    /// The TLA+ config file generated for the test.
+    pub config: PathBuf,
+    /// The TLA+ modules the test module depends on, so that it can be model
+    /// checked again from the directory.
+    pub dependencies: Vec<PathBuf>,
+    /// The counterexamples found by the model checker, as TLA+ modules
+    /// `counterexample_<n>.tla`.
+    pub counterexamples: Vec<PathBuf>,
+    /// The counterexamples as JSON traces `trace_<n>.json`.
+    pub traces: Vec<PathBuf>,
+    /// The output of the model checker, unless it was discarded (see
+    /// [`crate::model::checker::ModelCheckerRuntime::log`]).
+    pub log: Option<PathBuf>,
+    /// The error the test failed with, if any.
+    pub error: Option<String>,
+}
+
+/// Runs the tests and writes their artifacts to `dir`. See
+/// [`ModelatorRuntime::export_run`].
+pub(crate) fn export_run(
+    runtime: &ModelatorRuntime,
+    tests: &[TlaTest],
+    dir: &Path,
+    overwrite: bool,
+) -> Result<RunManifest, Error> {
+    // nothing is written over a previous export, unless asked to
+    if !overwrite && dir.read_dir().map_or(false, |mut dir| dir.next().is_some()) {
+        return Err(Error::FileAlreadyExists(dir.to_path_buf()));
+    }
+    std::fs::create_dir_all(dir)?;
+
+    let mut manifest = RunManifest::default();
+    for test in tests {
+        let test_dir = dir.join(&test.name);
+        if test_dir.exists() {
+            // on case-insensitive file systems, the directories of two tests
+            // whose names only differ in case are the same
+            if manifest
+                .tests
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case(&test.name))
+            {
+                return Err(Error::FileAlreadyExists(test_dir));
+            }
+            std::fs::remove_dir_all(&test_dir)?;
+        }
+        std::fs::create_dir_all(&test_dir)?;
+
+        let test_manifest = export_test(runtime, test, dir)?;
+        manifest.tests.insert(test.name.clone(), test_manifest);
+    }
+
+    let json = serde_json::to_string_pretty(&manifest)
+        .expect("should not fail as the manifest is serializable");
+    std::fs::write(dir.join(MANIFEST_FILE_NAME), json)?;
+    Ok(manifest)
+}
+
+/// Model checks a single test and writes its artifacts to its directory in
+/// `dir`, returning their paths relative to `dir`.
+fn export_test(
+    runtime: &ModelatorRuntime,
+    test: &TlaTest,
+    dir: &Path,
+) -> Result<TestManifest, Error> {
+    let file_suite = &test.file_suite;
+    let test_dir = Path::new(&test.name);
+    try_write_to_dir(dir.join(test_dir), file_suite)?;
+    let mut manifest = TestManifest {
+        module: test_dir.join(file_suite.tla_file.filename()),
+        config: test_dir.join(file_suite.tla_config_file.filename()),
+        dependencies: file_suite
+            .dependency_tla_files
+            .iter()
+            .map(|file| test_dir.join(file.filename()))
+            .collect(),
+        ..TestManifest::default()
+    };
+
+    let module_name = file_suite.tla_file.module_name();
+    let mut runtime = runtime.clone();
+    runtime.model_checker_runtime.log = runtime
+        .model_checker_runtime
+        .log
+        .resolve(&runtime.dir)
+        .for_test(crate::test_module_name(module_name, &test.name), &test.name);
+    let result = match runtime.model_checker_runtime.model_checker {
+        ModelChecker::Tlc => Tlc::test(file_suite, &runtime),
+        ModelChecker::Apalache => Apalache::test(file_suite, &runtime),
+    };
+    let (traces, stdout) = match result {
+        Ok(result) => result,
+        // a test expected to hold passes iff no counterexample is found
+        Err(Error::NoTestTraceFound(_)) if test.expectation == TestExpectation::Holds => {
+            return Ok(manifest)
+        }
+        Err(error) => {
+            manifest.error = Some(error.to_string());
+            return Ok(manifest);
+        }
+    };
+
+    let log = stdout.as_string();
+    if !log.is_empty() {
+        let path = test_dir.join(LOG_FILE_NAME);
+        std::fs::write(dir.join(&path), log)?;
+        manifest.log = Some(path);
+    }
+
+    for (index, mut trace) in traces.into_iter().enumerate() {
+        let name = format!("counterexample_{}", index);
+        trace.extends_module_name = Some(module_name.to_owned());
+        let counterexample = test_dir.join(format!("{}.tla", name));
+        std::fs::write(dir.join(&counterexample), trace.to_tla_module(&name))?;
+        manifest.counterexamples.push(counterexample);
+
+        let json_trace = Tla::tla_trace_to_json_trace_with_limits(
+            trace,
+            runtime.model_checker_runtime.max_trace_states,
+            runtime.model_checker_runtime.max_state_bytes,
+        )?;
+        let path = test_dir.join(format!("trace_{}.json", index));
+        json_trace.try_write_to_file(&dir.join(&path))?;
+        manifest.traces.push(path);
+
+        if test.expectation == TestExpectation::Holds && manifest.error.is_none() {
+            manifest.error = Some(
+                Error::HoldsTestViolated {
+                    test: test.name.clone(),
+                    trace: json_trace,
+                }
+                .to_string(),
+            );
+        }
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_run() {
+        let tla_tests_file = "tests/integration/resource/NumbersHoldsTests.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = ModelatorRuntime::default();
+        let manifest = runtime
+            .export_run(tla_tests_file, tla_config_file, dir.path(), false)
+            .unwrap();
+
+        let names: Vec<_> = manifest.tests.keys().map(String::as_str).collect();
+        assert_eq!(names, ["AMaxBMinTest", "ASmallHolds", "InvHolds"]);
+
+        let test = &manifest.tests["AMaxBMinTest"];
+        assert_eq!(
+            test.module,
+            Path::new("AMaxBMinTest/NumbersHoldsTests_AMaxBMinTest.tla")
+        );
+        assert_eq!(
+            test.dependencies,
+            [
+                Path::new("AMaxBMinTest/Numbers.tla"),
+                Path::new("AMaxBMinTest/NumbersHoldsTests.tla")
+            ]
+        );
+        assert_eq!(test.traces, [Path::new("AMaxBMinTest/trace_0.json")]);
+        assert_eq!(test.counterexamples.len(), 1);
+        assert!(test.log.is_some());
+        assert_eq!(test.error, None);
+        let paths = [
+            &test.module,
+            &test.config,
+            &test.traces[0],
+            &test.counterexamples[0],
+        ];
+        for path in paths.iter().chain(&test.log.as_ref()) {
+            assert!(dir.path().join(path).is_file(), "{:?}", path);
+        }
+
+        // a counterexample to a test expected to hold is exported as well
+        let test = &manifest.tests["ASmallHolds"];
+        assert_eq!(test.traces.len(), 1);
+        assert!(test.error.as_ref().unwrap().contains("expected to hold"));
+        let test = &manifest.tests["InvHolds"];
+        assert!(test.traces.is_empty());
+        assert_eq!(test.error, None);
+
+        let json = std::fs::read_to_string(dir.path().join(MANIFEST_FILE_NAME)).unwrap();
+        assert_eq!(json, serde_json::to_string_pretty(&manifest).unwrap());
+
+        // a previous export is only overwritten if asked to
+        let result = runtime.export_run(tla_tests_file, tla_config_file, dir.path(), false);
+        assert!(matches!(result, Err(Error::FileAlreadyExists(path)) if path == dir.path()));
+        std::fs::write(dir.path().join("AMaxBMinTest/stale.json"), "").unwrap();
+        let overwritten = runtime
+            .export_run(tla_tests_file, tla_config_file, dir.path(), true)
+            .unwrap();
+        assert_eq!(overwritten, manifest);
+        assert!(!dir.path().join("AMaxBMinTest/stale.json").exists());
+    }
+}
@@ -1,16 +1,17 @@
 use crate::error::Error;
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
 // The minimum java version supported by Apalache is Java 8:
 // https://apalache.informal.systems/docs/apalache/system-reqs.html
 // TLC doesn't seem to have such requirement.
-const MIN_JAVA_VERSION: usize = 8;
+pub(crate) const MIN_JAVA_VERSION: usize = 8;
 
 pub(crate) const TLA_JAR: &str = "tla2tools-v1.8.0.jar";
 pub(crate) const COMMUNITY_MODULES_JAR: &str = "CommunityModules-202103092123.jar";
 pub(crate) const APALACHE_JAR: &str = "apalache-pkg-0.17.5-full.jar";
+/// Version of [`APALACHE_JAR`].
+pub(crate) const APALACHE_VERSION: [u32; 3] = [0, 17, 5];
 
 /* To update to new checksum, execute
 cd jars/
@@ -53,7 +54,7 @@ impl Jar {
     fn download<P: AsRef<Path>>(&self, modelator_dir: P) -> Result<(), Error> {
         let modelator_dir = modelator_dir.as_ref();
         // compute file where the jar should be stored
-        let path = self.path(&modelator_dir);
+        let path = self.path(modelator_dir);
 
         // download the jar
         let response = ureq::get(&self.link()).call()?;
@@ -105,10 +106,6 @@ pub(crate) fn download_jars_if_necessary<P: AsRef<Path>>(modelator_dir: P) -> Re
         }
         println!("[modelator] Done!");
 
-        // if we have downloaded the jar(s) for the first time, check that we have a
-        // compatible version of java
-        check_java_version()?;
-
         // if we have downloaded the jar(s) for the first time, check that the
         // checksums match
         if !checksums_correct(&modelator_dir)? {
@@ -132,7 +129,7 @@ fn existing_jars<P: AsRef<Path>>(modelator_dir: P) -> Result<HashSet<Jar>, Error
             Ok(jar) => Some(Ok(jar)),
             Err(file_name) => {
                 match std::fs::remove_file(modelator_dir.as_ref().to_path_buf().join(file_name)) {
-                    Err(e) => Some(Err(Error::IO(format!("IO error: {:?}", e)))),
+                    Err(e) => Some(Err(e.into())),
                     _ => None,
                 }
             }
@@ -168,52 +165,3 @@ fn checksums_correct<P: AsRef<Path>>(modelator_dir: P) -> Result<bool, Error> {
     tracing::debug!("jars checksum: {} | expected: {}", hash, JARS_CHECKSUM);
     Ok(hash == JARS_CHECKSUM)
 }
-
-fn check_java_version() -> Result<(), Error> {
-    let mut cmd = Command::new("java");
-    cmd.arg("-XshowSettings:all").arg("-version");
-    // show command being run
-    tracing::debug!("{}", crate::util::cmd_show(&cmd));
-
-    match cmd.output() {
-        Ok(output) => {
-            let stderr = crate::util::cmd_output_to_string(&output.stderr);
-            let mut versions: Vec<_> = stderr
-                .lines()
-                .filter(|line| line.trim().starts_with("java.specification.version ="))
-                .collect();
-            tracing::debug!("java version {:?}", versions);
-
-            assert_eq!(
-                versions.len(),
-                1,
-                "[modelator] expected at most one 'java.specification.version'"
-            );
-
-            // it's okay to unwrap as we have already asserted that the version exists
-            let version_parts: Vec<_> = versions.pop().unwrap().split('=').collect();
-            assert_eq!(
-                version_parts.len(),
-                2,
-                "[modelator] unexpected 'java.specification.version' format"
-            );
-            let version_number: usize = version_parts[1]
-                .trim()
-                .split('.')
-                .last()
-                .unwrap()
-                .parse()
-                .expect("[modelator] unexpected 'java.specification.version' format");
-
-            if version_number < MIN_JAVA_VERSION {
-                Err(Error::MinimumJavaVersion(version_number, MIN_JAVA_VERSION))
-            } else {
-                Ok(())
-            }
-        }
-        Err(err) => {
-            tracing::debug!("error checking Java version: {}", err);
-            Err(Error::MissingJava)
-        }
-    }
-}
@@ -25,6 +25,9 @@ mod cache;
 /// Jar utilities.
 mod jar;
 
+/// Project configuration file.
+pub mod config;
+
 /// Command-line interface.
 pub mod cli;
 
@@ -46,27 +49,50 @@ pub mod event;
 /// A runner for steps obtained from Json traces
 pub mod step_runner;
 
+/// Checking of observed system states against Json traces
+pub mod trace_monitor;
+
+/// Recording of the steps executed against a system under test
+pub mod execution_log;
+
+/// Export of the artifacts of a run for archival
+pub mod export;
+
 /// Testing utilities
 pub mod test_util;
 
 use artifact::model_checker_stdout::ModelCheckerStdout;
 use artifact::TlaFileSuite;
 /// Re-exports.
-pub use datachef::Recipe;
-pub use error::{Error, TestError};
-pub use event::{ActionHandler, Event, EventRunner, EventStream, StateHandler};
-use model::checker::{Apalache, ModelChecker, ModelCheckerRuntime, Tlc};
-use model::language::Tla;
+pub use datachef::{CookError, Ingredient, Recipe};
+pub use error::{Error, FailedStep, FailedTest, StepDiff, TestError};
+pub use event::{
+    ActionHandler, Event, EventRunner, EventStepRunner, EventStream, ExpectedFieldsOnly,
+    FullEquality, StateHandler, StateMatcher,
+};
+pub use execution_log::ExecutionLog;
+pub use export::{RunManifest, TestManifest};
+use model::checker::{
+    Apalache, LogSink, ModelChecker, ModelCheckerRuntime, ModelCheckerWorkers, RandomWalk,
+    TlaAndJsonState, TlaVariables, Tlc,
+};
+use model::language::{TestDiscovery, TestExpectation, Tla};
 use serde::de::DeserializeOwned;
-pub use step_runner::StepRunner;
+pub use step_runner::{Invariant, Snapshot, StepContext, StepRunner, StepTimings};
+pub use trace_monitor::TraceMonitor;
 
 use crate::artifact::{Artifact, ArtifactCreator};
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::env;
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use tempfile::tempdir;
 
@@ -75,19 +101,107 @@ use std::sync::Mutex;
 
 static FILE_SYSTEM_MUTEX: Lazy<Mutex<()>> = Lazy::new(Mutex::default);
 
+/// Number of consecutive runs of the model checker without new traces after
+/// which [`ModelatorRuntime::traces_diverse`] gives up.
+const DIVERSE_TRACES_MAX_STALE_RUNS: usize = 3;
+
+/// Default maximum number of model checkers run at once, unless there are
+/// fewer cores.
+const DEFAULT_MAX_PARALLEL_CHECKERS: usize = 4;
+
+/// Result of running a trace against the system under test.
+#[derive(Clone)]
+struct TraceExecution {
+    result: Result<(), TestError>,
+    /// The steps recorded, if any.
+    execution_log: Option<ExecutionLog>,
+    /// The final state of the system, if captured.
+    final_state: Option<String>,
+    /// The durations of the steps, if timed.
+    step_timings: Option<StepTimings>,
+}
+
+/// Outcome of a single test of a [`TestReport`]. As in pytest, a test expected
+/// to fail (see [`ModelatorRuntime::expect_fail`]) passes if it fails, and
+/// fails if it passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestOutcome {
+    /// The test passed.
+    Passed,
+    /// The test failed.
+    Failed,
+    /// The test failed, as expected (`xfail`).
+    ExpectedFailure,
+    /// The test passed, while it was expected to fail (`xpass`).
+    UnexpectedPass,
+    /// The test wasn't run, as a previous test failed
+    /// (see [`ModelatorRuntime::fail_fast`]).
+    Skipped,
+}
+
+impl TestOutcome {
+    /// Returns true iff the outcome fails the report: the test failed
+    /// unexpectedly, passed while it was expected to fail, or was skipped.
+    pub const fn is_failure(self) -> bool {
+        matches!(self, Self::Failed | Self::UnexpectedPass | Self::Skipped)
+    }
+}
+
 /// Wraps the data from running test(s), allowing more convenient access to the results.
 pub struct TestReport {
     test_name_to_trace_execution_result: BTreeMap<String, Vec<Result<(), TestError>>>,
+    holds_test_name_to_result: BTreeMap<String, Result<(), TestError>>,
+    execution_logs: BTreeMap<(String, usize), ExecutionLog>,
+    trace_ids: BTreeMap<(String, usize), String>,
+    final_states: BTreeMap<(String, usize), String>,
+    expected_failures: BTreeSet<String>,
+    deduplicated_executions: usize,
+    step_timings: BTreeMap<(String, usize), StepTimings>,
 }
 
 impl TestReport {
-    /// Returns true iff no test failed
+    /// Returns true iff no test failed, apart from the tests expected to fail
+    /// (see [`TestOutcome::is_failure`])
     pub fn no_test_failed(&self) -> bool {
-        !self
-            .test_name_to_trace_execution_result
-            .values()
-            .flatten()
-            .any(Result::is_err)
+        !self.outcomes().values().any(|outcome| outcome.is_failure())
+    }
+
+    /// Get the outcome of a single test: it failed if any of its traces failed
+    pub fn outcome(&self, name: &str) -> Option<TestOutcome> {
+        let results = match self.test_name_to_trace_execution_result.get(name) {
+            Some(results) => results.as_slice(),
+            None => std::slice::from_ref(self.holds_test_name_to_result.get(name)?),
+        };
+        let skipped = |result: &Result<(), TestError>| {
+            matches!(result, Err(TestError::Modelator(Error::TestSkipped(_))))
+        };
+        if results.iter().any(skipped) {
+            return Some(TestOutcome::Skipped);
+        }
+        let failed = results.iter().any(Result::is_err);
+        let expected_failure = self.expected_failures.contains(name);
+        Some(match (failed, expected_failure) {
+            (false, false) => TestOutcome::Passed,
+            (true, false) => TestOutcome::Failed,
+            (true, true) => TestOutcome::ExpectedFailure,
+            (false, true) => TestOutcome::UnexpectedPass,
+        })
+    }
+
+    /// Returns the number of trace executions saved by
+    /// [`ModelatorRuntime::dedup_executions`]: the traces identical to a trace
+    /// of a previous test, or of the same test, whose result was reused
+    pub fn deduplicated_executions(&self) -> usize {
+        self.deduplicated_executions
+    }
+
+    /// Returns the outcome of each test
+    pub fn outcomes(&self) -> BTreeMap<&str, TestOutcome> {
+        self.test_name_to_trace_execution_result
+            .keys()
+            .chain(self.holds_test_name_to_result.keys())
+            .filter_map(|name| Some((name.as_str(), self.outcome(name)?)))
+            .collect()
     }
 
     /// Get the vector of results from running counterexample(s) for a single test
@@ -95,6 +209,54 @@ impl TestReport {
         self.test_name_to_trace_execution_result.get(name)
     }
 
+    /// Get the steps executed while running the trace with the given index
+    /// among the traces of a single test, if recorded
+    /// (see [`ModelatorRuntime::record_execution`])
+    pub fn execution_log(&self, name: &str, trace_index: usize) -> Option<&ExecutionLog> {
+        self.execution_logs.get(&(name.to_owned(), trace_index))
+    }
+
+    /// Get the id of the trace with the given index among the traces of a
+    /// single test (see [`artifact::JsonTrace::id`]), which is also attached
+    /// to its failure
+    pub fn trace_id(&self, name: &str, trace_index: usize) -> Option<&str> {
+        self.trace_ids
+            .get(&(name.to_owned(), trace_index))
+            .map(String::as_str)
+    }
+
+    /// Get the state of the system under test when the trace with the given
+    /// index among the traces of a single test finished or failed: its
+    /// [`Snapshot`] if run by [`ModelatorRuntime::run_tla_steps_with_snapshot`],
+    /// and its [`Debug`] output otherwise
+    pub fn final_state(&self, name: &str, trace_index: usize) -> Option<&str> {
+        self.final_states
+            .get(&(name.to_owned(), trace_index))
+            .map(String::as_str)
+    }
+
+    /// Get the durations of the steps of the trace with the given index among
+    /// the traces of a single test, if timed (see [`ModelatorRuntime::step_timings`])
+    pub fn step_timings(&self, name: &str, trace_index: usize) -> Option<&StepTimings> {
+        self.step_timings.get(&(name.to_owned(), trace_index))
+    }
+
+    /// Get the step on which the trace with the given index among the traces
+    /// of a single test failed, if known
+    pub fn failed_step(&self, name: &str, trace_index: usize) -> Option<&FailedStep> {
+        self.result_of_test(name)?
+            .get(trace_index)?
+            .as_ref()
+            .err()?
+            .failed_step()
+    }
+
+    /// Get the result of a single test expected to hold, which failed iff the
+    /// model checker found a counterexample to it
+    pub fn result_of_holds_test(&self, name: &str) -> Option<&Result<(), TestError>> {
+        self.holds_test_name_to_result.get(name)
+    }
+
     /// Returns the vector containing the results for each test
     pub fn all(
         &self,
@@ -120,21 +282,250 @@ pub struct ModelatorRuntime {
 
     /// Modelator directory.
     pub dir: PathBuf,
+
+    /// Directories where to look for the TLA+ modules that are extended,
+    /// but can't be found next to the module extending them.
+    pub tla_library_paths: Vec<PathBuf>,
+
+    /// How test operators are discovered in TLA+ tests modules.
+    pub test_discovery: TestDiscovery,
+
+    /// Whether [`ModelatorRuntime::run_tla_steps`] should look for the shortest
+    /// prefix of a failing trace on which the system under test still fails.
+    /// Each prefix is run on a copy of the system, as it was before running
+    /// the whole trace.
+    pub shrink: bool,
+
+    /// Whether to record, for each trace, the steps executed against the
+    /// system under test along with its state after each of them.
+    /// Records are attached to [`TestError::FailedTest`] and to the [`TestReport`].
+    pub record_execution: bool,
+
+    /// Maximum number of the last steps kept when recording a trace.
+    pub execution_log_cap: usize,
+
+    /// Whether [`ModelatorRuntime::traces`] should stop model checking tests
+    /// after the first failing one. The tests not started yet are reported
+    /// as [`Error::TestSkipped`].
+    /// [`ModelatorRuntime::run_tla_steps`] and [`ModelatorRuntime::run_tla_events`]
+    /// also stop running traces after the first failing one: the remaining
+    /// tests are reported as [`TestOutcome::Skipped`].
+    pub fail_fast: bool,
+
+    /// Whether [`ModelatorRuntime::run_tla_steps`] should fail the traces
+    /// whose states have variables that the steps don't deserialize, which
+    /// serde otherwise silently ignores. The failure is reported as
+    /// [`TestError::MissingStepFields`].
+    pub strict_fields: bool,
+
+    /// Maximum number of model checkers run at once by [`ModelatorRuntime::traces`],
+    /// each in its own JVM (`min(number of cores, 4)` by default). If `None`,
+    /// as many are run as rayon has threads.
+    /// When several are run, and [`ModelCheckerRuntime::workers`] is
+    /// [`model::checker::ModelCheckerWorkers::Auto`], the cores are split between the TLC runs:
+    /// each one has `number of cores / max_parallel_checkers` workers.
+    pub max_parallel_checkers: Option<usize>,
+
+    /// Whether to compose the commands running the model checkers without
+    /// running them: each run fails with [`Error::DryRun`] and its command
+    /// instead (see [`ModelatorRuntime::plan`]). In a dry run, Apalache
+    /// doesn't generate a view, as it would have to be run to do so.
+    pub dry_run: bool,
+
+    /// Names of the tests expected to fail, e.g. because they exercise behavior
+    /// the system under test doesn't implement yet. In a [`TestReport`], such
+    /// a test passes if it fails, and fails if it passes (see [`TestOutcome`]).
+    pub expected_failures: BTreeSet<String>,
+
+    /// Whether [`ModelatorRuntime::run_tla_steps`] and
+    /// [`ModelatorRuntime::run_tla_events`] should run each distinct trace
+    /// only once, when several tests (or several traces of the same test)
+    /// generate identical traces. The result of its first run, including the
+    /// location of its failure, is reported for each of them (see
+    /// [`TestReport::deduplicated_executions`]).
+    /// Traces are identical as with [`ModelCheckerRuntime::dedup_traces`], which
+    /// instead drops the duplicate traces of a test before they are run: with
+    /// both set, only the traces identical across tests are run once.
+    pub dedup_executions: bool,
+
+    /// Whether [`ModelatorRuntime::run_tla_steps`] should time each step run
+    /// against the system under test, including the invariants checked after
+    /// it (see [`TestReport::step_timings`]).
+    pub step_timings: bool,
+
+    /// Duration above which [`ModelatorRuntime::run_tla_steps`] logs a warning
+    /// about a step, which is then timed even if [`ModelatorRuntime::step_timings`]
+    /// isn't set.
+    pub slow_step_threshold: Option<Duration>,
+
+    /// Whether the states computed by [`ModelatorRuntime::next_states`] are
+    /// cached in the modelator directory, and read back from there.
+    pub cache: bool,
+}
+
+/// Returns the default modelator directory, in the data directory of the user.
+pub(crate) fn default_dir() -> PathBuf {
+    directories::ProjectDirs::from("systems", "Informal", "modelator")
+        .expect("there is no valid home directory")
+        .data_dir()
+        .into()
 }
 
 impl Default for ModelatorRuntime {
     fn default() -> Self {
         Self {
             model_checker_runtime: ModelCheckerRuntime::default(),
-            dir: directories::ProjectDirs::from("systems", "Informal", "modelator")
-                .expect("there is no valid home directory")
-                .data_dir()
-                .into(), // env::home_dir().unwrap().join(".modelator"), //Path::new(".modelator").to_path_buf(),
+            dir: default_dir(),
+            tla_library_paths: Vec::new(),
+            test_discovery: TestDiscovery::default(),
+            shrink: false,
+            record_execution: false,
+            execution_log_cap: execution_log::DEFAULT_EXECUTION_LOG_CAP,
+            fail_fast: false,
+            strict_fields: false,
+            max_parallel_checkers: Some(num_cpus::get().min(DEFAULT_MAX_PARALLEL_CHECKERS)),
+            dry_run: false,
+            cache: true,
+            expected_failures: BTreeSet::new(),
+            dedup_executions: false,
+            step_timings: false,
+            slow_step_threshold: None,
         }
     }
 }
 
 impl ModelatorRuntime {
+    /// Create a runtime from a `modelator.toml` project configuration file
+    /// (see [`config::ModelatorConfig`]), with the default value of the
+    /// options the file doesn't set.
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::default().config(&config::ModelatorConfig::from_file(path)?))
+    }
+
+    /// Create a runtime from the `MODELATOR_*` environment variables, with the
+    /// default value of the options they don't set:
+    /// - `MODELATOR_DIR`: the modelator directory;
+    /// - `MODELATOR_CHECKER`: the model checker, `tlc` or `apalache`;
+    /// - `MODELATOR_WORKERS`: the TLC workers, `auto` or a number;
+    /// - `MODELATOR_TRACES_PER_TEST`: the number of traces per test;
+    /// - `MODELATOR_LOG`: where the model checker output is logged, `memory`,
+    ///   `none`, or a directory in which each run writes its own log file;
+    /// - `MODELATOR_NO_CACHE`: `1`/`true` to disable the cache of
+    ///   [`ModelatorRuntime::next_states`], `0`/`false` to keep it.
+    ///
+    /// Empty variables are ignored. Setters called on the returned runtime
+    /// take precedence over the environment, which takes precedence over the
+    /// defaults.
+    pub fn from_env() -> Result<Self, Error> {
+        Self::default().env_overrides(|name| env::var_os(name))
+    }
+
+    /// Set the options of the `MODELATOR_*` variables returned by `var`
+    /// (see [`ModelatorRuntime::from_env`]).
+    fn env_overrides(
+        mut self,
+        var: impl Fn(&str) -> Option<std::ffi::OsString>,
+    ) -> Result<Self, Error> {
+        let invalid = |name: &str, value: &str, reason: String| Error::InvalidEnvVar {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            reason,
+        };
+        let read = |name: &str| -> Result<Option<String>, Error> {
+            match var(name) {
+                None => Ok(None),
+                Some(value) => match value.into_string() {
+                    Ok(value) if value.is_empty() => Ok(None),
+                    Ok(value) => Ok(Some(value)),
+                    Err(value) => Err(invalid(
+                        name,
+                        &value.to_string_lossy(),
+                        "not valid unicode".to_owned(),
+                    )),
+                },
+            }
+        };
+
+        if let Some(dir) = read("MODELATOR_DIR")? {
+            self.dir = PathBuf::from(dir);
+        }
+        let checker_runtime = &mut self.model_checker_runtime;
+        if let Some(checker) = read("MODELATOR_CHECKER")? {
+            checker_runtime.model_checker = checker.parse().map_err(|_| {
+                invalid(
+                    "MODELATOR_CHECKER",
+                    &checker,
+                    "expected `tlc` or `apalache`".to_owned(),
+                )
+            })?;
+        }
+        if let Some(workers) = read("MODELATOR_WORKERS")? {
+            checker_runtime.workers = workers
+                .parse()
+                .map_err(|reason| invalid("MODELATOR_WORKERS", &workers, reason))?;
+        }
+        if let Some(traces) = read("MODELATOR_TRACES_PER_TEST")? {
+            // the same check as for `traces_per_test` in the config file
+            checker_runtime.traces_per_test = match traces.parse() {
+                Ok(n) if n > 0 => n,
+                _ => {
+                    return Err(invalid(
+                        "MODELATOR_TRACES_PER_TEST",
+                        &traces,
+                        "`traces_per_test` should be a positive integer".to_owned(),
+                    ))
+                }
+            };
+        }
+        if let Some(log) = read("MODELATOR_LOG")? {
+            checker_runtime.log = match log.as_str() {
+                "memory" => LogSink::Memory,
+                "none" => LogSink::None,
+                dir => LogSink::Dir(PathBuf::from(dir)),
+            };
+        }
+        if let Some(no_cache) = read("MODELATOR_NO_CACHE")? {
+            self.cache = match no_cache.as_str() {
+                "1" | "true" => false,
+                "0" | "false" => true,
+                _ => {
+                    return Err(invalid(
+                        "MODELATOR_NO_CACHE",
+                        &no_cache,
+                        "expected `1`, `true`, `0` or `false`".to_owned(),
+                    ))
+                }
+            };
+        }
+        Ok(self)
+    }
+
+    /// Set the options of a project configuration file, keeping the current
+    /// value of the options it doesn't set.
+    pub fn config(mut self, config: &config::ModelatorConfig) -> Self {
+        let checker_runtime = &mut self.model_checker_runtime;
+        if let Some(model_checker) = config.model_checker {
+            checker_runtime.model_checker = model_checker;
+        }
+        if let Some(workers) = config.workers {
+            checker_runtime.workers = workers;
+        }
+        if let Some(traces_per_test) = config.traces_per_test {
+            checker_runtime.traces_per_test = traces_per_test;
+        }
+        if let Some(jvm_args) = &config.jvm_args {
+            checker_runtime.jvm_args = jvm_args.clone();
+        }
+        checker_runtime.constants.extend(config.constants.clone());
+        if let Some(tla_library_paths) = &config.tla_library_paths {
+            self.tla_library_paths = tla_library_paths.clone();
+        }
+        if let Some(tests) = &config.tests {
+            self.test_discovery = TestDiscovery::Names(tests.clone());
+        }
+        self
+    }
+
     /// Set TLC runtime.
     pub fn model_checker_runtime(mut self, model_checker_runtime: ModelCheckerRuntime) -> Self {
         self.model_checker_runtime = model_checker_runtime;
@@ -147,8 +538,116 @@ impl ModelatorRuntime {
         self
     }
 
+    /// Set the directories where to look for extended TLA+ modules.
+    pub fn tla_library_paths(mut self, tla_library_paths: Vec<PathBuf>) -> Self {
+        self.tla_library_paths = tla_library_paths;
+        self
+    }
+
+    /// Set how test operators are discovered in TLA+ tests modules.
+    pub fn test_discovery(mut self, test_discovery: TestDiscovery) -> Self {
+        self.test_discovery = test_discovery;
+        self
+    }
+
+    /// Set the regex matching the names of test operators in TLA+ tests
+    /// modules, e.g. `^Scenario_`, instead of the default `Test` prefix or suffix.
+    /// See [`TestDiscovery::Regex`].
+    pub fn test_operator_pattern(mut self, test_operator_pattern: regex::Regex) -> Self {
+        self.test_discovery = TestDiscovery::Regex(test_operator_pattern);
+        self
+    }
+
+    /// Set the maximum number of model checkers run at once by [`ModelatorRuntime::traces`].
+    pub fn max_parallel_checkers(mut self, max_parallel_checkers: usize) -> Self {
+        self.max_parallel_checkers = Some(max_parallel_checkers.max(1));
+        self
+    }
+
+    /// Set whether failing traces run by [`ModelatorRuntime::run_tla_steps`]
+    /// should be shrunk to their shortest failing prefix.
+    pub const fn shrink(mut self, shrink: bool) -> Self {
+        self.shrink = shrink;
+        self
+    }
+
+    /// Set whether the steps executed against the system under test are recorded.
+    pub const fn record_execution(mut self, record_execution: bool) -> Self {
+        self.record_execution = record_execution;
+        self
+    }
+
+    /// Set the maximum number of the last steps kept when recording a trace.
+    pub const fn execution_log_cap(mut self, execution_log_cap: usize) -> Self {
+        self.execution_log_cap = execution_log_cap;
+        self
+    }
+
+    /// Set whether model checking stops after the first failing test.
+    pub const fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Set whether identical traces are run only once.
+    pub const fn dedup_executions(mut self, dedup_executions: bool) -> Self {
+        self.dedup_executions = dedup_executions;
+        self
+    }
+
+    /// Set whether the steps run by [`ModelatorRuntime::run_tla_steps`] are timed.
+    pub const fn step_timings(mut self, step_timings: bool) -> Self {
+        self.step_timings = step_timings;
+        self
+    }
+
+    /// Set the duration above which a step is reported as slow.
+    pub const fn slow_step_threshold(mut self, slow_step_threshold: Duration) -> Self {
+        self.slow_step_threshold = Some(slow_step_threshold);
+        self
+    }
+
+    /// Mark a test as expected to fail.
+    pub fn expect_fail(mut self, test: impl Into<String>) -> Self {
+        self.expected_failures.insert(test.into());
+        self
+    }
+
+    /// Set whether traces with variables that the steps don't deserialize fail.
+    pub const fn strict_fields(mut self, strict_fields: bool) -> Self {
+        self.strict_fields = strict_fields;
+        self
+    }
+
+    /// Set whether the model checkers are run, or only their commands composed.
+    pub const fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Set whether the states computed by [`ModelatorRuntime::next_states`] are cached.
+    pub const fn cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
     pub(crate) fn setup(&self) -> Result<(), Error> {
-        // init tracing subscriber (in case it's not already)
+        Self::init_tracing();
+
+        // the Java installation doesn't change during a run, so it's checked once
+        static JAVA_CHECK: once_cell::sync::OnceCell<Result<(), Error>> =
+            once_cell::sync::OnceCell::new();
+        JAVA_CHECK
+            .get_or_init(|| util::check_java_version(jar::MIN_JAVA_VERSION))
+            .clone()?;
+
+        self.ensure_dependencies_exist_on_filesystem()?;
+
+        Ok(())
+    }
+
+    /// Inits the tracing subscriber, in case it's not already.
+    fn init_tracing() {
         if let Err(e) = tracing_subscriber::fmt()
             .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
             .try_init()
@@ -158,10 +657,6 @@ impl ModelatorRuntime {
                 e
             );
         }
-
-        self.ensure_dependencies_exist_on_filesystem()?;
-
-        Ok(())
     }
 
     fn ensure_dependencies_exist_on_filesystem(&self) -> Result<(), Error> {
@@ -174,6 +669,14 @@ impl ModelatorRuntime {
 
         // download missing jars
         jar::download_jars_if_necessary(&self.dir)?;
+
+        // remove the stale logs of previous runs
+        if let (LogSink::Dir(log_dir), Some(retention)) = (
+            &self.model_checker_runtime.log.resolve(&self.dir),
+            self.model_checker_runtime.log_retention,
+        ) {
+            model::checker::prune_logs(log_dir, retention)?;
+        }
         tracing::trace!("modelator setup completed");
 
         Ok(())
@@ -200,37 +703,580 @@ impl ModelatorRuntime {
         tla_tests_file_path: P,
         tla_config_file_path: P,
     ) -> Result<BTreeMap<String, Result<Vec<artifact::JsonTrace>, Error>>, Error> {
-        // Each test maps to a result containing the vec of all it's traces.
+        let mut res = self.traces_many(&[(tla_tests_file_path, tla_config_file_path)])?;
+        Ok(res
+            .pop()
+            .expect("[modelator] there should be a result for each TLA+ tests file"))
+    }
 
-        let mut res: BTreeMap<String, Result<Vec<artifact::JsonTrace>, Error>> = BTreeMap::new();
+    /// Same as [`ModelatorRuntime::traces`], but for several pairs of TLA+
+    /// tests and config files at once.
+    /// Tests that are byte-identical across the given files are only model
+    /// checked once, with their traces being shared.
+    ///
+    /// Tests annotated with `\* @modelator:expect-holds` are expected to hold:
+    /// their result is empty if no counterexample is found, and an
+    /// [`Error::HoldsTestViolated`] otherwise.
+    #[allow(clippy::type_complexity)]
+    pub fn traces_many<P: AsRef<Path>>(
+        &self,
+        tla_files: &[(P, P)],
+    ) -> Result<Vec<BTreeMap<String, Result<Vec<artifact::JsonTrace>, Error>>>, Error> {
+        Ok(self
+            .expectations_and_traces_many(tla_files)?
+            .into_iter()
+            .map(|test_traces| {
+                test_traces
+                    .into_iter()
+                    .map(|(test_name, (_, traces))| (test_name, traces))
+                    .collect()
+            })
+            .collect())
+    }
 
+    /// Same as [`ModelatorRuntime::traces`], but runs the TLA+ tests file
+    /// against each of the given config files, e.g. to sweep over the values
+    /// of its constants.
+    /// Results are keyed by the name of the test and the path of the config file.
+    #[allow(clippy::type_complexity)]
+    pub fn traces_multi<P: AsRef<Path>>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_paths: &[P],
+    ) -> Result<BTreeMap<(String, String), Result<Vec<artifact::JsonTrace>, Error>>, Error> {
+        let tla_files: Vec<_> = tla_config_file_paths
+            .iter()
+            .map(|tla_config_file_path| {
+                (tla_tests_file_path.as_ref(), tla_config_file_path.as_ref())
+            })
+            .collect();
+        let results = self.traces_many(&tla_files)?;
+        Ok(tla_config_file_paths
+            .iter()
+            .zip(results)
+            .flat_map(|(tla_config_file_path, test_traces)| {
+                let config_name = tla_config_file_path.as_ref().to_string_lossy().into_owned();
+                test_traces
+                    .into_iter()
+                    .map(move |(test_name, traces)| ((test_name, config_name.clone()), traces))
+            })
+            .collect())
+    }
+
+    /// Same as [`ModelatorRuntime::traces`], but collects up to `n` distinct
+    /// traces per test by running the model checker several times, each time
+    /// with a different random seed (see
+    /// [`ModelCheckerRuntime::random_seed`]), starting from the configured
+    /// one. This gives more diverse traces than
+    /// [`ModelCheckerRuntime::traces_per_test`], whose traces tend to be
+    /// similar.
+    ///
+    /// The model checker is run until each test has `n` traces, or until
+    /// several runs in a row find no new trace. A test keeps the result of
+    /// its first run if that run fails. Only Apalache is randomized: with
+    /// TLC, the traces are those of a single run.
+    pub fn traces_diverse<P: AsRef<Path>>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_path: P,
+        n: usize,
+    ) -> Result<BTreeMap<String, Result<Vec<artifact::JsonTrace>, Error>>, Error> {
+        let mut test_traces: BTreeMap<String, Result<Vec<artifact::JsonTrace>, Error>> =
+            BTreeMap::new();
+        let mut runtime = self.clone();
+        let mut random_seed = self.model_checker_runtime.random_seed.unwrap_or_default();
+        let mut stale_runs = 0;
+        let mut first_run = true;
+
+        while stale_runs < DIVERSE_TRACES_MAX_STALE_RUNS {
+            runtime.model_checker_runtime.random_seed = Some(random_seed);
+            random_seed = random_seed.wrapping_add(1);
+
+            let mut found = false;
+            let results =
+                runtime.traces(tla_tests_file_path.as_ref(), tla_config_file_path.as_ref())?;
+            for (test_name, result) in results {
+                let diverse = test_traces
+                    .entry(test_name)
+                    .or_insert_with(|| Ok(Vec::new()));
+                match (diverse, result) {
+                    (Ok(diverse), Ok(traces)) => {
+                        for trace in traces {
+                            if diverse.len() < n && !diverse.contains(&trace) {
+                                diverse.push(trace);
+                                found = true;
+                            }
+                        }
+                    }
+                    // the failure of a later run doesn't hide the traces found
+                    (diverse, Err(error)) if first_run => *diverse = Err(error),
+                    _ => {}
+                }
+            }
+            first_run = false;
+
+            let done = test_traces.values().all(|result| match result {
+                Ok(traces) => traces.len() >= n,
+                Err(_) => true,
+            });
+            if done || self.model_checker_runtime.model_checker == ModelChecker::Tlc {
+                break;
+            }
+            stale_runs = if found { 0 } else { stale_runs + 1 };
+        }
+
+        Ok(test_traces)
+    }
+
+    /// Explore the states of the spec in a TLA+ file that are reachable in at
+    /// most `depth` steps from its initial states, and return their graph as a
+    /// [Graphviz](https://graphviz.org/) graph in the DOT language.
+    ///
+    /// The states are explored with TLC, regardless of the model checker
+    /// selected via [`ModelatorRuntime`].
+    pub fn explore_state_graph<P: AsRef<Path>>(
+        &self,
+        tla_file_path: P,
+        tla_config_file_path: P,
+        depth: usize,
+    ) -> Result<String, Error> {
         // setup modelator
         self.setup()?;
 
-        let file_suite =
-            TlaFileSuite::from_tla_and_config_paths(tla_tests_file_path, tla_config_file_path)?;
-
-        let tests = Tla::generate_tests(&file_suite)?;
-
-        #[allow(clippy::needless_collect)]
-        // rust iterators are lazy
-        // so we need to collect the traces in memory before deleting the work directory
-        let trace_results = (&tests)
-            .into_par_iter()
-            .map(|test| match self.model_checker_runtime.model_checker {
-                ModelChecker::Tlc => Tlc::test(&test.file_suite, self),
-                ModelChecker::Apalache => Apalache::test(&test.file_suite, self),
-            })
-            .collect::<Vec<_>>();
+        let file_suite = TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+            tla_file_path,
+            tla_config_file_path,
+            &self.tla_library_paths,
+        )?;
+        Tlc::state_graph(&file_suite, depth, self)
+    }
 
-        for (i, trace_result) in trace_results.into_iter().enumerate() {
-            let test_name = tests[i].name.clone();
-            let (traces, _) = trace_result?;
-            let jsons: Result<Vec<artifact::JsonTrace>, Error> = traces
+    /// Explore the states of the spec in a TLA+ file that are reachable in at
+    /// most `depth` steps from its initial states, as
+    /// [`ModelatorRuntime::explore_state_graph`] does, and return their graph
+    /// with the states as JSON. The states are numbered breadth-first from
+    /// the initial states; only the first `max_states` ones are kept, in
+    /// which case the graph is marked as truncated.
+    pub fn state_graph<P: AsRef<Path>>(
+        &self,
+        tla_file_path: P,
+        tla_config_file_path: P,
+        depth: usize,
+        max_states: usize,
+    ) -> Result<artifact::StateGraphArtifact, Error> {
+        let dot = self.explore_state_graph(tla_file_path, tla_config_file_path, depth)?;
+        let graph = model::checker::parse_tlc_state_graph(&dot, max_states)?;
+        if graph.truncated {
+            tracing::warn!("the state graph is truncated to {} states", max_states);
+        }
+        Ok(graph)
+    }
+
+    /// Explore the states of the spec in a TLA+ file that are reachable in
+    /// one step from `start_state`, a TLA+ formula such as
+    /// `/\ a = 0 /\ b = 0`, or its initial states if `None`. The states are
+    /// returned both as TLA+ and as JSON, in the order of their TLA+
+    /// formulas, skipping the first `skip` ones and returning at most `count`.
+    ///
+    /// The TLA+ formula of a returned state can be explored in turn, so as to
+    /// walk the state graph of the spec. The states explored are cached in
+    /// the modelator directory.
+    ///
+    /// The states are explored with TLC, regardless of the model checker
+    /// selected via [`ModelatorRuntime`].
+    pub fn next_states<P: AsRef<Path>>(
+        &self,
+        tla_file_path: P,
+        tla_config_file_path: P,
+        start_state: Option<&str>,
+        count: usize,
+        skip: usize,
+    ) -> Result<Vec<TlaAndJsonState>, Error> {
+        // setup modelator
+        self.setup()?;
+
+        let file_suite = TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+            tla_file_path,
+            tla_config_file_path,
+            &self.tla_library_paths,
+        )?;
+        let mut cache = if self.cache {
+            Some(cache::NextStatesCache::new(self)?)
+        } else {
+            None
+        };
+        let key =
+            cache::NextStatesCache::key(&file_suite, start_state, &self.model_checker_runtime);
+        let cached = match &mut cache {
+            Some(cache) => cache.get(&key)?,
+            None => None,
+        };
+        let states = match cached {
+            Some(states) => states,
+            None => {
+                let states: Vec<String> = match start_state {
+                    Some(state) => Tlc::next_states(&file_suite, state, self)?.next_states,
+                    None => Tlc::initial_states(&file_suite, self)?,
+                }
                 .into_iter()
-                .map(Tla::tla_trace_to_json_trace)
+                .map(|state| state.tla)
                 .collect();
-            res.insert(test_name, jsons);
+                if let Some(cache) = &mut cache {
+                    cache.insert(key, &states)?;
+                }
+                states
+            }
+        };
+        states
+            .into_iter()
+            .skip(skip)
+            .take(count)
+            .map(TlaAndJsonState::from_tla)
+            .collect()
+    }
+
+    /// Walk the state graph of the spec in a TLA+ file `walks` times, each
+    /// time starting at one of its initial states and taking `depth` steps,
+    /// picking the next state among the successors of the current one at
+    /// random. The same `seed` gives the same walks.
+    ///
+    /// The traces of the walks can be run with [`StepRunner::run`], like
+    /// those of [`ModelatorRuntime::run_tla_steps`], so as to exercise a
+    /// system with long scenarios that aren't tied to a test. A walk reaching
+    /// a state without successors stops there, and is marked as a dead end.
+    ///
+    /// The states are explored with TLC via [`ModelatorRuntime::next_states`].
+    pub fn random_walks<P: AsRef<Path>>(
+        &self,
+        tla_file_path: P,
+        tla_config_file_path: P,
+        walks: usize,
+        depth: usize,
+        seed: u64,
+    ) -> Result<Vec<RandomWalk>, Error> {
+        let tla_file_path = tla_file_path.as_ref();
+        let tla_config_file_path = tla_config_file_path.as_ref();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let initial_states =
+            self.next_states(tla_file_path, tla_config_file_path, None, usize::MAX, 0)?;
+
+        (0..walks)
+            .map(|_| {
+                let mut states = Vec::new();
+                let mut state = initial_states.choose(&mut rng).cloned();
+                while let Some(current) = state {
+                    states.push(current.json);
+                    if states.len() > depth {
+                        return Ok(RandomWalk {
+                            trace: states.into(),
+                            dead_end: false,
+                        });
+                    }
+                    let next_states = self.next_states(
+                        tla_file_path,
+                        tla_config_file_path,
+                        Some(&current.tla),
+                        usize::MAX,
+                        0,
+                    )?;
+                    state = next_states.choose(&mut rng).cloned();
+                }
+                Ok(RandomWalk {
+                    trace: states.into(),
+                    dead_end: true,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the names of the variables declared by the TLA+ module in a
+    /// file, including the variables of the modules it extends, e.g. to check
+    /// that a `Step` deserialized from the states of its traces has a field
+    /// per variable.
+    ///
+    /// The module is parsed with Apalache, regardless of the model checker
+    /// selected via [`ModelatorRuntime`].
+    pub fn tla_variables<P: AsRef<Path>>(&self, tla_file_path: P) -> Result<TlaVariables, Error> {
+        // setup modelator
+        self.setup()?;
+
+        let file_suite =
+            TlaFileSuite::from_tla_path_with_library_paths(tla_file_path, &self.tla_library_paths)?;
+        Apalache::tla_variables(&file_suite, self)
+    }
+
+    /// Returns the key under which the traces of a TLA+ file and its TLA+
+    /// config file are cached, e.g. to locate a cached trace under
+    /// `<dir>/cache/` when debugging. The key is computed from the names and
+    /// contents of the files and of their dependencies, so that byte-identical
+    /// files share the same key, wherever they are.
+    ///
+    /// Nothing is model checked, and the cache isn't modified.
+    pub fn trace_cache_key<P: AsRef<Path>>(
+        &self,
+        tla_file_path: P,
+        tla_config_file_path: P,
+    ) -> Result<String, Error> {
+        let file_suite = TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+            tla_file_path,
+            tla_config_file_path,
+            &self.tla_library_paths,
+        )?;
+        Ok(cache::key(&file_suite))
+    }
+
+    /// Returns the commands that would model check each test of a TLA+ tests
+    /// file, in order, without running them (see [`ModelatorRuntime::dry_run`]),
+    /// e.g. to reproduce an issue by hand. Each command changes to a
+    /// temporary directory where the files of its test are kept. As the
+    /// commands aren't run, Java and the model checker jars aren't required.
+    pub fn plan<P: AsRef<Path>>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_path: P,
+    ) -> Result<Vec<String>, Error> {
+        // the commands are only composed: neither Java nor the jars are needed
+        Self::init_tracing();
+
+        let file_suite = TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+            tla_tests_file_path,
+            tla_config_file_path,
+            &self.tla_library_paths,
+        )?;
+        let runtime = self.clone().dry_run(true);
+        Tla::generate_tests(&file_suite, &self.test_discovery)?
+            .iter()
+            .map(|test| {
+                let result = match self.model_checker_runtime.model_checker {
+                    ModelChecker::Tlc => Tlc::test(&test.file_suite, &runtime).map(|_| ()),
+                    ModelChecker::Apalache => {
+                        Apalache::test(&test.file_suite, &runtime).map(|_| ())
+                    }
+                };
+                match result {
+                    Err(Error::DryRun(command)) => Ok(command),
+                    Err(e) => Err(e),
+                    Ok(()) => unreachable!("[modelator] a model checker ran in a dry run"),
+                }
+            })
+            .collect()
+    }
+
+    /// Checks that the TLA+ module in a file and its TLA+ config file are
+    /// well-formed, without generating any trace, e.g. to get quick feedback
+    /// while editing a spec.
+    ///
+    /// The module is parsed with Apalache, regardless of the model checker
+    /// selected via [`ModelatorRuntime`]. Its syntax errors are returned as
+    /// [`Error::ApalacheParseError`], along with their line and column.
+    pub fn validate_spec<P: AsRef<Path>>(
+        &self,
+        tla_file_path: P,
+        tla_config_file_path: P,
+    ) -> Result<(), Error> {
+        // setup modelator
+        self.setup()?;
+
+        let file_suite = TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+            tla_file_path,
+            tla_config_file_path,
+            &self.tla_library_paths,
+        )?;
+        file_suite.tla_config_file.parts()?;
+        Apalache::parse(&file_suite, self).map(|_| ())
+    }
+
+    /// Model checks the tests of a TLA+ tests file, like
+    /// [`ModelatorRuntime::traces`], and writes all the artifacts generated
+    /// for them to `dir`, e.g. for archival. Each test has its own directory,
+    /// containing its TLA+ module and config file along with their
+    /// dependencies, its counterexamples as TLA+ modules
+    /// (`counterexample_<n>.tla`) and JSON traces (`trace_<n>.json`), and the
+    /// output of the model checker (`checker.log`). The returned
+    /// [`RunManifest`] describing them is also written to `manifest.json`.
+    ///
+    /// A test failing doesn't stop the export: its error is recorded in the
+    /// manifest instead.
+    ///
+    /// Returns [`Error::FileAlreadyExists`] if `dir` isn't empty, unless
+    /// `overwrite` is set, in which case the directories of the tests are
+    /// replaced, or if two tests would be written to the same directory.
+    pub fn export_run<P: AsRef<Path>>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_path: P,
+        dir: impl AsRef<Path>,
+        overwrite: bool,
+    ) -> Result<RunManifest, Error> {
+        // setup modelator
+        self.setup()?;
+
+        let file_suite = TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+            tla_tests_file_path,
+            tla_config_file_path,
+            &self.tla_library_paths,
+        )?;
+        let tests = Tla::generate_tests(&file_suite, &self.test_discovery)?;
+        export::export_run(self, &tests, dir.as_ref(), overwrite)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn expectations_and_traces_many<P: AsRef<Path>>(
+        &self,
+        tla_files: &[(P, P)],
+    ) -> Result<
+        Vec<BTreeMap<String, (TestExpectation, Result<Vec<artifact::JsonTrace>, Error>)>>,
+        Error,
+    > {
+        // setup modelator
+        self.setup()?;
+
+        let tests_per_file = tla_files
+            .iter()
+            .map(|(tla_tests_file_path, tla_config_file_path)| {
+                let file_suite = TlaFileSuite::from_tla_and_config_paths_with_library_paths(
+                    tla_tests_file_path,
+                    tla_config_file_path,
+                    &self.tla_library_paths,
+                )?;
+                Tla::generate_tests(&file_suite, &self.test_discovery)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // the tests of a file share its dependencies, so they are written only once
+        let dependencies_dirs = tests_per_file
+            .iter()
+            .map(|tests| {
+                let dir = tempdir()?;
+                if let Some(test) = tests.first() {
+                    model::checker::write_dependencies(dir.path(), &test.file_suite)?;
+                }
+                Ok(dir)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let tests: Vec<_> = tests_per_file
+            .iter()
+            .zip(&dependencies_dirs)
+            .flat_map(|(tests, dir)| tests.iter().map(move |test| (test, dir.path())))
+            .collect();
+        let keys: Vec<String> = tests
+            .iter()
+            .map(|(test, _)| cache::key(&test.file_suite))
+            .collect();
+
+        // set once a test fails, so that the tests not started yet are skipped
+        let failed = AtomicBool::new(false);
+
+        // each model checker runs in its own JVM, so their number is limited
+        let parallel_checkers = self
+            .max_parallel_checkers
+            .unwrap_or_else(rayon::current_num_threads)
+            .min(tests.len())
+            .max(1);
+        let checkers = util::Semaphore::new(parallel_checkers);
+
+        // byte-identical tests share the same key, so they are checked only once
+        let mut trace_results = cache::single_flight(&keys, |i| {
+            let (test, dependencies_dir) = tests[i];
+            let _permit = checkers.acquire();
+            if self.fail_fast && failed.load(Ordering::SeqCst) {
+                return Err(Error::TestSkipped(test.name.clone()));
+            }
+            // the tests are run in parallel, so each one has its own log
+            let mut runtime = self.clone();
+            runtime.model_checker_runtime.log =
+                self.model_checker_runtime.log.resolve(&self.dir).for_test(
+                    test_module_name(test.file_suite.tla_file.module_name(), &test.name),
+                    &test.name,
+                );
+            // and the cores are split between the model checkers
+            if parallel_checkers > 1
+                && self.model_checker_runtime.workers == ModelCheckerWorkers::Auto
+            {
+                runtime.model_checker_runtime.workers =
+                    ModelCheckerWorkers::Count((num_cpus::get() / parallel_checkers).max(1));
+            }
+            let trace_result = match self.model_checker_runtime.model_checker {
+                ModelChecker::Tlc => Tlc::test_with_dependencies_dir(
+                    &test.file_suite,
+                    &runtime,
+                    Some(dependencies_dir),
+                ),
+                ModelChecker::Apalache => Apalache::test_with_dependencies_dir(
+                    &test.file_suite,
+                    &runtime,
+                    Some(dependencies_dir),
+                ),
+            }
+            .map(|(traces, stdout)| {
+                tracing::info!("{}: {}", test.name, stdout.stats());
+                traces
+            });
+            let passed = match (test.expectation, &trace_result) {
+                (TestExpectation::Holds, Ok(traces)) => traces.is_empty(),
+                (TestExpectation::Holds, Err(Error::NoTestTraceFound(_))) => true,
+                (_, result) => result.is_ok(),
+            };
+            if !passed {
+                failed.store(true, Ordering::SeqCst);
+            }
+            trace_result
+        })
+        .into_iter();
+
+        let mut res = Vec::new();
+        for tests in &tests_per_file {
+            // Each test maps to a result containing the vec of all it's traces.
+            let mut test_traces = BTreeMap::new();
+            for (test, trace_result) in tests.iter().zip(trace_results.by_ref()) {
+                let traces = match (test.expectation, trace_result) {
+                    // a test expected to hold passes iff no counterexample is found
+                    (TestExpectation::Holds, Err(Error::NoTestTraceFound(_))) => Vec::new(),
+                    // skipped tests fail on their own, without hiding the failure causing it
+                    (_, Err(Error::TestSkipped(name))) => {
+                        test_traces.insert(
+                            test.name.clone(),
+                            (test.expectation, Err(Error::TestSkipped(name))),
+                        );
+                        continue;
+                    }
+                    (_, trace_result) => trace_result?,
+                };
+                let jsons: Result<Vec<artifact::JsonTrace>, Error> = traces
+                    .into_iter()
+                    .map(|trace| {
+                        let json = Tla::tla_trace_to_json_trace_with_limits(
+                            trace,
+                            self.model_checker_runtime.max_trace_states,
+                            self.model_checker_runtime.max_state_bytes,
+                        )?;
+                        if let Some(omitted) = json.omitted_states() {
+                            tracing::warn!(
+                                "{}: trace truncated, {} states omitted",
+                                test.name,
+                                omitted
+                            );
+                        }
+                        Ok(json)
+                    })
+                    .collect();
+                let jsons = match test.expectation {
+                    TestExpectation::Counterexample if self.model_checker_runtime.dedup_traces => {
+                        jsons.map(artifact::json_trace::dedup)
+                    }
+                    TestExpectation::Counterexample => jsons,
+                    TestExpectation::Holds => jsons.and_then(|mut jsons| match jsons.pop() {
+                        None => Ok(jsons),
+                        Some(trace) => Err(Error::HoldsTestViolated {
+                            test: test.name.clone(),
+                            trace,
+                        }),
+                    }),
+                };
+                test_traces.insert(test.name.clone(), (test.expectation, jsons));
+            }
+            res.push(test_traces);
         }
 
         Ok(res)
@@ -316,7 +1362,7 @@ impl ModelatorRuntime {
     ///     assert!(runtime.run_tla_steps(tla_tests_file_path, tla_config_file_path, &mut system).is_ok());
     /// }
     /// ```
-    pub fn run_tla_steps<P, System, Step>(
+    pub fn run_tla_steps<P, System, Step, E>(
         &self,
         tla_tests_file_path: P,
         tla_config_file_path: P,
@@ -324,24 +1370,279 @@ impl ModelatorRuntime {
     ) -> Result<TestReport, Error>
     where
         P: AsRef<Path>,
-        System: StepRunner<Step> + Debug + Clone,
+        System: StepRunner<Step, E> + Debug + Clone,
         Step: DeserializeOwned + Debug + Clone,
+        E: std::fmt::Display + Debug,
     {
-        Ok(TestReport {
-            test_name_to_trace_execution_result: {
-                let mut ret = BTreeMap::new();
+        self.run_tla_steps_with_invariants(tla_tests_file_path, tla_config_file_path, system, &[])
+    }
 
-                let traces_for_tests = self.traces(tla_tests_file_path, tla_config_file_path)?;
+    /// Same as [`ModelatorRuntime::run_tla_steps`], but also checks the given
+    /// named invariants of the SUT after each step of each trace. A violated
+    /// invariant fails the trace at the step after which it was checked.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// fn sum_is_consistent(system: &NumberSystem) -> Result<(), String> {
+    ///     if system.sum == system.a + system.b {
+    ///         Ok(())
+    ///     } else {
+    ///         Err(format!("{} + {} != {}", system.a, system.b, system.sum))
+    ///     }
+    /// }
+    ///
+    /// runtime.run_tla_steps_with_invariants(
+    ///     tla_tests_file_path,
+    ///     tla_config_file_path,
+    ///     &mut system,
+    ///     &[("SumIsConsistent", sum_is_consistent)],
+    /// )
+    /// ```
+    pub fn run_tla_steps_with_invariants<P, System, Step, E>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_path: P,
+        system: &mut System,
+        invariants: &[(&str, step_runner::Invariant<System>)],
+    ) -> Result<TestReport, Error>
+    where
+        P: AsRef<Path>,
+        System: StepRunner<Step, E> + Debug + Clone,
+        Step: DeserializeOwned + Debug + Clone,
+        E: std::fmt::Display + Debug,
+    {
+        self.run_tla_steps_with(
+            tla_tests_file_path,
+            tla_config_file_path,
+            system,
+            invariants,
+            |system| format!("{:?}", system),
+        )
+    }
 
-                for (test_name, traces) in traces_for_tests {
-                    let traces = traces?;
-                    let results: Vec<Result<(), TestError>> =
-                        traces.into_iter().map(|it| system.run(it)).collect();
-                    ret.insert(test_name, results);
-                }
-                ret
+    /// Same as [`ModelatorRuntime::run_tla_steps`], but the final state of the
+    /// system under test after each trace is captured as its [`Snapshot`],
+    /// instead of its [`Debug`] output (see [`TestReport::final_state`]).
+    pub fn run_tla_steps_with_snapshot<P, System, Step, E>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_path: P,
+        system: &mut System,
+    ) -> Result<TestReport, Error>
+    where
+        P: AsRef<Path>,
+        System: StepRunner<Step, E> + Snapshot + Debug + Clone,
+        Step: DeserializeOwned + Debug + Clone,
+        E: std::fmt::Display + Debug,
+    {
+        self.run_tla_steps_with(
+            tla_tests_file_path,
+            tla_config_file_path,
+            system,
+            &[],
+            Snapshot::snapshot,
+        )
+    }
+
+    /// Same as [`ModelatorRuntime::run_tla_steps`], but fails the steps that
+    /// take longer than `step_timeout`: the remaining steps of their trace are
+    /// skipped, and the system under test is restored as it was before the trace.
+    ///
+    /// Each step is run on a separate thread, by a copy of the system which
+    /// replaces it once the step is over, hence the additional `Send + 'static`
+    /// bounds. The copy running a timed out step is finalized if the step ever ends.
+    /// There is no such timeout for [`ModelatorRuntime::run_tla_events`], nor
+    /// for an [`event::EventStepRunner`], which aren't [`Send`].
+    pub fn run_tla_steps_with_timeout<P, System, Step, E>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_path: P,
+        system: &mut System,
+        step_timeout: Duration,
+    ) -> Result<TestReport, Error>
+    where
+        P: AsRef<Path>,
+        System: StepRunner<Step, E> + Debug + Clone + Send + 'static,
+        Step: DeserializeOwned + Debug + Clone + Send + 'static,
+        E: std::fmt::Display + Debug + Send + 'static,
+    {
+        let mut time_limited = step_runner::TimeLimited::new(system.clone(), step_timeout);
+        let report = self.run_tla_steps_with(
+            tla_tests_file_path,
+            tla_config_file_path,
+            &mut time_limited,
+            &[],
+            |time_limited| format!("{:?}", time_limited.system),
+        );
+        *system = time_limited.system;
+        report
+    }
+
+    /// Runs a step runner on the traces generated from TLA+ tests, checking
+    /// the given invariants and capturing the final state of the system with
+    /// the given function.
+    fn run_tla_steps_with<P, System, Step, E>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_path: P,
+        system: &mut System,
+        invariants: &[(&str, step_runner::Invariant<System>)],
+        snapshot: impl Fn(&System) -> String,
+    ) -> Result<TestReport, Error>
+    where
+        P: AsRef<Path>,
+        System: StepRunner<Step, E> + Debug + Clone,
+        Step: DeserializeOwned + Debug + Clone,
+        E: std::fmt::Display + Debug,
+    {
+        self.test_report(
+            tla_tests_file_path,
+            tla_config_file_path,
+            |test_name, trace_index, trace| {
+                let mut execution = if invariants.is_empty() {
+                    self.run_timed_trace(system, trace, test_name, trace_index)
+                } else {
+                    let mut checked = step_runner::Checked::new(system.clone(), invariants);
+                    let execution =
+                        self.run_timed_trace(&mut checked, trace, test_name, trace_index);
+                    *system = checked.system;
+                    execution
+                };
+                execution.final_state = Some(snapshot(system));
+                execution
             },
-        })
+        )
+    }
+
+    /// Runs a step runner on a trace, timing its steps if
+    /// [`ModelatorRuntime::step_timings`] or [`ModelatorRuntime::slow_step_threshold`] is set.
+    fn run_timed_trace<System, Step, E>(
+        &self,
+        system: &mut System,
+        trace: &artifact::JsonTrace,
+        test_name: &str,
+        trace_index: usize,
+    ) -> TraceExecution
+    where
+        System: StepRunner<Step, E> + Debug + Clone,
+        Step: DeserializeOwned + Debug + Clone,
+        E: std::fmt::Display + Debug,
+    {
+        let (result, execution_log, step_timings) = if self.step_timings
+            || self.slow_step_threshold.is_some()
+        {
+            let mut timed = step_runner::Timed::new(system.clone(), self.slow_step_threshold);
+            let (result, execution_log) = self.run_trace(&mut timed, trace, test_name, trace_index);
+            *system = timed.system;
+            (result, execution_log, Some(timed.timings))
+        } else {
+            let (result, execution_log) = self.run_trace(system, trace, test_name, trace_index);
+            (result, execution_log, None)
+        };
+        TraceExecution {
+            result,
+            execution_log,
+            final_state: None,
+            step_timings,
+        }
+    }
+
+    /// Runs a step runner on a trace, recording and shrinking it as configured.
+    fn run_trace<System, Step, E>(
+        &self,
+        system: &mut System,
+        trace: &artifact::JsonTrace,
+        test_name: &str,
+        trace_index: usize,
+    ) -> (Result<(), TestError>, Option<ExecutionLog>)
+    where
+        System: StepRunner<Step, E> + Debug + Clone,
+        Step: DeserializeOwned + Debug + Clone,
+        E: std::fmt::Display + Debug,
+    {
+        if self.strict_fields {
+            let variables = step_runner::ignored_variables::<Step>(trace);
+            if !variables.is_empty() {
+                let error = TestError::MissingStepFields {
+                    step_type: std::any::type_name::<Step>().to_owned(),
+                    variables: variables.into_iter().collect(),
+                };
+                return (Err(error), None);
+            }
+        }
+        let initial_system = self.shrink.then(|| system.clone());
+        let (result, execution_log) = if self.record_execution {
+            let mut recorded =
+                step_runner::Recorded::new(system.clone(), trace, self.execution_log_cap);
+            let result = recorded.run_with_ctx(trace.clone(), test_name, trace_index);
+            *system = recorded.system;
+            (result, Some(recorded.log))
+        } else {
+            (
+                system.run_with_ctx(trace.clone(), test_name, trace_index),
+                None,
+            )
+        };
+        let result = result.map_err(|error| error.with_execution_log(execution_log.as_ref()));
+        let result = match (result, initial_system) {
+            (Err(error @ TestError::FailedTest(_)), Some(initial_system)) => {
+                let steps: Vec<_> = trace.clone().into_iter().collect();
+                let prefix_length = step_runner::failing_prefix_length(&initial_system, &steps)
+                    .unwrap_or(steps.len());
+                Err(TestError::ShrunkFailedTest {
+                    error: Box::new(error),
+                    prefix_length,
+                    trace_length: steps.len(),
+                    prefix: steps[..prefix_length].to_vec().into(),
+                })
+            }
+            (result, _) => result,
+        };
+        (result, execution_log)
+    }
+
+    /// Shrinks a trace on which the system under test (SUT) fails, when run
+    /// as a [`StepRunner`], into a shorter trace on which it still fails.
+    ///
+    /// First, the shortest failing prefix of the trace is found; then, its
+    /// intermediate steps are greedily removed as long as the SUT keeps failing.
+    /// Each attempt is run on a copy of `system`, while `system` itself is
+    /// finally run on the shrunk trace, so that its failing state can be inspected.
+    ///
+    /// Returns `None` if the SUT doesn't fail on the given trace.
+    pub fn shrink_trace<System, Step, E>(
+        &self,
+        trace: artifact::JsonTrace,
+        system: &mut System,
+    ) -> Option<artifact::JsonTrace>
+    where
+        System: StepRunner<Step, E> + Debug + Clone,
+        Step: DeserializeOwned + Debug + Clone,
+        E: std::fmt::Display + Debug,
+    {
+        let steps: Vec<_> = trace.into_iter().collect();
+
+        // find the shortest failing prefix
+        let len = step_runner::failing_prefix_length(system, &steps)?;
+        let mut steps = steps[..len].to_vec();
+
+        // greedily remove intermediate steps, always keeping the initial one
+        let mut i = 1;
+        while i < steps.len() {
+            let mut candidate = steps.clone();
+            candidate.remove(i);
+            if step_runner::fails(system, &candidate) {
+                steps = candidate;
+            } else {
+                i += 1;
+            }
+        }
+
+        let shrunk: artifact::JsonTrace = steps.into();
+        let _ =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| system.run(shrunk.clone())));
+        Some(shrunk)
     }
 
     /// Run the system under test (SUT) using the abstract events obtained
@@ -350,6 +1651,11 @@ impl ModelatorRuntime {
     /// as well as [`StateHandler`] and [`ActionHandler`] to be implemented
     /// for abstract states and actions you want to handle.
     ///
+    /// The events aren't limited in time: unlike
+    /// [`ModelatorRuntime::run_tla_steps_with_timeout`], which runs each step
+    /// on a separate thread, the handlers of an [`EventRunner`] can't be moved
+    /// to another thread, so a hanging handler hangs the run.
+    ///
     /// # Example
     ///
     /// ```
@@ -399,7 +1705,9 @@ impl ModelatorRuntime {
     ///     }
     /// }
     ///
-    /// // We define also an action handler that processes abstract actions
+    /// // We define also an action handler that processes abstract actions.
+    /// // The outcome it returns is checked against the `actionOutcome` of the
+    /// // TLA+ trace, so it doesn't need to be asserted here.
     /// impl ActionHandler<Action> for NumberSystem {
     ///     type Outcome = String;
     ///
@@ -456,46 +1764,780 @@ impl ModelatorRuntime {
         P: AsRef<Path>,
         System: Debug + Default,
     {
-        Ok(TestReport {
-            test_name_to_trace_execution_result: {
-                let mut ret = BTreeMap::new();
-
-                let traces_for_tests = self.traces(tla_tests_file_path, tla_config_file_path)?;
-
-                for (test_name, traces) in traces_for_tests {
-                    let traces = traces?;
-                    let results: Vec<Result<(), TestError>> = traces
-                        .iter()
-                        .map(|trace| {
-                            let events: EventStream = trace.clone().into();
-                            runner
-                                .run(system, &mut events.into_iter())
-                                .map_err(|op| match op {
-                                    TestError::UnhandledTest { system, .. } => {
-                                        TestError::UnhandledTest {
-                                            test: trace.to_string(),
-                                            system,
-                                        }
-                                    }
-                                    TestError::FailedTest {
-                                        message,
-                                        location,
-                                        system,
-                                        ..
-                                    } => TestError::FailedTest {
-                                        test: trace.to_string(),
-                                        message,
-                                        location,
-                                        system,
-                                    },
-                                    TestError::Modelator(_) => op,
-                                })
-                        })
-                        .collect();
-                    ret.insert(test_name, results);
-                }
-                ret
-            },
+        self.test_report(tla_tests_file_path, tla_config_file_path, |_, _, trace| {
+            let result = self.run_json_trace_events(trace, system, runner);
+            TraceExecution {
+                result,
+                execution_log: runner.execution_log().cloned(),
+                final_state: Some(format!("{:?}", system)),
+                step_timings: None,
+            }
         })
     }
+
+    /// Run the system under test (SUT) using the abstract events of a single
+    /// trace, such as one read with [`artifact::JsonTrace::read_json`], instead
+    /// of the traces generated from TLA+ tests (see [`ModelatorRuntime::run_tla_events`]).
+    /// Failures report the whole trace as their test, and the trace step at
+    /// which the SUT failed.
+    pub fn run_json_trace_events<System>(
+        &self,
+        trace: &artifact::JsonTrace,
+        system: &mut System,
+        runner: &mut event::EventRunner<System>,
+    ) -> Result<(), TestError>
+    where
+        System: Debug + Default,
+    {
+        if self.record_execution && runner.execution_log.is_none() {
+            runner.execution_log = Some(ExecutionLog::new(self.execution_log_cap));
+        }
+        let events = EventStream::from_json_trace(trace.clone(), runner.outcome_field());
+        runner
+            .run(system, &mut events.into_iter())
+            .map_err(|op| match op {
+                TestError::UnhandledTest { system, .. } => TestError::UnhandledTest {
+                    test: trace.to_string(),
+                    system,
+                },
+                TestError::FailedTest(mut failure) => {
+                    if let Some(step) = failure.step.as_mut() {
+                        step.value = trace.clone().into_iter().nth(step.index);
+                    }
+                    failure.test = trace.to_string();
+                    TestError::FailedTest(failure)
+                }
+                TestError::Modelator(_)
+                | TestError::FinalizationFailed { .. }
+                | TestError::ShrunkFailedTest { .. }
+                | TestError::TraceDivergence { .. }
+                | TestError::MissingStepFields { .. } => op,
+            })
+    }
+
+    /// Runs the given function on each trace generated from TLA+ tests,
+    /// along with the name of the test and the index of the trace.
+    /// Tests expected to hold don't generate traces, so the function is not run
+    /// for them: their result only depends on the model checker.
+    /// The function may also return the steps it recorded while running the trace,
+    /// and the final state of the system under test.
+    fn test_report<P, F>(
+        &self,
+        tla_tests_file_path: P,
+        tla_config_file_path: P,
+        mut run: F,
+    ) -> Result<TestReport, Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&str, usize, &artifact::JsonTrace) -> TraceExecution,
+    {
+        let mut res =
+            self.expectations_and_traces_many(&[(tla_tests_file_path, tla_config_file_path)])?;
+        let traces_for_tests = res
+            .pop()
+            .expect("[modelator] there should be a result for each TLA+ tests file");
+
+        let mut report = TestReport {
+            test_name_to_trace_execution_result: BTreeMap::new(),
+            holds_test_name_to_result: BTreeMap::new(),
+            execution_logs: BTreeMap::new(),
+            trace_ids: BTreeMap::new(),
+            final_states: BTreeMap::new(),
+            expected_failures: self.expected_failures.clone(),
+            deduplicated_executions: 0,
+            step_timings: BTreeMap::new(),
+        };
+        // results of the traces already run, by trace hash, if deduplicating
+        let mut executions: BTreeMap<String, TraceExecution> = BTreeMap::new();
+        // set once a test fails, so that the next ones are skipped if failing fast
+        let mut failed = false;
+        for (test_name, (expectation, traces)) in traces_for_tests {
+            let skipped = match &traces {
+                Err(Error::TestSkipped(_)) => true,
+                _ => self.fail_fast && failed,
+            };
+            if skipped {
+                let result = Err(TestError::Modelator(Error::TestSkipped(test_name.clone())));
+                match expectation {
+                    TestExpectation::Counterexample => {
+                        report
+                            .test_name_to_trace_execution_result
+                            .insert(test_name, vec![result]);
+                    }
+                    TestExpectation::Holds => {
+                        report.holds_test_name_to_result.insert(test_name, result);
+                    }
+                }
+                continue;
+            }
+            match (expectation, traces) {
+                (TestExpectation::Counterexample, traces) => {
+                    let mut results = Vec::new();
+                    for (trace_index, trace) in traces?.iter().enumerate() {
+                        let hash = self.dedup_executions.then(|| trace.hash());
+                        let TraceExecution {
+                            result,
+                            execution_log,
+                            final_state,
+                            step_timings,
+                        } = match hash.as_ref().and_then(|hash| executions.get(hash)) {
+                            Some(execution) => {
+                                report.deduplicated_executions += 1;
+                                execution.clone()
+                            }
+                            None => {
+                                let execution = run(&test_name, trace_index, trace);
+                                if let Some(hash) = hash {
+                                    executions.insert(hash, execution.clone());
+                                }
+                                execution
+                            }
+                        };
+                        if let Some(execution_log) = execution_log {
+                            report
+                                .execution_logs
+                                .insert((test_name.clone(), trace_index), execution_log);
+                        }
+                        if let Some(step_timings) = step_timings {
+                            report
+                                .step_timings
+                                .insert((test_name.clone(), trace_index), step_timings);
+                        }
+                        let result = match final_state {
+                            Some(final_state) => {
+                                let result =
+                                    result.map_err(|error| error.with_system(&final_state));
+                                report
+                                    .final_states
+                                    .insert((test_name.clone(), trace_index), final_state);
+                                result
+                            }
+                            None => result,
+                        };
+                        let id = trace.id();
+                        report
+                            .trace_ids
+                            .insert((test_name.clone(), trace_index), id.clone());
+                        let result = result.map_err(|error| error.with_trace_id(id));
+                        failed |= result.is_err();
+                        results.push(result);
+                        // the remaining traces are skipped if failing fast
+                        if self.fail_fast && failed {
+                            break;
+                        }
+                    }
+                    report
+                        .test_name_to_trace_execution_result
+                        .insert(test_name, results);
+                }
+                (TestExpectation::Holds, Ok(_)) => {
+                    report.holds_test_name_to_result.insert(test_name, Ok(()));
+                }
+                (TestExpectation::Holds, Err(e @ Error::HoldsTestViolated { .. })) => {
+                    failed = true;
+                    report
+                        .holds_test_name_to_result
+                        .insert(test_name, Err(TestError::Modelator(e)));
+                }
+                (TestExpectation::Holds, Err(e)) => return Err(e),
+            }
+        }
+        if report.deduplicated_executions > 0 {
+            tracing::info!(
+                "{} trace executions saved by deduplicating identical traces",
+                report.deduplicated_executions
+            );
+        }
+        Ok(report)
+    }
+}
+
+/// Returns the name of the TLA+ tests module a test was generated from: the
+/// module of the test is named `<module>_<test>`.
+fn test_module_name<'a>(module_name: &'a str, test_name: &str) -> &'a str {
+    module_name
+        .strip_suffix(test_name)
+        .and_then(|module_name| module_name.strip_suffix('_'))
+        .unwrap_or(module_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// Serializes the tests setting environment variables, which are global
+    /// to the process.
+    static ENV_MUTEX: Lazy<Mutex<()>> = Lazy::new(Mutex::default);
+
+    /// Runs `f` with the given environment variables set, restoring them after.
+    fn with_env<T>(vars: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+        let _guard = ENV_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        let previous: Vec<_> = vars
+            .iter()
+            .map(|(name, value)| {
+                let previous = env::var_os(name);
+                env::set_var(name, value);
+                (name, previous)
+            })
+            .collect();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+        for (name, previous) in previous {
+            match previous {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        result.unwrap_or_else(|e| std::panic::resume_unwind(e))
+    }
+
+    #[derive(Debug, Clone, Deserialize, PartialEq)]
+    struct A {
+        a: u64,
+    }
+
+    #[derive(Debug, Clone, Deserialize, PartialEq)]
+    struct B {
+        b: u64,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    enum NumbersAction {
+        None,
+        IncreaseA,
+        IncreaseB,
+    }
+
+    impl StateHandler<A> for test_util::NumberSystem {
+        fn init(&mut self, state: A) {
+            self.a = state.a
+        }
+        fn read(&self) -> A {
+            A { a: self.a }
+        }
+    }
+
+    impl StateHandler<B> for test_util::NumberSystem {
+        fn init(&mut self, state: B) {
+            self.b = state.b
+        }
+        fn read(&self) -> B {
+            B { b: self.b }
+        }
+    }
+
+    impl ActionHandler<NumbersAction> for test_util::NumberSystem {
+        type Outcome = ();
+
+        fn handle(&mut self, action: NumbersAction) {
+            let _ = match action {
+                NumbersAction::None => Ok(()),
+                NumbersAction::IncreaseA => self.increase_a(1),
+                NumbersAction::IncreaseB => self.increase_b(2),
+            };
+        }
+    }
+
+    #[test]
+    fn test_run_json_trace_events() {
+        let runtime = ModelatorRuntime::default();
+        let mut runner = EventRunner::new()
+            .with_state::<A>()
+            .with_state::<B>()
+            .with_action::<NumbersAction>();
+        let trace =
+            artifact::JsonTrace::read_json("tests/integration/resource/NumbersTrace.json").unwrap();
+
+        let mut system = test_util::NumberSystem::default();
+        runtime
+            .run_json_trace_events(&trace, &mut system, &mut runner)
+            .unwrap();
+        assert_eq!((system.a, system.b, system.sum), (1, 2, 3));
+
+        // the last step expects `b` to be increased by 3 instead of 2
+        let mut states = trace.states().to_vec();
+        states[2]["b"] = 3.into();
+        let trace: artifact::JsonTrace = states.into();
+        let mut system = test_util::NumberSystem::default();
+        match runtime.run_json_trace_events(&trace, &mut system, &mut runner) {
+            Err(TestError::FailedTest(failure)) => {
+                assert_eq!(failure.test, trace.to_string());
+                let step = failure.step.unwrap();
+                assert_eq!(step.index, 2);
+                assert_eq!(step.value, trace.state(2).cloned());
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_expected_failures() {
+        let failure = || {
+            Err(TestError::FailedTest(Box::new(FailedTest {
+                message: "failed".to_string(),
+                location: String::new(),
+                step: None,
+                test: String::new(),
+                system: String::new(),
+                execution_log: None,
+                trace_id: None,
+            })))
+        };
+        let mut report = TestReport {
+            test_name_to_trace_execution_result: [
+                ("Passes".to_string(), vec![Ok(()), Ok(())]),
+                ("Fails".to_string(), vec![Ok(()), failure()]),
+                ("XFails".to_string(), vec![failure()]),
+            ]
+            .into_iter()
+            .collect(),
+            holds_test_name_to_result: [("XPasses".to_string(), Ok(()))].into_iter().collect(),
+            execution_logs: BTreeMap::new(),
+            trace_ids: BTreeMap::new(),
+            final_states: BTreeMap::new(),
+            expected_failures: ["XFails", "XPasses"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            deduplicated_executions: 0,
+            step_timings: BTreeMap::new(),
+        };
+        assert_eq!(report.outcome("Passes"), Some(TestOutcome::Passed));
+        assert_eq!(report.outcome("Fails"), Some(TestOutcome::Failed));
+        assert_eq!(report.outcome("XFails"), Some(TestOutcome::ExpectedFailure));
+        assert_eq!(report.outcome("XPasses"), Some(TestOutcome::UnexpectedPass));
+        assert_eq!(report.outcome("Unknown"), None);
+        assert_eq!(report.outcomes().len(), 4);
+        assert!(!report.no_test_failed());
+
+        report.test_name_to_trace_execution_result.remove("Fails");
+        assert!(!report.no_test_failed());
+        report.expected_failures.remove("XPasses");
+        assert!(report.no_test_failed());
+
+        // a skipped test fails the report, even if expected to fail
+        let skipped = Err(TestError::Modelator(Error::TestSkipped(
+            "Skipped".to_string(),
+        )));
+        report
+            .test_name_to_trace_execution_result
+            .insert("Skipped".to_string(), vec![skipped]);
+        report.expected_failures.insert("Skipped".to_string());
+        assert_eq!(report.outcome("Skipped"), Some(TestOutcome::Skipped));
+        assert!(!report.no_test_failed());
+    }
+
+    #[test]
+    fn test_from_env() {
+        let runtime = with_env(
+            &[
+                ("MODELATOR_DIR", "env_dir"),
+                ("MODELATOR_CHECKER", "apalache"),
+                ("MODELATOR_WORKERS", "3"),
+                ("MODELATOR_TRACES_PER_TEST", "5"),
+                ("MODELATOR_LOG", "memory"),
+                ("MODELATOR_NO_CACHE", "true"),
+            ],
+            ModelatorRuntime::from_env,
+        )
+        .unwrap();
+        assert_eq!(runtime.dir, PathBuf::from("env_dir"));
+        let checker_runtime = &runtime.model_checker_runtime;
+        assert_eq!(checker_runtime.model_checker, ModelChecker::Apalache);
+        assert_eq!(checker_runtime.workers, ModelCheckerWorkers::Count(3));
+        assert_eq!(checker_runtime.traces_per_test, 5);
+        assert_eq!(checker_runtime.log, LogSink::Memory);
+        assert!(!runtime.cache);
+
+        // explicit setters take precedence, empty variables are ignored
+        let runtime = with_env(
+            &[("MODELATOR_DIR", "env_dir"), ("MODELATOR_LOG", "")],
+            || ModelatorRuntime::from_env().map(|runtime| runtime.dir("explicit_dir")),
+        )
+        .unwrap();
+        assert_eq!(runtime.dir, PathBuf::from("explicit_dir"));
+        assert_eq!(
+            runtime.model_checker_runtime.log,
+            ModelCheckerRuntime::default().log
+        );
+    }
+
+    #[test]
+    fn test_from_env_invalid() {
+        for (name, value) in [
+            ("MODELATOR_CHECKER", "spin"),
+            ("MODELATOR_WORKERS", "many"),
+            ("MODELATOR_TRACES_PER_TEST", "-1"),
+            ("MODELATOR_TRACES_PER_TEST", "0"),
+            ("MODELATOR_NO_CACHE", "maybe"),
+        ] {
+            let result = with_env(&[(name, value)], ModelatorRuntime::from_env);
+            match result {
+                Err(Error::InvalidEnvVar {
+                    name: error_name,
+                    value: error_value,
+                    reason,
+                }) => {
+                    assert_eq!(error_name, name);
+                    assert_eq!(error_value, value);
+                    if name == "MODELATOR_TRACES_PER_TEST" {
+                        assert_eq!(reason, "`traces_per_test` should be a positive integer");
+                    }
+                }
+                other => panic!("{}={}: unexpected {:?}", name, value, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_traces_truncated() {
+        let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let checker_runtime = ModelCheckerRuntime::default()
+            .model_checker(ModelChecker::Tlc)
+            .max_trace_states(Some(2));
+        let runtime = ModelatorRuntime::default().model_checker_runtime(checker_runtime);
+        let traces = runtime.traces(tla_tests_file, tla_config_file).unwrap();
+        let trace = &traces["AMaxBMinTest"].as_ref().unwrap()[0];
+        assert_eq!(trace.len(), 3);
+        assert!(trace.omitted_states().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_dedup_traces() {
+        let tla_tests_file = "tests/integration/resource/2PossibleTracesTests.tla";
+        let tla_config_file = "tests/integration/resource/2PossibleTraces.cfg";
+        // the two traces reaching 3 only differ after their initial state, so
+        // they are identical once truncated to it
+        let checker_runtime = ModelCheckerRuntime::default()
+            .traces_per_test(2)
+            .max_trace_states(Some(1));
+        let traces_len = |checker_runtime| {
+            let runtime = ModelatorRuntime::default().model_checker_runtime(checker_runtime);
+            let traces = runtime.traces(tla_tests_file, tla_config_file).unwrap();
+            traces["TestIsThree"].as_ref().unwrap().len()
+        };
+        assert_eq!(traces_len(checker_runtime.clone()), 2);
+        assert_eq!(traces_len(checker_runtime.dedup_traces(true)), 1);
+    }
+
+    #[test]
+    fn test_traces_multi() {
+        let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_files = [
+            "tests/integration/resource/Numbers.cfg",
+            "tests/integration/resource/NumbersSmall.cfg",
+        ];
+        let runtime = ModelatorRuntime::default();
+        let results = runtime
+            .traces_multi(tla_tests_file, &tla_config_files)
+            .unwrap();
+
+        // the test reaches MaxNumber, which differs across configs
+        assert_eq!(results.len(), 2);
+        for (config, max_number) in tla_config_files.iter().zip([6, 2]) {
+            let traces = results[&("AMaxBMinTest".to_owned(), config.to_string())]
+                .as_ref()
+                .unwrap();
+            let last = traces[0].clone().into_iter().last().unwrap();
+            assert_eq!(last["a"], max_number, "{}", config);
+        }
+    }
+    #[test]
+    fn test_trace_cache_key() {
+        let tla_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = ModelatorRuntime::default();
+        let key = runtime.trace_cache_key(tla_file, tla_config_file).unwrap();
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // the key only depends on the names and contents of the files
+        let dir = tempdir().unwrap();
+        for file in ["NumbersAMaxBMinTest.tla", "Numbers.tla", "Numbers.cfg"] {
+            std::fs::copy(
+                Path::new("tests/integration/resource").join(file),
+                dir.path().join(file),
+            )
+            .unwrap();
+        }
+        let copy = runtime
+            .trace_cache_key(
+                dir.path().join("NumbersAMaxBMinTest.tla"),
+                dir.path().join("Numbers.cfg"),
+            )
+            .unwrap();
+        assert_eq!(copy, key);
+
+        let other_config = "tests/integration/resource/NumbersSmall.cfg";
+        let other = runtime.trace_cache_key(tla_file, other_config).unwrap();
+        assert_ne!(other, key);
+    }
+
+    #[test]
+    fn test_next_states() {
+        let tla_file = "tests/integration/resource/Numbers.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = ModelatorRuntime::default();
+        let state = |a: u64, b: u64, action: &str| serde_json::json!({ "a": a, "b": b, "action": action, "actionOutcome": "OK" });
+
+        let initial_states = runtime
+            .next_states(tla_file, tla_config_file, None, 10, 0)
+            .unwrap();
+        assert_eq!(initial_states.len(), 1);
+        assert_eq!(initial_states[0].json, state(0, 0, "None"));
+
+        // the successors are ordered by their TLA+ formulas, which start
+        // with the action
+        let start_state = Some(initial_states[0].tla.as_str());
+        let next_states = runtime
+            .next_states(tla_file, tla_config_file, start_state, 10, 0)
+            .unwrap();
+        let jsons: Vec<_> = next_states.iter().map(|state| state.json.clone()).collect();
+        assert_eq!(
+            jsons,
+            vec![state(1, 0, "IncreaseA"), state(0, 2, "IncreaseB")]
+        );
+        let next_states = runtime
+            .next_states(tla_file, tla_config_file, start_state, 1, 1)
+            .unwrap();
+        assert_eq!(next_states.len(), 1);
+        assert_eq!(next_states[0].json, state(0, 2, "IncreaseB"));
+
+        // the successors can be explored in turn
+        let start_state = Some(next_states[0].tla.as_str());
+        let next_states = runtime
+            .next_states(tla_file, tla_config_file, start_state, 10, 0)
+            .unwrap();
+        assert!(next_states
+            .iter()
+            .any(|next_state| next_state.json == state(1, 2, "IncreaseA")));
+    }
+
+    #[test]
+    fn test_next_states_action_constraint() {
+        let tla_file = "tests/integration/resource/Numbers.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = ModelatorRuntime::default();
+        let actions = |runtime: &ModelatorRuntime| -> Vec<_> {
+            let initial_states = runtime
+                .next_states(tla_file, tla_config_file, None, 10, 0)
+                .unwrap();
+            let start_state = Some(initial_states[0].tla.as_str());
+            runtime
+                .next_states(tla_file, tla_config_file, start_state, 10, 0)
+                .unwrap()
+                .into_iter()
+                .map(|state| state.json["action"].clone())
+                .collect()
+        };
+
+        // both explorations share the cache, which must tell them apart
+        assert_eq!(actions(&runtime), vec!["IncreaseA", "IncreaseB"]);
+        let checker_runtime = runtime
+            .model_checker_runtime
+            .clone()
+            .action_constraint("b' = b");
+        let runtime = runtime.model_checker_runtime(checker_runtime);
+        assert_eq!(actions(&runtime), vec!["IncreaseA"]);
+    }
+
+    #[test]
+    fn test_traces_diverse() {
+        let tla_tests_file = "tests/integration/resource/2PossibleTracesTests.tla";
+        let tla_config_file = "tests/integration/resource/2PossibleTraces.cfg";
+        // each run finds a single trace, so the second one can only be found
+        // by a run with another random seed
+        let runtime = ModelatorRuntime::default()
+            .model_checker_runtime(ModelCheckerRuntime::default().traces_per_test(1));
+
+        let results = runtime
+            .traces_diverse(tla_tests_file, tla_config_file, 2)
+            .unwrap();
+        let traces = results["TestIsThree"].as_ref().unwrap();
+        // the two traces reaching 3, through 1 and through 2
+        assert_eq!(traces.len(), 2);
+        assert_ne!(traces[0], traces[1]);
+    }
+
+    #[test]
+    fn test_state_graph() {
+        let tla_file = "tests/integration/resource/Numbers.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = ModelatorRuntime::default();
+
+        let graph = runtime
+            .state_graph(tla_file, tla_config_file, 1, 10)
+            .unwrap();
+        assert!(!graph.truncated());
+        let json = graph.to_json();
+        let states = json["states"].as_array().unwrap();
+        assert_eq!(states.len(), 3);
+        assert_eq!(states[0]["initial"], true);
+        assert_eq!(states[0]["successors"], serde_json::json!([1, 2]));
+        // the states at the maximum depth aren't explored
+        assert_eq!(states[1]["successors"], serde_json::json!([]));
+
+        let graph = runtime
+            .state_graph(tla_file, tla_config_file, 1, 2)
+            .unwrap();
+        assert!(graph.truncated());
+        assert_eq!(graph.len(), 2);
+    }
+
+    #[test]
+    fn test_validate_spec() {
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = ModelatorRuntime::default();
+
+        assert!(runtime
+            .validate_spec("tests/integration/resource/Numbers.tla", tla_config_file)
+            .is_ok());
+        match runtime.validate_spec("tests/integration/resource/ParseError.tla", tla_config_file) {
+            Err(Error::ApalacheParseError {
+                module,
+                line,
+                column,
+                ..
+            }) => {
+                assert_eq!(module, "ParseError");
+                assert_eq!((line, column), (Some(4), Some(9)));
+            }
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = ModelatorRuntime::default();
+
+        let commands = runtime.plan(tla_tests_file, tla_config_file).unwrap();
+        assert_eq!(commands.len(), 1);
+        // the generated test module and its config are named `<Module>_<Test>`
+        assert!(commands[0].contains(" check --config=NumbersAMaxBMinTest_AMaxBMinTest.cfg "));
+        assert!(commands[0].ends_with(" NumbersAMaxBMinTest_AMaxBMinTest.tla"));
+
+        let runtime = runtime
+            .model_checker_runtime(ModelCheckerRuntime::default().model_checker(ModelChecker::Tlc));
+        let commands = runtime.plan(tla_tests_file, tla_config_file).unwrap();
+        assert!(commands[0].contains(
+            " tlc2.TLC NumbersAMaxBMinTest_AMaxBMinTest.tla -config NumbersAMaxBMinTest_AMaxBMinTest.cfg "
+        ));
+    }
+
+    #[test]
+    fn test_random_walks() {
+        let tla_file = "tests/integration/resource/Numbers.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = ModelatorRuntime::default();
+
+        let walks = runtime
+            .random_walks(tla_file, tla_config_file, 2, 3, 42)
+            .unwrap();
+        assert_eq!(walks.len(), 2);
+        for walk in &walks {
+            // the spec can always take a step
+            assert!(!walk.dead_end);
+            let states = walk.trace.states();
+            assert_eq!(states.len(), 4);
+            assert_eq!(states[0]["a"], 0);
+            assert_eq!(states[0]["b"], 0);
+            // the numbers never decrease
+            for (state, next_state) in states.iter().zip(&states[1..]) {
+                assert!(state["a"].as_u64() <= next_state["a"].as_u64());
+                assert!(state["b"].as_u64() <= next_state["b"].as_u64());
+            }
+        }
+
+        // the same seed gives the same walks
+        let same_walks = runtime
+            .random_walks(tla_file, tla_config_file, 2, 3, 42)
+            .unwrap();
+        assert_eq!(walks, same_walks);
+    }
+
+    #[test]
+    fn test_log_per_test() {
+        let tla_tests_file = "tests/integration/resource/NumbersHoldsTests.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let tdir = tempfile::tempdir().unwrap();
+        let runtime = ModelatorRuntime::default().model_checker_runtime(
+            ModelCheckerRuntime::default()
+                .model_checker(ModelChecker::Tlc)
+                .log(tdir.path().join("mc.log")),
+        );
+
+        let results = runtime.traces(tla_tests_file, tla_config_file).unwrap();
+        assert_eq!(results.len(), 3);
+        // the tests are run in parallel, each writing its own log
+        for test_name in results.keys() {
+            let log = tdir.path().join(format!("mc_{}.log", test_name));
+            assert!(std::fs::read_to_string(log).unwrap().contains("TLC"));
+        }
+        assert!(!tdir.path().join("mc.log").exists());
+
+        // logging to a directory, each test has its own timestamped log
+        let log_dir = tdir.path().join("logs");
+        let runtime = ModelatorRuntime::default().model_checker_runtime(
+            ModelCheckerRuntime::default()
+                .model_checker(ModelChecker::Tlc)
+                .log_dir(&log_dir),
+        );
+        runtime.traces(tla_tests_file, tla_config_file).unwrap();
+        let log_names: Vec<_> = std::fs::read_dir(&log_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(log_names.len(), 3);
+        for test_name in results.keys() {
+            let prefix = format!("mc-NumbersHoldsTests-{}-", test_name);
+            assert!(
+                log_names.iter().any(|name| name.starts_with(&prefix)),
+                "{:?}",
+                log_names
+            );
+        }
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        let tla_tests_file = "tests/integration/resource/NumbersFailFastTests.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = ModelatorRuntime::default()
+            .model_checker_runtime(ModelCheckerRuntime::default().model_checker(ModelChecker::Tlc))
+            .fail_fast(true);
+
+        // with a single thread, the tests are model checked in order
+        let results = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(|| runtime.traces(tla_tests_file, tla_config_file))
+            .unwrap();
+        assert!(matches!(
+            results["ASmallHolds"],
+            Err(Error::HoldsTestViolated { .. })
+        ));
+        assert!(matches!(
+            &results["InvHolds"],
+            Err(Error::TestSkipped(test)) if test == "InvHolds"
+        ));
+    }
+
+    #[test]
+    fn test_state_constraint() {
+        let tla_tests_file = "tests/integration/resource/NumbersUnboundedTests.tla";
+        let tla_config_file = "tests/integration/resource/NumbersUnbounded.cfg";
+
+        // the state space is unbounded, so TLC terminates thanks to the constraint
+        let runtime = ModelatorRuntime::default().model_checker_runtime(
+            ModelCheckerRuntime::default()
+                .model_checker(ModelChecker::Tlc)
+                .state_constraint("a <= 3 /\\ b <= 6"),
+        );
+        let results = runtime.traces(tla_tests_file, tla_config_file).unwrap();
+        assert_eq!(results["BIsEvenHolds"].as_ref().unwrap(), &Vec::new());
+    }
 }
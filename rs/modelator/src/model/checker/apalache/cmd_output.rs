@@ -1,7 +1,11 @@
+use crate::model::checker::CheckerStats;
 use crate::Error;
 
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::Serialize;
 use std::fmt;
+use std::time::Duration;
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub(crate) struct CmdOutput {
@@ -17,21 +21,26 @@ impl fmt::Display for CmdOutput {
 }
 
 fn is_counterexample_line(line: &str) -> bool {
+    // Older Apalache versions report the violation and the counterexample files
+    // in the same error, while newer ones use a separate error for each, and
+    // conclude with a summary error.
     line.contains("Check the counterexample in:")
+        || (line.contains("invariant") && line.contains("violated."))
+        || line.starts_with("Checker has found an error")
 }
 
-fn parse_filename(line: &str) -> String {
-    // The line looks like '...d. Check the counterexample in: counterexample1.tla, MC1.out, counterexample1.json E@11:13:37.003'
-    assert!(is_counterexample_line(line));
-    let (_, files) = line.split_once("in:").expect(
-        "Expected to find substring 'in:' in line when parsing for counterexample file name",
-    );
-    let tla_file = files
-        .trim()
-        .split_whitespace()
-        .next()
-        .expect("Must have counterexample1.tla, MC1.OUT, counterexample1.json");
-    tla_file.trim().into()
+fn parse_filenames(text: &str) -> Vec<String> {
+    // The counterexample files are listed after 'Check the counterexample in:',
+    // either comma-separated or one per line, and the TLA+ one is listed first:
+    // - '... in: counterexample1.tla, MC1.out, counterexample1.json E@11:13:37.003'
+    // - '... in: /tmp/_apalache-out/Test.tla/<date>/violation1.tla E@11:13:37.003'
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r"Check the counterexample in:\s*([^\s,]+\.tla)\b").unwrap();
+    }
+    RE.captures_iter(text)
+        .map(|caps| caps[1].to_owned())
+        .collect()
 }
 
 fn is_deadlock_line(line: &str) -> bool {
@@ -74,6 +83,13 @@ impl CmdOutput {
             .collect()
     }
 
+    /// Returns true iff Apalache reported that it found no error.
+    pub(crate) fn completed_without_error(&self) -> bool {
+        self.stdout.iter().any(|line| {
+            line.starts_with("The outcome is: NoError") || line.trim() == "EXITCODE: OK"
+        })
+    }
+
     /// Try to get a list of counterexample filenames from stdout. If other Apalache errors are found then
     /// return a Result<Error>
     pub(crate) fn parse_counterexample_filenames(&self) -> Result<Vec<String>, Error> {
@@ -85,18 +101,219 @@ impl CmdOutput {
                 .filter(|line| is_counterexample_line(line))
                 .collect()),
         }?;
-        Ok(raw_lines_that_must_be_parsed
+        let mut filenames = parse_filenames(&raw_lines_that_must_be_parsed.join("\n"));
+        // The same counterexample may be reported more than once
+        let mut seen = std::collections::HashSet::new();
+        filenames.retain(|filename| seen.insert(filename.clone()));
+        Ok(filenames)
+    }
+
+    /// Try to get the first type error reported by Apalache's type checker,
+    /// as a pair of its location and message
+    pub(crate) fn type_error(&self) -> Option<(String, String)> {
+        lazy_static! {
+            // Match '[<file>:<line>:<column>-<line>:<column>]: <message> E@XX:XX:XX.XXX'
+            static ref RE: Regex = Regex::new(r"^\[([^\]]+)\]: (.*\S)\s+E@\d\d:\d\d:\d\d\.\d{3}$").unwrap();
+        }
+        self.stdout.iter().find_map(|line| {
+            RE.captures(line)
+                .map(|caps| (caps[1].to_owned(), caps[2].to_owned()))
+        })
+    }
+
+    /// Try to get the error reported by Apalache's parser, as a pair of the
+    /// module that can't be parsed and the parser's message
+    pub(crate) fn parse_error(&self) -> Option<(String, String)> {
+        let summary = self
+            .stdout
+            .iter()
+            .find(|line| line.starts_with("Error by TLA+ parser"))?;
+        let module = self
+            .stdout
+            .iter()
+            .find_map(|line| line.strip_prefix("In module "))
+            .unwrap_or_default()
+            .trim()
+            .to_owned();
+        // the parser's message follows '***Parse Error***', up to an empty line
+        let message: Vec<&str> = self
+            .stdout
             .iter()
-            .map(|line| parse_filename(line))
-            .collect())
+            .skip_while(|line| line.trim() != "***Parse Error***")
+            .skip(1)
+            .map(|line| line.trim())
+            .take_while(|line| !line.is_empty())
+            .collect();
+        if !message.is_empty() {
+            return Some((module, message.join(" ")));
+        }
+        // the semantic errors follow the summary, e.g.
+        // 'line 5, col 14 to line 5, col 16 of module U' and 'Unknown operator: `Foo'.'
+        let message: Vec<&str> = self
+            .stdout
+            .iter()
+            .skip_while(|line| !line.starts_with("Error by TLA+ parser"))
+            .skip(1)
+            .map(|line| line.trim())
+            .take_while(|line| !line.starts_with("E@"))
+            .filter(|line| !line.is_empty())
+            .collect();
+        if message.is_empty() {
+            return Some((module, summary.trim().to_owned()));
+        }
+        let message = message.join(" ");
+        let module = match message.split_once(" of module ") {
+            Some((_, rest)) if module.is_empty() => rest
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_owned(),
+            _ => module,
+        };
+        Some((module, message))
+    }
+
+    /// Try to get the line and column of the first location in the message of
+    /// Apalache's parser
+    pub(crate) fn parse_error_location(message: &str) -> Option<(usize, usize)> {
+        lazy_static! {
+            // Match 'at line <line>, column <column>' or 'line <line>, col <column> to ...'
+            static ref RE: Regex = Regex::new(r"line (\d+), col(?:umn)? (\d+)").unwrap();
+        }
+        let caps = RE.captures(message)?;
+        Some((caps[1].parse().ok()?, caps[2].parse().ok()?))
+    }
+
+    /// Try to get the operator set in the TLA+ config file that Apalache
+    /// can't find, as a pair of its name and its role, e.g. `transition
+    /// predicate`
+    fn missing_operator(&self) -> Option<(String, String)> {
+        lazy_static! {
+            // Match 'Configuration error (see the manual): Operator <name> not found (used as the <role>) E@XX:XX:XX.XXX'
+            static ref RE: Regex = Regex::new(r"Operator (\S+) not found \(used as the ([^)]+)\)").unwrap();
+        }
+        self.stdout.iter().find_map(|line| {
+            RE.captures(line)
+                .map(|caps| (caps[1].to_owned(), caps[2].to_owned()))
+        })
+    }
+
+    /// Try to get the first assignment error, e.g. a primed variable used
+    /// before it's assigned, as a pair of its location and message
+    fn assignment_error(&self) -> Option<(String, String)> {
+        lazy_static! {
+            // Match 'Assignment error: <file>:<line>:<column>-<line>:<column>: <message> E@XX:XX:XX.XXX'
+            static ref RE: Regex = Regex::new(r"^Assignment error: (\S+): (.*\S)\s+E@\d\d:\d\d:\d\d\.\d{3}$").unwrap();
+        }
+        self.stdout.iter().find_map(|line| {
+            RE.captures(line)
+                .map(|caps| (caps[1].to_owned(), caps[2].to_owned()))
+        })
+    }
+
+    /// Try to get the message of the deadlock found by Apalache, if any,
+    /// ignoring the spurious one (see `is_deadlock_line`)
+    fn deadlock(&self) -> Option<String> {
+        lazy_static! {
+            // Match '[State <n>: ]Found a deadlock.<message> E@XX:XX:XX.XXX'
+            static ref RE: Regex = Regex::new(r"^(?:State \d+: )?(Found a deadlock\..*?)\s*E@\d\d:\d\d:\d\d\.\d{3}$").unwrap();
+        }
+        self.stdout
+            .iter()
+            .filter(|line| !is_deadlock_line(line))
+            .find_map(|line| RE.captures(line).map(|caps| caps[1].to_owned()))
+    }
+
+    /// Classifies the error reported by Apalache, from the known formats of
+    /// its messages.
+    pub(crate) fn error_kind(&self) -> ApalacheErrorKind {
+        let out_of_memory = self
+            .stdout
+            .iter()
+            .chain(&self.stderr)
+            .any(|line| line.contains("java.lang.OutOfMemoryError"));
+        if out_of_memory {
+            return ApalacheErrorKind::OutOfMemory;
+        }
+        if let Some((module, message)) = self.parse_error() {
+            let location = Self::parse_error_location(&message);
+            return ApalacheErrorKind::ParseError {
+                module,
+                line: location.map(|(line, _)| line),
+                column: location.map(|(_, column)| column),
+                message,
+            };
+        }
+        if let Some((operator, role)) = self.missing_operator() {
+            return ApalacheErrorKind::MissingOperator { operator, role };
+        }
+        if let Some((location, message)) = self.assignment_error() {
+            return ApalacheErrorKind::AssignmentError { location, message };
+        }
+        if let Some((location, message)) = self.type_error() {
+            return ApalacheErrorKind::TypeError { location, message };
+        }
+        if let Some(message) = self.deadlock() {
+            return ApalacheErrorKind::Deadlock { message };
+        }
+        // Apalache gives up with a runtime error when the SMT solver can't
+        // decide a query in time
+        if self
+            .stdout
+            .iter()
+            .any(|line| line.starts_with("The outcome is: RuntimeError"))
+        {
+            return ApalacheErrorKind::SmtTimeout;
+        }
+        ApalacheErrorKind::Other
+    }
+
+    /// Parses the statistics of a run: Apalache reports the length of the
+    /// explored executions and its running time, but not the states.
+    pub(crate) fn stats(&self) -> CheckerStats {
+        lazy_static! {
+            // 'State 6: ...', 'Step 6: ...' or '... up to computation length 6'
+            static ref DEPTH_RE: Regex = Regex::new(
+                r"^(?:State (\d+):|Step (\d+):|Checker reports no error up to computation length (\d+))"
+            )
+            .unwrap();
+            static ref TIME_RE: Regex = Regex::new(r"^Total time: ([\d.]+) sec").unwrap();
+        }
+        let depth = self
+            .stdout
+            .iter()
+            .filter_map(|line| DEPTH_RE.captures(line))
+            .filter_map(|caps| {
+                caps.iter()
+                    .skip(1)
+                    .flatten()
+                    .next()
+                    .and_then(|m| m.as_str().parse().ok())
+            })
+            .max();
+        let duration = self
+            .stdout
+            .iter()
+            .rev()
+            .filter_map(|line| TIME_RE.captures(line))
+            .find_map(|caps| caps[1].parse().ok())
+            .map(Duration::from_secs_f64);
+        CheckerStats {
+            states_generated: None,
+            distinct_states: None,
+            depth,
+            duration,
+        }
+    }
+
+    /// Returns an error with the given summary, along with this output.
+    pub(crate) fn failure(&self, summary: impl Into<String>) -> ApalacheError {
+        ApalacheError::new(vec![summary.into()], self)
     }
 
     pub(crate) fn non_counterexample_error(&self) -> Option<ApalacheError> {
         match (self.stdout.is_empty(), self.stderr.is_empty()) {
-            (true, true) => Some(ApalacheError {
-                summary: vec!["stdout and stderr both empty".into()],
-                output: self.clone(),
-            }),
+            (true, true) => Some(self.failure("stdout and stderr both empty")),
             (false, true) => {
                 let non_counterexample_error_lines: Vec<String> = self
                     .apalache_stdout_error_lines()
@@ -104,36 +321,126 @@ impl CmdOutput {
                     .filter(|line| !is_counterexample_line(line))
                     .collect();
 
-                match non_counterexample_error_lines.is_empty() {
-                    true => None,
-                    false => Some(ApalacheError {
-                        summary: std::iter::once(
-                            "Non counterexample errors found in stdout:".into(),
-                        )
-                        .chain(non_counterexample_error_lines)
-                        .collect(),
-                        output: self.clone(),
-                    }),
+                match (non_counterexample_error_lines.is_empty(), self.error_kind()) {
+                    // a deadlock comes with a counterexample, as a violation
+                    (true, ApalacheErrorKind::Other | ApalacheErrorKind::Deadlock { .. }) => None,
+                    // errors such as SMT timeouts aren't reported as error lines
+                    (true, _) => Some(self.failure("Apalache gave up without an error line")),
+                    (false, _) => Some(ApalacheError::new(
+                        std::iter::once("Non counterexample errors found in stdout:".into())
+                            .chain(non_counterexample_error_lines)
+                            .collect(),
+                        self,
+                    )),
                 }
             }
-            _ => Some(ApalacheError {
-                summary: vec!["stderr not empty".into()],
-                output: self.clone(),
-            }),
+            _ => Some(self.failure("stderr not empty")),
         }
     }
 }
 
+/// The kind of an [`ApalacheError`], classified from the messages of
+/// Apalache, so that the common failures can be told apart.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApalacheErrorKind {
+    /// Apalache's parser rejected a module, e.g. because of a syntax error or
+    /// an unknown operator.
+    ParseError {
+        /// Name of the module, if reported.
+        module: String,
+        /// Line of the error, if reported.
+        line: Option<usize>,
+        /// Column of the error, if reported.
+        column: Option<usize>,
+        /// Message of the parser.
+        message: String,
+    },
+    /// Apalache's type checker rejected an expression.
+    TypeError {
+        /// Location of the expression, e.g. `Test.tla:8:17-8:23`.
+        location: String,
+        /// Message of the type checker.
+        message: String,
+    },
+    /// An operator set in the TLA+ config file doesn't exist.
+    MissingOperator {
+        /// Name of the operator.
+        operator: String,
+        /// Role of the operator, e.g. `initialization predicate` for `INIT`.
+        role: String,
+    },
+    /// A variable can't be assigned, e.g. it's used primed before being
+    /// assigned.
+    AssignmentError {
+        /// Location of the variable, e.g. `Test.tla:8:13-8:14`.
+        location: String,
+        /// Message of Apalache.
+        message: String,
+    },
+    /// Apalache found a state without successor, while checking for deadlocks.
+    Deadlock {
+        /// Message of Apalache, e.g. with the files of the counterexample.
+        message: String,
+    },
+    /// The SMT solver gave up on a query, e.g. because it timed out (see
+    /// Apalache's `search.smt.timeout` tuning option).
+    SmtTimeout,
+    /// The JVM running Apalache ran out of memory (see
+    /// [`crate::model::checker::ModelCheckerRuntime::jvm_args`]).
+    OutOfMemory,
+    /// An error that isn't classified.
+    Other,
+}
+
 /// Contains an Apalache stdout string together with a summary
 /// string containing either the line of a matched error, or a string
 /// explaining that no error match was found.
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub struct ApalacheError {
+    // boxed to keep `Error` small
+    kind: Box<ApalacheErrorKind>,
     summary: Vec<String>,
     output: CmdOutput,
 }
 
+impl ApalacheError {
+    fn new(summary: Vec<String>, output: &CmdOutput) -> Self {
+        Self {
+            kind: Box::new(output.error_kind()),
+            summary,
+            output: output.clone(),
+        }
+    }
+
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> &ApalacheErrorKind {
+        &self.kind
+    }
+
+    /// Returns the lines summarizing the error.
+    pub fn summary(&self) -> &[String] {
+        &self.summary
+    }
+
+    /// Returns the lines written by Apalache to stdout.
+    pub fn stdout(&self) -> &[String] {
+        &self.output.stdout
+    }
+
+    /// Returns the lines written by Apalache to stderr, e.g. the errors of
+    /// the JVM.
+    pub fn stderr(&self) -> &[String] {
+        &self.output.stderr
+    }
+
+    /// Returns the exit status of Apalache, if it wasn't killed by a signal.
+    pub fn status(&self) -> Option<i32> {
+        self.output.status
+    }
+}
+
 impl fmt::Display for ApalacheError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", serde_json::to_string_pretty(&self).unwrap())
@@ -152,10 +459,211 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_filename() {
+    fn test_parse_error() {
+        let to_parse = r#"Parsing file /tmp/Bad.tla
+***Parse Error***
+Was expecting "Expression or Instance"
+Encountered "Beginning of definition" at line 3, column 9 and token "==" 
+
+Residual stack trace follows:
+Definition starting at line 3, column 1.
+
+Error by TLA+ parser: *** Abort messages: 1
+
+In module Bad
+
+Could not parse module Bad from file /tmp/Bad.tla
+
+
+ E@07:22:12.319
+EXITCODE: ERROR (255)"#;
+        let output = CmdOutput {
+            stdout: to_parse.lines().map(Into::into).collect(),
+            stderr: vec![],
+            status: Some(255),
+        };
+        assert_eq!(
+            output.parse_error(),
+            Some((
+                "Bad".to_owned(),
+                r#"Was expecting "Expression or Instance" Encountered "Beginning of definition" at line 3, column 9 and token "==""#.to_owned()
+            ))
+        );
+
+        let output = CmdOutput {
+            stdout: vec!["Parsed successfully".to_owned(), "EXITCODE: OK".to_owned()],
+            stderr: vec![],
+            status: Some(0),
+        };
+        assert_eq!(output.parse_error(), None);
+
+        let output = captured_output("unknown_operator", 255);
+        let (module, message) = output.parse_error().unwrap();
+        assert_eq!(module, "U");
+        assert_eq!(
+            message,
+            "line 5, col 14 to line 5, col 16 of module U Unknown operator: `Foo'."
+        );
+        assert_eq!(CmdOutput::parse_error_location(&message), Some((5, 14)));
+        assert_eq!(
+            CmdOutput::parse_error_location(
+                r#"Encountered "Beginning of definition" at line 3, column 9 and token "==""#
+            ),
+            Some((3, 9))
+        );
+    }
+
+    /// Reads the output of Apalache captured in a fixture.
+    fn captured_output(name: &str, status: i32) -> CmdOutput {
+        let read = |extension: &str| {
+            let path = format!(
+                "tests/integration/resource/apalache_output/{}.{}",
+                name, extension
+            );
+            std::fs::read_to_string(path)
+                .map(|output| output.lines().map(Into::into).collect())
+                .unwrap_or_default()
+        };
+        CmdOutput {
+            stdout: read("stdout"),
+            stderr: read("stderr"),
+            status: Some(status),
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(
+            captured_output("violation", 12).stats(),
+            CheckerStats {
+                states_generated: None,
+                distinct_states: None,
+                depth: Some(6),
+                duration: Some(Duration::from_millis(4407)),
+            }
+        );
+        assert_eq!(
+            captured_output("holds", 0).stats(),
+            CheckerStats {
+                states_generated: None,
+                distinct_states: None,
+                depth: Some(3),
+                duration: Some(Duration::from_millis(3813)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_kind() {
+        let error_kind = |name, status| {
+            captured_output(name, status)
+                .non_counterexample_error()
+                .unwrap()
+                .kind()
+                .clone()
+        };
+        assert_eq!(
+            error_kind("type_error", 12),
+            ApalacheErrorKind::TypeError {
+                location: "Spec.tla:8:17-8:23".to_owned(),
+                message: "No match between operator signature ((Int, Int) => Int) and arguments Int and Str".to_owned(),
+            }
+        );
+        assert_eq!(
+            error_kind("missing_init", 255),
+            ApalacheErrorKind::MissingOperator {
+                operator: "Foo".to_owned(),
+                role: "initialization predicate".to_owned(),
+            }
+        );
+        assert_eq!(
+            error_kind("missing_next", 255),
+            ApalacheErrorKind::MissingOperator {
+                operator: "Bar".to_owned(),
+                role: "transition predicate".to_owned(),
+            }
+        );
+        assert_eq!(
+            error_kind("assignment_error", 255),
+            ApalacheErrorKind::AssignmentError {
+                location: "Spec.tla:8:13-8:14".to_owned(),
+                message: "x' is used before it is assigned. See https://apalache.informal.systems/docs/apalache/principles.html#assignments".to_owned(),
+            }
+        );
+        assert_eq!(
+            error_kind("unknown_operator", 255),
+            ApalacheErrorKind::ParseError {
+                module: "U".to_owned(),
+                line: Some(5),
+                column: Some(14),
+                message: "line 5, col 14 to line 5, col 16 of module U Unknown operator: `Foo'."
+                    .to_owned(),
+            }
+        );
+        // the timeout isn't reported as an error line
+        assert_eq!(error_kind("smt_timeout", 12), ApalacheErrorKind::SmtTimeout);
+        assert_eq!(
+            error_kind("out_of_memory", 1),
+            ApalacheErrorKind::OutOfMemory
+        );
+
+        let output = CmdOutput {
+            stdout: vec!["EXITCODE: OK".to_owned()],
+            stderr: vec![],
+            status: Some(0),
+        };
+        assert_eq!(output.error_kind(), ApalacheErrorKind::Other);
+        assert_eq!(output.non_counterexample_error(), None);
+    }
+
+    #[test]
+    fn test_deadlock_kind() {
+        let output = |line: &str| CmdOutput {
+            stdout: vec![line.to_owned(), "EXITCODE: ERROR (12)".to_owned()],
+            stderr: vec![],
+            status: Some(12),
+        };
+
+        // a deadlock with a counterexample isn't a failure
+        let deadlock = output("State 3: Found a deadlock. Check the counterexample in: counterexample.tla, MC.out, counterexample.json E@11:13:37.003");
+        assert_eq!(
+            deadlock.error_kind(),
+            ApalacheErrorKind::Deadlock {
+                message: "Found a deadlock. Check the counterexample in: counterexample.tla, MC.out, counterexample.json".to_owned()
+            }
+        );
+        assert_eq!(deadlock.non_counterexample_error(), None);
+
+        // the spurious deadlock isn't one
+        let spurious = output(
+            "Found a deadlock. No SMT model.                                   E@11:13:37.003",
+        );
+        assert_eq!(spurious.error_kind(), ApalacheErrorKind::Other);
+    }
+
+    #[test]
+    fn test_failure() {
+        let output = CmdOutput {
+            stdout: vec!["EXITCODE: OK".to_owned()],
+            stderr: vec!["Error: Could not find or load main class".to_owned()],
+            status: Some(0),
+        };
+        let error = output.failure("Invalid counterexample");
+        assert_eq!(error.summary(), ["Invalid counterexample".to_owned()]);
+        assert_eq!(error.stdout(), output.stdout.as_slice());
+        assert_eq!(error.stderr(), output.stderr.as_slice());
+        assert_eq!(error.status(), Some(0));
+    }
+
+    #[test]
+    fn test_parse_filenames() {
         let line = "State 2: state invariant 0 violated. Check the counterexample in:\n  counterexample1.tla\n  MC1.out\n  counterexample1.json E@11:13:37.003";
-        let res = parse_filename(line);
-        assert_eq!("counterexample1.tla".to_owned(), res);
+        let res = parse_filenames(line);
+        assert_eq!(vec!["counterexample1.tla".to_owned()], res);
+
+        let line = "State 2: state invariant 0 violated. Check the counterexample in: /tmp/counterexample1.tla, MC1.out, counterexample1.json E@11:13:37.003";
+        let res = parse_filenames(line);
+        assert_eq!(vec!["/tmp/counterexample1.tla".to_owned()], res);
     }
 
     #[test]
@@ -180,8 +688,102 @@ EXITCODE: ERROR (12)
             status: Some(12),
         };
         let res = output.parse_counterexample_filenames().unwrap();
-        let expect = vec!["counterexample1.tla", "counterexample2.tla"];
+        let expect = ["counterexample1.tla", "counterexample2.tla"];
         assert_eq!(expect[0], res[0]);
         assert_eq!(expect[1], res[1]);
     }
+
+    #[test]
+    fn test_parse_violation_filenames() {
+        // Output of Apalache 0.25, which reports the violation and the
+        // counterexample files in separate errors
+        let to_parse = r#"State 1: Checking 1 state invariants                              I@09:41:12.231
+State 1: state invariant 0 violated.                              E@09:41:12.305
+Check the counterexample in: /tmp/x/_apalache-out/TestNeg.tla/2022-06-20T09-41-09_1/violation1.tla E@09:41:12.361
+State 1: state invariant 0 violated.                              E@09:41:12.402
+Check the counterexample in: /tmp/x/_apalache-out/TestNeg.tla/2022-06-20T09-41-09_1/violation2.tla E@09:41:12.431
+Found 2 error(s)                                                  I@09:41:12.433
+The outcome is: Error                                             I@09:41:12.437
+Checker has found an error                                        E@09:41:12.439
+It took me 0 days  0 hours  0 min  3 sec                          I@09:41:12.440
+EXITCODE: ERROR (12)
+        "#;
+        let output = CmdOutput {
+            stdout: to_parse.lines().map(Into::into).collect(),
+            stderr: vec![],
+            status: Some(12),
+        };
+        let res = output.parse_counterexample_filenames().unwrap();
+        let dir = "/tmp/x/_apalache-out/TestNeg.tla/2022-06-20T09-41-09_1";
+        assert_eq!(
+            res,
+            vec![
+                format!("{}/violation1.tla", dir),
+                format!("{}/violation2.tla", dir)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_simulate_filenames() {
+        // `apalache simulate` reports the counterexamples of its random runs
+        // like `apalache check` does, in the order they were found
+        let to_parse = r#"State 7: state invariant 0 violated.                              E@10:02:31.118
+Check the counterexample in: /tmp/x/_apalache-out/TestNeg.tla/2022-08-02T10-02-28_2/violation2.tla E@10:02:31.170
+State 3: state invariant 0 violated.                              E@10:02:31.301
+Check the counterexample in: /tmp/x/_apalache-out/TestNeg.tla/2022-08-02T10-02-28_2/violation1.tla E@10:02:31.322
+The outcome is: Error                                             I@10:02:31.340
+Checker has found an error                                        E@10:02:31.341
+EXITCODE: ERROR (12)
+        "#;
+        let output = CmdOutput {
+            stdout: to_parse.lines().map(Into::into).collect(),
+            stderr: vec![],
+            status: Some(12),
+        };
+        let res = output.parse_counterexample_filenames().unwrap();
+        let dir = "/tmp/x/_apalache-out/TestNeg.tla/2022-08-02T10-02-28_2";
+        assert_eq!(
+            res,
+            vec![
+                format!("{}/violation2.tla", dir),
+                format!("{}/violation1.tla", dir)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_error() {
+        let to_parse = r#"PASS #1: TypeCheckerSnowcat                                       I@04:43:18.956
+ > Running Snowcat .::.                                           I@04:43:18.957
+[Bad.tla:6:9-6:15]: No match between operator signature ((c, c) => Bool) and arguments Int and Str E@04:43:19.254
+[Bad.tla:6:1-6:15]: Error when computing the type of Init         E@04:43:19.261
+ > Snowcat asks you to fix the types. Meow.                       I@04:43:19.262
+Type checker [FAILED]                                             I@04:43:19.264
+EXITCODE: ERROR (255)
+"#;
+        let output = CmdOutput {
+            stdout: to_parse.lines().map(Into::into).collect(),
+            stderr: vec![],
+            status: Some(255),
+        };
+        assert_eq!(
+            output.type_error(),
+            Some((
+                "Bad.tla:6:9-6:15".to_owned(),
+                "No match between operator signature ((c, c) => Bool) and arguments Int and Str"
+                    .to_owned()
+            ))
+        );
+
+        let output = CmdOutput {
+            stdout: vec![
+                "Type checker [OK]                                                 I@04:43:19.264"
+                    .to_owned(),
+            ],
+            stderr: vec![],
+            status: Some(0),
+        };
+        assert_eq!(output.type_error(), None);
+    }
 }
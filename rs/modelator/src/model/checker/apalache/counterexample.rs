@@ -1,5 +1,22 @@
 use crate::artifact::tla_trace::{TlaState, TlaTrace};
 use crate::Error;
+use serde_json::Value as JsonValue;
+use std::path::Path;
+
+/// Reads the JSON counterexample written by Apalache next to the given
+/// `.tla` counterexample, preferring the ITF format if available.
+pub(crate) fn read_json(counterexample_path: &Path) -> Result<JsonValue, Error> {
+    let candidates = vec![
+        counterexample_path.with_extension("itf.json"),
+        counterexample_path.with_extension("json"),
+    ];
+    let path = candidates
+        .iter()
+        .find(|path| path.is_file())
+        .ok_or_else(|| Error::FileNotFoundInAny(candidates.clone()))?;
+    let content = crate::util::try_read_file_contents(path)?;
+    serde_json::from_str(&content).map_err(Error::from)
+}
 
 pub(crate) fn parse(counterexample: &str) -> Result<TlaTrace, Error> {
     let lines = counterexample.lines();
@@ -21,7 +38,7 @@ pub(crate) fn parse(counterexample: &str) -> Result<TlaTrace, Error> {
         // check if found the start of the next state
         let next_state = format!("State{} ==", state_index);
         let inv_violation = "InvariantViolation";
-        if line.starts_with(&next_state) || line.starts_with(&inv_violation) {
+        if line.starts_with(&next_state) || line.starts_with(inv_violation) {
             if state_index > 0 {
                 // the previous state has ended, so we save it
                 let state = state
@@ -35,7 +52,7 @@ pub(crate) fn parse(counterexample: &str) -> Result<TlaTrace, Error> {
             // trim the two possible prefixes
             let line = line
                 .trim_start_matches(&next_state)
-                .trim_start_matches(&inv_violation);
+                .trim_start_matches(inv_violation);
             // save remaining of the line
             tla_state.push_str(line);
             tla_state.push('\n');
@@ -58,3 +75,72 @@ pub(crate) fn parse(counterexample: &str) -> Result<TlaTrace, Error> {
         counterexample.to_owned(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let counterexample_path = dir.path().join("counterexample1.tla");
+
+        assert!(matches!(
+            read_json(&counterexample_path),
+            Err(Error::FileNotFoundInAny(_))
+        ));
+
+        std::fs::write(
+            dir.path().join("counterexample1.json"),
+            r#"{ "name": "ApalacheIR" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_json(&counterexample_path).unwrap(),
+            serde_json::json!({ "name": "ApalacheIR" })
+        );
+
+        // ITF is preferred over Apalache's IR
+        std::fs::write(
+            dir.path().join("counterexample1.itf.json"),
+            r#"{ "states": [] }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_json(&counterexample_path).unwrap(),
+            serde_json::json!({ "states": [] })
+        );
+    }
+
+    #[test]
+    fn test_parse_violation() {
+        // Counterexample written by Apalache 0.25 to 'violation1.tla'
+        let counterexample = r#"---------------------------- MODULE counterexample ----------------------------
+
+EXTENDS TestNeg
+
+(* Constant initialization state *)
+ConstInit == TRUE
+
+(* Initial state *)
+State0 == a = 0 /\ b = 0
+
+(* Transition 1 to State1 *)
+State1 == a = 1 /\ b = 0
+
+(* The following formula holds true in the last state and violates the invariant *)
+InvariantViolation == a = 1
+
+================================================================================
+(* Created by Apalache on Mon Jun 20 09:41:12 UTC 2022 *)
+(* https://github.com/informalsystems/apalache *)
+"#;
+        let trace = parse(counterexample).unwrap();
+        let states: Vec<_> = trace
+            .into_iter()
+            .map(|state| state.trim().to_owned())
+            .collect();
+        // the invariant violation is not a state of the trace
+        assert_eq!(states, vec!["a = 0 /\\ b = 0", "a = 1 /\\ b = 0"]);
+    }
+}
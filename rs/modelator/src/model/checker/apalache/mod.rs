@@ -3,16 +3,18 @@ pub(crate) mod cmd_output;
 use cmd_output::{ApalacheError, CmdOutput};
 
 /// Parsing of Apalache's counterexample file.
-mod counterexample;
+pub(crate) mod counterexample;
 
 use crate::artifact::{
     try_write_to_dir, Artifact, ArtifactCreator, ArtifactSaver, ModelCheckerStdout, TlaConfigFile,
     TlaFile, TlaFileSuite, TlaTrace,
 };
 use crate::cache::TlaTraceCache;
-use crate::model::checker::apalache;
+use crate::model::checker::{self, apalache};
+use crate::model::language;
 use crate::{jar, Error, ModelatorRuntime};
-use std::env::temp_dir;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeSet;
 use std::path::Path;
 use std::process::Command;
 
@@ -20,6 +22,106 @@ use std::process::Command;
 #[derive(Debug, Clone, Copy)]
 pub struct Apalache;
 
+/// Name of the view defined by a test module, for the test operator it negates.
+const VIEW_OPERATOR: &str = "ViewForTestNeg";
+
+/// Names of the variables declared by a TLA+ module (see [`Apalache::tla_variables`]).
+pub type TlaVariables = BTreeSet<String>;
+
+/// Output format of [`Apalache::parse_with_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApalacheParseFormat {
+    /// A TLA+ module.
+    Tla,
+    /// Apalache's JSON intermediate representation of the TLA+ module.
+    Json,
+}
+
+impl ApalacheParseFormat {
+    const fn extension(&self) -> &'static str {
+        match self {
+            Self::Tla => "tla",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Search algorithm used by `apalache check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApalacheAlgo {
+    /// Encodes all the steps of an execution at once, then solves them with
+    /// a single SMT query. Predictable, but the query grows with the length.
+    Offline,
+    /// Checks each step with incremental SMT queries. Usually faster on
+    /// specifications with many short executions, but may use more memory.
+    Incremental,
+}
+
+impl ApalacheAlgo {
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Offline => "offline",
+            Self::Incremental => "incremental",
+        }
+    }
+}
+
+/// Apalache command generating the traces of a test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApalacheMode {
+    /// `apalache check`: explores all the executions up to a bounded length
+    /// with the SMT solver.
+    Check,
+    /// `apalache simulate`: samples random executions, usually much faster
+    /// than [`ApalacheMode::Check`] to find shallow violations, but may miss
+    /// the deeper ones. Requires Apalache 0.30 or later: with the Apalache
+    /// 0.17.5 jar downloaded by modelator, the tests fail with
+    /// [`Error::UnsupportedApalacheMode`].
+    Simulate {
+        /// Number of random executions sampled (Apalache's `--max-run` option).
+        max_run: usize,
+        /// Number of steps of each execution (Apalache's `--length` option).
+        length: usize,
+    },
+}
+
+impl ApalacheMode {
+    const fn command(&self) -> &'static str {
+        match self {
+            Self::Check => "check",
+            Self::Simulate { .. } => "simulate",
+        }
+    }
+
+    /// The first version of Apalache with the command.
+    const fn min_version(&self) -> [u32; 3] {
+        match self {
+            Self::Check => [0, 0, 0],
+            Self::Simulate { .. } => [0, 30, 0],
+        }
+    }
+
+    /// Fails if the command isn't supported by the Apalache jar, whose usage
+    /// error would be less clear.
+    fn check_supported(&self) -> Result<(), Error> {
+        let version = |version: [u32; 3]| {
+            version
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(".")
+        };
+        if jar::APALACHE_VERSION < self.min_version() {
+            return Err(Error::UnsupportedApalacheMode {
+                command: self.command().to_owned(),
+                min_version: version(self.min_version()),
+                version: version(jar::APALACHE_VERSION),
+            });
+        }
+        Ok(())
+    }
+}
+
 impl Apalache {
     ///
     /// Generate a TLA+ trace given a [`TlaFile`] and a [`TlaConfigFile`] produced
@@ -29,7 +131,7 @@ impl Apalache {
     ///
     /// ```ignore
     /// use modelator::artifact::TlaFileSuite;
-    /// use modelator::model::{language::Tla, checker::Apalache};
+    /// use modelator::model::{language::{Tla, TestDiscovery}, checker::Apalache};
     /// use modelator::ModelatorRuntime;
     /// use std::convert::TryFrom;
     ///
@@ -37,7 +139,7 @@ impl Apalache {
     /// let tla_config_file = "tests/integration/resource/Numbers.cfg";
     /// let tla_suite = TlaFileSuite::from_tla_and_config_paths(tla_tests_file, tla_config_file).unwrap();
     ///
-    /// let mut tests = Tla::generate_tests(&tla_suite).unwrap();
+    /// let mut tests = Tla::generate_tests(&tla_suite, &TestDiscovery::default()).unwrap();
     /// let test_tla_suite = tests.pop().unwrap();
     /// let runtime = ModelatorRuntime::default();
     /// let (tla_trace, _) = Apalache::test(&test_tla_suite, &runtime).unwrap();
@@ -47,6 +149,49 @@ impl Apalache {
         input_artifacts: &TlaFileSuite,
         runtime: &ModelatorRuntime,
     ) -> Result<(Vec<TlaTrace>, ModelCheckerStdout), Error> {
+        Self::test_with_dependencies_dir(input_artifacts, runtime, None)
+    }
+
+    /// Same as [`Apalache::test`], but the dependencies of the [`TlaFileSuite`]
+    /// may have already been written to `dependencies_dir`.
+    pub(crate) fn test_with_dependencies_dir(
+        input_artifacts: &TlaFileSuite,
+        runtime: &ModelatorRuntime,
+        dependencies_dir: Option<&Path>,
+    ) -> Result<(Vec<TlaTrace>, ModelCheckerStdout), Error> {
+        let (traces, apalache_log) = Self::check(input_artifacts, runtime, dependencies_dir)?;
+        Ok((
+            traces.into_iter().map(|(trace, _)| trace).collect(),
+            apalache_log,
+        ))
+    }
+
+    /// Same as [`Apalache::test`], but also returns, along with each TLA+
+    /// trace, the raw JSON counterexample produced by Apalache.
+    pub fn traces_raw(
+        input_artifacts: &TlaFileSuite,
+        runtime: &ModelatorRuntime,
+    ) -> Result<(Vec<(TlaTrace, JsonValue)>, ModelCheckerStdout), Error> {
+        let (traces, apalache_log) = Self::check(input_artifacts, runtime, None)?;
+        let traces = traces
+            .into_iter()
+            .map(|(trace, raw)| Ok((trace, raw?)))
+            .collect::<Result<_, Error>>()?;
+        Ok((traces, apalache_log))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn check(
+        input_artifacts: &TlaFileSuite,
+        runtime: &ModelatorRuntime,
+        dependencies_dir: Option<&Path>,
+    ) -> Result<
+        (
+            Vec<(TlaTrace, Result<JsonValue, Error>)>,
+            ModelCheckerStdout,
+        ),
+        Error,
+    > {
         // TODO: this method currently just uses the paths of the files so no need for whole artifact objects!
 
         tracing::debug!(
@@ -55,29 +200,52 @@ impl Apalache {
             input_artifacts.tla_config_file,
             runtime
         );
+        runtime
+            .model_checker_runtime
+            .apalache_mode
+            .check_supported()?;
+        let runtime = &*checker::run_runtime(runtime, input_artifacts.tla_file.module_name());
 
         let tdir = tempfile::tempdir()?;
 
-        try_write_to_dir(&tdir, input_artifacts)?;
+        checker::write_suite(
+            tdir.path(),
+            input_artifacts,
+            dependencies_dir,
+            &runtime.model_checker_runtime,
+        )?;
 
         // Gets Apalache command with tdir as working dir
-        let cmd = apalache_start_cmd(&tdir, runtime);
-
-        // Check if the main tla module contains a View
-        // The view will have a generated name 'ViewForTestNeg'
-        // If it has one, then use it.
-        let view = input_artifacts
-            .tla_file
-            .file_contents_backing()
-            .contains("ViewForTestNeg")
-            .then(|| "ViewForTestNeg".to_owned());
-
-        // create 'apalache check' command
+        let cmd = apalache_start_cmd(&tdir, runtime, dependencies_dir);
+
+        // A view set explicitly takes precedence; otherwise, if the test
+        // module or a module it extends defines a view, with the generated
+        // name 'ViewForTestNeg', then use it.
+        let mut view = match &runtime.model_checker_runtime.view {
+            Some(view) => Some(view.clone()),
+            None => defines_view(input_artifacts)?.then(|| VIEW_OPERATOR.to_owned()),
+        };
+
+        // Otherwise, a view over all the variables may be generated, which
+        // requires running Apalache, so not in a dry run
+        if view.is_none() && runtime.model_checker_runtime.generate_view && !runtime.dry_run {
+            let variables = Self::tla_variables(input_artifacts, runtime)?;
+            if !variables.is_empty() {
+                let tla_file = checker::with_constraints(
+                    &input_artifacts.tla_file,
+                    &runtime.model_checker_runtime,
+                )?;
+                with_view(&tla_file, &variables)?.try_write_to_dir(tdir.path())?;
+                view = Some(VIEW_OPERATOR.to_owned());
+            }
+        }
+
+        // create 'apalache check' (or 'apalache simulate') command
         let cmd = check_cmd(
             cmd,
             input_artifacts.tla_file.file_name(),
             input_artifacts.tla_config_file.filename(),
-            runtime.model_checker_runtime.traces_per_test,
+            &runtime.model_checker_runtime,
             &view,
         );
 
@@ -86,38 +254,55 @@ impl Apalache {
             runtime.model_checker_runtime.workers
         );
 
+        if runtime.dry_run {
+            return Err(checker::dry_run(&cmd, tdir));
+        }
+
         let apalache_output = run_apalache(cmd)?;
+        let mut apalache_log = runtime
+            .model_checker_runtime
+            .log
+            .log(&apalache_output.stdout.join("\n"))?;
+        apalache_log.stats = apalache_output.stats();
 
-        let counterexample_paths = apalache_output.parse_counterexample_filenames()?;
+        let counterexample_paths = counterexample_paths(&apalache_output, tdir.path())?;
 
         if counterexample_paths.is_empty() {
-            return Err(Error::NoTestTraceFound(
-                //TODO: this will have to be changed to reflect new in-memory log
-                runtime.model_checker_runtime.log.clone(),
-            ));
+            return Err(Error::NoTestTraceFound(checker::no_trace_path(
+                input_artifacts,
+                &runtime.model_checker_runtime,
+            )));
         }
 
-        let traces = counterexample_paths
+        let mut traces: Vec<_> = counterexample_paths
             .iter()
-            .map(|counterexample_path_base| {
+            .map(|counterexample_path_base| -> Result<_, Error> {
                 // Read the  apalache counterexample from disk and parse a trace from it
-                let counterexample_path = tdir.path().join(&counterexample_path_base);
-
-                if !counterexample_path.is_file() {
-                    panic!("[modelator] expected to find Apalache's counterexample1.tla file");
-                }
-
-                let counterexample: TlaFile = TlaFile::try_read_from_file(counterexample_path)?;
+                let counterexample_path = tdir.path().join(counterexample_path_base);
+                let counterexample = crate::util::try_read_file_contents(&counterexample_path)?;
                 tracing::debug!("Apalache counterexample:\n{}", counterexample);
-                counterexample::parse(counterexample.file_contents_backing())
+                // report the whole output of Apalache along with the parse error
+                let trace = counterexample::parse(&counterexample).map_err(|e| {
+                    Error::ApalacheFailure(apalache_output.failure(format!(
+                        "Invalid counterexample {}: {}",
+                        counterexample_path_base, e
+                    )))
+                })?;
+                // the temporary directory is only deleted once all counterexamples are read
+                let index = counterexample_index(counterexample_path_base);
+                let order = (index.is_none(), index, trace_hash(&trace));
+                Ok((
+                    order,
+                    (trace, counterexample::read_json(&counterexample_path)),
+                ))
             })
-            .filter_map(Result::ok)
-            .collect();
+            .collect::<Result<_, Error>>()?;
+        // the order in which Apalache reports the counterexamples may vary
+        // between runs, so they're sorted by index, and then by content
+        traces.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let traces = traces.into_iter().map(|(_, trace)| trace).collect();
 
-        Ok((
-            traces,
-            ModelCheckerStdout::from_string(&apalache_output.stdout.join("\n"))?,
-        ))
+        Ok((traces, apalache_log))
     }
 
     ///
@@ -143,18 +328,62 @@ impl Apalache {
         tla_file_suite: &TlaFileSuite,
         runtime: &ModelatorRuntime,
     ) -> Result<(TlaFile, ModelCheckerStdout), Error> {
-        // tracing::debug!("Apalache::parse {} {:?}", tla_file, runtime);
+        let (content, stdout) =
+            Self::parse_with_format(tla_file_suite, ApalacheParseFormat::Tla, runtime)?;
+        // Apalache changes the module name in the output file, so it doesn't match the file name
+        Ok((TlaFile::from_string(&content)?, stdout))
+    }
+
+    /// Returns the names of the variables declared by a TLA+ module, including
+    /// the variables of the modules it extends, as parsed by Apalache.
+    ///
+    /// Returns an [`Error::ApalacheParseError`] if the module can't be parsed.
+    pub fn tla_variables(
+        tla_file_suite: &TlaFileSuite,
+        runtime: &ModelatorRuntime,
+    ) -> Result<TlaVariables, Error> {
+        tracing::debug!("Apalache::tla_variables {}", tla_file_suite.tla_file);
+
+        let (content, _) =
+            Self::parse_with_format(tla_file_suite, ApalacheParseFormat::Json, runtime)?;
+        let parsed: JsonValue = serde_json::from_str(&content)?;
+        let declarations = parsed["modules"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|module| module["declarations"].as_array().into_iter().flatten());
+        Ok(declarations
+            .filter(|declaration| declaration["kind"] == "TlaVarDecl")
+            .filter_map(|declaration| declaration["name"].as_str())
+            .map(ToOwned::to_owned)
+            .collect())
+    }
+
+    ///
+    /// Same as [`Apalache::parse`], but returns the content of the file
+    /// produced by Apalache in the given format.
+    /// Returns an [`Error::ApalacheParseError`] if the module can't be parsed.
+    pub fn parse_with_format(
+        tla_file_suite: &TlaFileSuite,
+        format: ApalacheParseFormat,
+        runtime: &ModelatorRuntime,
+    ) -> Result<(String, ModelCheckerStdout), Error> {
+        tracing::debug!(
+            "Apalache::parse_with_format {} {:?}",
+            tla_file_suite.tla_file,
+            format
+        );
 
         let tdir = tempfile::tempdir()?;
 
         try_write_to_dir(&tdir, tla_file_suite)?;
 
         // Gets Apalache command with tdir as working dir
-        let cmd = apalache_start_cmd(&tdir, runtime);
+        let cmd = apalache_start_cmd(&tdir, runtime, None);
 
         let tla_file_module_name = tla_file_suite.tla_file.module_name();
 
-        let output_path = format!("{}Parsed.tla", tla_file_module_name);
+        let output_path = format!("{}Parsed.{}", tla_file_module_name, format.extension());
 
         // create apalache parse command
         let cmd = parse_cmd(cmd, &tla_file_suite.tla_file.file_name(), &output_path);
@@ -162,19 +391,74 @@ impl Apalache {
         // run apalache
         let apalache_output = run_apalache(cmd)?;
 
+        if let Some((module, message)) = apalache_output.parse_error() {
+            let location = CmdOutput::parse_error_location(&message);
+            return Err(Error::ApalacheParseError {
+                module,
+                line: location.map(|(line, _)| line),
+                column: location.map(|(_, column)| column),
+                message,
+            });
+        }
+
         match apalache_output.non_counterexample_error() {
             None => {}
             Some(err) => return Err(Error::ApalacheFailure(err)),
         }
 
-        // create tla file
         let full_output_path = tdir.path().join(output_path);
-        let tla_parsed_file = TlaFile::try_read_from_file(full_output_path)?;
         Ok((
-            tla_parsed_file,
+            crate::util::try_read_file_contents(full_output_path)?,
             ModelCheckerStdout::from_string(&apalache_output.stdout.join("\n"))?,
         ))
     }
+
+    ///
+    /// Runs Apalache's `typecheck` command, returning an
+    /// [`Error::ApalacheTypeError`] with the location of the first type error
+    /// reported, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use modelator::artifact::TlaFileSuite;
+    /// use modelator::model::checker::Apalache;
+    /// use modelator::ModelatorRuntime;
+    ///
+    /// let tla_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+    /// let tla_file_suite = TlaFileSuite::from_tla_path(tla_file).unwrap();
+    ///
+    /// let runtime = ModelatorRuntime::default();
+    /// assert!(Apalache::typecheck(&tla_file_suite, &runtime).is_ok());
+    /// ```
+    pub fn typecheck(
+        tla_file_suite: &TlaFileSuite,
+        runtime: &ModelatorRuntime,
+    ) -> Result<(), Error> {
+        tracing::debug!("Apalache::typecheck {}", tla_file_suite.tla_file);
+
+        let tdir = tempfile::tempdir()?;
+
+        try_write_to_dir(&tdir, tla_file_suite)?;
+
+        // Gets Apalache command with tdir as working dir
+        let cmd = apalache_start_cmd(&tdir, runtime, None);
+
+        // create apalache typecheck command
+        let cmd = typecheck_cmd(cmd, &tla_file_suite.tla_file.file_name());
+
+        // run apalache
+        let apalache_output = run_apalache(cmd)?;
+
+        if let Some((location, message)) = apalache_output.type_error() {
+            return Err(Error::ApalacheTypeError { location, message });
+        }
+
+        match apalache_output.non_counterexample_error() {
+            None => Ok(()),
+            Some(err) => Err(Error::ApalacheFailure(err)),
+        }
+    }
 }
 
 fn run_apalache(mut cmd: Command) -> Result<CmdOutput, Error> {
@@ -195,26 +479,125 @@ fn run_apalache(mut cmd: Command) -> Result<CmdOutput, Error> {
     })
 }
 
+/// Returns the counterexample files reported in the output of Apalache, run
+/// in `dir`. Apalache reports either errors, counterexamples, or that the
+/// test holds: any other output, e.g. of an Apalache killed while running, is
+/// unexpected.
+fn counterexample_paths(apalache_output: &CmdOutput, dir: &Path) -> Result<Vec<String>, Error> {
+    let unexpected = || Error::UnexpectedModelCheckerOutput {
+        stdout: apalache_output.stdout.join("\n"),
+        stderr: apalache_output.stderr.join("\n"),
+    };
+    let counterexample_paths = apalache_output.parse_counterexample_filenames()?;
+    if counterexample_paths.is_empty() && !apalache_output.completed_without_error() {
+        return Err(unexpected());
+    }
+
+    // Apalache reported counterexample files that it didn't write to the
+    // temporary directory of this run; reading files from anywhere else
+    // (e.g. the current directory) could mix up the traces of tests run
+    // in parallel
+    let dir = dir.canonicalize()?;
+    if counterexample_paths.iter().any(|path| {
+        dir.join(path)
+            .canonicalize()
+            .map_or(true, |path| !path.starts_with(&dir) || !path.is_file())
+    }) {
+        return Err(unexpected());
+    }
+    Ok(counterexample_paths)
+}
+
+/// Returns the index of a counterexample from the name of its file, e.g. `2`
+/// for `violation2.tla` or `counterexample2.tla`.
+fn counterexample_index(path: &str) -> Option<u64> {
+    let stem = path.strip_suffix(".tla")?;
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[stem.len() - digits..].parse().ok()
+}
+
+/// Returns the hash of the states of a trace, to order counterexamples
+/// without index.
+fn trace_hash(trace: &TlaTrace) -> String {
+    use sha2::Digest;
+    let mut digest = sha2::Sha256::default();
+    digest.update(trace.to_string());
+    crate::util::digest::encode(digest)
+}
+
+/// Returns whether the test module of a [`TlaFileSuite`], or one of the
+/// modules it extends, defines the view of a test (see [`VIEW_OPERATOR`]).
+fn defines_view(tla_file_suite: &TlaFileSuite) -> Result<bool, Error> {
+    for tla_file in
+        std::iter::once(&tla_file_suite.tla_file).chain(&tla_file_suite.dependency_tla_files)
+    {
+        if language::defines_operator(tla_file.file_contents_backing(), VIEW_OPERATOR)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Returns the test module with a view over the given variables, defined
+/// at the end of the module. The view is a record, as Apalache's type checker
+/// can't tell a tuple of variables from a sequence without annotations.
+fn with_view(tla_file: &TlaFile, variables: &TlaVariables) -> Result<TlaFile, Error> {
+    let fields: Vec<_> = variables
+        .iter()
+        .map(|variable| format!("{} |-> {}", variable, variable))
+        .collect();
+    checker::with_definitions(
+        tla_file,
+        &format!("{} == [{}]\n", VIEW_OPERATOR, fields.join(", ")),
+    )
+}
+
 fn check_cmd<P: AsRef<Path>>(
     mut cmd: Command,
     tla_file_base_name: P,
     tla_config_file_base_name: P,
-    max_error: usize,
+    checker_runtime: &checker::ModelCheckerRuntime,
     view: &Option<String>,
 ) -> Command {
-    cmd.arg("check")
+    cmd.arg(checker_runtime.apalache_mode.command())
         .arg(format!(
             "--config={}",
             tla_config_file_base_name.as_ref().to_string_lossy()
         ))
-        .arg(format!("--max-error={}", max_error))
-        .arg("--algo=offline");
+        .arg(format!("--max-error={}", checker_runtime.traces_per_test));
+
+    match checker_runtime.apalache_mode {
+        ApalacheMode::Check => {
+            cmd.arg(format!("--algo={}", checker_runtime.algo.name()));
+        }
+        ApalacheMode::Simulate { max_run, length } => {
+            cmd.arg(format!("--max-run={}", max_run))
+                .arg(format!("--length={}", length));
+        }
+    }
+
+    if let Some(random_seed) = checker_runtime.random_seed {
+        cmd.arg(format!("--tuning-options=smt.randomSeed={}", random_seed));
+    }
+
+    let overrides = [
+        ("init", &checker_runtime.init),
+        ("next", &checker_runtime.next),
+        ("inv", &checker_runtime.inv),
+        ("cinit", &checker_runtime.cinit),
+    ];
+    for (option, operator) in overrides {
+        if let Some(operator) = operator {
+            cmd.arg(format!("--{}={}", option, operator));
+        }
+    }
 
     if let Some(view) = view {
         cmd.arg(format!("--view={}", view));
     };
 
-    cmd.arg(tla_file_base_name.as_ref());
+    cmd.args(&checker_runtime.extra_checker_args)
+        .arg(tla_file_base_name.as_ref());
 
     // show command being run
     tracing::debug!("{}", crate::util::cmd_show(&cmd));
@@ -238,16 +621,33 @@ fn parse_cmd<P: AsRef<Path>>(
     cmd
 }
 
-/// Creates an Apalache start command providing `temp_dir` as a library directory and the Apalache jar
-fn apalache_start_cmd(temp_dir: &tempfile::TempDir, runtime: &ModelatorRuntime) -> Command {
+fn typecheck_cmd<P: AsRef<Path>>(mut cmd: Command, tla_file_base_name: &P) -> Command {
+    cmd.arg("typecheck").arg(tla_file_base_name.as_ref());
+
+    // show command being run
+    tracing::debug!("{}", crate::util::cmd_show(&cmd));
+    cmd
+}
+
+// Each command starts a new JVM: a long-lived Apalache server, reused across
+// the tests of a `traces()` call, isn't supported, as the Apalache version we
+// download (see `jar::APALACHE_JAR`) has no server mode to submit checks to.
+/// Creates an Apalache start command providing `temp_dir`, and `dependencies_dir` if any,
+/// as library directories and the Apalache jar
+fn apalache_start_cmd(
+    temp_dir: &tempfile::TempDir,
+    runtime: &ModelatorRuntime,
+    dependencies_dir: Option<&Path>,
+) -> Command {
     let apalache = jar::Jar::Apalache.path(&runtime.dir);
 
     let mut cmd = Command::new("java");
 
     cmd.current_dir(temp_dir)
+        .args(&runtime.model_checker_runtime.jvm_args)
         .arg(format!(
             "-DTLA-Library={}",
-            temp_dir.path().to_string_lossy()
+            checker::tla_library(temp_dir.path(), dependencies_dir).to_string_lossy()
         ))
         .arg("-jar")
         .arg(format!(
@@ -258,3 +658,290 @@ fn apalache_start_cmd(temp_dir: &tempfile::TempDir, runtime: &ModelatorRuntime)
 
     cmd
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::language::{TestDiscovery, Tla};
+
+    #[test]
+    fn test_apalache_mode_supported() {
+        assert!(ApalacheMode::Check.check_supported().is_ok());
+        // the Apalache jar run by modelator has no `simulate` command
+        let mode = ApalacheMode::Simulate {
+            max_run: 10,
+            length: 5,
+        };
+        match mode.check_supported() {
+            Err(error @ Error::UnsupportedApalacheMode { .. }) => assert_eq!(
+                error.to_string(),
+                "`apalache simulate` requires Apalache 0.30.0 or later, \
+                 but modelator runs Apalache 0.17.5"
+            ),
+            other => panic!("expected an unsupported mode, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_check_cmd_algo() {
+        let show = |checker_runtime: &checker::ModelCheckerRuntime| {
+            let cmd = check_cmd(
+                Command::new("apalache"),
+                "A.tla",
+                "A.cfg",
+                checker_runtime,
+                &None,
+            );
+            crate::util::cmd_show(&cmd)
+        };
+        let checker_runtime = checker::ModelCheckerRuntime::default();
+        assert!(show(&checker_runtime).contains("--algo=offline"));
+        let checker_runtime = checker_runtime.algo(checker::ApalacheAlgo::Incremental);
+        assert!(show(&checker_runtime).contains("--algo=incremental"));
+    }
+
+    #[test]
+    fn test_check_cmd_random_seed() {
+        let show = |checker_runtime: &checker::ModelCheckerRuntime| {
+            let cmd = check_cmd(
+                Command::new("apalache"),
+                "A.tla",
+                "A.cfg",
+                checker_runtime,
+                &None,
+            );
+            crate::util::cmd_show(&cmd)
+        };
+        let checker_runtime = checker::ModelCheckerRuntime::default();
+        assert!(!show(&checker_runtime).contains("--tuning-options"));
+        let checker_runtime = checker_runtime.random_seed(7);
+        assert!(show(&checker_runtime).contains("--tuning-options=smt.randomSeed=7"));
+    }
+
+    #[test]
+    fn test_check_cmd_mode() {
+        let show = |checker_runtime: &checker::ModelCheckerRuntime| {
+            let cmd = check_cmd(
+                Command::new("apalache"),
+                "A.tla",
+                "A.cfg",
+                checker_runtime,
+                &None,
+            );
+            crate::util::cmd_show(&cmd)
+        };
+        let checker_runtime = checker::ModelCheckerRuntime::default();
+        assert!(show(&checker_runtime).contains(" check --config=A.cfg"));
+        let checker_runtime = checker_runtime.apalache_mode(ApalacheMode::Simulate {
+            max_run: 100,
+            length: 5,
+        });
+        let cmd = show(&checker_runtime);
+        assert!(cmd.contains(" simulate --config=A.cfg"));
+        assert!(cmd.contains("--max-run=100 --length=5"));
+        assert!(!cmd.contains("--algo"));
+    }
+
+    #[test]
+    fn test_check_cmd_operators() {
+        let show = |checker_runtime: &checker::ModelCheckerRuntime| {
+            let cmd = check_cmd(
+                Command::new("apalache"),
+                "A.tla",
+                "A.cfg",
+                checker_runtime,
+                &None,
+            );
+            crate::util::cmd_show(&cmd)
+        };
+        let checker_runtime = checker::ModelCheckerRuntime::default();
+        assert!(!show(&checker_runtime).contains("--init"));
+        let checker_runtime = checker_runtime.init("SafeInit").next("SystemNext");
+        let cmd = show(&checker_runtime);
+        assert!(cmd.contains("--init=SafeInit --next=SystemNext A.tla"));
+        assert!(!cmd.contains("--inv"));
+
+        let checker_runtime = checker_runtime.extra_checker_args(vec!["--debug".to_owned()]);
+        assert!(show(&checker_runtime).ends_with("--next=SystemNext --debug A.tla"));
+    }
+
+    #[test]
+    fn test_parallel_tests() {
+        checker::tests::check_parallel_tests(checker::ModelChecker::Apalache);
+    }
+
+    #[test]
+    fn test_tla_variables() {
+        let runtime = ModelatorRuntime::default();
+        runtime.setup().unwrap();
+
+        // the variables of the extended module are included
+        let tla_file_suite =
+            TlaFileSuite::from_tla_path("tests/integration/resource/NumbersAMaxBMaxTest.tla")
+                .unwrap();
+        let variables = Apalache::tla_variables(&tla_file_suite, &runtime).unwrap();
+        assert_eq!(
+            variables.into_iter().collect::<Vec<_>>(),
+            vec!["a", "action", "actionOutcome", "b"]
+        );
+    }
+
+    #[test]
+    fn test_with_view() {
+        let tla_file =
+            TlaFile::from_string("---- MODULE M_Test ----\nEXTENDS M\nTestNeg == ~Test\n====\n")
+                .unwrap();
+        let variables = ["y", "x"].iter().map(|&v| v.to_owned()).collect();
+        assert_eq!(
+            with_view(&tla_file, &variables)
+                .unwrap()
+                .file_contents_backing(),
+            "---- MODULE M_Test ----\nEXTENDS M\nTestNeg == ~Test\nViewForTestNeg == [x |-> x, y |-> y]\n====\n"
+        );
+    }
+
+    #[test]
+    fn test_defines_view() {
+        let suite = |content: &str, dependency: &str| TlaFileSuite {
+            tla_file: TlaFile::from_string(content).unwrap(),
+            tla_config_file: TlaConfigFile::from_string("").unwrap(),
+            dependency_tla_files: vec![TlaFile::from_string(dependency).unwrap()],
+        };
+        let test = "---- MODULE M_Test ----\nEXTENDS M\nTestNeg == ~Test\n";
+        let spec = "---- MODULE M ----\nTest == TRUE\n====\n";
+
+        // a mention in a comment isn't a definition
+        let commented = format!("{}\\* ViewForTestNeg == x\n====\n", test);
+        assert!(!defines_view(&suite(&commented, spec)).unwrap());
+        let defined = format!("{}ViewForTestNeg == x\n====\n", test);
+        assert!(defines_view(&suite(&defined, spec)).unwrap());
+        // the view may be defined in an extended module
+        let extended = "---- MODULE M ----\nTest == TRUE\nViewForTestNeg == x\n====\n";
+        assert!(defines_view(&suite(&format!("{}====\n", test), extended)).unwrap());
+    }
+
+    #[test]
+    fn test_explicit_view() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/2PossibleTracesTests.tla",
+            "tests/integration/resource/2PossibleTraces.cfg",
+        )
+        .unwrap();
+        let test = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default())
+            .unwrap()
+            .pop()
+            .unwrap();
+        let command = |checker_runtime: checker::ModelCheckerRuntime| {
+            let runtime = ModelatorRuntime::default()
+                .model_checker_runtime(checker_runtime)
+                .dry_run(true);
+            match Apalache::test(&test.file_suite, &runtime) {
+                Err(Error::DryRun(command)) => {
+                    let dir = command
+                        .strip_prefix("cd ")
+                        .and_then(|command| command.split(" && ").next())
+                        .unwrap();
+                    std::fs::remove_dir_all(dir).unwrap();
+                    command
+                }
+                result => panic!(
+                    "expected a dry run, got {:?}",
+                    result.map(|(traces, _)| traces)
+                ),
+            }
+        };
+
+        // the view of the test module is detected, unless overridden
+        let checker_runtime = checker::ModelCheckerRuntime::default();
+        assert!(command(checker_runtime.clone()).contains("--view=ViewForTestNeg"));
+        let command = command(checker_runtime.view("MyView"));
+        assert!(command.contains("--view=MyView"));
+        assert!(!command.contains("ViewForTestNeg"));
+    }
+
+    #[test]
+    fn test_generate_view() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/2PossibleTracesNoViewTests.tla",
+            "tests/integration/resource/2PossibleTraces.cfg",
+        )
+        .unwrap();
+        let test = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default())
+            .unwrap()
+            .pop()
+            .unwrap();
+        let runtime = ModelatorRuntime::default().model_checker_runtime(
+            checker::ModelCheckerRuntime::default()
+                .traces_per_test(2)
+                .generate_view(true),
+        );
+        runtime.setup().unwrap();
+
+        // the view over `x` makes the two traces reaching 3 differ
+        let (traces, _) = Apalache::test(&test.file_suite, &runtime).unwrap();
+        let traces: Vec<_> = traces
+            .into_iter()
+            .map(|trace| Tla::tla_trace_to_json_trace(trace).unwrap())
+            .collect();
+        assert_eq!(traces.len(), 2);
+        assert_ne!(traces[0], traces[1]);
+    }
+
+    #[test]
+    fn test_counterexample_index() {
+        assert_eq!(counterexample_index("counterexample1.tla"), Some(1));
+        assert_eq!(counterexample_index("/tmp/out/violation12.tla"), Some(12));
+        assert_eq!(counterexample_index("counterexample.tla"), None);
+        assert_eq!(counterexample_index("counterexample1.json"), None);
+    }
+
+    #[test]
+    fn test_traces_order() {
+        let runtime = ModelatorRuntime::default()
+            .model_checker_runtime(checker::ModelCheckerRuntime::default().traces_per_test(2));
+        let trace_ids = || -> Vec<String> {
+            let traces = runtime
+                .traces(
+                    "tests/integration/resource/2PossibleTracesTests.tla",
+                    "tests/integration/resource/2PossibleTraces.cfg",
+                )
+                .unwrap();
+            traces
+                .into_values()
+                .flat_map(Result::unwrap)
+                .map(|trace| trace.id())
+                .collect()
+        };
+
+        // the same traces come in the same order across runs
+        let ids = trace_ids();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(trace_ids(), ids);
+    }
+
+    #[test]
+    fn test_counterexample_paths_unexpected_output() {
+        let tdir = tempfile::tempdir().unwrap();
+        let output = |stdout: &[&str]| CmdOutput {
+            stdout: stdout.iter().map(|line| line.to_string()).collect(),
+            stderr: vec![],
+            status: Some(0),
+        };
+
+        let holds = output(&[
+            "Checker reports no error up to computation length 2",
+            "The outcome is: NoError",
+        ]);
+        assert!(counterexample_paths(&holds, tdir.path())
+            .unwrap()
+            .is_empty());
+
+        // Apalache killed before reporting whether the test holds
+        let killed = output(&["Step 1: picking a transition out of 2 transition(s)"]);
+        assert!(matches!(
+            counterexample_paths(&killed, tdir.path()),
+            Err(Error::UnexpectedModelCheckerOutput { .. })
+        ));
+    }
+}
@@ -5,17 +5,50 @@ mod tlc;
 mod apalache;
 
 // Re-exports.
-pub use apalache::{cmd_output::ApalacheError, Apalache};
-pub use tlc::Tlc;
+pub(crate) use apalache::counterexample::parse as parse_apalache_counterexample;
+pub use apalache::{
+    cmd_output::{ApalacheError, ApalacheErrorKind},
+    Apalache, ApalacheAlgo, ApalacheMode, ApalacheParseFormat, TlaVariables,
+};
+pub(crate) use tlc::parse_state_graph as parse_tlc_state_graph;
+pub use tlc::{Coverage, RandomWalk, TlaAndJsonState, TlaNextStates, Tlc, TlcErrorKind};
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::env;
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::Deserialize;
 
+use crate::artifact::{
+    try_write_to_dir, ArtifactCreator, ArtifactSaver, ModelCheckerStdout, TlaConfigFile, TlaFile,
+    TlaFileSuite,
+};
+use crate::{Error, ModelatorRuntime};
+
 const DEFAULT_TRACES_PER_TEST: usize = 1;
 
+/// Logs older than a week are pruned by default.
+const DEFAULT_LOG_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Traces are truncated after 1000 states by default.
+const DEFAULT_MAX_TRACE_STATES: usize = 1000;
+
+/// Traces are truncated before a state of more than 1 MiB by default.
+const DEFAULT_MAX_STATE_BYTES: usize = 1 << 20;
+
+/// Name of the operator defined in test modules for
+/// [`ModelCheckerRuntime::state_constraint`].
+const STATE_CONSTRAINT_OPERATOR: &str = "ModelatorStateConstraint";
+
+/// Name of the operator defined in test modules for
+/// [`ModelCheckerRuntime::action_constraint`].
+const ACTION_CONSTRAINT_OPERATOR: &str = "ModelatorActionConstraint";
+
 /// Set of options to select the model checker to be used and configure them.
 #[derive(Clone, Debug)]
 pub struct ModelCheckerRuntime {
@@ -27,11 +60,108 @@ pub struct ModelCheckerRuntime {
     /// cores; and any number (e.g. '4') precising the number of workers threads.
     pub workers: ModelCheckerWorkers,
 
-    /// Model checker log file for debugging purposes.
-    pub log: PathBuf,
+    /// Where the output of the model checker is logged for debugging
+    /// purposes: to a new file for each run, in the `logs` directory of the
+    /// modelator directory, by default (see [`LogSink::Default`]).
+    pub log: LogSink,
+
+    /// How long the log files of a [`LogSink::Dir`] are kept: older ones are
+    /// removed when the runtime is set up. They are never removed if `None`.
+    pub log_retention: Option<Duration>,
 
     /// The maximum number of traces to try to generate for a single test.
+    /// The traces of a test come in the same order across runs: by the
+    /// index of Apalache's counterexample, and then by content.
     pub traces_per_test: usize,
+
+    /// Whether to drop traces of a single test that are identical once
+    /// converted to JSON, i.e. whose states have the same hash (see
+    /// [`crate::ModelatorRuntime::dedup_executions`] to run the traces
+    /// identical across tests only once).
+    pub dedup_traces: bool,
+
+    /// Maximum number of states of a trace converted to JSON: the states
+    /// after it are replaced by a marker state (see
+    /// [`crate::artifact::JsonTrace::omitted_states`]). Not limited if `None`.
+    pub max_trace_states: Option<usize>,
+
+    /// Maximum size, in bytes of TLA+, of a state of a trace converted to
+    /// JSON: the trace is truncated before the first larger state, as for
+    /// [`ModelCheckerRuntime::max_trace_states`]. Not limited if `None`.
+    pub max_state_bytes: Option<usize>,
+
+    /// Interval, in minutes, at which TLC reports action coverage (TLC's
+    /// `-coverage` option). Coverage isn't reported if `None`.
+    pub coverage: Option<usize>,
+
+    /// Arguments passed to the JVM running the model checker, e.g. `-Xss16M`.
+    pub jvm_args: Vec<String>,
+
+    /// TLA+ values assigned to constants, overriding their assignments in
+    /// the TLA+ config files.
+    pub constants: BTreeMap<String, String>,
+
+    /// Apalache command generating the traces: [`ApalacheMode::Check`], the
+    /// default, or [`ApalacheMode::Simulate`], whose counterexamples are
+    /// parsed the same way. Ignored by TLC.
+    pub apalache_mode: ApalacheMode,
+
+    /// TLA+ expression restricting the states explored, e.g. `a <= 10` to
+    /// bound an otherwise unbounded model. It's defined as an operator of
+    /// each test module, added to the `CONSTRAINT`s of its TLA+ config file.
+    /// Apalache, which explores executions of a bounded length, may ignore it.
+    pub state_constraint: Option<String>,
+
+    /// TLA+ expression restricting the steps explored, e.g. `a' > a`, as for
+    /// [`ModelCheckerRuntime::state_constraint`], but added to the
+    /// `ACTION_CONSTRAINT`s of the TLA+ config file.
+    pub action_constraint: Option<String>,
+
+    /// Search algorithm used by Apalache (Apalache's `--algo` option).
+    /// [`ApalacheAlgo::Offline`], the default, solves each execution with a
+    /// single SMT query; [`ApalacheAlgo::Incremental`] is often faster, but
+    /// may use more memory. Ignored by TLC, and by
+    /// [`ApalacheMode::Simulate`].
+    pub algo: ApalacheAlgo,
+
+    /// Name of the operator overriding the initial predicate of the TLA+
+    /// config file (Apalache's `--init` option). Ignored by TLC.
+    pub init: Option<String>,
+
+    /// Name of the operator overriding the next-state relation of the TLA+
+    /// config file (Apalache's `--next` option). Ignored by TLC.
+    pub next: Option<String>,
+
+    /// Name of the operator overriding the invariant of the TLA+ config file
+    /// (Apalache's `--inv` option). Ignored by TLC.
+    pub inv: Option<String>,
+
+    /// Name of the operator initializing the constants that aren't assigned
+    /// in the TLA+ config file (Apalache's `--cinit` option), e.g.
+    /// `ConstInit == MaxNumber \in 1..10`. Ignored by TLC.
+    pub cinit: Option<String>,
+
+    /// Seed of the SMT solver used by Apalache (Apalache's `smt.randomSeed`
+    /// tuning option): different seeds may lead to different counterexamples.
+    /// Ignored by TLC.
+    pub random_seed: Option<u64>,
+
+    /// Name of the operator used as the view of every test (Apalache's
+    /// `--view` option), overriding the `ViewForTestNeg` view defined by the
+    /// test modules, and the generated one. Ignored by TLC.
+    pub view: Option<String>,
+
+    /// Whether to generate a view over all the variables of a test that
+    /// doesn't define one (Apalache's `--view` option), so that the traces of
+    /// [`ModelCheckerRuntime::traces_per_test`] differ in their states.
+    /// Finding the variables takes an additional Apalache run. Ignored by TLC.
+    pub generate_view: bool,
+
+    /// Arguments appended verbatim to the command checking a model, e.g.
+    /// `--tuning-options=search.smt.timeout=10` for Apalache, or `-seed 42`
+    /// for TLC (split into `-seed` and `42`). Useful for the options not
+    /// exposed by modelator yet.
+    pub extra_checker_args: Vec<String>,
 }
 
 impl ModelCheckerRuntime {
@@ -47,9 +177,29 @@ impl ModelCheckerRuntime {
         self
     }
 
-    /// Set model checker log file.
+    /// Set a single model checker log file, overwritten by each run.
     pub fn log(mut self, log: impl AsRef<Path>) -> Self {
-        self.log = log.as_ref().to_path_buf();
+        self.log = LogSink::File(log.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the directory where each model checker run writes its own log file.
+    pub fn log_dir(mut self, log_dir: impl AsRef<Path>) -> Self {
+        self.log = LogSink::Dir(log_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set how long the log files of a [`LogSink::Dir`] are kept.
+    pub fn log_retention(mut self, log_retention: Option<Duration>) -> Self {
+        self.log_retention = log_retention;
+        self
+    }
+
+    /// Set where the output of the model checker is logged, e.g.
+    /// [`LogSink::Memory`] so that tests run in parallel don't overwrite each
+    /// other's log file.
+    pub fn log_sink(mut self, log: LogSink) -> Self {
+        self.log = log;
         self
     }
 
@@ -58,6 +208,116 @@ impl ModelCheckerRuntime {
         self.traces_per_test = n;
         self
     }
+
+    /// Set whether identical traces of a single test should be deduplicated.
+    pub const fn dedup_traces(mut self, dedup_traces: bool) -> Self {
+        self.dedup_traces = dedup_traces;
+        self
+    }
+
+    /// Set the maximum number of states of a trace converted to JSON, after
+    /// which it's truncated. Not limited if `None`.
+    pub const fn max_trace_states(mut self, max_trace_states: Option<usize>) -> Self {
+        self.max_trace_states = max_trace_states;
+        self
+    }
+
+    /// Set the maximum size, in bytes of TLA+, of a state of a trace
+    /// converted to JSON, before which it's truncated. Not limited if `None`.
+    pub const fn max_state_bytes(mut self, max_state_bytes: Option<usize>) -> Self {
+        self.max_state_bytes = max_state_bytes;
+        self
+    }
+
+    /// Set the interval, in minutes, at which TLC reports action coverage.
+    pub const fn coverage(mut self, coverage: Option<usize>) -> Self {
+        self.coverage = coverage;
+        self
+    }
+
+    /// Set the arguments passed to the JVM running the model checker.
+    pub fn jvm_args(mut self, jvm_args: Vec<String>) -> Self {
+        self.jvm_args = jvm_args;
+        self
+    }
+
+    /// Assign a TLA+ value to a constant, overriding the TLA+ config files.
+    pub fn constant(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.constants.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the Apalache command generating the traces.
+    pub const fn apalache_mode(mut self, apalache_mode: ApalacheMode) -> Self {
+        self.apalache_mode = apalache_mode;
+        self
+    }
+
+    /// Set the TLA+ expression restricting the states explored.
+    pub fn state_constraint(mut self, expr: impl Into<String>) -> Self {
+        self.state_constraint = Some(expr.into());
+        self
+    }
+
+    /// Set the TLA+ expression restricting the steps explored.
+    pub fn action_constraint(mut self, expr: impl Into<String>) -> Self {
+        self.action_constraint = Some(expr.into());
+        self
+    }
+
+    /// Set the search algorithm used by Apalache.
+    pub const fn algo(mut self, algo: ApalacheAlgo) -> Self {
+        self.algo = algo;
+        self
+    }
+
+    /// Set the operator overriding the initial predicate, for Apalache.
+    pub fn init(mut self, init: impl Into<String>) -> Self {
+        self.init = Some(init.into());
+        self
+    }
+
+    /// Set the operator overriding the next-state relation, for Apalache.
+    pub fn next(mut self, next: impl Into<String>) -> Self {
+        self.next = Some(next.into());
+        self
+    }
+
+    /// Set the operator overriding the invariant, for Apalache.
+    pub fn inv(mut self, inv: impl Into<String>) -> Self {
+        self.inv = Some(inv.into());
+        self
+    }
+
+    /// Set the operator initializing the constants, for Apalache.
+    pub fn cinit(mut self, cinit: impl Into<String>) -> Self {
+        self.cinit = Some(cinit.into());
+        self
+    }
+
+    /// Set the seed of the SMT solver, for Apalache.
+    pub const fn random_seed(mut self, random_seed: u64) -> Self {
+        self.random_seed = Some(random_seed);
+        self
+    }
+
+    /// Set the operator used as the view of every test, for Apalache.
+    pub fn view(mut self, view: impl Into<String>) -> Self {
+        self.view = Some(view.into());
+        self
+    }
+
+    /// Set whether a view is generated for the tests that don't define one, for Apalache.
+    pub const fn generate_view(mut self, generate_view: bool) -> Self {
+        self.generate_view = generate_view;
+        self
+    }
+
+    /// Set the arguments appended verbatim to the command checking a model.
+    pub fn extra_checker_args(mut self, extra_checker_args: Vec<String>) -> Self {
+        self.extra_checker_args = extra_checker_args;
+        self
+    }
 }
 
 impl Default for ModelCheckerRuntime {
@@ -65,8 +325,27 @@ impl Default for ModelCheckerRuntime {
         Self {
             model_checker: ModelChecker::Apalache,
             workers: ModelCheckerWorkers::Auto,
-            log: Path::new("mc.log").to_path_buf(),
+            log: LogSink::Default,
+            log_retention: Some(DEFAULT_LOG_RETENTION),
             traces_per_test: DEFAULT_TRACES_PER_TEST,
+            dedup_traces: false,
+            max_trace_states: Some(DEFAULT_MAX_TRACE_STATES),
+            max_state_bytes: Some(DEFAULT_MAX_STATE_BYTES),
+            coverage: None,
+            jvm_args: Vec::new(),
+            constants: BTreeMap::new(),
+            state_constraint: None,
+            action_constraint: None,
+            apalache_mode: ApalacheMode::Check,
+            algo: ApalacheAlgo::Offline,
+            init: None,
+            next: None,
+            inv: None,
+            cinit: None,
+            random_seed: None,
+            view: None,
+            generate_view: false,
+            extra_checker_args: Vec::new(),
         }
     }
 }
@@ -94,8 +373,141 @@ impl FromStr for ModelChecker {
     }
 }
 
+/// Where the output of the model checker is logged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LogSink {
+    /// The output is written to a file, overwriting it, and is also returned
+    /// as the [`ModelCheckerStdout`] of the run.
+    File(PathBuf),
+    /// Each run writes its output to a new file in the directory, named
+    /// `mc-<module>-<test>-<timestamp>.log`, and also returns it as the
+    /// [`ModelCheckerStdout`] of the run.
+    Dir(PathBuf),
+    /// Same as [`LogSink::Dir`], with the `logs` directory of the modelator
+    /// directory of the runtime (see [`crate::ModelatorRuntime::dir`]).
+    Default,
+    /// The output is only returned as the [`ModelCheckerStdout`] of the run.
+    Memory,
+    /// The output is discarded: the [`ModelCheckerStdout`] of the run is empty.
+    None,
+}
+
+impl LogSink {
+    /// Returns the path of the log file, if the output is logged to a file.
+    /// A log directory has no path until a run is given its own file.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::File(path) => Some(path),
+            Self::Dir(_) | Self::Default | Self::Memory | Self::None => None,
+        }
+    }
+
+    /// Returns the sink with the [`LogSink::Default`] directory resolved
+    /// against a modelator directory.
+    pub(crate) fn resolve(&self, modelator_dir: &Path) -> Self {
+        match self {
+            Self::Default => Self::Dir(modelator_dir.join("logs")),
+            Self::File(_) | Self::Dir(_) | Self::Memory | Self::None => self.clone(),
+        }
+    }
+
+    /// Returns the sink of the log of a single test: a log file is suffixed
+    /// with the name of the test, e.g. `mc_MyTest.log` for `mc.log`, so that
+    /// tests run in parallel don't overwrite each other's log. A log directory
+    /// is given a new file named after the module and the test.
+    pub(crate) fn for_test(&self, module_name: &str, test_name: &str) -> Self {
+        match self {
+            Self::File(path) => {
+                let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+                file_name.push(format!("_{}", test_name));
+                if let Some(extension) = path.extension() {
+                    file_name.push(".");
+                    file_name.push(extension);
+                }
+                Self::File(path.with_file_name(file_name))
+            }
+            Self::Dir(dir) => Self::File(dir.join(log_file_name(&[module_name, test_name]))),
+            Self::Default | Self::Memory | Self::None => self.clone(),
+        }
+    }
+
+    /// Returns the sink of a single run checking the module: a log directory
+    /// is given a new file named after the module.
+    pub(crate) fn for_run(&self, module_name: &str) -> Self {
+        match self {
+            Self::Dir(dir) => Self::File(dir.join(log_file_name(&[module_name]))),
+            Self::File(_) | Self::Default | Self::Memory | Self::None => self.clone(),
+        }
+    }
+
+    /// Logs the output of a model checker run, returning it unless it's
+    /// discarded.
+    pub(crate) fn log(&self, stdout: &str) -> Result<ModelCheckerStdout, Error> {
+        match self {
+            Self::File(path) => {
+                // the log is written to a temporary file first, and then
+                // renamed, so that runs writing the same log concurrently
+                // don't mix their outputs
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                std::fs::create_dir_all(dir)?;
+                let mut file = tempfile::NamedTempFile::new_in(dir)?;
+                std::io::Write::write_all(&mut file, stdout.as_bytes())?;
+                file.persist(path).map_err(|e| e.error)?;
+                let mut log = ModelCheckerStdout::from_string(stdout)?;
+                log.log_path = Some(path.clone());
+                Ok(log)
+            }
+            Self::Dir(_) => self.for_run("").log(stdout),
+            // the runs resolve the default sink (see `run_runtime`), without
+            // which there's no directory to log to
+            Self::Default | Self::Memory => ModelCheckerStdout::from_string(stdout),
+            Self::None => ModelCheckerStdout::from_string(""),
+        }
+    }
+}
+
+/// Statistics of a model checker run, as reported in its output. The
+/// statistics a model checker doesn't report are `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CheckerStats {
+    /// Number of states generated. Only reported by TLC.
+    pub states_generated: Option<u64>,
+    /// Number of distinct states found. Only reported by TLC.
+    pub distinct_states: Option<u64>,
+    /// Depth of the search: the number of states of the longest behavior explored.
+    pub depth: Option<u64>,
+    /// Duration of the run, as measured by the model checker.
+    pub duration: Option<Duration>,
+}
+
+impl std::fmt::Display for CheckerStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut stats = Vec::new();
+        if let Some(states_generated) = self.states_generated {
+            stats.push(format!("{} states generated", states_generated));
+        }
+        if let Some(distinct_states) = self.distinct_states {
+            stats.push(format!("{} distinct states", distinct_states));
+        }
+        if let Some(depth) = self.depth {
+            stats.push(format!("depth {}", depth));
+        }
+        if let Some(duration) = self.duration {
+            stats.push(format!("{:.1}s", duration.as_secs_f64()));
+        }
+        if stats.is_empty() {
+            write!(f, "no statistics reported")
+        } else {
+            write!(f, "{}", stats.join(", "))
+        }
+    }
+}
+
 /// Configuration option to select the number of model checker workers.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ModelCheckerWorkers {
     /// Automatically select the number of model checker worker threads based
     /// on the number of available cores.
@@ -121,6 +533,413 @@ impl std::str::FromStr for ModelCheckerWorkers {
     }
 }
 
+/// Returns the [`Error::DryRun`] of a command that isn't run, as
+/// [`ModelatorRuntime::dry_run`] is set. The directory it would have run in
+/// is kept, so that it can be run by hand.
+pub(crate) fn dry_run(cmd: &Command, dir: tempfile::TempDir) -> Error {
+    let dir = dir.into_path();
+    tracing::debug!("dry run: keeping {}", dir.display());
+    Error::DryRun(crate::util::cmd_show(cmd))
+}
+
+/// Writes the files of a [`TlaFileSuite`] to `dir`, except for its dependencies
+/// if they were already written to `dependencies_dir`.
+/// The module is written with the definitions of the constraints, and the
+/// config file with the constraints added and the constants overridden.
+pub(crate) fn write_suite(
+    dir: &Path,
+    tla_file_suite: &TlaFileSuite,
+    dependencies_dir: Option<&Path>,
+    checker_runtime: &ModelCheckerRuntime,
+) -> Result<(), Error> {
+    match dependencies_dir {
+        None => try_write_to_dir(dir, tla_file_suite)?,
+        // the suite's dependencies come first
+        Some(_) => try_write_to_dir(
+            dir,
+            tla_file_suite
+                .into_iter()
+                .skip(tla_file_suite.dependency_tla_files.len()),
+        )?,
+    }
+    if !checker_runtime.constraints().is_empty() {
+        with_constraints(&tla_file_suite.tla_file, checker_runtime)?.try_write_to_dir(dir)?;
+    }
+    if !checker_runtime.constraints().is_empty() || !checker_runtime.constants.is_empty() {
+        run_config(&tla_file_suite.tla_config_file, checker_runtime)?.try_write_to_dir(dir)?;
+    }
+    Ok(())
+}
+
+impl ModelCheckerRuntime {
+    /// Returns the constraints set, along with the name of the operator of
+    /// each one.
+    pub(crate) fn constraints(&self) -> Vec<(&'static str, &str)> {
+        [
+            (STATE_CONSTRAINT_OPERATOR, &self.state_constraint),
+            (ACTION_CONSTRAINT_OPERATOR, &self.action_constraint),
+        ]
+        .into_iter()
+        .filter_map(|(operator, expr)| expr.as_deref().map(|expr| (operator, expr)))
+        .collect()
+    }
+}
+
+/// Returns a copy of a TLA+ module with the given definitions appended, right
+/// before the end of the module.
+pub(crate) fn with_definitions(tla_file: &TlaFile, definitions: &str) -> Result<TlaFile, Error> {
+    let content = tla_file.file_contents_backing();
+    let end = content
+        .rfind("\n====")
+        .map_or(content.len(), |index| index + 1);
+    TlaFile::from_string(&format!(
+        "{}{}{}",
+        &content[..end],
+        definitions,
+        &content[end..]
+    ))
+}
+
+/// Returns a copy of a TLA+ module with the operators of the constraints of
+/// the runtime defined (see [`ModelCheckerRuntime::state_constraint`]).
+pub(crate) fn with_constraints(
+    tla_file: &TlaFile,
+    checker_runtime: &ModelCheckerRuntime,
+) -> Result<TlaFile, Error> {
+    let definitions: String = checker_runtime
+        .constraints()
+        .into_iter()
+        .map(|(operator, expr)| format!("{} == {}\n", operator, expr))
+        .collect();
+    with_definitions(tla_file, &definitions)
+}
+
+/// Returns a copy of a TLA+ config file with the constraints of the runtime
+/// added to their sections, and its constants overridden.
+fn run_config(
+    tla_config_file: &TlaConfigFile,
+    checker_runtime: &ModelCheckerRuntime,
+) -> Result<TlaConfigFile, Error> {
+    if checker_runtime.constraints().is_empty() {
+        return Ok(tla_config_file.with_constants(&checker_runtime.constants));
+    }
+    // the config file is rewritten from its parts, so that the constraints
+    // are added to the existing ones
+    let mut parts = tla_config_file.parts()?;
+    if checker_runtime.state_constraint.is_some() {
+        parts.constraints.push(STATE_CONSTRAINT_OPERATOR.to_owned());
+    }
+    if checker_runtime.action_constraint.is_some() {
+        parts.directives.push((
+            "ACTION_CONSTRAINT".to_owned(),
+            ACTION_CONSTRAINT_OPERATOR.to_owned(),
+        ));
+    }
+    let mut config = parts.build();
+    config.set_path(tla_config_file.path());
+    Ok(config.with_constants(&checker_runtime.constants))
+}
+
+/// Writes the dependencies of a [`TlaFileSuite`] to `dir`, so that they can
+/// be shared by several model checker runs.
+pub(crate) fn write_dependencies(dir: &Path, tla_file_suite: &TlaFileSuite) -> Result<(), Error> {
+    try_write_to_dir(
+        dir,
+        tla_file_suite
+            .dependency_tla_files
+            .iter()
+            .map(|file| Box::new(file as &dyn ArtifactSaver)),
+    )
+}
+
+/// Returns the runtime of a single run checking the module, with its own log
+/// file if the output is logged to a directory.
+pub(crate) fn run_runtime<'a>(
+    runtime: &'a ModelatorRuntime,
+    module_name: &str,
+) -> Cow<'a, ModelatorRuntime> {
+    match runtime.model_checker_runtime.log {
+        LogSink::Dir(_) | LogSink::Default => {
+            let mut run_runtime = runtime.clone();
+            run_runtime.model_checker_runtime.log = runtime
+                .model_checker_runtime
+                .log
+                .resolve(&runtime.dir)
+                .for_run(module_name);
+            Cow::Owned(run_runtime)
+        }
+        LogSink::File(_) | LogSink::Memory | LogSink::None => Cow::Borrowed(runtime),
+    }
+}
+
+/// Returns the name of a new log file: `mc-<parts>-<timestamp>.log`, the
+/// timestamp being in milliseconds since the Unix epoch.
+fn log_file_name(parts: &[&str]) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let mut name = String::from("mc");
+    for part in parts.iter().filter(|part| !part.is_empty()) {
+        name.push('-');
+        name.push_str(part);
+    }
+    format!("{}-{}.log", name, timestamp)
+}
+
+/// Removes the log files of `log_dir` that were last modified longer than
+/// `retention` ago.
+pub(crate) fn prune_logs(log_dir: &Path, retention: Duration) -> Result<(), Error> {
+    if !log_dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(log_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if !file_name.starts_with("mc-") || !file_name.ends_with(".log") {
+            continue;
+        }
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map(|modified| modified.elapsed().unwrap_or_default() > retention)
+            .unwrap_or(false);
+        if stale {
+            // another runtime may be pruning the same directory
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                tracing::debug!("couldn't remove the log file {:?}: {}", entry.path(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the path reported when no trace is found: the log file, or the
+/// TLA+ file if the output of the model checker isn't logged to a file.
+pub(crate) fn no_trace_path(
+    tla_file_suite: &TlaFileSuite,
+    checker_runtime: &ModelCheckerRuntime,
+) -> PathBuf {
+    match checker_runtime.log.path() {
+        Some(path) => path.to_path_buf(),
+        None => PathBuf::from(tla_file_suite.tla_file.file_name()),
+    }
+}
+
+/// Returns the value of the `TLA-Library` Java property, used by the model
+/// checkers to find the modules that are not in their working directory.
+pub(crate) fn tla_library(dir: &Path, dependencies_dir: Option<&Path>) -> OsString {
+    env::join_paths(std::iter::once(dir).chain(dependencies_dir))
+        .expect("[modelator] temporary directories shouldn't contain a path separator")
+}
+
 fn unsupported(s: &str) -> String {
     format!("unsupported value {:?}", s)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::language::{TestDiscovery, Tla};
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+    /// Checks tests of the same spec in parallel with the given model
+    /// checker, each run getting its own working directory.
+    pub(super) fn check_parallel_tests(model_checker: ModelChecker) {
+        let runtime = ModelatorRuntime::default()
+            .model_checker_runtime(ModelCheckerRuntime::default().model_checker(model_checker));
+        runtime.setup().unwrap();
+
+        // (test, expected `a` and `b` in the last state)
+        let tests = [
+            ("AMaxBMaxTest", 6, 6),
+            ("AMinBMaxTest", 0, 6),
+            ("AMaxBMinTest", 6, 0),
+        ];
+        tests.par_iter().for_each(|(test, a, b)| {
+            let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+                format!("tests/integration/resource/Numbers{}.tla", test),
+                "tests/integration/resource/Numbers.cfg".to_string(),
+            )
+            .unwrap();
+            let test_suite = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default())
+                .unwrap()
+                .pop()
+                .unwrap();
+            let (mut traces, _) = match model_checker {
+                ModelChecker::Tlc => Tlc::test(&test_suite.file_suite, &runtime),
+                ModelChecker::Apalache => Apalache::test(&test_suite.file_suite, &runtime),
+            }
+            .unwrap();
+            let trace = Tla::tla_trace_to_json_trace(traces.pop().unwrap()).unwrap();
+            let last = trace.into_iter().last().unwrap();
+            assert_eq!(
+                (&last["a"], &last["b"]),
+                (&serde_json::json!(a), &serde_json::json!(b)),
+                "{}",
+                test
+            );
+        });
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_write_suite() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/NumbersAMaxBMinTest.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let mut tests = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default()).unwrap();
+        let test = tests.pop().unwrap();
+
+        let dependencies_dir = tempfile::tempdir().unwrap();
+        write_dependencies(dependencies_dir.path(), &test.file_suite).unwrap();
+        assert_eq!(
+            file_names(dependencies_dir.path()),
+            vec!["Numbers.tla", "NumbersAMaxBMinTest.tla"]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        write_suite(
+            dir.path(),
+            &test.file_suite,
+            Some(dependencies_dir.path()),
+            &ModelCheckerRuntime::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            file_names(dir.path()),
+            vec![
+                "NumbersAMaxBMinTest_AMaxBMinTest.cfg",
+                "NumbersAMaxBMinTest_AMaxBMinTest.tla"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_suite_constraints() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/NumbersUnboundedTests.tla",
+            "tests/integration/resource/NumbersUnbounded.cfg",
+        )
+        .unwrap();
+        let checker_runtime = ModelCheckerRuntime::default()
+            .state_constraint("a <= 3")
+            .action_constraint("b' >= b");
+
+        let dir = tempfile::tempdir().unwrap();
+        write_suite(dir.path(), &tla_file_suite, None, &checker_runtime).unwrap();
+        let module = std::fs::read_to_string(dir.path().join("NumbersUnboundedTests.tla")).unwrap();
+        // the definitions come right before the end of the module
+        assert!(module.contains(
+            "ModelatorStateConstraint == a <= 3\nModelatorActionConstraint == b' >= b\n====="
+        ));
+        let config =
+            TlaConfigFile::try_read_from_file(dir.path().join("NumbersUnboundedTests.cfg"))
+                .unwrap()
+                .parts()
+                .unwrap();
+        assert_eq!(config.init.as_deref(), Some("Init"));
+        assert_eq!(config.constraints, vec!["ModelatorStateConstraint"]);
+        assert_eq!(
+            config.directives,
+            vec![(
+                "ACTION_CONSTRAINT".to_owned(),
+                "ModelatorActionConstraint".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_log_sink_for_test() {
+        let log = LogSink::File(PathBuf::from("logs/mc.log"));
+        assert_eq!(
+            log.for_test("MyModule", "MyTest"),
+            LogSink::File(PathBuf::from("logs/mc_MyTest.log"))
+        );
+        let log = LogSink::File(PathBuf::from("mc"));
+        assert_eq!(
+            log.for_test("MyModule", "MyTest"),
+            LogSink::File(PathBuf::from("mc_MyTest"))
+        );
+        assert_eq!(
+            LogSink::Memory.for_test("MyModule", "MyTest"),
+            LogSink::Memory
+        );
+
+        let log = LogSink::Dir(PathBuf::from("logs"));
+        let path = log
+            .for_test("MyModule", "MyTest")
+            .path()
+            .unwrap()
+            .to_owned();
+        assert_eq!(path.parent(), Some(Path::new("logs")));
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        assert!(
+            file_name.starts_with("mc-MyModule-MyTest-"),
+            "{}",
+            file_name
+        );
+        assert!(file_name.ends_with(".log"), "{}", file_name);
+        // a run of a test already has its own log file
+        let run_log = log.for_run("MyModule");
+        let file_name = run_log.path().unwrap().file_name().unwrap();
+        assert!(file_name.to_string_lossy().starts_with("mc-MyModule-"));
+        assert_eq!(run_log.for_run("Other"), run_log);
+    }
+
+    #[test]
+    fn test_log_sink_default() {
+        let tdir = tempfile::tempdir().unwrap();
+        let runtime = ModelatorRuntime::default().dir(tdir.path());
+        assert_eq!(runtime.model_checker_runtime.log, LogSink::Default);
+
+        // the runs log to the modelator directory of the runtime
+        let run_runtime = run_runtime(&runtime, "MyModule");
+        let path = run_runtime.model_checker_runtime.log.path().unwrap();
+        assert_eq!(path.parent(), Some(tdir.path().join("logs").as_path()));
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("mc-MyModule-"));
+    }
+
+    #[test]
+    fn test_prune_logs() {
+        let log_dir = tempfile::tempdir().unwrap();
+        let log = LogSink::Dir(log_dir.path().join("logs"));
+        let log_path = log.for_run("MyModule").path().unwrap().to_owned();
+        let stdout = LogSink::File(log_path.clone()).log("output").unwrap();
+        assert_eq!(stdout.log_path(), Some(log_path.as_path()));
+        std::fs::write(log_dir.path().join("logs/other.txt"), "").unwrap();
+
+        prune_logs(&log_dir.path().join("logs"), Duration::from_secs(3600)).unwrap();
+        assert_eq!(
+            file_names(&log_dir.path().join("logs")),
+            vec![
+                log_path.file_name().unwrap().to_string_lossy().into_owned(),
+                "other.txt".to_owned()
+            ]
+        );
+
+        std::thread::sleep(Duration::from_millis(20));
+        prune_logs(&log_dir.path().join("logs"), Duration::from_millis(10)).unwrap();
+        assert_eq!(
+            file_names(&log_dir.path().join("logs")),
+            vec!["other.txt".to_owned()]
+        );
+        // a missing log directory has nothing to prune
+        prune_logs(&log_dir.path().join("missing"), Duration::ZERO).unwrap();
+    }
+}
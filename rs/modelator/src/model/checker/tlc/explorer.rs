@@ -0,0 +1,630 @@
+use super::{output, test_cmd, Tlc, TlcErrorKind};
+use crate::artifact::state_graph::StateGraphNode;
+use crate::artifact::{
+    ArtifactCreator, JsonTrace, StateGraphArtifact, TlaConfigFile, TlaFile, TlaFileSuite, TlaTrace,
+};
+use crate::model::checker;
+use crate::model::language::Tla;
+use crate::{Error, ModelatorRuntime};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::Path;
+use std::process::Command;
+
+/// Name of the operator limiting the depth of the exploration.
+const DEPTH_CONSTRAINT: &str = "ExplorerDepthConstraint";
+
+/// Name of the file TLC dumps the state graph to.
+const STATE_GRAPH_FILE: &str = "state_graph.dot";
+
+/// Name of the operator defining the state whose successors are explored.
+const NEXT_STATES_INIT: &str = "ExplorerInit";
+
+/// Name of the invariant violated by the successors of the explored state.
+const NEXT_STATES_INVARIANT: &str = "ExplorerNextStatesInvariant";
+
+/// A state of a spec, both as a TLA+ formula and as JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlaAndJsonState {
+    /// The state as a TLA+ formula, e.g. `/\ a = 1 /\ b = 0`.
+    pub tla: String,
+    /// The state as JSON, as the states of [`crate::artifact::JsonTrace`]s.
+    pub json: JsonValue,
+}
+
+impl TlaAndJsonState {
+    /// JSON-encodes a TLA+ state the way the states of traces are.
+    pub(crate) fn from_tla(tla: String) -> Result<Self, Error> {
+        let mut trace = TlaTrace::new();
+        trace.add(tla.clone());
+        let json = Tla::tla_trace_to_json_trace(trace)?
+            .states()
+            .first()
+            .cloned()
+            .unwrap_or_default();
+        Ok(Self { tla, json })
+    }
+}
+
+/// A trace of a spec built by picking a successor of each of its states at
+/// random, as returned by [`ModelatorRuntime::random_walks`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomWalk {
+    /// The states walked, starting with an initial state.
+    pub trace: JsonTrace,
+    /// Whether the walk stopped before the requested depth because its last
+    /// state has no successors.
+    pub dead_end: bool,
+}
+
+/// A state of a spec along with the states reachable from it in one step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlaNextStates {
+    /// The state explored.
+    pub initial_state: TlaAndJsonState,
+    /// The states reachable from the initial state in one step, other than itself.
+    pub next_states: Vec<TlaAndJsonState>,
+}
+
+impl Tlc {
+    /// Explores the states of the spec in a [`TlaFileSuite`] that are reachable
+    /// in at most `depth` steps from the initial states, and returns their
+    /// graph as a [Graphviz](https://graphviz.org/) graph in the DOT language.
+    /// Edges are labeled with the name of the action taken.
+    pub fn state_graph(
+        tla_file_suite: &TlaFileSuite,
+        depth: usize,
+        runtime: &ModelatorRuntime,
+    ) -> Result<String, Error> {
+        tracing::debug!(
+            "Tlc::state_graph {} {} {}",
+            tla_file_suite.tla_file,
+            tla_file_suite.tla_config_file,
+            depth
+        );
+
+        let explorer_suite = generate_explorer(tla_file_suite, depth)?;
+        let tdir = tempfile::tempdir()?;
+        checker::write_suite(
+            tdir.path(),
+            &explorer_suite,
+            None,
+            &runtime.model_checker_runtime,
+        )?;
+
+        let mut cmd = test_cmd(
+            &tdir,
+            explorer_suite.tla_file.file_name(),
+            explorer_suite.tla_config_file.filename(),
+            runtime,
+            None,
+        );
+        // dump the explored states and the actions between them
+        cmd.arg("-dump")
+            .arg("dot,actionlabels")
+            .arg(STATE_GRAPH_FILE);
+
+        let stdout = run(cmd)?;
+
+        // report the errors of TLC, if any
+        output::parse_traces(&stdout, &runtime.model_checker_runtime)?;
+
+        read_state_graph(&tdir.path().join(STATE_GRAPH_FILE), stdout)
+    }
+
+    /// Explores the states reachable in one step from `state`, a TLA+ formula
+    /// such as a state of a [`TlaTrace`], in the spec of a [`TlaFileSuite`].
+    /// The states are returned both as TLA+ and as JSON, in the order of
+    /// their TLA+ formulas.
+    ///
+    /// A successor equal to `state` isn't returned, as TLC reports new states only.
+    pub fn next_states(
+        tla_file_suite: &TlaFileSuite,
+        state: &str,
+        runtime: &ModelatorRuntime,
+    ) -> Result<TlaNextStates, Error> {
+        tracing::debug!(
+            "Tlc::next_states {} {} {}",
+            tla_file_suite.tla_file,
+            tla_file_suite.tla_config_file,
+            state
+        );
+        Ok(TlaNextStates {
+            initial_state: TlaAndJsonState::from_tla(state.to_owned())?,
+            next_states: explore_next_states(tla_file_suite, Some(state), runtime)?,
+        })
+    }
+
+    /// Explores the initial states of the spec in a [`TlaFileSuite`]. The
+    /// states are returned both as TLA+ and as JSON, in the order of their
+    /// TLA+ formulas.
+    pub fn initial_states(
+        tla_file_suite: &TlaFileSuite,
+        runtime: &ModelatorRuntime,
+    ) -> Result<Vec<TlaAndJsonState>, Error> {
+        tracing::debug!(
+            "Tlc::initial_states {} {}",
+            tla_file_suite.tla_file,
+            tla_file_suite.tla_config_file
+        );
+        explore_next_states(tla_file_suite, None, runtime)
+    }
+}
+
+/// Explores the states reachable in one step from `state`, or the initial
+/// states if `None`.
+fn explore_next_states(
+    tla_file_suite: &TlaFileSuite,
+    state: Option<&str>,
+    runtime: &ModelatorRuntime,
+) -> Result<Vec<TlaAndJsonState>, Error> {
+    let explorer_suite = generate_next_states_explorer(tla_file_suite, state)?;
+    let tdir = tempfile::tempdir()?;
+    checker::write_suite(
+        tdir.path(),
+        &explorer_suite,
+        None,
+        &runtime.model_checker_runtime,
+    )?;
+
+    let mut cmd = test_cmd(
+        &tdir,
+        explorer_suite.tla_file.file_name(),
+        explorer_suite.tla_config_file.filename(),
+        runtime,
+        None,
+    );
+    // each successor violates the invariant: report all of them, and
+    // don't report the explored state as a deadlock
+    if runtime.model_checker_runtime.traces_per_test <= 1 {
+        cmd.arg("-continue");
+    }
+    cmd.arg("-deadlock");
+
+    let stdout = run(cmd)?;
+
+    // each trace ends with a successor of the explored state (or an
+    // initial state), which may be reported several times
+    let next_states: BTreeSet<_> = output::parse_traces(&stdout, &runtime.model_checker_runtime)?
+        .into_iter()
+        .filter_map(|trace| trace.states.into_iter().nth(usize::from(state.is_some())))
+        .collect();
+    next_states
+        .into_iter()
+        .map(TlaAndJsonState::from_tla)
+        .collect()
+}
+
+/// Runs TLC, returning its stdout.
+fn run(mut cmd: Command) -> Result<String, Error> {
+    let output = cmd.output()?;
+    let stdout = crate::util::cmd_output_to_string(&output.stdout);
+    let stderr = crate::util::cmd_output_to_string(&output.stderr);
+    tracing::debug!("TLC stdout:\n{}", stdout);
+    tracing::debug!("TLC stderr:\n{}", stderr);
+    if !stderr.is_empty() {
+        return Err(Error::TLCFailure {
+            message: "TLC wrote to stderr".to_owned(),
+            kind: Box::new(TlcErrorKind::Other { code: None }),
+            stderr,
+        });
+    }
+    Ok(stdout)
+}
+
+/// Converts a state graph dumped by TLC (see [`Tlc::state_graph`]) into a
+/// [`StateGraphArtifact`], numbering its states breadth-first from the
+/// initial states, in the order of their TLA+ formulas. Once `max_states`
+/// states are numbered, the others are dropped, along with the steps to
+/// them, and the graph is marked as truncated.
+pub(crate) fn parse_state_graph(dot: &str, max_states: usize) -> Result<StateGraphArtifact, Error> {
+    // the TLA+ formulas of the states, and the fingerprints of their
+    // successors, by fingerprint
+    let mut formulas = BTreeMap::new();
+    let mut successors: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut initial_states = Vec::new();
+    for line in dot.lines() {
+        // the states and steps are on lines starting with fingerprints; the
+        // other lines are the attributes of the graph and its legend
+        let (node, rest) = line.split_once(' ').unwrap_or((line, ""));
+        if node.parse::<i64>().is_err() {
+            continue;
+        }
+        if let Some(rest) = rest.strip_prefix("-> ") {
+            let successor = rest
+                .split_once(' ')
+                .map_or(rest, |(successor, _)| successor);
+            let successor = successor.trim_end_matches(';');
+            successors.entry(node).or_default().insert(successor);
+        } else if let Some(label) = rest.strip_prefix("[label=\"") {
+            let (tla, attributes) =
+                unescape_dot_label(label).ok_or_else(|| Error::UnexpectedModelCheckerOutput {
+                    stdout: dot.to_owned(),
+                    stderr: String::new(),
+                })?;
+            // TLC fills the initial states
+            if attributes.contains("style = filled") {
+                initial_states.push(node);
+            }
+            formulas.insert(node, tla);
+        }
+    }
+    let formula = |node: &&str| formulas.get(node).cloned().unwrap_or_default();
+
+    let mut graph = StateGraphArtifact {
+        states: Vec::new(),
+        truncated: false,
+    };
+    let mut ids = BTreeMap::new();
+    let mut queue = VecDeque::new();
+    initial_states.sort_by_key(formula);
+    for node in initial_states {
+        if graph.states.len() >= max_states {
+            graph.truncated = true;
+            break;
+        }
+        ids.insert(node, graph.states.len());
+        graph.states.push(StateGraphNode {
+            state: TlaAndJsonState::from_tla(formula(&node))?.json,
+            initial: true,
+            successors: Vec::new(),
+        });
+        queue.push_back(node);
+    }
+    while let Some(node) = queue.pop_front() {
+        let mut next_nodes: Vec<_> = successors
+            .remove(node)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        next_nodes.sort_by_key(formula);
+        let mut next_ids = Vec::new();
+        for next_node in next_nodes {
+            let id = match ids.get(next_node) {
+                Some(&id) => id,
+                None if graph.states.len() >= max_states => {
+                    graph.truncated = true;
+                    continue;
+                }
+                None => {
+                    let id = graph.states.len();
+                    ids.insert(next_node, id);
+                    graph.states.push(StateGraphNode {
+                        state: TlaAndJsonState::from_tla(formula(&next_node))?.json,
+                        initial: false,
+                        successors: Vec::new(),
+                    });
+                    queue.push_back(next_node);
+                    id
+                }
+            };
+            next_ids.push(id);
+        }
+        graph.states[ids[node]].successors = next_ids;
+    }
+    Ok(graph)
+}
+
+/// Unescapes the label of a state dumped by TLC, given the text following its
+/// opening quote, and returns it along with the text following its closing
+/// quote, if any.
+fn unescape_dot_label(label: &str) -> Option<(String, &str)> {
+    let mut unescaped = String::new();
+    let mut chars = label.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((unescaped, &label[index + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => unescaped.push('\n'),
+                escaped => unescaped.push(escaped),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    None
+}
+
+/// Reads the state graph dumped by TLC, whose output is unexpected if it
+/// reported no error but dumped no graph.
+fn read_state_graph(path: &Path, stdout: String) -> Result<String, Error> {
+    if !path.is_file() {
+        return Err(Error::UnexpectedModelCheckerOutput {
+            stdout,
+            stderr: String::new(),
+        });
+    }
+    crate::util::try_read_file_contents(path)
+}
+
+/// Generates a module extending the spec with a constraint on the depth of
+/// the states explored, along with its config.
+fn generate_explorer(tla_file_suite: &TlaFileSuite, depth: usize) -> Result<TlaFileSuite, Error> {
+    let module_name = tla_file_suite.tla_file.module_name();
+    let explorer_module_name = format!("{}Explorer", module_name);
+    let tla_file = TlaFile::from_string(&generate_explorer_module(
+        &explorer_module_name,
+        module_name,
+        depth,
+    ))?;
+    let mut tla_config_file = generate_explorer_config(&tla_file_suite.tla_config_file)?;
+    tla_config_file.set_path(Path::new(&format!("{}.cfg", explorer_module_name)));
+
+    let mut dependency_tla_files = tla_file_suite.dependency_tla_files.clone();
+    dependency_tla_files.push(tla_file_suite.tla_file.clone());
+    Ok(TlaFileSuite {
+        tla_file,
+        tla_config_file,
+        dependency_tla_files,
+    })
+}
+
+/// Generates a module extending the spec whose initial state is `state`,
+/// along with a config in which the successors of `state` violate an invariant.
+/// If `state` is `None`, the initial states of the spec violate the invariant.
+fn generate_next_states_explorer(
+    tla_file_suite: &TlaFileSuite,
+    state: Option<&str>,
+) -> Result<TlaFileSuite, Error> {
+    let module_name = tla_file_suite.tla_file.module_name();
+    let explorer_module_name = format!("{}NextStates", module_name);
+    // the initial states are at level 1, and their successors at level 2
+    let level = if state.is_some() { 2 } else { 1 };
+    let init = state.map_or_else(String::new, |state| {
+        format!("{} ==\n{}\n", NEXT_STATES_INIT, state.trim_end())
+    });
+    let tla_file = TlaFile::from_string(&format!(
+        r#"
+---------- MODULE {} ----------
+EXTENDS {}, TLC
+
+{}
+{} == TLCGet("level") < {}
+
+{} == TLCGet("level") < {}
+===============================
+"#,
+        explorer_module_name,
+        module_name,
+        init,
+        NEXT_STATES_INVARIANT,
+        level,
+        DEPTH_CONSTRAINT,
+        level
+    ))?;
+    let mut tla_config_file =
+        generate_next_states_config(&tla_file_suite.tla_config_file, state.is_some())?;
+    tla_config_file.set_path(Path::new(&format!("{}.cfg", explorer_module_name)));
+
+    let mut dependency_tla_files = tla_file_suite.dependency_tla_files.clone();
+    dependency_tla_files.push(tla_file_suite.tla_file.clone());
+    Ok(TlaFileSuite {
+        tla_file,
+        tla_config_file,
+        dependency_tla_files,
+    })
+}
+
+/// Rewrites the config so that the explored states violate an invariant,
+/// which makes TLC report them, and aren't explored further. Its initial
+/// predicate is replaced if `replace_init`.
+fn generate_next_states_config(
+    tla_config_file: &TlaConfigFile,
+    replace_init: bool,
+) -> Result<TlaConfigFile, Error> {
+    let mut parts = tla_config_file.parts()?;
+    if replace_init {
+        if parts
+            .directives
+            .iter()
+            .any(|(keyword, _)| keyword == "SPECIFICATION")
+        {
+            return Err(Error::InvalidTlaConfig(
+                "the successors of a state can't be explored from a SPECIFICATION, \
+                 set INIT and NEXT instead"
+                    .to_owned(),
+            ));
+        }
+        parts.init = Some(NEXT_STATES_INIT.to_owned());
+    }
+    // other violations would be reported instead of the explored states
+    parts.invariants = vec![NEXT_STATES_INVARIANT.to_owned()];
+    parts.properties.clear();
+    parts.constraints.push(DEPTH_CONSTRAINT.to_owned());
+    Ok(parts.build())
+}
+
+fn generate_explorer_module(explorer_module_name: &str, module_name: &str, depth: usize) -> String {
+    // the initial states are at level 1, and their successors at level 2:
+    // states at level `depth + 1` are still reported, but not explored
+    format!(
+        r#"
+---------- MODULE {} ----------
+EXTENDS {}, TLC
+
+{} == TLCGet("level") <= {}
+===============================
+"#,
+        explorer_module_name, module_name, DEPTH_CONSTRAINT, depth
+    )
+}
+
+/// Rewrites the config so that the states are explored up to the depth
+/// constraint, without stopping at violations.
+fn generate_explorer_config(tla_config_file: &TlaConfigFile) -> Result<TlaConfigFile, Error> {
+    let mut parts = tla_config_file.parts()?;
+    parts.invariants.clear();
+    parts.properties.clear();
+    parts.constraints.push(DEPTH_CONSTRAINT.to_owned());
+    Ok(parts.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_graph() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/Numbers.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let runtime = ModelatorRuntime::default();
+        runtime.setup().unwrap();
+
+        let state_graph = Tlc::state_graph(&tla_file_suite, 1, &runtime).unwrap();
+        assert!(state_graph.starts_with("strict digraph DiskGraph {"));
+        // the initial state and its successors by IncreaseA and IncreaseB
+        let states: Vec<_> = state_graph
+            .lines()
+            .filter(|line| line.contains("[label=\"/\\\\ "))
+            .collect();
+        assert_eq!(states.len(), 3, "{}", state_graph);
+        for action in ["IncreaseA", "IncreaseB", "Next"] {
+            assert!(state_graph.contains(&format!("[label=\"{}\"", action)));
+        }
+
+        // the initial state only
+        let state_graph = Tlc::state_graph(&tla_file_suite, 0, &runtime).unwrap();
+        assert!(!state_graph.contains("->"), "{}", state_graph);
+    }
+
+    #[test]
+    fn test_next_states() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/Numbers.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let runtime = ModelatorRuntime::default();
+        runtime.setup().unwrap();
+
+        let state = |a: u64, b: u64, action: &str| serde_json::json!({ "a": a, "b": b, "action": action, "actionOutcome": "OK" });
+        let next_states = Tlc::next_states(
+            &tla_file_suite,
+            "/\\ a = 0\n/\\ b = 0\n/\\ action = \"None\"\n/\\ actionOutcome = \"OK\"",
+            &runtime,
+        )
+        .unwrap();
+        assert_eq!(next_states.initial_state.json, state(0, 0, "None"));
+
+        let mut jsons: Vec<_> = next_states
+            .next_states
+            .iter()
+            .map(|state| state.json.clone())
+            .collect();
+        jsons.sort_by_key(|json| json.to_string());
+        assert_eq!(
+            jsons,
+            vec![state(0, 2, "IncreaseB"), state(1, 0, "IncreaseA")]
+        );
+        // the TLA+ states can be explored in turn
+        assert!(next_states
+            .next_states
+            .iter()
+            .all(|state| state.tla.contains("/\\ a = ")));
+    }
+
+    #[test]
+    fn test_generate_config() {
+        let config = TlaConfigFile::from_string(
+            "CONSTANTS\n    MaxNumber = 6\n\nINIT\n    Init\nNEXT Next\nINVARIANT Inv\nVIEW View\n",
+        )
+        .unwrap();
+        // the sections set by the explorer aren't duplicated
+        assert_eq!(
+            generate_next_states_config(&config, true)
+                .unwrap()
+                .content(),
+            "CONSTANTS\n    MaxNumber = 6\nINIT ExplorerInit\nNEXT Next\n\
+             INVARIANT ExplorerNextStatesInvariant\nCONSTRAINT ExplorerDepthConstraint\nVIEW View\n"
+        );
+        assert_eq!(
+            generate_explorer_config(&config).unwrap().content(),
+            "CONSTANTS\n    MaxNumber = 6\nINIT Init\nNEXT Next\n\
+             CONSTRAINT ExplorerDepthConstraint\nVIEW View\n"
+        );
+
+        let config = TlaConfigFile::from_string("SPECIFICATION Spec").unwrap();
+        assert!(generate_next_states_config(&config, false).is_ok());
+        assert!(matches!(
+            generate_next_states_config(&config, true),
+            Err(Error::InvalidTlaConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_config_keeps_symmetry() {
+        let config = TlaConfigFile::builder()
+            .constant("Procs", "{p1, p2}")
+            .init("Init")
+            .next("Next")
+            .symmetry("Perms")
+            .build();
+        for rewritten in [
+            generate_explorer_config(&config),
+            generate_next_states_config(&config, false),
+            generate_next_states_config(&config, true),
+        ] {
+            let parts = rewritten.unwrap().parts().unwrap();
+            assert_eq!(parts.symmetry.as_deref(), Some("Perms"));
+            assert_eq!(parts.constants, config.parts().unwrap().constants);
+        }
+    }
+
+    #[test]
+    fn test_read_state_graph_unexpected_output() {
+        let tdir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            read_state_graph(&tdir.path().join(STATE_GRAPH_FILE), "Starting...".to_owned()),
+            Err(Error::UnexpectedModelCheckerOutput { stdout, .. }) if stdout == "Starting..."
+        ));
+    }
+
+    #[test]
+    fn test_parse_state_graph() {
+        let dot = r#"strict digraph DiskGraph {
+nodesep=0.35
+subgraph cluster_graph {
+color="white"
+-1463423386585337216 [label="/\\ a = 0\n/\\ b = 0\n/\\ action = \"None\"",style = filled]
+-1463423386585337216 -> 7164328839581024339 [label="IncreaseB",color="3",fontcolor="3"];
+7164328839581024339 [label="/\\ a = 0\n/\\ b = 2\n/\\ action = \"IncreaseB\""];
+-1463423386585337216 -> -3219045412052939003 [label="IncreaseA",color="2",fontcolor="2"];
+-3219045412052939003 [label="/\\ a = 1\n/\\ b = 0\n/\\ action = \"IncreaseA\""];
+-3219045412052939003 -> -1463423386585337216 [label="Reset",color="4",fontcolor="4"];
+}
+subgraph cluster_legend {graph[style=bold];label = "Next State Actions" style="solid"
+node [ labeljust="l",colorscheme="dark26",style=filled,shape=record ]
+IncreaseB [label="IncreaseB",fillcolor=3]
+IncreaseA [label="IncreaseA",fillcolor=2]
+}}
+"#;
+        let state =
+            |a: u64, b: u64, action: &str| serde_json::json!({ "a": a, "b": b, "action": action });
+
+        // the successors are numbered in the order of their TLA+ formulas
+        let graph = parse_state_graph(dot, 10).unwrap();
+        assert!(!graph.truncated());
+        let json = graph.to_json();
+        let states = json["states"].as_array().unwrap();
+        assert_eq!(states.len(), 3);
+        assert_eq!(states[0]["state"], state(0, 0, "None"));
+        assert_eq!(states[0]["initial"], true);
+        assert_eq!(states[0]["successors"], serde_json::json!([1, 2]));
+        assert_eq!(states[1]["state"], state(0, 2, "IncreaseB"));
+        assert_eq!(states[1]["initial"], false);
+        assert_eq!(states[1]["successors"], serde_json::json!([]));
+        assert_eq!(states[2]["state"], state(1, 0, "IncreaseA"));
+        assert_eq!(states[2]["successors"], serde_json::json!([0]));
+
+        // the steps to the states dropped are dropped too
+        let graph = parse_state_graph(dot, 2).unwrap();
+        assert!(graph.truncated());
+        assert_eq!(graph.len(), 2);
+        assert_eq!(
+            graph.to_json()["states"][0]["successors"],
+            serde_json::json!([1])
+        );
+    }
+}
@@ -1,12 +1,21 @@
 /// Parsing of TLC's output.
 mod output;
 
+/// Exploration of the state space of a spec.
+mod explorer;
+
+// Re-exports.
+pub(crate) use explorer::parse_state_graph;
+pub use explorer::{RandomWalk, TlaAndJsonState, TlaNextStates};
+pub use output::TlcErrorKind;
+
 use crate::artifact::{
-    tla_file, try_write_to_dir, Artifact, ArtifactCreator, ModelCheckerStdout, TlaConfigFile,
-    TlaFile, TlaFileSuite, TlaTrace,
+    tla_file, Artifact, ArtifactCreator, ModelCheckerStdout, TlaConfigFile, TlaFile, TlaFileSuite,
+    TlaTrace,
 };
 use crate::cache::TlaTraceCache;
-use crate::{jar, model::checker::ModelCheckerWorkers, Error, ModelatorRuntime};
+use crate::model::checker::{self, ModelCheckerWorkers};
+use crate::{jar, Error, ModelatorRuntime};
 use std::path::Path;
 use std::process::Command;
 
@@ -14,6 +23,18 @@ use std::process::Command;
 #[derive(Debug, Clone, Copy)]
 pub struct Tlc;
 
+/// Coverage of an action, as reported by TLC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    /// Name of the action, e.g. `Next` for an unnamed disjunct of `Next`.
+    pub action: String,
+    /// Location of the action, e.g. `line 26, col 1 to line 26, col 9 of module Numbers`.
+    pub location: String,
+    /// Number of states generated by the action. An action that never fired
+    /// has a count of 0.
+    pub count: u64,
+}
+
 impl Tlc {
     /// Generate a TLA+ trace given a [`TlaFile`] and a [`TlaConfigFile`] produced
     /// by [`crate::model::language::Tla::generate_tests`].
@@ -21,7 +42,7 @@ impl Tlc {
     /// # Examples
     /// ```ignore
     /// use modelator::artifact::TlaFileSuite;
-    /// use modelator::model::{language::Tla, checker::Tlc};
+    /// use modelator::model::{language::{Tla, TestDiscovery}, checker::Tlc};
     /// use modelator::ModelatorRuntime;
     /// use std::convert::TryFrom;
     ///
@@ -29,7 +50,7 @@ impl Tlc {
     /// let tla_config_file = "tests/integration/resource/Numbers.cfg";
     /// let tla_suite = TlaFileSuite::from_tla_and_config_paths(tla_tests_file, tla_config_file).unwrap();
     ///
-    /// let mut tests = Tla::generate_tests(&tla_suite).unwrap();
+    /// let mut tests = Tla::generate_tests(&tla_suite, &TestDiscovery::default()).unwrap();
     /// let test_tla_suite = tests.pop().unwrap();
     /// let runtime = ModelatorRuntime::default();
     /// let (tla_trace, _) = Tlc::test(&test_tla_suite, &runtime).unwrap();
@@ -38,14 +59,30 @@ impl Tlc {
     pub fn test(
         tla_file_suite: &TlaFileSuite,
         runtime: &ModelatorRuntime,
+    ) -> Result<(Vec<TlaTrace>, ModelCheckerStdout), Error> {
+        Self::test_with_dependencies_dir(tla_file_suite, runtime, None)
+    }
+
+    /// Same as [`Tlc::test`], but the dependencies of the [`TlaFileSuite`]
+    /// may have already been written to `dependencies_dir`.
+    pub(crate) fn test_with_dependencies_dir(
+        tla_file_suite: &TlaFileSuite,
+        runtime: &ModelatorRuntime,
+        dependencies_dir: Option<&Path>,
     ) -> Result<(Vec<TlaTrace>, ModelCheckerStdout), Error> {
         let tla_file = &tla_file_suite.tla_file;
         let tla_config_file = &tla_file_suite.tla_config_file;
         tracing::debug!("Tlc::test {} {} {:?}", tla_file, tla_config_file, runtime);
+        let runtime = &*checker::run_runtime(runtime, tla_file.module_name());
 
         let tdir = tempfile::tempdir()?;
 
-        try_write_to_dir(&tdir, tla_file_suite)?;
+        checker::write_suite(
+            tdir.path(),
+            tla_file_suite,
+            dependencies_dir,
+            &runtime.model_checker_runtime,
+        )?;
 
         // create tlc command
         let mut cmd = test_cmd(
@@ -53,8 +90,13 @@ impl Tlc {
             tla_file.file_name(),
             tla_config_file.filename(),
             runtime,
+            dependencies_dir,
         );
 
+        if runtime.dry_run {
+            return Err(checker::dry_run(&cmd, tdir));
+        }
+
         // start tlc
         let output = cmd.output()?;
 
@@ -65,39 +107,64 @@ impl Tlc {
         tracing::debug!("TLC stderr:\n{}", stderr);
 
         match (stdout.is_empty(), stderr.is_empty()) {
-            (true, true) => {
-                panic!("[modelator] stdout and stderr from TLC both empty")
-            }
+            (true, true) => Err(Error::UnexpectedModelCheckerOutput { stdout, stderr }),
             (false, true) => {
-                let tlc_log = ModelCheckerStdout::from_string(&stdout)?;
+                let mut tlc_log = runtime.model_checker_runtime.log.log(&stdout)?;
+                if runtime.model_checker_runtime.coverage.is_some() {
+                    tlc_log.coverage = output::parse_coverage(&stdout);
+                }
+                tlc_log.stats = output::parse_stats(&stdout);
 
-                let mut traces = output::parse_traces(&stdout, &runtime.model_checker_runtime)?;
+                let mut traces = parse_test_traces(&stdout, &runtime.model_checker_runtime)?;
 
                 traces.truncate(runtime.model_checker_runtime.traces_per_test);
 
                 // check if no trace was found
                 if traces.is_empty() {
-                    return Err(Error::NoTestTraceFound(
-                        //TODO: this will have to be changed to reflect new in-memory log
-                        runtime.model_checker_runtime.log.clone(),
-                    ));
+                    return Err(Error::NoTestTraceFound(checker::no_trace_path(
+                        tla_file_suite,
+                        &runtime.model_checker_runtime,
+                    )));
                 }
 
                 Ok((traces, tlc_log))
             }
             _ => {
                 // stderr not empty
-                Err(Error::TLCFailure(stderr))
+                Err(Error::TLCFailure {
+                    message: "TLC wrote to stderr".to_owned(),
+                    kind: Box::new(TlcErrorKind::Other { code: None }),
+                    stderr,
+                })
             }
         }
     }
 }
 
+/// Parses the traces of the violations reported in the output of TLC, which
+/// reports either errors, violations, or that model checking completed
+/// without error. Any other output, e.g. of a TLC killed while running, is
+/// unexpected.
+fn parse_test_traces(
+    stdout: &str,
+    runtime: &checker::ModelCheckerRuntime,
+) -> Result<Vec<TlaTrace>, Error> {
+    let traces = output::parse_traces(stdout, runtime)?;
+    if traces.is_empty() && !output::completed_without_error(stdout) {
+        return Err(Error::UnexpectedModelCheckerOutput {
+            stdout: stdout.to_owned(),
+            stderr: String::new(),
+        });
+    }
+    Ok(traces)
+}
+
 fn test_cmd<P: AsRef<Path>>(
     temp_dir: &tempfile::TempDir,
     tla_file: P,
     tla_config_file_path: P,
     runtime: &ModelatorRuntime,
+    dependencies_dir: Option<&Path>,
 ) -> Command {
     let tla2tools = jar::Jar::Tla.path(&runtime.dir);
     let community_modules = jar::Jar::CommunityModules.path(&runtime.dir);
@@ -111,6 +178,7 @@ fn test_cmd<P: AsRef<Path>>(
 
     let mut cmd = Command::new("java");
     cmd.current_dir(temp_dir)
+        .args(&runtime.model_checker_runtime.jvm_args)
         // set classpath
         .arg("-cp")
         .arg(
@@ -124,6 +192,11 @@ fn test_cmd<P: AsRef<Path>>(
             "-Djava.io.tmpdir={}",
             temp_dir.path().to_string_lossy()
         ))
+        // set the directories where to look for the tla files
+        .arg(format!(
+            "-DTLA-Library={}",
+            checker::tla_library(temp_dir.path(), dependencies_dir).to_string_lossy()
+        ))
         // set tla file
         .arg("tlc2.TLC")
         .arg(tla_file.as_ref())
@@ -136,6 +209,11 @@ fn test_cmd<P: AsRef<Path>>(
         .arg("-workers")
         .arg(workers(runtime));
 
+    if let Some(coverage) = runtime.model_checker_runtime.coverage {
+        // report action coverage every `coverage` minutes
+        cmd.arg("-coverage").arg(coverage.to_string());
+    }
+
     if 1 < runtime.model_checker_runtime.traces_per_test {
         // Allow TLC to continue model checking after violating the test invariant
         // NOTE: currently, limiting the number of tests generated or halting when exploring
@@ -147,6 +225,8 @@ TLC not terminating if the state space is unbounded."#
         );
     }
 
+    cmd.args(&runtime.model_checker_runtime.extra_checker_args);
+
     // show command being run
     tracing::debug!("{}", crate::util::cmd_show(&cmd));
     cmd
@@ -158,3 +238,242 @@ fn workers(runtime: &ModelatorRuntime) -> String {
         ModelCheckerWorkers::Count(count) => count.to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::Artifact;
+    use crate::model::checker::{LogSink, ModelChecker, ModelCheckerRuntime};
+    use crate::model::language::{TestDiscovery, Tla};
+
+    #[test]
+    fn test_extra_checker_args() {
+        let tdir = tempfile::tempdir().unwrap();
+        let runtime = ModelatorRuntime::default().model_checker_runtime(
+            ModelCheckerRuntime::default()
+                .extra_checker_args(vec!["-seed".to_owned(), "42".to_owned()]),
+        );
+        let cmd = test_cmd(&tdir, "A.tla", "A.cfg", &runtime, None);
+        assert!(crate::util::cmd_show(&cmd).ends_with("-workers auto -seed 42"));
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/Numbers.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let runtime = ModelatorRuntime::default().dry_run(true);
+        match Tlc::test(&tla_file_suite, &runtime) {
+            Err(Error::DryRun(command)) => {
+                assert!(command.contains("tlc2.TLC Numbers.tla -config Numbers.cfg"));
+                // the files are kept in the directory of the command
+                let dir = command
+                    .strip_prefix("cd ")
+                    .and_then(|command| command.split(" && ").next())
+                    .unwrap();
+                assert!(Path::new(dir).join("Numbers.tla").is_file());
+                std::fs::remove_dir_all(dir).unwrap();
+            }
+            result => panic!(
+                "expected a dry run, got {:?}",
+                result.map(|(traces, _)| traces)
+            ),
+        }
+    }
+
+    #[test]
+    fn test_multiple_traces() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/MultipleViolations.tla",
+            "tests/integration/resource/MultipleViolations.cfg",
+        )
+        .unwrap();
+        let test = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default())
+            .unwrap()
+            .pop()
+            .unwrap();
+
+        let runtime = |traces_per_test| {
+            ModelatorRuntime::default().model_checker_runtime(
+                ModelCheckerRuntime::default()
+                    .model_checker(ModelChecker::Tlc)
+                    .traces_per_test(traces_per_test),
+            )
+        };
+        runtime(1).setup().unwrap();
+
+        let (traces, _) = Tlc::test(&test.file_suite, &runtime(1)).unwrap();
+        assert_eq!(traces.len(), 1);
+
+        // TLC reports both violations, and no more than asked for are returned
+        let (traces, _) = Tlc::test(&test.file_suite, &runtime(3)).unwrap();
+        assert_eq!(traces.len(), 2);
+    }
+
+    #[test]
+    fn test_log_sink() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/NumbersAMaxBMinTest.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let test = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default())
+            .unwrap()
+            .pop()
+            .unwrap();
+
+        let runtime = |log| {
+            ModelatorRuntime::default().model_checker_runtime(
+                ModelCheckerRuntime::default()
+                    .model_checker(ModelChecker::Tlc)
+                    .log_sink(log),
+            )
+        };
+        let tdir = tempfile::tempdir().unwrap();
+        let log_path = tdir.path().join("mc.log");
+        runtime(LogSink::Memory).setup().unwrap();
+
+        let (_, stdout) =
+            Tlc::test(&test.file_suite, &runtime(LogSink::File(log_path.clone()))).unwrap();
+        assert!(!stdout.as_string().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(&log_path).unwrap(),
+            stdout.as_string()
+        );
+        assert_eq!(stdout.log_path(), Some(log_path.as_path()));
+
+        // each run logging to a directory has its own log file
+        let log_dir = tdir.path().join("logs");
+        let (_, stdout) =
+            Tlc::test(&test.file_suite, &runtime(LogSink::Dir(log_dir.clone()))).unwrap();
+        let run_log_path = stdout.log_path().unwrap();
+        assert_eq!(run_log_path.parent(), Some(log_dir.as_path()));
+        assert!(run_log_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with(&format!("mc-{}-", test.file_suite.tla_file.module_name())));
+        assert_eq!(
+            std::fs::read_to_string(run_log_path).unwrap(),
+            stdout.as_string()
+        );
+
+        let (_, stdout) = Tlc::test(&test.file_suite, &runtime(LogSink::Memory)).unwrap();
+        assert!(stdout.as_string().contains("TLC"));
+
+        let (_, stdout) = Tlc::test(&test.file_suite, &runtime(LogSink::None)).unwrap();
+        assert!(stdout.as_string().is_empty());
+    }
+
+    #[test]
+    fn test_coverage() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/NumbersAMaxBMinTest.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let test = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default())
+            .unwrap()
+            .pop()
+            .unwrap();
+
+        let runtime = |coverage| {
+            ModelatorRuntime::default().model_checker_runtime(
+                ModelCheckerRuntime::default()
+                    .model_checker(ModelChecker::Tlc)
+                    .coverage(coverage),
+            )
+        };
+        runtime(None).setup().unwrap();
+
+        let (_, stdout) = Tlc::test(&test.file_suite, &runtime(None)).unwrap();
+        assert!(stdout.coverage().is_empty());
+
+        let (_, stdout) = Tlc::test(&test.file_suite, &runtime(Some(1))).unwrap();
+        let actions: Vec<_> = stdout
+            .coverage()
+            .iter()
+            .map(|coverage| coverage.action.as_str())
+            .collect();
+        assert_eq!(actions, vec!["Init", "IncreaseA", "IncreaseB", "Next"]);
+        assert!(stdout.coverage().iter().all(|coverage| coverage.count > 0));
+    }
+
+    #[test]
+    fn test_parallel_tests() {
+        checker::tests::check_parallel_tests(ModelChecker::Tlc);
+    }
+
+    #[test]
+    fn test_initial_state_violation() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/NumbersInitialStateTest.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let test = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default())
+            .unwrap()
+            .pop()
+            .unwrap();
+        let runtime = ModelatorRuntime::default().model_checker_runtime(
+            ModelCheckerRuntime::default()
+                .model_checker(ModelChecker::Tlc)
+                .log_sink(LogSink::Memory),
+        );
+        runtime.setup().unwrap();
+
+        // TLC stops before exploring any transition, so it may not write states
+        let (traces, _) = Tlc::test(&test.file_suite, &runtime).unwrap();
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].states.len(), 1);
+        assert!(!Path::new("states").exists());
+        assert!(!Path::new("tests/integration/resource/states").exists());
+    }
+
+    #[test]
+    fn test_failure_before_output() {
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/NumbersAMaxBMinTest.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let test = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default())
+            .unwrap()
+            .pop()
+            .unwrap();
+        // the JVM exits before TLC writes anything
+        let runtime = ModelatorRuntime::default().model_checker_runtime(
+            ModelCheckerRuntime::default()
+                .model_checker(ModelChecker::Tlc)
+                .log_sink(LogSink::Memory)
+                .jvm_args(vec!["-XX:+NoSuchModelatorOption".to_owned()]),
+        );
+        runtime.setup().unwrap();
+
+        match Tlc::test(&test.file_suite, &runtime) {
+            Err(Error::TLCFailure { stderr, .. }) => {
+                assert!(stderr.contains("NoSuchModelatorOption"), "{}", stderr)
+            }
+            Err(e) => panic!("expected a TLC failure, got {:?}", e),
+            Ok(_) => panic!("expected a TLC failure"),
+        }
+        assert!(!Path::new("states").exists());
+    }
+
+    #[test]
+    fn test_parse_test_traces_unexpected_output() {
+        let runtime = ModelCheckerRuntime::default();
+        let stdout =
+            std::fs::read_to_string("tests/integration/resource/tlc_output/holds.stdout").unwrap();
+        assert!(parse_test_traces(&stdout, &runtime).unwrap().is_empty());
+
+        // TLC killed before reporting whether the test holds
+        let truncated = stdout.lines().take(30).collect::<Vec<_>>().join("\n");
+        assert!(matches!(
+            parse_test_traces(&truncated, &runtime),
+            Err(Error::UnexpectedModelCheckerOutput { stdout, .. }) if stdout == truncated
+        ));
+    }
+}
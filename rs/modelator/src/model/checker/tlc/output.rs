@@ -1,15 +1,211 @@
 use crate::artifact::tla_trace::{TlaState, TlaTrace};
-use crate::model::checker::ModelCheckerRuntime;
+use crate::model::checker::{CheckerStats, Coverage, ModelCheckerRuntime};
 use crate::Error;
 
-use std::collections::HashMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// Message Codes ref
+// https://github.com/tlaplus/tlaplus/blob/master/tlatools/org.lamport.tlatools/src/tlc2/output/EC.java
+const TLC_NESTED_EXPRESSION: usize = 2103;
+const TLC_INVARIANT_VIOLATED_INITIAL: usize = 2107;
+const TLC_PROPERTY_VIOLATED_INITIAL: usize = 2108;
+const TLC_INVARIANT_VIOLATED_BEHAVIOR: usize = 2110;
+const TLC_ACTION_PROPERTY_VIOLATED_BEHAVIOR: usize = 2112;
+const TLC_DEADLOCK_REACHED: usize = 2114;
+const TLC_STATES_AND_NO_NEXT_ACTION: usize = 2115;
+const TLC_TEMPORAL_PROPERTY_VIOLATED: usize = 2116;
+const TLC_BEHAVIOR_UP_TO_THIS_POINT: usize = 2121;
+const TLC_FINGERPRINT_EXCEPTION: usize = 2147;
+const TLC_SUCCESS: usize = 2193;
+const TLC_STATE_PRINT2: usize = 2217;
+const TLC_CONFIG_SPECIFIED_NOT_DEFINED: usize = 2229;
+const TLC_CONFIG_MISSING_INIT: usize = 2231;
+const TLC_CONFIG_MISSING_NEXT: usize = 2232;
+const TLC_COUNTER_EXAMPLE: usize = 2264;
+const TLC_PARSING_FAILED: usize = 3002;
+
+/// Codes of the errors reporting a violation, which come with a trace.
+const VIOLATION_CODES: [usize; 8] = [
+    TLC_INVARIANT_VIOLATED_INITIAL,
+    TLC_PROPERTY_VIOLATED_INITIAL,
+    TLC_INVARIANT_VIOLATED_BEHAVIOR,
+    TLC_ACTION_PROPERTY_VIOLATED_BEHAVIOR,
+    TLC_DEADLOCK_REACHED,
+    TLC_TEMPORAL_PROPERTY_VIOLATED,
+    TLC_BEHAVIOR_UP_TO_THIS_POINT,
+    TLC_COUNTER_EXAMPLE,
+];
+
+/// The kind of a [`Error::TLCFailure`], classified from the codes of the
+/// messages of TLC, so that the common failures can be told apart.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TlcErrorKind {
+    /// The TLA+ config file doesn't set the initial predicate.
+    MissingInit,
+    /// The TLA+ config file doesn't set the next-state relation, or it
+    /// defines no action.
+    MissingNext,
+    /// An operator used by the spec or by the TLA+ config file isn't defined.
+    UnknownOperator {
+        /// Name of the operator.
+        operator: String,
+        /// Module using the operator, unless it's used by the TLA+ config file.
+        module: Option<String>,
+        /// Line of the module using the operator.
+        line: Option<usize>,
+    },
+    /// TLC failed to evaluate an expression, e.g. a division by zero.
+    EvaluationError {
+        /// Message of TLC.
+        message: String,
+        /// Module of the expression.
+        module: Option<String>,
+        /// Line of the expression.
+        line: Option<usize>,
+    },
+    /// Two distinct states have the same fingerprint.
+    FingerprintCollision,
+    /// A safety property is violated, but TLC's output has no trace of the
+    /// violation, e.g. because TLC stopped before printing it. Initial-state
+    /// violations come with their state, as one-state traces.
+    SafetyViolationWithoutTrace {
+        /// Name of the property, if reported.
+        property: Option<String>,
+    },
+    /// An error that isn't classified.
+    Other {
+        /// Code of the first error reported by TLC, if any.
+        code: Option<usize>,
+    },
+}
+
+impl TlcErrorKind {
+    /// Returns a short hint on how to fix the error, if there's one.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::MissingInit => Some("set the initial predicate with INIT in the TLA+ config file"),
+            Self::MissingNext => Some("set the next-state relation with NEXT in the TLA+ config file"),
+            Self::UnknownOperator { .. } => {
+                Some("define the operator, or fix its name in the spec or the TLA+ config file")
+            }
+            Self::EvaluationError { .. } => Some(
+                "check the expression, e.g. for a division by zero or a function applied outside of its domain",
+            ),
+            Self::FingerprintCollision => Some("run TLC again with another fingerprint seed (-fp)"),
+            Self::SafetyViolationWithoutTrace { .. } => {
+                Some("look for the violating states in the TLC log, which TLC may have stopped before printing")
+            }
+            Self::Other { .. } => None,
+        }
+    }
+
+    /// Classifies the errors reported by TLC, given the messages of each
+    /// class and code.
+    fn classify(parsed_output: &BTreeMap<u8, BTreeMap<usize, Vec<String>>>) -> Self {
+        lazy_static! {
+            static ref CONFIG_OPERATOR_RE: Regex =
+                Regex::new(r"The .+ (\S+) specified in the configuration file").unwrap();
+            static ref SANY_OPERATOR_RE: Regex = Regex::new(
+                r"line (\d+), col \d+ to line \d+, col \d+ of module (\S+)\s+Unknown operator: `([^']+)'"
+            )
+            .unwrap();
+            static ref POSITION_RE: Regex =
+                Regex::new(r"Line (\d+), column \d+ to line \d+, column \d+ in (\S+)").unwrap();
+            static ref PROPERTY_RE: Regex =
+                Regex::new(r"(?:Invariant|property) (\S+) is violated").unwrap();
+        }
+        let messages = |class: u8, code: usize| {
+            parsed_output
+                .get(&class)
+                .and_then(|messages| messages.get(&code))
+                .map(|messages| messages.join("\n"))
+        };
+        let errors = match parsed_output.get(&1) {
+            Some(errors) => errors,
+            None => return Self::Other { code: None },
+        };
+        if errors.contains_key(&TLC_CONFIG_MISSING_INIT) {
+            Self::MissingInit
+        } else if errors.contains_key(&TLC_CONFIG_MISSING_NEXT)
+            || errors.contains_key(&TLC_STATES_AND_NO_NEXT_ACTION)
+        {
+            Self::MissingNext
+        } else if let Some(captures) = messages(1, TLC_CONFIG_SPECIFIED_NOT_DEFINED)
+            .as_deref()
+            .and_then(|message| CONFIG_OPERATOR_RE.captures(message))
+        {
+            Self::UnknownOperator {
+                operator: captures[1].to_owned(),
+                module: None,
+                line: None,
+            }
+        } else if let Some(captures) = messages(1, TLC_PARSING_FAILED)
+            // the errors of SANY are output without annotation
+            .and(messages(0, 0))
+            .as_deref()
+            .and_then(|message| SANY_OPERATOR_RE.captures(message))
+        {
+            Self::UnknownOperator {
+                operator: captures[3].to_owned(),
+                module: Some(captures[2].to_owned()),
+                line: captures[1].parse().ok(),
+            }
+        } else if let Some(positions) = messages(1, TLC_NESTED_EXPRESSION) {
+            // the last position is the one of the innermost expression
+            let position = POSITION_RE.captures_iter(&positions).last();
+            let message = errors
+                .iter()
+                .filter(|(code, _)| {
+                    **code != TLC_NESTED_EXPRESSION && !VIOLATION_CODES.contains(code)
+                })
+                .flat_map(|(_, messages)| messages.iter().map(|message| message.trim()))
+                .collect::<Vec<_>>()
+                .join(" ");
+            Self::EvaluationError {
+                message,
+                module: position.as_ref().map(|captures| captures[2].to_owned()),
+                line: position.and_then(|captures| captures[1].parse().ok()),
+            }
+        } else if errors.contains_key(&TLC_FINGERPRINT_EXCEPTION) {
+            Self::FingerprintCollision
+        } else if errors.keys().any(|code| VIOLATION_CODES.contains(code)) {
+            let messages = errors
+                .values()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("\n");
+            Self::SafetyViolationWithoutTrace {
+                property: PROPERTY_RE
+                    .captures(&messages)
+                    .map(|captures| captures[1].to_owned()),
+            }
+        } else {
+            Self::Other {
+                code: errors.keys().next().copied(),
+            }
+        }
+    }
+}
+
+/// Returns true iff TLC's output reports that model checking completed
+/// without finding any error.
+pub(crate) fn completed_without_error(output: &str) -> bool {
+    let marker = format!("@!@!@STARTMSG {}:", TLC_SUCCESS);
+    output.lines().any(|line| line.starts_with(&marker))
+}
 
 /// Parses all tla traces from a .tla trace generated by tlc.
 pub(crate) fn parse_traces(
     output: &str,
     runtime: &ModelCheckerRuntime,
 ) -> Result<Vec<TlaTrace>, Error> {
-    let mut parsed_output: HashMap<u8, HashMap<usize, Vec<String>>> = HashMap::new();
+    let mut parsed_output: BTreeMap<u8, BTreeMap<usize, Vec<String>>> = BTreeMap::new();
 
     let mut curr_message_id = None;
     let mut curr_message = String::new();
@@ -23,9 +219,9 @@ pub(crate) fn parse_traces(
                 .or_default()
                 .entry(code)
                 .or_default()
-                .push(curr_message.drain(..).collect());
+                .push(std::mem::take(&mut curr_message));
 
-            let (code, class) = line.splitn(3, ' ').nth(1).unwrap().split_once(':').unwrap();
+            let (code, class) = line.split(' ').nth(1).unwrap().split_once(':').unwrap();
             let _ = curr_message_id.insert((code.parse().unwrap(), class.parse().unwrap()));
         } else if line.starts_with("@!@!@ENDMSG ") {
             let (code, class) = curr_message_id.take().unwrap_or((0, 0));
@@ -34,9 +230,9 @@ pub(crate) fn parse_traces(
                 .or_default()
                 .entry(code)
                 .or_default()
-                .push(curr_message.drain(..).collect());
+                .push(std::mem::take(&mut curr_message));
 
-            let c_code = line.splitn(3, ' ').nth(1).unwrap();
+            let c_code = line.split(' ').nth(1).unwrap();
             assert_eq!(code, c_code.parse::<usize>().unwrap());
         } else {
             curr_message.push_str(line);
@@ -44,7 +240,22 @@ pub(crate) fn parse_traces(
         }
     });
 
-    if let Some(lines) = parsed_output.get(&4).and_then(|x| x.get(&2217)) {
+    // errors other than violations, e.g. of evaluation, come with the partial
+    // trace reaching them, which isn't a counterexample
+    let failed = parsed_output.get(&1).map_or(false, |errors| {
+        errors.keys().any(|code| !VIOLATION_CODES.contains(code))
+    });
+    let traces = parsed_output
+        .get(&4)
+        .and_then(|x| x.get(&TLC_STATE_PRINT2))
+        .filter(|_| !failed);
+    // an invariant violated by an initial state is reported along with the
+    // state, without a trace
+    let initial_violations = parsed_output
+        .get(&1)
+        .and_then(|errors| errors.get(&TLC_INVARIANT_VIOLATED_INITIAL))
+        .filter(|_| !failed);
+    if let Some(lines) = traces {
         let mut traces = Vec::new();
         let mut trace = None;
         for line in lines {
@@ -64,9 +275,17 @@ pub(crate) fn parse_traces(
             traces.push(t);
         }
         Ok(traces)
+    } else if let Some(messages) = initial_violations {
+        Ok(messages
+            .iter()
+            .filter_map(|message| message.split_once('\n'))
+            .map(|(_, state)| {
+                let mut trace = TlaTrace::new();
+                trace.add(state.trim_end().into());
+                trace
+            })
+            .collect())
     } else if let Some(errors) = parsed_output.get(&1) {
-        // Message Codes ref
-        // https://github.com/tlaplus/tlaplus/blob/master/tlatools/org.lamport.tlatools/src/tlc2/output/EC.java
         // Message Classes ref
         // https://github.com/tlaplus/tlaplus/blob/master/tlatools/org.lamport.tlatools/src/tlc2/output/MP.java
         // NONE = 0; ERROR = 1; TLCBUG = 2; WARNING = 3; STATE = 4;
@@ -74,9 +293,13 @@ pub(crate) fn parse_traces(
         let message = errors
             .iter()
             .map(|(code, message)| {
+                let location = match runtime.log.path() {
+                    Some(path) => format!("{}:", path.to_string_lossy()),
+                    None => String::new(),
+                };
                 format!(
-                    "[{}:{}]: {}",
-                    runtime.log.to_string_lossy(),
+                    "[{}{}]: {}",
+                    location,
                     code,
                     &message
                         .iter()
@@ -87,8 +310,340 @@ pub(crate) fn parse_traces(
             })
             .collect::<Vec<_>>()
             .join("\n");
-        Err(Error::TLCFailure(message))
+        Err(Error::TLCFailure {
+            message,
+            kind: Box::new(TlcErrorKind::classify(&parsed_output)),
+            stderr: String::new(),
+        })
     } else {
         Ok(vec![])
     }
 }
+
+/// Parses the statistics reported by TLC at the end of a run, or its last
+/// progress report if the run was interrupted.
+pub(crate) fn parse_stats(output: &str) -> CheckerStats {
+    lazy_static! {
+        // TLC_STATS (2199)
+        static ref STATS_RE: Regex =
+            Regex::new(r"(?m)^([\d,]+) states generated, ([\d,]+) distinct states found").unwrap();
+        // TLC_PROGRESS_STATS (2200)
+        static ref PROGRESS_RE: Regex = Regex::new(
+            r"(?m)^Progress\((\d+)\) at .*?: ([\d,]+) states generated .*?, ([\d,]+) distinct states found"
+        )
+        .unwrap();
+        // TLC_SEARCH_DEPTH (2194)
+        static ref DEPTH_RE: Regex =
+            Regex::new(r"The depth of the complete state graph search is (\d+)\.").unwrap();
+        // TLC_FINISHED (2186), e.g. 'Finished in 1612ms at' or 'Finished in 01min 02s at'
+        static ref FINISHED_RE: Regex = Regex::new(r"(?m)^Finished in (.+?) at \(").unwrap();
+        static ref DURATION_RE: Regex = Regex::new(r"(\d+)(ms|h|min|s)\b").unwrap();
+    }
+    let number = |s: &str| s.replace(',', "").parse().ok();
+
+    let mut stats = CheckerStats::default();
+    if let Some(caps) = PROGRESS_RE.captures_iter(output).last() {
+        stats.depth = number(&caps[1]);
+        stats.states_generated = number(&caps[2]);
+        stats.distinct_states = number(&caps[3]);
+    }
+    if let Some(caps) = STATS_RE.captures_iter(output).last() {
+        stats.states_generated = number(&caps[1]);
+        stats.distinct_states = number(&caps[2]);
+    }
+    if let Some(caps) = DEPTH_RE.captures_iter(output).last() {
+        stats.depth = number(&caps[1]);
+    }
+    if let Some(caps) = FINISHED_RE.captures_iter(output).last() {
+        stats.duration = Some(DURATION_RE.captures_iter(&caps[1]).fold(
+            Duration::ZERO,
+            |duration, caps| {
+                let n: u64 = caps[1].parse().unwrap_or_default();
+                duration
+                    + match &caps[2] {
+                        "ms" => Duration::from_millis(n),
+                        "h" => Duration::from_secs(n * 60 * 60),
+                        "min" => Duration::from_secs(n * 60),
+                        _ => Duration::from_secs(n),
+                    }
+            },
+        ));
+    }
+    stats
+}
+
+/// Parses the action coverage of the last coverage statistics reported by TLC.
+pub(crate) fn parse_coverage(output: &str) -> Vec<Coverage> {
+    // Message Codes ref
+    // https://github.com/tlaplus/tlaplus/blob/master/tlatools/org.lamport.tlatools/src/tlc2/output/EC.java
+    const TLC_COVERAGE_START: &str = "2201";
+    const TLC_COVERAGE_NEXT: &str = "2772";
+    const TLC_COVERAGE_INIT: &str = "2773";
+
+    let mut coverage = Vec::new();
+    let mut curr_code = None;
+    for line in output.lines() {
+        if let Some(message_id) = line.strip_prefix("@!@!@STARTMSG ") {
+            let code = message_id.split(':').next().unwrap_or_default();
+            if code == TLC_COVERAGE_START {
+                // a new report supersedes the previous one
+                coverage.clear();
+            }
+            curr_code = Some(code.to_owned());
+        } else if line.starts_with("@!@!@ENDMSG ") {
+            curr_code = None;
+        } else if matches!(
+            curr_code.as_deref(),
+            Some(TLC_COVERAGE_NEXT | TLC_COVERAGE_INIT)
+        ) {
+            // <action location>: distinct states:states generated
+            if let Some(action) = parse_action_coverage(line) {
+                coverage.push(action);
+            }
+        }
+    }
+    coverage
+}
+
+fn parse_action_coverage(line: &str) -> Option<Coverage> {
+    let (action, counts) = line.strip_prefix('<')?.rsplit_once(">: ")?;
+    let (action, location) = action.split_once(' ')?;
+    let count = counts.split(':').nth(1)?.trim().parse().ok()?;
+    Some(Coverage {
+        action: action.to_owned(),
+        location: location.to_owned(),
+        count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the kind of the error of TLC in one of the captured outputs of
+    /// `tests/integration/resource/tlc_output`.
+    fn error_kind(name: &str) -> TlcErrorKind {
+        let path = format!("tests/integration/resource/tlc_output/{}.stdout", name);
+        let output = std::fs::read_to_string(path).unwrap();
+        match parse_traces(&output, &ModelCheckerRuntime::default()) {
+            Err(error @ Error::TLCFailure { .. }) => {
+                let hint = error.to_string().contains("\nhint: ");
+                match error {
+                    Error::TLCFailure { kind, .. } => {
+                        assert_eq!(hint, kind.hint().is_some());
+                        *kind
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            result => panic!("expected a TLC failure, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(error_kind("missing_init"), TlcErrorKind::MissingInit);
+        assert_eq!(error_kind("missing_next"), TlcErrorKind::MissingNext);
+        assert_eq!(
+            error_kind("undefined_config_operator"),
+            TlcErrorKind::UnknownOperator {
+                operator: "Foo".to_owned(),
+                module: None,
+                line: None,
+            }
+        );
+        assert_eq!(
+            error_kind("unknown_operator"),
+            TlcErrorKind::UnknownOperator {
+                operator: "Foo".to_owned(),
+                module: Some("U".to_owned()),
+                line: Some(5),
+            }
+        );
+        // the partial trace reaching the error isn't a counterexample
+        assert_eq!(
+            error_kind("evaluation_error"),
+            TlcErrorKind::EvaluationError {
+                message: "The second argument of \\div is 0.".to_owned(),
+                module: Some("M".to_owned()),
+                line: Some(6),
+            }
+        );
+    }
+
+    #[test]
+    fn test_initial_state_violation() {
+        let path = "tests/integration/resource/tlc_output/initial_state_violation.stdout";
+        let output = std::fs::read_to_string(path).unwrap();
+        // the violating initial state is the whole trace
+        let traces = parse_traces(&output, &ModelCheckerRuntime::default()).unwrap();
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].states, vec!["x = 0".to_owned()]);
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let stats = |name| {
+            let path = format!("tests/integration/resource/tlc_output/{}.stdout", name);
+            parse_stats(&std::fs::read_to_string(path).unwrap())
+        };
+        assert_eq!(
+            stats("violation"),
+            CheckerStats {
+                states_generated: Some(90),
+                distinct_states: Some(43),
+                depth: Some(7),
+                duration: Some(Duration::from_millis(1612)),
+            }
+        );
+        assert_eq!(
+            stats("holds"),
+            CheckerStats {
+                states_generated: Some(231),
+                distinct_states: Some(84),
+                depth: Some(11),
+                duration: Some(Duration::from_millis(1184)),
+            }
+        );
+        // the statistics of an error are partial
+        assert_eq!(
+            stats("missing_init"),
+            CheckerStats {
+                duration: Some(Duration::from_millis(2170)),
+                ..CheckerStats::default()
+            }
+        );
+
+        // an interrupted run only has its progress reports
+        let output = r#"@!@!@STARTMSG 2200:0 @!@!@
+Progress(12) at 2021-11-30 10:00:00: 1,234,567 states generated (1,000 s/min), 2,345 distinct states found (100 ds/min), 3 states left on queue.
+@!@!@ENDMSG 2200 @!@!@
+@!@!@STARTMSG 2186:0 @!@!@
+Finished in 01h 02min 03s at (2021-11-30 10:00:00)
+@!@!@ENDMSG 2186 @!@!@
+"#;
+        assert_eq!(
+            parse_stats(output),
+            CheckerStats {
+                states_generated: Some(1_234_567),
+                distinct_states: Some(2_345),
+                depth: Some(12),
+                duration: Some(Duration::from_secs(3723)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_coverage() {
+        let output = r#"@!@!@STARTMSG 2201:0 @!@!@
+The coverage statistics at 2021-11-30 10:00:00
+@!@!@ENDMSG 2201 @!@!@
+@!@!@STARTMSG 2773:0 @!@!@
+<Init line 20, col 1 to line 20, col 4 of module Numbers>: 1:1
+@!@!@ENDMSG 2773 @!@!@
+@!@!@STARTMSG 2202:0 @!@!@
+End of statistics.
+@!@!@ENDMSG 2202 @!@!@
+@!@!@STARTMSG 2201:0 @!@!@
+The coverage statistics at 2021-11-30 10:01:00
+@!@!@ENDMSG 2201 @!@!@
+@!@!@STARTMSG 2773:0 @!@!@
+<Init line 20, col 1 to line 20, col 4 of module Numbers>: 1:1
+@!@!@ENDMSG 2773 @!@!@
+@!@!@STARTMSG 2221:0 @!@!@
+  line 21, col 5 to line 24, col 27 of module Numbers: 1
+@!@!@ENDMSG 2221 @!@!@
+@!@!@STARTMSG 2772:0 @!@!@
+<IncreaseA line 26, col 1 to line 26, col 9 of module Numbers>: 28:76
+@!@!@ENDMSG 2772 @!@!@
+@!@!@STARTMSG 2772:0 @!@!@
+<Next line 48, col 1 to line 48, col 4 of module Numbers (51 8 53 28)>: 0:0
+@!@!@ENDMSG 2772 @!@!@
+@!@!@STARTMSG 2202:0 @!@!@
+End of statistics.
+@!@!@ENDMSG 2202 @!@!@
+"#;
+        let coverage = |action: &str, location: &str, count| Coverage {
+            action: action.to_owned(),
+            location: location.to_owned(),
+            count,
+        };
+        assert_eq!(
+            parse_coverage(output),
+            vec![
+                coverage(
+                    "Init",
+                    "line 20, col 1 to line 20, col 4 of module Numbers",
+                    1
+                ),
+                coverage(
+                    "IncreaseA",
+                    "line 26, col 1 to line 26, col 9 of module Numbers",
+                    76
+                ),
+                coverage(
+                    "Next",
+                    "line 48, col 1 to line 48, col 4 of module Numbers (51 8 53 28)",
+                    0
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_multiple_traces() {
+        // Output of TLC with `-continue`, reporting two violations
+        let output = r#"@!@!@STARTMSG 2110:1 @!@!@
+Invariant Inv is violated.
+@!@!@ENDMSG 2110 @!@!@
+@!@!@STARTMSG 2121:1 @!@!@
+The behavior up to this point is:
+@!@!@ENDMSG 2121 @!@!@
+@!@!@STARTMSG 2217:4 @!@!@
+1: <Initial predicate>
+x = 0
+
+@!@!@ENDMSG 2217 @!@!@
+@!@!@STARTMSG 2217:4 @!@!@
+2: <Next line 5, col 9 to line 5, col 60 of module MultipleViolations>
+x = 2
+
+@!@!@ENDMSG 2217 @!@!@
+@!@!@STARTMSG 2110:1 @!@!@
+Invariant Inv is violated.
+@!@!@ENDMSG 2110 @!@!@
+@!@!@STARTMSG 2121:1 @!@!@
+The behavior up to this point is:
+@!@!@ENDMSG 2121 @!@!@
+@!@!@STARTMSG 2217:4 @!@!@
+1: <Initial predicate>
+x = 0
+
+@!@!@ENDMSG 2217 @!@!@
+@!@!@STARTMSG 2217:4 @!@!@
+2: <Next line 5, col 9 to line 5, col 60 of module MultipleViolations>
+x = 1
+
+@!@!@ENDMSG 2217 @!@!@
+@!@!@STARTMSG 2217:4 @!@!@
+3: <Next line 5, col 9 to line 5, col 60 of module MultipleViolations>
+x = 3
+
+@!@!@ENDMSG 2217 @!@!@
+"#;
+        let traces = parse_traces(output, &ModelCheckerRuntime::default()).unwrap();
+        let traces: Vec<Vec<String>> = traces
+            .into_iter()
+            .map(|trace| {
+                trace
+                    .into_iter()
+                    .map(|state| state.trim().to_owned())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            traces,
+            vec![vec!["x = 0", "x = 2"], vec!["x = 0", "x = 1", "x = 3"]]
+        );
+    }
+}
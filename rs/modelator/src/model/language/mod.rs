@@ -2,4 +2,6 @@
 mod tla;
 
 /// Re-exports.
-pub use tla::Tla;
+pub use tla::{DiscoveredTest, TestDiscovery, TestDiscoveryRule, TestExpectation, Tla};
+
+pub(crate) use tla::{defines_operator, TlaTest};
@@ -15,3 +15,100 @@ pub(crate) fn state_to_json(state: &str) -> Result<JsonValue, Error> {
         })
         .map_err(Into::into)
 }
+
+/// Converts a state encoded as a Json object (as returned by [`state_to_json`])
+/// back into a TLA+ state, i.e. a conjunction of variable assignments.
+///
+/// Since sets and sequences are both encoded as Json arrays, arrays are
+/// converted into sequences. Json objects are converted into records, or
+/// into functions if all their keys are integers.
+pub(crate) fn json_to_state(state: &JsonValue) -> Result<String, String> {
+    let vars = state
+        .as_object()
+        .ok_or_else(|| format!("state is not a record: {}", state))?;
+    vars.iter()
+        .map(|(var, value)| {
+            if !is_identifier(var) {
+                return Err(format!("variable '{}' is not a TLA+ identifier", var));
+            }
+            Ok(format!("/\\ {} = {}", var, json_to_value(value)?))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|assignments| assignments.join("\n"))
+}
+
+fn json_to_value(value: &JsonValue) -> Result<String, String> {
+    match value {
+        JsonValue::Bool(true) => Ok("TRUE".to_owned()),
+        JsonValue::Bool(false) => Ok("FALSE".to_owned()),
+        JsonValue::Number(number) if number.is_i64() || number.is_u64() => Ok(number.to_string()),
+        JsonValue::String(string) if !string.contains('"') => Ok(format!("\"{}\"", string)),
+        JsonValue::Array(values) => Ok(format!(
+            "<<{}>>",
+            values
+                .iter()
+                .map(json_to_value)
+                .collect::<Result<Vec<_>, _>>()?
+                .join(", ")
+        )),
+        JsonValue::Object(fields)
+            if !fields.is_empty() && fields.keys().all(|key| is_identifier(key)) =>
+        {
+            Ok(format!(
+                "[{}]",
+                fields
+                    .iter()
+                    .map(|(key, value)| Ok(format!("{} |-> {}", key, json_to_value(value)?)))
+                    .collect::<Result<Vec<_>, String>>()?
+                    .join(", ")
+            ))
+        }
+        JsonValue::Object(fields)
+            if !fields.is_empty() && fields.keys().all(|key| key.parse::<i64>().is_ok()) =>
+        {
+            Ok(format!(
+                "({})",
+                fields
+                    .iter()
+                    .map(|(key, value)| Ok(format!("{} :> {}", key, json_to_value(value)?)))
+                    .collect::<Result<Vec<_>, String>>()?
+                    .join(" @@ ")
+            ))
+        }
+        _ => Err(format!("value has no TLA+ representation: {}", value)),
+    }
+}
+
+/// Whether the string is a TLA+ identifier: letters, digits and underscores,
+/// with at least one letter.
+fn is_identifier(string: &str) -> bool {
+    string
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && string.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_to_state() {
+        let state = json!({
+            "bool": true,
+            "number": -1,
+            "string": "a b",
+            "seq": [1, [], ["x"]],
+            "record": { "status": "-", "tags": [false] },
+            "function": { "1": { "a": 2 }, "2": 3 },
+        });
+        let tla_state = json_to_state(&state).unwrap();
+        assert_eq!(state_to_json(&tla_state).unwrap(), state);
+
+        for value in [json!(1.5), json!({}), json!({"a b": 1}), json!("\"")] {
+            assert!(json_to_state(&json!({ "x": value })).is_err());
+        }
+        assert!(json_to_state(&json!([1])).is_err());
+    }
+}
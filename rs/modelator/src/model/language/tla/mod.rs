@@ -4,7 +4,8 @@ use regex::Regex;
 mod json;
 
 use crate::artifact::{
-    tla_file, Artifact, ArtifactCreator, JsonTrace, TlaConfigFile, TlaFile, TlaFileSuite, TlaTrace,
+    tla_file, tla_file_suite, Artifact, ArtifactCreator, JsonTrace, TlaConfigFile, TlaFile,
+    TlaFileSuite, TlaTrace,
 };
 use crate::model::language::tla;
 use crate::Error;
@@ -15,9 +16,139 @@ use std::path::Path;
 #[derive(Debug, Clone, Copy)]
 pub struct Tla;
 
+/// Annotation marking the operator defined right after it as a test,
+/// when using [`TestDiscovery::Annotations`].
+const TEST_ANNOTATION: &str = "@modelator:test";
+
+/// Annotation marking the operator defined right after it as a test that is
+/// expected to hold, regardless of the [`TestDiscovery`] used.
+const EXPECT_HOLDS_ANNOTATION: &str = "@modelator:expect-holds";
+
+/// Configuration of how test operators are discovered in a TLA+ tests module.
+#[derive(Clone, Debug)]
+pub enum TestDiscovery {
+    /// Operators whose name starts with `prefix` or ends with `suffix`.
+    /// An empty prefix or suffix doesn't match any operator.
+    Pattern {
+        /// Prefix of test operator names.
+        prefix: String,
+        /// Suffix of test operator names.
+        suffix: String,
+    },
+    /// Operators whose name is in the list.
+    Names(Vec<String>),
+    /// Operators preceded by a `\* @modelator:test` comment.
+    Annotations,
+    /// Operators whose name matches the regex, e.g. `^Scenario_`. The regex
+    /// isn't anchored: it may match any part of the name.
+    Regex(Regex),
+}
+
+impl PartialEq for TestDiscovery {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Pattern { prefix, suffix },
+                Self::Pattern {
+                    prefix: other_prefix,
+                    suffix: other_suffix,
+                },
+            ) => prefix == other_prefix && suffix == other_suffix,
+            (Self::Names(names), Self::Names(other_names)) => names == other_names,
+            (Self::Annotations, Self::Annotations) => true,
+            // regexes don't implement `PartialEq`
+            (Self::Regex(regex), Self::Regex(other_regex)) => {
+                regex.as_str() == other_regex.as_str()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TestDiscovery {}
+
+impl Default for TestDiscovery {
+    fn default() -> Self {
+        Self::Pattern {
+            prefix: "Test".to_owned(),
+            suffix: "Test".to_owned(),
+        }
+    }
+}
+
+impl std::fmt::Display for TestDiscovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pattern { prefix, suffix } => {
+                write!(
+                    f,
+                    "names starting with {:?} or ending with {:?}",
+                    prefix, suffix
+                )
+            }
+            Self::Names(names) => write!(f, "names in {:?}", names),
+            Self::Annotations => write!(f, "operators annotated with '\\* {}'", TEST_ANNOTATION),
+            Self::Regex(regex) => write!(f, "names matching regex {:?}", regex.as_str()),
+        }
+    }
+}
+
+/// What is expected from model checking a test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestExpectation {
+    /// The test describes states to reach: its negation is checked, and the
+    /// counterexamples found are the test traces.
+    Counterexample,
+    /// The test is a property that must hold: it is checked as is, and the
+    /// test passes iff no counterexample is found.
+    Holds,
+}
+
+/// The rule by which an operator of a TLA+ tests module was discovered as a test.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TestDiscoveryRule {
+    /// The operator name starts with the prefix of a [`TestDiscovery::Pattern`].
+    Prefix(String),
+    /// The operator name ends with the suffix of a [`TestDiscovery::Pattern`].
+    Suffix(String),
+    /// The operator name is listed in [`TestDiscovery::Names`].
+    Name,
+    /// The operator name matches the regex of a [`TestDiscovery::Regex`].
+    Regex(String),
+    /// The operator is annotated with `\* @modelator:test`.
+    Annotation,
+    /// The operator is annotated with `\* @modelator:expect-holds`.
+    ExpectHolds,
+}
+
+impl std::fmt::Display for TestDiscoveryRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Prefix(prefix) => write!(f, "prefix {:?}", prefix),
+            Self::Suffix(suffix) => write!(f, "suffix {:?}", suffix),
+            Self::Name => write!(f, "listed name"),
+            Self::Regex(regex) => write!(f, "regex {:?}", regex),
+            Self::Annotation => write!(f, "annotation '\\* {}'", TEST_ANNOTATION),
+            Self::ExpectHolds => write!(f, "annotation '\\* {}'", EXPECT_HOLDS_ANNOTATION),
+        }
+    }
+}
+
+/// An operator of a TLA+ tests module that is treated as a test.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveredTest {
+    /// Name of the test operator.
+    pub name: String,
+    /// What is expected from model checking the test.
+    pub expectation: TestExpectation,
+    /// The rule by which the operator was discovered.
+    pub rule: TestDiscoveryRule,
+}
+
 pub struct TlaTest {
     pub file_suite: TlaFileSuite,
     pub name: String,
+    pub expectation: TestExpectation,
 }
 
 impl Tla {
@@ -26,7 +157,7 @@ impl Tla {
     /// # Examples
     /// ```ignore
     /// use modelator::artifact::TlaFileSuite;
-    /// use modelator::model::{language::Tla, checker::Tlc};
+    /// use modelator::model::{language::{Tla, TestDiscovery}, checker::Tlc};
     /// use modelator::ModelatorRuntime;
     /// use std::convert::TryFrom;
     ///
@@ -34,7 +165,7 @@ impl Tla {
     /// let tla_config_file = "tests/integration/resource/Numbers.cfg";
     /// let tla_suite = TlaFileSuite::from_tla_and_config_paths(tla_tests_file, tla_config_file).unwrap();
     ///
-    /// let mut tests = Tla::generate_tests(&tla_suite).unwrap();
+    /// let mut tests = Tla::generate_tests(&tla_suite, &TestDiscovery::default()).unwrap();
     /// let test_tla_suite = tests.pop().unwrap();
     /// let runtime = ModelatorRuntime::default();
     /// let (tla_trace, _) = Tlc::test(&test_tla_suite, &runtime).unwrap();
@@ -42,125 +173,235 @@ impl Tla {
     /// println!("{:?}", json_trace);
     /// ```
     pub fn tla_trace_to_json_trace(tla_trace: TlaTrace) -> Result<JsonTrace, Error> {
+        Self::tla_trace_to_json_trace_with_limits(tla_trace, None, None)
+    }
+
+    /// Same as [`Tla::tla_trace_to_json_trace`], but only the first
+    /// `max_trace_states` states are converted, up to the first state whose
+    /// TLA+ is larger than `max_state_bytes`. The states omitted are replaced
+    /// by a marker state `{"#truncated": true, "omitted": <n>}` (see
+    /// [`JsonTrace::omitted_states`]), so that huge counterexamples don't
+    /// hang their conversion.
+    pub fn tla_trace_to_json_trace_with_limits(
+        mut tla_trace: TlaTrace,
+        max_trace_states: Option<usize>,
+        max_state_bytes: Option<usize>,
+    ) -> Result<JsonTrace, Error> {
         tracing::debug!("Tla::tla_trace_to_json_trace:\n{}", tla_trace);
-        let states: Vec<JsonValue> = tla_trace
+        let kept = kept_states(&tla_trace, max_trace_states, max_state_bytes);
+        let omitted = tla_trace.states.len() - kept;
+        tla_trace.states.truncate(kept);
+        let mut states: Vec<JsonValue> = tla_trace
             .into_iter()
             .map(|state| json::state_to_json(&state))
             .collect::<Result<_, _>>()?;
+        if omitted > 0 {
+            states.push(serde_json::json!({
+                crate::artifact::json_trace::TRUNCATED_KEY: true,
+                "omitted": omitted,
+            }));
+        }
         Ok(states.into())
     }
 
+    /// Convert a [`JsonTrace`] into a [`TlaTrace`], e.g. to feed a trace
+    /// edited as Json to TLC's trace explorer. This is the inverse of
+    /// [`Tla::tla_trace_to_json_trace`].
+    ///
+    /// Json arrays are converted into TLA+ sequences, since sets and
+    /// sequences have the same Json encoding. Json objects are converted into
+    /// records, or into functions if all their keys are integers.
+    ///
+    /// Returns [`Error::TraceConversion`] if a state isn't a Json object or
+    /// contains a value with no TLA+ representation (e.g. a float).
+    pub fn json_trace_to_tla_trace(json_trace: &JsonTrace) -> Result<TlaTrace, Error> {
+        tracing::debug!("Tla::json_trace_to_tla_trace:\n{}", json_trace);
+        let mut tla_trace = TlaTrace::new();
+        for (index, state) in json_trace.iter().enumerate() {
+            let state = json::json_to_state(state).map_err(|reason| Error::TraceConversion {
+                format: "TLA+".to_owned(),
+                reason: format!("state {}: {}", index, reason),
+            })?;
+            tla_trace.add(state);
+        }
+        Ok(tla_trace)
+    }
+
     /// Generate TLA+ test and config files given a [`TlaFile`] containing TLA+
     /// test assertions and a [`TlaConfigFile`].
     ///
     /// # Examples
     /// ```ignore
     /// use modelator::artifact::TlaFileSuite;
-    /// use modelator::model::language::Tla;
+    /// use modelator::model::language::{Tla, TestDiscovery};
     /// use std::convert::TryFrom;
     ///
     /// let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
     /// let tla_config_file = "tests/integration/resource/Numbers.cfg";
     /// let tla_suite = TlaFileSuite::from_tla_and_config_paths(tla_tests_file, tla_config_file).unwrap();
-    /// let mut tests = Tla::generate_tests(&tla_suite).unwrap();
+    /// let mut tests = Tla::generate_tests(&tla_suite, &TestDiscovery::default()).unwrap();
     /// println!("{:?}", tests);
     /// ```
-    pub fn generate_tests(tla_file_suite: &TlaFileSuite) -> Result<Vec<TlaTest>, Error> {
+    pub fn generate_tests(
+        tla_file_suite: &TlaFileSuite,
+        test_discovery: &TestDiscovery,
+    ) -> Result<Vec<TlaTest>, Error> {
         tracing::debug!(
             "Tla::generate_tests {} {}",
             tla_file_suite.tla_file,
             tla_file_suite.tla_config_file
         );
 
-        let tla_tests_module_name = tla_file_suite.tla_file.module_name();
-
-        // retrieve test names from tla tests file
-        let test_names = Self::extract_test_names(tla_file_suite.tla_file.file_contents_backing())?;
+        // retrieve tests from tla tests file
+        let tests = Self::discover_tests(&tla_file_suite.tla_file, test_discovery)?;
 
         tracing::debug!(
-            "test names extracted from {}:\n{:?}",
+            "tests discovered in {}:\n{:?}",
             tla_file_suite.tla_file,
-            test_names
+            tests
         );
 
-        // check if no test was found
-        if test_names.is_empty() {
-            return Err(Error::NoTestFound(tla_tests_module_name.to_string()));
-        }
-
-        // generate a tla test file and config for each test name found
-        test_names
+        // generate a tla test file and config for each test found
+        tests
             .into_iter()
-            .map(|test_name| {
+            .map(|test| {
                 Ok(TlaTest {
-                    name: test_name.clone(),
-                    file_suite: Self::generate_test(&test_name, tla_file_suite)?,
+                    file_suite: generate_test(&test.name, tla_file_suite, test.expectation)
+                        .map_err(|e| in_file(e, &tla_file_suite.tla_file))?,
+                    name: test.name,
+                    expectation: test.expectation,
                 })
             })
             .collect()
     }
 
+    /// Discover the tests of a TLA+ tests module, i.e. the operators that
+    /// [`Tla::generate_tests`] would generate tests for, along with the rule
+    /// by which each one was discovered. No model checker is involved.
+    ///
+    /// Returns [`Error::NoTestFound`] if the module contains no test.
+    pub fn discover_tests(
+        tla_file: &TlaFile,
+        test_discovery: &TestDiscovery,
+    ) -> Result<Vec<DiscoveredTest>, Error> {
+        let content = tla_file.file_contents_backing();
+        let holds_test_names =
+            Self::extract_holds_test_names(content).map_err(|e| in_file(e, tla_file))?;
+        let mut tests: Vec<_> = discover_tests_with_rules(content, test_discovery)
+            .map_err(|e| in_file(e, tla_file))?
+            .into_iter()
+            .filter(|(name, _)| !holds_test_names.contains(name))
+            .map(|(name, rule)| DiscoveredTest {
+                name,
+                expectation: TestExpectation::Counterexample,
+                rule,
+            })
+            .collect();
+        tests.extend(holds_test_names.into_iter().map(|name| DiscoveredTest {
+            name,
+            expectation: TestExpectation::Holds,
+            rule: TestDiscoveryRule::ExpectHolds,
+        }));
+
+        // check if no test was found
+        if tests.is_empty() {
+            return Err(Error::NoTestFound {
+                file: tla_file.path().map(Path::to_path_buf),
+                module: tla_file.module_name().to_owned(),
+                details: format!(
+                    "[discovery: {}, inspected operators: {:?}]",
+                    test_discovery,
+                    extract_operator_names(content).map_err(|e| in_file(e, tla_file))?
+                ),
+            });
+        }
+        Ok(tests)
+    }
+
     /// Generate test names from a tla file
     pub fn extract_test_names(content: &str) -> Result<Vec<String>, Error> {
-        let names = extract_operator_names(content)?;
-        Ok(names
-            .iter()
-            .filter_map(|name| {
-                let is_test = name.starts_with("Test") || name.ends_with("Test");
-                let is_commented_out = name.starts_with("\\*") || name.starts_with("(*");
-                if is_test && !is_commented_out {
-                    Some(name.to_string())
-                } else {
-                    None
-                }
-            })
+        Self::discover_test_names(content, &TestDiscovery::default())
+    }
+
+    /// Generate test names from a tla file, using the given test discovery
+    pub fn discover_test_names(
+        content: &str,
+        test_discovery: &TestDiscovery,
+    ) -> Result<Vec<String>, Error> {
+        Ok(discover_tests_with_rules(content, test_discovery)?
+            .into_iter()
+            .map(|(name, _)| name)
             .collect())
     }
 
+    /// Generate the names of the tests expected to hold from a tla file, i.e.
+    /// operators annotated with `\* @modelator:expect-holds`
+    pub fn extract_holds_test_names(content: &str) -> Result<Vec<String>, Error> {
+        let names = extract_operator_names(content)?;
+        Ok(
+            extract_annotated_operator_names(content, EXPECT_HOLDS_ANNOTATION)
+                .into_iter()
+                .filter(|name| names.contains(name))
+                .collect(),
+        )
+    }
+
     /// Generate test tla file and config for a testname
     pub fn generate_test(
         test_name: &str,
         tla_file_suite: &TlaFileSuite,
     ) -> Result<TlaFileSuite, Error> {
-        let tla_tests_file_name = tla_file_suite.tla_file.module_name();
-        let test_module_name = format!("{}_{}", tla_tests_file_name, test_name);
-        let negated_test_name = format!("{}Neg", test_name);
-        let view_operator =
-            extract_view_operator(test_name, tla_file_suite.tla_file.file_contents_backing())?;
-
-        // create tla module where the test is negated
-        let test_module = generate_test_module(
-            &test_module_name,
-            tla_tests_file_name,
-            &negated_test_name,
-            test_name,
-            &view_operator,
-        );
-        // create test config with negated test as an invariant
-        let test_config =
-            generate_test_config(tla_file_suite.tla_config_file.content(), &negated_test_name);
-
-        let test_module_file = TlaFile::from_string(&test_module)?;
-        let mut test_config_file = TlaConfigFile::from_string(&test_config)?;
-        test_config_file.set_path(std::path::Path::new(&format!(
-            "{}_{}.cfg",
-            tla_tests_file_name, test_name
-        )));
-
-        let collected = {
-            let mut dependencies = tla_file_suite.dependency_tla_files.clone();
-            dependencies.push(tla_file_suite.tla_file.clone());
-            dependencies
-        };
-
-        Ok(TlaFileSuite {
-            tla_file: test_module_file,
-            tla_config_file: test_config_file,
-            dependency_tla_files: collected,
-        })
+        generate_test(test_name, tla_file_suite, TestExpectation::Counterexample)
     }
 }
 
+fn generate_test(
+    test_name: &str,
+    tla_file_suite: &TlaFileSuite,
+    expectation: TestExpectation,
+) -> Result<TlaFileSuite, Error> {
+    let tla_tests_file_name = tla_file_suite.tla_file.module_name();
+    let test_module_name = format!("{}_{}", tla_tests_file_name, test_name);
+    let negated_test_name = format!("{}Neg", test_name);
+    let view_operator =
+        extract_view_operator(test_name, tla_file_suite.tla_file.file_contents_backing())?;
+
+    // create tla module where the test is negated
+    let test_module = generate_test_module(
+        &test_module_name,
+        tla_tests_file_name,
+        &negated_test_name,
+        test_name,
+        &view_operator,
+    );
+    // create test config with negated test as an invariant, unless the
+    // test is expected to hold
+    let invariant = match expectation {
+        TestExpectation::Counterexample => &negated_test_name,
+        TestExpectation::Holds => test_name,
+    };
+    let test_config = generate_test_config(tla_file_suite.tla_config_file.content(), invariant);
+
+    let test_module_file = TlaFile::from_string(&test_module)?;
+    let mut test_config_file = TlaConfigFile::from_string(&test_config)?;
+    test_config_file.set_path(std::path::Path::new(&format!(
+        "{}_{}.cfg",
+        tla_tests_file_name, test_name
+    )));
+
+    let collected = {
+        let mut dependencies = tla_file_suite.dependency_tla_files.clone();
+        dependencies.push(tla_file_suite.tla_file.clone());
+        dependencies
+    };
+
+    Ok(TlaFileSuite {
+        tla_file: test_module_file,
+        tla_config_file: test_config_file,
+        dependency_tla_files: collected,
+    })
+}
+
 /// Returns a list of operator names
 fn extract_operator_names(tla_file_contents: &str) -> Result<Vec<String>, Error> {
     let cnt_operators = tla_file_contents.match_indices("==").count();
@@ -177,12 +418,125 @@ fn extract_operator_names(tla_file_contents: &str) -> Result<Vec<String>, Error>
 
     match ret.len() == cnt_operators {
         true => Ok(ret),
-        false => Err(Error::TlaOperatorNameParseError(
-            tla_file_contents.to_owned(),
-        )),
+        false => {
+            // report the first definition not matched by the regex
+            let matched: Vec<usize> = RE
+                .find_iter(tla_file_contents)
+                .map(|m| m.end() - 2)
+                .collect();
+            let index = tla_file_contents
+                .match_indices("==")
+                .map(|(index, _)| index)
+                .find(|index| !matched.contains(index))
+                .unwrap_or_default();
+            let line_start = tla_file_contents[..index].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = tla_file_contents[index..]
+                .find('\n')
+                .map_or(tla_file_contents.len(), |i| index + i);
+            Err(Error::TlaOperatorNameParseError {
+                file: None,
+                line: tla_file_contents[..index].matches('\n').count() + 1,
+                snippet: tla_file_contents[line_start..line_end].trim().to_owned(),
+            })
+        }
+    }
+}
+
+/// Returns whether an operator is defined in the contents of a TLA+ file,
+/// ignoring its mentions in comments and strings.
+pub(crate) fn defines_operator(tla_file_contents: &str, operator: &str) -> Result<bool, Error> {
+    let contents = tla_file_suite::remove_comments_and_strings(tla_file_contents);
+    Ok(extract_operator_names(&contents)?
+        .iter()
+        .any(|name| name == operator))
+}
+
+/// Attaches the path of the TLA+ file to the errors that don't have it, as
+/// operators are parsed from the contents of the file only.
+fn in_file(error: Error, tla_file: &TlaFile) -> Error {
+    match error {
+        Error::TlaOperatorNameParseError {
+            file: None,
+            line,
+            snippet,
+        } => Error::TlaOperatorNameParseError {
+            file: tla_file.path().map(Path::to_path_buf),
+            line,
+            snippet,
+        },
+        error => error,
     }
 }
 
+/// Returns the number of states of a trace kept when converting it to JSON:
+/// at most `max_trace_states`, and only those before the first state whose
+/// TLA+ is larger than `max_state_bytes`.
+pub(crate) fn kept_states(
+    tla_trace: &TlaTrace,
+    max_trace_states: Option<usize>,
+    max_state_bytes: Option<usize>,
+) -> usize {
+    let states = &tla_trace.states;
+    let kept = max_trace_states.map_or(states.len(), |max| max.min(states.len()));
+    max_state_bytes
+        .and_then(|max| states[..kept].iter().position(|state| state.len() > max))
+        .unwrap_or(kept)
+}
+
+/// Returns the names of the test operators, along with the rule that matched
+/// each one
+fn discover_tests_with_rules(
+    content: &str,
+    test_discovery: &TestDiscovery,
+) -> Result<Vec<(String, TestDiscoveryRule)>, Error> {
+    let names = extract_operator_names(content)?;
+    let annotated_names = match test_discovery {
+        TestDiscovery::Annotations => extract_annotated_operator_names(content, TEST_ANNOTATION),
+        _ => Vec::new(),
+    };
+    Ok(names
+        .into_iter()
+        .filter_map(|name| {
+            let rule = match test_discovery {
+                TestDiscovery::Pattern { prefix, suffix } => {
+                    if !prefix.is_empty() && name.starts_with(prefix.as_str()) {
+                        Some(TestDiscoveryRule::Prefix(prefix.clone()))
+                    } else if !suffix.is_empty() && name.ends_with(suffix.as_str()) {
+                        Some(TestDiscoveryRule::Suffix(suffix.clone()))
+                    } else {
+                        None
+                    }
+                }
+                TestDiscovery::Names(test_names) => {
+                    test_names.contains(&name).then(|| TestDiscoveryRule::Name)
+                }
+                TestDiscovery::Annotations => annotated_names
+                    .contains(&name)
+                    .then(|| TestDiscoveryRule::Annotation),
+                TestDiscovery::Regex(regex) => regex
+                    .is_match(&name)
+                    .then(|| TestDiscoveryRule::Regex(regex.as_str().to_owned())),
+            }?;
+            let is_commented_out = name.starts_with("\\*") || name.starts_with("(*");
+            (!is_commented_out).then(|| (name, rule))
+        })
+        .collect())
+}
+
+/// Returns the names of the operators defined right after a
+/// `\* <annotation>` comment
+fn extract_annotated_operator_names(tla_file_contents: &str, annotation: &str) -> Vec<String> {
+    // Match '\* <annotation><newline><whitespace><identifier>'
+    let re = Regex::new(&format!(
+        r"(?m)^[ \t]*\\\*[ \t]*{}[ \t]*$\s*([a-zA-Z0-9_]+)",
+        regex::escape(annotation)
+    ))
+    .expect("[modelator] annotation regex should be valid");
+    re.captures_iter(tla_file_contents)
+        .map(|caps| caps[1].to_owned())
+        .collect()
+}
+
 /// Scan the contents of the tla file to try to find an operator named
 /// '<test_name>View'. If none is found then use an operator named 'View', if found.
 /// If no operator is found then returns None.
@@ -319,6 +673,197 @@ My4Op
         }
     }
 
+    const TEST_DISCOVERY_FIXTURE: &str = r#"
+---------- MODULE Fixture ----------
+Helper == 1
+TestFirst == 2
+SecondTest == 3
+
+\* @modelator:test
+Scenario1 == 4
+    \*   @modelator:test
+  Scenario2
+    == 5
+\* @modelator:test is not on its own line
+Scenario3 == 6
+===============================
+"#;
+
+    #[test]
+    fn test_discover_test_names() {
+        let discover = |test_discovery| {
+            Tla::discover_test_names(TEST_DISCOVERY_FIXTURE, &test_discovery).unwrap()
+        };
+
+        assert_eq!(
+            discover(TestDiscovery::default()),
+            vec!["TestFirst", "SecondTest"]
+        );
+        assert_eq!(
+            discover(TestDiscovery::Pattern {
+                prefix: "Scenario".to_owned(),
+                suffix: "".to_owned(),
+            }),
+            vec!["Scenario1", "Scenario2", "Scenario3"]
+        );
+        assert_eq!(
+            discover(TestDiscovery::Names(vec![
+                "Helper".to_owned(),
+                "Scenario3".to_owned(),
+                "Missing".to_owned(),
+            ])),
+            vec!["Helper", "Scenario3"]
+        );
+        assert_eq!(
+            discover(TestDiscovery::Annotations),
+            vec!["Scenario1", "Scenario2"]
+        );
+        assert_eq!(
+            discover(TestDiscovery::Regex(Regex::new("^Scenario[12]$").unwrap())),
+            vec!["Scenario1", "Scenario2"]
+        );
+    }
+
+    #[test]
+    fn test_discover_tests() {
+        let tla_file = TlaFile::from_string(
+            r#"
+---------- MODULE Fixture ----------
+Helper == 1
+SecondTest == 2
+
+\* @modelator:expect-holds
+TestFirst == 3
+===============================
+"#,
+        )
+        .unwrap();
+        let tests = Tla::discover_tests(&tla_file, &TestDiscovery::default()).unwrap();
+        let tests: Vec<_> = tests
+            .iter()
+            .map(|test| (test.name.as_str(), test.expectation, test.rule.to_string()))
+            .collect();
+        assert_eq!(
+            tests,
+            vec![
+                (
+                    "SecondTest",
+                    TestExpectation::Counterexample,
+                    r#"suffix "Test""#.to_owned()
+                ),
+                (
+                    "TestFirst",
+                    TestExpectation::Holds,
+                    r"annotation '\* @modelator:expect-holds'".to_owned()
+                ),
+            ]
+        );
+
+        let test_discovery = TestDiscovery::Names(vec!["Helper".to_owned()]);
+        let tests = Tla::discover_tests(&tla_file, &test_discovery).unwrap();
+        assert_eq!(
+            tests.iter().map(|test| &test.rule).collect::<Vec<_>>(),
+            vec![&TestDiscoveryRule::Name, &TestDiscoveryRule::ExpectHolds]
+        );
+    }
+
+    #[test]
+    fn test_no_test_found() {
+        let tla_file_suite = TlaFileSuite {
+            tla_file: TlaFile::from_string(TEST_DISCOVERY_FIXTURE).unwrap(),
+            tla_config_file: TlaConfigFile::from_string("").unwrap(),
+            dependency_tla_files: vec![],
+        };
+        let test_discovery = TestDiscovery::Names(vec!["Missing".to_owned()]);
+        match Tla::generate_tests(&tla_file_suite, &test_discovery) {
+            Err(Error::NoTestFound {
+                file,
+                module,
+                details,
+            }) => {
+                assert_eq!(file, None);
+                assert_eq!(module, "Fixture");
+                assert!(details.contains(r#"names in ["Missing"]"#));
+                assert!(details.contains(r#""Helper", "TestFirst""#));
+            }
+            _ => panic!("expected no test to be found"),
+        }
+
+        // a regex matching no operator is reported
+        let test_discovery = TestDiscovery::Regex(Regex::new("^Scenario_").unwrap());
+        match Tla::generate_tests(&tla_file_suite, &test_discovery) {
+            Err(Error::NoTestFound { details, .. }) => {
+                assert!(details.contains(r#"names matching regex "^Scenario_""#));
+            }
+            _ => panic!("expected no test to be found"),
+        }
+
+        // the path of a file read from disk is reported
+        let tla_file_suite = TlaFileSuite::from_tla_and_config_paths(
+            "tests/integration/resource/Numbers.tla",
+            "tests/integration/resource/Numbers.cfg",
+        )
+        .unwrap();
+        let error = match Tla::generate_tests(&tla_file_suite, &TestDiscovery::default()) {
+            Err(error) => error,
+            Ok(_) => panic!("expected no test to be found"),
+        };
+        assert!(
+            matches!(&error, Error::NoTestFound { file: Some(file), module, .. }
+                if file.ends_with("resource/Numbers.tla") && module == "Numbers"),
+            "{:?}",
+            error
+        );
+        assert!(error.to_string().starts_with(
+            "No test found in module Numbers (tests/integration/resource/Numbers.tla): "
+        ));
+    }
+
+    #[test]
+    fn test_generate_holds_tests() {
+        let tla_file_suite = TlaFileSuite {
+            tla_file: TlaFile::from_string(
+                r#"
+---------- MODULE Fixture ----------
+FirstTest == 1
+
+\* @modelator:expect-holds
+SecondTest == 2
+
+\* @modelator:expect-holds
+Invariant == 3
+===============================
+"#,
+            )
+            .unwrap(),
+            tla_config_file: TlaConfigFile::from_string("").unwrap(),
+            dependency_tla_files: vec![],
+        };
+        let tests = Tla::generate_tests(&tla_file_suite, &TestDiscovery::default()).unwrap();
+        let tests: Vec<_> = tests
+            .iter()
+            .map(|test| {
+                (
+                    test.name.as_str(),
+                    test.expectation,
+                    test.file_suite.tla_config_file.content().trim(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            tests,
+            vec![
+                (
+                    "FirstTest",
+                    TestExpectation::Counterexample,
+                    "INVARIANT FirstTestNeg"
+                ),
+                ("SecondTest", TestExpectation::Holds, "INVARIANT SecondTest"),
+                ("Invariant", TestExpectation::Holds, "INVARIANT Invariant"),
+            ]
+        );
+    }
+
     #[test]
     fn test_extract_test_names() {
         let content = r#"
@@ -345,4 +890,37 @@ My4Test
             Err(_) => panic!(),
         };
     }
+
+    #[test]
+    fn test_tla_trace_to_json_trace_with_limits() {
+        let mut trace = TlaTrace::new();
+        trace.add("x = 0".to_owned());
+        trace.add("x = 1".to_owned());
+        trace.add(format!("x = \"{}\"", "a".repeat(100)));
+        trace.add("x = 3".to_owned());
+
+        let json = Tla::tla_trace_to_json_trace_with_limits(trace.clone(), None, None).unwrap();
+        assert_eq!(json.len(), 4);
+        assert_eq!(json.omitted_states(), None);
+
+        // the omitted states are replaced by a marker state
+        let json = Tla::tla_trace_to_json_trace_with_limits(trace.clone(), Some(2), None).unwrap();
+        assert_eq!(
+            json.states(),
+            [
+                serde_json::json!({ "x": 0 }),
+                serde_json::json!({ "x": 1 }),
+                serde_json::json!({ "#truncated": true, "omitted": 2 }),
+            ]
+        );
+        assert_eq!(json.omitted_states(), Some(2));
+
+        // the trace is truncated before the first state that is too large
+        let json =
+            Tla::tla_trace_to_json_trace_with_limits(trace.clone(), Some(10), Some(50)).unwrap();
+        assert_eq!(json.len(), 3);
+        assert_eq!(json.omitted_states(), Some(2));
+        let json = Tla::tla_trace_to_json_trace_with_limits(trace, Some(1), Some(50)).unwrap();
+        assert_eq!(json.omitted_states(), Some(3));
+    }
 }
@@ -1,49 +1,629 @@
 use crate::artifact::JsonTrace;
-use crate::{Error, TestError};
+use crate::execution_log::ExecutionLog;
+use crate::{Error, FailedStep, FailedTest, TestError};
 use serde::de::DeserializeOwned;
-use std::fmt::Debug;
+use serde_json::Value as JsonValue;
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Display};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Context in which a [`StepRunner`] executes a step.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepContext {
+    /// Name of the test the trace was generated from.
+    pub test_name: String,
+    /// Index of the step in the trace.
+    pub step_index: usize,
+    /// Number of steps in the trace.
+    pub step_count: usize,
+    /// Index of the trace among the traces generated from the test.
+    pub trace_index: usize,
+}
+
+impl StepContext {
+    /// Returns true iff the step is the last one of the trace.
+    pub fn is_last_step(&self) -> bool {
+        self.step_index + 1 == self.step_count
+    }
+}
+
+impl std::fmt::Display for StepContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "test '{}', trace {}, step {} of {}",
+            self.test_name,
+            self.trace_index,
+            self.step_index + 1,
+            self.step_count
+        )
+    }
+}
 
 /// A `StepRunner` drives a SUT by executing a series of steps
 /// (see [`crate::ModelatorRuntime::run_tla_steps`]).
-pub trait StepRunner<Step: DeserializeOwned + Debug + Clone> {
+///
+/// Steps fail by returning an error of type `E`, or by panicking (e.g. with
+/// `assert_eq!`): either way, only the trace being run fails.
+pub trait StepRunner<Step: DeserializeOwned + Debug + Clone, E: Display + Debug = String> {
     /// Executes the first step against  the runner.
-    fn initial_step(&mut self, step: Step) -> Result<(), String>;
+    fn initial_step(&mut self, step: Step) -> Result<(), E>;
 
     /// Executes each next step against the runner.
-    fn next_step(&mut self, step: Step) -> Result<(), String>;
+    fn next_step(&mut self, step: Step) -> Result<(), E>;
+
+    /// Executes the first step against the runner, given its context.
+    /// Defaults to [`StepRunner::initial_step`].
+    fn initial_step_with_ctx(&mut self, step: Step, _ctx: &StepContext) -> Result<(), E> {
+        self.initial_step(step)
+    }
+
+    /// Executes each next step against the runner, given its context.
+    /// Defaults to [`StepRunner::next_step`].
+    fn next_step_with_ctx(&mut self, step: Step, _ctx: &StepContext) -> Result<(), E> {
+        self.next_step(step)
+    }
+
+    /// Finalizes the runner once all steps of a trace are executed, e.g. to
+    /// release resources or to check global postconditions.
+    /// If a step fails, it's still called, but its error is ignored.
+    fn finalize(&mut self) -> Result<(), E> {
+        Ok(())
+    }
 
     /// Run this runner on a Json trace
     fn run(&mut self, trace: JsonTrace) -> Result<(), TestError> {
+        self.run_with_ctx(trace, "", 0)
+    }
+
+    /// Run this runner on the Json trace with the given index among the
+    /// traces generated from the given test
+    fn run_with_ctx(
+        &mut self,
+        trace: JsonTrace,
+        test_name: &str,
+        trace_index: usize,
+    ) -> Result<(), TestError> {
         // parse test
-        let steps = trace
-            .into_iter()
+        let json_steps: Vec<JsonValue> = trace.into_iter().collect();
+        let steps = json_steps
+            .iter()
             .map(|step| {
-                serde_json::from_value(step)
-                    .map_err(|e| TestError::Modelator(Error::JsonParseError(e.to_string())))
+                serde_json::from_value(step.clone()).map_err(|e| TestError::Modelator(e.into()))
             })
             .collect::<Result<Vec<Step>, _>>()?;
 
+        let mut ctx = StepContext {
+            test_name: test_name.to_owned(),
+            step_index: 0,
+            step_count: steps.len(),
+            trace_index,
+        };
         for (i, step) in steps.clone().into_iter().enumerate() {
-            // check each step
-            let result = if i == 0 {
-                self.initial_step(step)
-            } else {
-                self.next_step(step)
-            };
+            ctx.step_index = i;
+            // check each step, catching panics so that only this trace fails
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                if i == 0 {
+                    self.initial_step_with_ctx(step, &ctx)
+                } else {
+                    self.next_step_with_ctx(step, &ctx)
+                }
+            }));
 
-            if let Err(message) = result {
-                return Err(TestError::FailedTest {
+            let failure = match result {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some((e.to_string(), ctx.to_string())),
+                Err(payload) => Some((
+                    format!("panicked: {}", panic_message(payload.as_ref())),
+                    format!("{}: {}", ctx, json_steps[i]),
+                )),
+            };
+            if let Some((message, location)) = failure {
+                // the step failure is reported, rather than the finalization one
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| self.finalize()));
+                return Err(TestError::FailedTest(Box::new(FailedTest {
                     message,
-                    location: "".to_string(),
+                    location,
+                    step: Some(FailedStep {
+                        index: i,
+                        value: Some(json_steps[i].clone()),
+                        diff: None,
+                    }),
                     test: format!("{:?}", steps),
                     system: "".to_string(),
-                });
+                    execution_log: None,
+                    trace_id: None,
+                })));
             }
         }
+
+        self.finalize().map_err(|e| TestError::FinalizationFailed {
+            message: e.to_string(),
+            step_count: steps.len(),
+            test: format!("{:?}", steps),
+        })
+    }
+}
+
+/// Returns the message of a panic, if it's a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "<non-string panic payload>"
+    }
+}
+
+/// An invariant of the system under test, returning a description of its
+/// violation, if any.
+pub type Invariant<System> = fn(&System) -> Result<(), String>;
+
+/// A serialization of the state of the system under test, captured when each
+/// trace finishes or fails (see [`crate::ModelatorRuntime::run_tla_steps_with_snapshot`]).
+/// Systems that don't implement it are captured with their [`Debug`] output.
+pub trait Snapshot {
+    /// Returns the current state of the system.
+    fn snapshot(&self) -> String;
+}
+
+/// Error of a step run by [`Checked`].
+#[derive(Debug)]
+pub(crate) enum CheckedError<E> {
+    /// The step itself failed.
+    Step(E),
+    /// The step succeeded, but left the system violating an invariant.
+    Invariant { name: String, message: String },
+}
+
+impl<E: Display> Display for CheckedError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Step(e) => e.fmt(f),
+            Self::Invariant { name, message } => {
+                write!(f, "invariant '{}' violated: {}", name, message)
+            }
+        }
+    }
+}
+
+/// Runs a step runner while checking invariants after each step.
+#[derive(Clone)]
+pub(crate) struct Checked<System> {
+    pub(crate) system: System,
+    invariants: Vec<(String, Invariant<System>)>,
+}
+
+impl<System> Checked<System> {
+    pub(crate) fn new(system: System, invariants: &[(&str, Invariant<System>)]) -> Self {
+        Self {
+            system,
+            invariants: invariants
+                .iter()
+                .map(|(name, invariant)| ((*name).to_owned(), *invariant))
+                .collect(),
+        }
+    }
+
+    fn check<E>(&self, result: Result<(), E>) -> Result<(), CheckedError<E>> {
+        result.map_err(CheckedError::Step)?;
+        for (name, invariant) in &self.invariants {
+            invariant(&self.system).map_err(|message| CheckedError::Invariant {
+                name: name.clone(),
+                message,
+            })?;
+        }
         Ok(())
     }
 }
 
+impl<System: Debug> Debug for Checked<System> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.system.fmt(f)
+    }
+}
+
+impl<System, Step, E> StepRunner<Step, CheckedError<E>> for Checked<System>
+where
+    System: StepRunner<Step, E>,
+    Step: DeserializeOwned + Debug + Clone,
+    E: Display + Debug,
+{
+    fn initial_step(&mut self, step: Step) -> Result<(), CheckedError<E>> {
+        let result = self.system.initial_step(step);
+        self.check(result)
+    }
+
+    fn next_step(&mut self, step: Step) -> Result<(), CheckedError<E>> {
+        let result = self.system.next_step(step);
+        self.check(result)
+    }
+
+    fn initial_step_with_ctx(
+        &mut self,
+        step: Step,
+        ctx: &StepContext,
+    ) -> Result<(), CheckedError<E>> {
+        let result = self.system.initial_step_with_ctx(step, ctx);
+        self.check(result)
+    }
+
+    fn next_step_with_ctx(&mut self, step: Step, ctx: &StepContext) -> Result<(), CheckedError<E>> {
+        let result = self.system.next_step_with_ctx(step, ctx);
+        self.check(result)
+    }
+
+    fn finalize(&mut self) -> Result<(), CheckedError<E>> {
+        self.system.finalize().map_err(CheckedError::Step)
+    }
+}
+
+/// Runs a step runner while recording the steps it executes.
+#[derive(Clone)]
+pub(crate) struct Recorded<System> {
+    pub(crate) system: System,
+    steps: Vec<JsonValue>,
+    pub(crate) log: ExecutionLog,
+}
+
+impl<System: Debug> Recorded<System> {
+    pub(crate) fn new(system: System, trace: &JsonTrace, cap: usize) -> Self {
+        Self {
+            system,
+            steps: trace.clone().into_iter().collect(),
+            log: ExecutionLog::new(cap),
+        }
+    }
+
+    fn record<E: Display>(
+        &mut self,
+        ctx: &StepContext,
+        step: impl FnOnce(&mut System) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| step(&mut self.system)));
+        let outcome = match &result {
+            Ok(Ok(())) => "OK".to_string(),
+            Ok(Err(e)) => e.to_string(),
+            Err(payload) => format!("panicked: {}", panic_message(payload.as_ref())),
+        };
+        self.log.record(
+            self.steps.get(ctx.step_index).cloned().unwrap_or_default(),
+            format!("{:?}", self.system),
+            outcome,
+        );
+        result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+}
+
+impl<System, Step, E> StepRunner<Step, E> for Recorded<System>
+where
+    System: StepRunner<Step, E> + Debug,
+    Step: DeserializeOwned + Debug + Clone,
+    E: Display + Debug,
+{
+    fn initial_step(&mut self, step: Step) -> Result<(), E> {
+        self.system.initial_step(step)
+    }
+
+    fn next_step(&mut self, step: Step) -> Result<(), E> {
+        self.system.next_step(step)
+    }
+
+    fn initial_step_with_ctx(&mut self, step: Step, ctx: &StepContext) -> Result<(), E> {
+        self.record(ctx, |system| system.initial_step_with_ctx(step, ctx))
+    }
+
+    fn next_step_with_ctx(&mut self, step: Step, ctx: &StepContext) -> Result<(), E> {
+        self.record(ctx, |system| system.next_step_with_ctx(step, ctx))
+    }
+
+    fn finalize(&mut self) -> Result<(), E> {
+        self.system.finalize()
+    }
+}
+
+/// Durations of the steps of a trace run against the system under test
+/// (see [`crate::ModelatorRuntime::step_timings`]).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepTimings {
+    /// Duration of each step, in order, starting with the initial step.
+    /// A step that panics isn't listed.
+    pub durations: Vec<Duration>,
+}
+
+impl StepTimings {
+    /// Returns the total duration of the steps.
+    pub fn total(&self) -> Duration {
+        self.durations.iter().sum()
+    }
+
+    /// Returns the index and the duration of the slowest step, if any.
+    pub fn slowest(&self) -> Option<(usize, Duration)> {
+        self.durations
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by_key(|(_, duration)| *duration)
+    }
+}
+
+/// Runs a step runner while timing the steps it executes, warning about
+/// those that take longer than the threshold, if any.
+#[derive(Clone)]
+pub(crate) struct Timed<System> {
+    pub(crate) system: System,
+    pub(crate) timings: StepTimings,
+    slow_step_threshold: Option<Duration>,
+}
+
+impl<System> Timed<System> {
+    pub(crate) fn new(system: System, slow_step_threshold: Option<Duration>) -> Self {
+        Self {
+            system,
+            timings: StepTimings::default(),
+            slow_step_threshold,
+        }
+    }
+
+    fn time<E>(
+        &mut self,
+        ctx: &StepContext,
+        step: impl FnOnce(&mut System) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if ctx.step_index == 0 {
+            self.timings.durations.clear();
+        }
+        let start = Instant::now();
+        let result = step(&mut self.system);
+        let duration = start.elapsed();
+        if let Some(threshold) = self.slow_step_threshold {
+            if duration > threshold {
+                tracing::warn!(
+                    "slow step: {} took {:?}, more than {:?}",
+                    ctx,
+                    duration,
+                    threshold
+                );
+            }
+        }
+        self.timings.durations.push(duration);
+        result
+    }
+}
+
+impl<System: Debug> Debug for Timed<System> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.system.fmt(f)
+    }
+}
+
+impl<System, Step, E> StepRunner<Step, E> for Timed<System>
+where
+    System: StepRunner<Step, E>,
+    Step: DeserializeOwned + Debug + Clone,
+    E: Display + Debug,
+{
+    fn initial_step(&mut self, step: Step) -> Result<(), E> {
+        self.system.initial_step(step)
+    }
+
+    fn next_step(&mut self, step: Step) -> Result<(), E> {
+        self.system.next_step(step)
+    }
+
+    fn initial_step_with_ctx(&mut self, step: Step, ctx: &StepContext) -> Result<(), E> {
+        self.time(ctx, |system| system.initial_step_with_ctx(step, ctx))
+    }
+
+    fn next_step_with_ctx(&mut self, step: Step, ctx: &StepContext) -> Result<(), E> {
+        self.time(ctx, |system| system.next_step_with_ctx(step, ctx))
+    }
+
+    fn finalize(&mut self) -> Result<(), E> {
+        self.system.finalize()
+    }
+}
+
+/// Error of a step run by [`TimeLimited`].
+#[derive(Debug)]
+pub(crate) enum TimeLimitedError<E> {
+    /// The step itself failed.
+    Step(E),
+    /// The step took longer than the timeout.
+    TimedOut(Duration),
+}
+
+impl<E: Display> Display for TimeLimitedError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Step(e) => e.fmt(f),
+            Self::TimedOut(step_timeout) => write!(f, "step timed out after {:?}", step_timeout),
+        }
+    }
+}
+
+/// Runs a step runner while failing the steps that take longer than
+/// `step_timeout` (see [`crate::ModelatorRuntime::run_tla_steps_with_timeout`]).
+///
+/// Each step is run on a separate thread, by a copy of the system which
+/// replaces it once the step is over. If a step times out, its copy is
+/// abandoned, and finalized if the step ever ends: the remaining steps are
+/// skipped, and the system is restored as it was before the trace.
+#[derive(Clone)]
+pub(crate) struct TimeLimited<System> {
+    pub(crate) system: System,
+    step_timeout: Duration,
+    /// The system before the current trace.
+    initial_system: Option<System>,
+    timed_out: bool,
+}
+
+impl<System: Clone + Send + 'static> TimeLimited<System> {
+    pub(crate) const fn new(system: System, step_timeout: Duration) -> Self {
+        Self {
+            system,
+            step_timeout,
+            initial_system: None,
+            timed_out: false,
+        }
+    }
+
+    fn run_step<Step, E>(
+        &mut self,
+        step: Step,
+        ctx: &StepContext,
+    ) -> Result<(), TimeLimitedError<E>>
+    where
+        System: StepRunner<Step, E>,
+        Step: DeserializeOwned + Debug + Clone + Send + 'static,
+        E: Display + Debug + Send + 'static,
+    {
+        if ctx.step_index == 0 {
+            self.initial_system = Some(self.system.clone());
+            self.timed_out = false;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let mut system = self.system.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                if ctx.step_index == 0 {
+                    system.initial_step_with_ctx(step, &ctx)
+                } else {
+                    system.next_step_with_ctx(step, &ctx)
+                }
+            }));
+            if let Err(mpsc::SendError((mut system, _))) = sender.send((system, result)) {
+                // the step timed out: finalize its abandoned system
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| system.finalize()));
+            }
+        });
+
+        match receiver.recv_timeout(self.step_timeout) {
+            Ok((system, result)) => {
+                self.system = system;
+                result
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload))
+                    .map_err(TimeLimitedError::Step)
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.timed_out = true;
+                if let Some(initial_system) = self.initial_system.take() {
+                    self.system = initial_system;
+                }
+                Err(TimeLimitedError::TimedOut(self.step_timeout))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                panic!("the thread running the step ended without a result")
+            }
+        }
+    }
+}
+
+impl<System: Debug> Debug for TimeLimited<System> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.system.fmt(f)
+    }
+}
+
+impl<System, Step, E> StepRunner<Step, TimeLimitedError<E>> for TimeLimited<System>
+where
+    System: StepRunner<Step, E> + Clone + Send + 'static,
+    Step: DeserializeOwned + Debug + Clone + Send + 'static,
+    E: Display + Debug + Send + 'static,
+{
+    fn initial_step(&mut self, step: Step) -> Result<(), TimeLimitedError<E>> {
+        self.system
+            .initial_step(step)
+            .map_err(TimeLimitedError::Step)
+    }
+
+    fn next_step(&mut self, step: Step) -> Result<(), TimeLimitedError<E>> {
+        self.system.next_step(step).map_err(TimeLimitedError::Step)
+    }
+
+    fn initial_step_with_ctx(
+        &mut self,
+        step: Step,
+        ctx: &StepContext,
+    ) -> Result<(), TimeLimitedError<E>> {
+        self.run_step(step, ctx)
+    }
+
+    fn next_step_with_ctx(
+        &mut self,
+        step: Step,
+        ctx: &StepContext,
+    ) -> Result<(), TimeLimitedError<E>> {
+        self.run_step(step, ctx)
+    }
+
+    fn finalize(&mut self) -> Result<(), TimeLimitedError<E>> {
+        if self.timed_out {
+            // the abandoned system is finalized by its thread
+            return Ok(());
+        }
+        self.system.finalize().map_err(TimeLimitedError::Step)
+    }
+}
+
+/// Returns true iff running a copy of `system` on the given steps
+/// either returns an error or panics.
+pub(crate) fn fails<System, Step, E>(system: &System, steps: &[JsonValue]) -> bool
+where
+    System: StepRunner<Step, E> + Clone,
+    Step: DeserializeOwned + Debug + Clone,
+    E: Display + Debug,
+{
+    let mut system = system.clone();
+    let trace: JsonTrace = steps.to_vec().into();
+    !matches!(
+        panic::catch_unwind(AssertUnwindSafe(|| system.run(trace))),
+        Ok(Ok(()))
+    )
+}
+
+/// Returns the length of the shortest prefix of the steps on which a copy of
+/// `system` fails (see [`fails`]), if any.
+pub(crate) fn failing_prefix_length<System, Step, E>(
+    system: &System,
+    steps: &[JsonValue],
+) -> Option<usize>
+where
+    System: StepRunner<Step, E> + Clone,
+    Step: DeserializeOwned + Debug + Clone,
+    E: Display + Debug,
+{
+    (1..=steps.len()).find(|&length| fails(system, &steps[..length]))
+}
+
+/// Returns the variables of the trace states that are silently ignored
+/// when deserializing them as steps, e.g. because the step type has no
+/// field for them.
+pub(crate) fn ignored_variables<Step>(trace: &JsonTrace) -> BTreeSet<String>
+where
+    Step: DeserializeOwned,
+{
+    let mut variables = BTreeSet::new();
+    for state in trace.iter() {
+        // errors are reported when running the steps
+        let _ = serde_ignored::deserialize::<_, _, Step>(state, |path| {
+            if let serde_ignored::Path::Map {
+                parent: serde_ignored::Path::Root,
+                key,
+            } = path
+            {
+                variables.insert(key.clone());
+            }
+        });
+    }
+    variables
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,6 +675,289 @@ mod tests {
         }
     }
 
+    #[derive(Debug, Clone, Default)]
+    struct ContextRecorder {
+        contexts: Vec<StepContext>,
+    }
+
+    impl StepRunner<NumbersStep> for ContextRecorder {
+        fn initial_step(&mut self, _step: NumbersStep) -> Result<(), String> {
+            unreachable!("initial_step_with_ctx is implemented")
+        }
+
+        fn next_step(&mut self, _step: NumbersStep) -> Result<(), String> {
+            unreachable!("next_step_with_ctx is implemented")
+        }
+
+        fn initial_step_with_ctx(
+            &mut self,
+            _step: NumbersStep,
+            ctx: &StepContext,
+        ) -> Result<(), String> {
+            self.contexts.push(ctx.clone());
+            Ok(())
+        }
+
+        fn next_step_with_ctx(
+            &mut self,
+            _step: NumbersStep,
+            ctx: &StepContext,
+        ) -> Result<(), String> {
+            self.contexts.push(ctx.clone());
+            if ctx.is_last_step() {
+                Err("last step".to_owned())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn test_step_context() {
+        let step_value =
+            serde_json::json!({ "a": 0, "b": 0, "action": "None", "actionOutcome": "OK" });
+        let trace: JsonTrace =
+            vec![step_value.clone(), step_value.clone(), step_value.clone()].into();
+
+        let mut runner = ContextRecorder::default();
+        match runner.run_with_ctx(trace, "SomeTest", 1) {
+            Err(error @ TestError::FailedTest(_)) => {
+                assert_eq!(
+                    error.to_string(),
+                    "Test failed: last step\n   test 'SomeTest', trace 1, step 3 of 3"
+                );
+                let step = error.failed_step().unwrap();
+                assert_eq!(step.index, 2);
+                assert_eq!(step.value.as_ref(), Some(&step_value));
+                assert_eq!(step.diff, None);
+                assert_eq!(
+                    error.with_system("a=0 b=0").to_string(),
+                    "Test failed: last step\n   test 'SomeTest', trace 1, step 3 of 3\n   system: a=0 b=0"
+                );
+            }
+            res => panic!("expected the last step to fail, got {:?}", res),
+        }
+        let ctx = |step_index| StepContext {
+            test_name: "SomeTest".to_owned(),
+            step_index,
+            step_count: 3,
+            trace_index: 1,
+        };
+        assert_eq!(runner.contexts, vec![ctx(0), ctx(1), ctx(2)]);
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct Finalizer {
+        fail_step: Option<usize>,
+        fail_finalize: bool,
+        steps: usize,
+        finalized: bool,
+    }
+
+    impl StepRunner<NumbersStep> for Finalizer {
+        fn initial_step(&mut self, step: NumbersStep) -> Result<(), String> {
+            self.next_step(step)
+        }
+
+        fn next_step(&mut self, _step: NumbersStep) -> Result<(), String> {
+            self.steps += 1;
+            if self.fail_step == Some(self.steps) {
+                Err("step failed".to_owned())
+            } else {
+                Ok(())
+            }
+        }
+
+        fn finalize(&mut self) -> Result<(), String> {
+            self.finalized = true;
+            if self.fail_finalize {
+                Err("finalize failed".to_owned())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn test_finalize() {
+        let step = serde_json::json!({ "a": 0, "b": 0, "action": "None", "actionOutcome": "OK" });
+        let trace: JsonTrace = vec![step.clone(), step].into();
+
+        let mut runner = Finalizer::default();
+        assert!(runner.run(trace.clone()).is_ok());
+        assert!(runner.finalized);
+
+        let mut runner = Finalizer {
+            fail_finalize: true,
+            ..Finalizer::default()
+        };
+        assert!(matches!(
+            runner.run(trace.clone()),
+            Err(TestError::FinalizationFailed { message, step_count: 2, .. }) if message == "finalize failed"
+        ));
+
+        // a step failure takes precedence over a finalization failure
+        let mut runner = Finalizer {
+            fail_step: Some(2),
+            fail_finalize: true,
+            ..Finalizer::default()
+        };
+        assert!(matches!(
+            runner.run(trace),
+            Err(TestError::FailedTest(failure)) if failure.message == "step failed"
+        ));
+        assert!(runner.finalized);
+    }
+
+    #[derive(Debug)]
+    enum StepError {
+        Unexpected { a: u64 },
+    }
+
+    impl std::fmt::Display for StepError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Unexpected { a } => write!(f, "unexpected a = {}", a),
+            }
+        }
+    }
+
+    /// Fails with a typed error when `a` is 1, and panics when `a` is 2.
+    #[derive(Debug, Clone, Default)]
+    struct TypedRunner;
+
+    impl StepRunner<NumbersStep, StepError> for TypedRunner {
+        fn initial_step(&mut self, step: NumbersStep) -> Result<(), StepError> {
+            self.next_step(step)
+        }
+
+        fn next_step(&mut self, step: NumbersStep) -> Result<(), StepError> {
+            assert_ne!(step.a, 2, "a is 2");
+            if step.a == 1 {
+                Err(StepError::Unexpected { a: step.a })
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn test_step_errors() {
+        let step =
+            |a: u64| serde_json::json!({ "a": a, "b": 0, "action": "None", "actionOutcome": "OK" });
+        let mut runner = TypedRunner;
+
+        assert!(runner.run(vec![step(0), step(0)].into()).is_ok());
+
+        match runner.run(vec![step(0), step(1)].into()) {
+            Err(TestError::FailedTest(failure)) => {
+                assert_eq!(failure.message, "unexpected a = 1")
+            }
+            res => panic!("expected the step to fail, got {:?}", res),
+        }
+
+        // a panicking step only fails its trace
+        match runner.run(vec![step(0), step(2)].into()) {
+            Err(TestError::FailedTest(failure)) => {
+                assert!(failure.message.starts_with("panicked: assertion"));
+                assert!(failure.message.contains("a is 2"));
+                assert!(failure
+                    .location
+                    .starts_with("test '', trace 0, step 2 of 2: {"));
+                assert!(failure.location.contains(r#""a":2"#));
+            }
+            res => panic!("expected the step to fail, got {:?}", res),
+        }
+    }
+
+    /// Sleeps on the steps where `a` is 2.
+    #[derive(Debug, Clone, Default)]
+    struct SleepyRunner {
+        steps: usize,
+        finalized: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl StepRunner<NumbersStep> for SleepyRunner {
+        fn initial_step(&mut self, step: NumbersStep) -> Result<(), String> {
+            self.next_step(step)
+        }
+
+        fn next_step(&mut self, step: NumbersStep) -> Result<(), String> {
+            self.steps += 1;
+            if step.a == 2 {
+                std::thread::sleep(Duration::from_millis(300));
+            }
+            Ok(())
+        }
+
+        fn finalize(&mut self) -> Result<(), String> {
+            self.finalized
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_step_timeout() {
+        let step =
+            |a: u64| serde_json::json!({ "a": a, "b": 0, "action": "None", "actionOutcome": "OK" });
+        let timeout = Duration::from_millis(100);
+        let mut runner = TimeLimited::new(SleepyRunner::default(), timeout);
+
+        // the system is updated by the trace run
+        let trace: JsonTrace = vec![step(0), step(1)].into();
+        assert!(runner.run_with_ctx(trace, "SomeTest", 0).is_ok());
+        assert_eq!(runner.system.steps, 2);
+
+        let trace: JsonTrace = vec![step(0), step(2), step(3)].into();
+        match runner.run_with_ctx(trace, "SomeTest", 1) {
+            Err(TestError::FailedTest(failure)) => {
+                assert_eq!(failure.message, "step timed out after 100ms");
+                assert_eq!(failure.location, "test 'SomeTest', trace 1, step 2 of 3");
+                assert_eq!(failure.step.unwrap().value.unwrap()["a"], 2);
+            }
+            res => panic!("expected the step to time out, got {:?}", res),
+        }
+        // the timed out trace doesn't update the system
+        assert_eq!(runner.system.steps, 2);
+
+        // the abandoned system is finalized once its step ends
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(
+            runner
+                .system
+                .finalized
+                .load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+    }
+
+    #[test]
+    fn test_shrink_trace() {
+        let step = |a: u64, action: &str, outcome: &str| serde_json::json!({ "a": a, "b": 0, "action": action, "actionOutcome": outcome });
+        // the fourth step expects `IncreaseA` to fail, which it doesn't
+        let trace: JsonTrace = vec![
+            step(0, "None", "OK"),
+            step(0, "None", "OK"),
+            step(0, "None", "OK"),
+            step(1, "IncreaseA", "FAIL"),
+            step(1, "None", "OK"),
+        ]
+        .into();
+
+        let runtime = crate::ModelatorRuntime::default();
+        let mut system = NumberSystem::default();
+        let shrunk = runtime.shrink_trace(trace, &mut system).unwrap();
+        assert_eq!(
+            shrunk,
+            vec![step(0, "None", "OK"), step(1, "IncreaseA", "FAIL")].into()
+        );
+
+        // a trace that doesn't fail can't be shrunk
+        let trace: JsonTrace = vec![step(0, "None", "OK"), step(1, "IncreaseA", "OK")].into();
+        assert!(runtime.shrink_trace(trace, &mut system).is_none());
+    }
+
     #[test]
     fn test_step_runner() {
         let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
@@ -105,4 +968,365 @@ mod tests {
             .run_tla_steps(tla_tests_file, tla_config_file, &mut runner)
             .map_or_else(|_| false, |v| v.no_test_failed()),);
     }
+
+    impl Snapshot for NumberSystem {
+        fn snapshot(&self) -> String {
+            format!("a={} b={}", self.a, self.b)
+        }
+    }
+
+    #[test]
+    fn test_final_state() {
+        let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = crate::ModelatorRuntime::default();
+
+        let mut runner = NumberSystem::default();
+        let report = runtime
+            .run_tla_steps_with_snapshot(tla_tests_file, tla_config_file, &mut runner)
+            .unwrap();
+        assert_eq!(
+            report.final_state("AMaxBMinTest", 0),
+            Some(runner.snapshot().as_str())
+        );
+
+        let mut runner = NumberSystem::default();
+        let report = runtime
+            .run_tla_steps(tla_tests_file, tla_config_file, &mut runner)
+            .unwrap();
+        assert_eq!(
+            report.final_state("AMaxBMinTest", 0),
+            Some(format!("{:?}", runner).as_str())
+        );
+        assert_eq!(report.final_state("AMaxBMinTest", 1), None);
+    }
+
+    #[test]
+    fn test_dedup_executions() {
+        let tla_tests_file = "tests/integration/resource/NumbersDuplicateTests.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let mut runner = NumberSystem::default();
+
+        let runtime = crate::ModelatorRuntime::default();
+        let report = runtime
+            .run_tla_steps(tla_tests_file, tla_config_file, &mut runner)
+            .unwrap();
+        assert_eq!(report.deduplicated_executions(), 0);
+
+        // both tests generate the shortest trace reaching `a = MaxNumber /\ b = 0`
+        let runtime = runtime.dedup_executions(true);
+        let report = runtime
+            .run_tla_steps(tla_tests_file, tla_config_file, &mut runner)
+            .unwrap();
+        assert!(report.no_test_failed());
+        assert_eq!(report.deduplicated_executions(), 1);
+        assert_eq!(
+            report.trace_id("AMaxBMinTest", 0),
+            report.trace_id("BMinAMaxTest", 0)
+        );
+        assert_eq!(report.result_of_test("BMinAMaxTest").unwrap().len(), 1);
+    }
+
+    /// Sleeps for the number of milliseconds of each step.
+    #[derive(Debug, Clone, Default)]
+    struct Sleeper;
+
+    impl StepRunner<u64> for Sleeper {
+        fn initial_step(&mut self, millis: u64) -> Result<(), String> {
+            std::thread::sleep(Duration::from_millis(millis));
+            Ok(())
+        }
+
+        fn next_step(&mut self, millis: u64) -> Result<(), String> {
+            self.initial_step(millis)
+        }
+    }
+
+    #[test]
+    fn test_step_timings() {
+        let trace: JsonTrace = vec![0.into(), 30.into(), 0.into()].into();
+        let mut timed = Timed::new(Sleeper, Some(Duration::from_millis(20)));
+        timed.run(trace.clone()).unwrap();
+        let timings = timed.timings.clone();
+        assert_eq!(timings.durations.len(), 3);
+        assert_eq!(timings.slowest().unwrap().0, 1);
+        assert!(timings.durations[1] >= Duration::from_millis(30));
+        assert!(timings.total() >= timings.durations[1]);
+
+        // the timings of the previous run are replaced
+        timed.run(trace).unwrap();
+        assert_eq!(timed.timings.durations.len(), 3);
+        assert_eq!(StepTimings::default().slowest(), None);
+    }
+
+    #[test]
+    fn test_holds_tests() {
+        let tla_tests_file = "tests/integration/resource/NumbersHoldsTests.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = crate::ModelatorRuntime::default();
+        let mut runner = NumberSystem::default();
+        let report = runtime
+            .run_tla_steps(tla_tests_file, tla_config_file, &mut runner)
+            .unwrap();
+
+        assert!(report
+            .result_of_test("AMaxBMinTest")
+            .unwrap()
+            .iter()
+            .all(Result::is_ok));
+        assert!(report.result_of_test("InvHolds").is_none());
+        assert!(report.result_of_holds_test("InvHolds").unwrap().is_ok());
+        assert!(matches!(
+            report.result_of_holds_test("ASmallHolds"),
+            Some(Err(TestError::Modelator(Error::HoldsTestViolated { test, .. }))) if test == "ASmallHolds"
+        ));
+        let source = std::error::Error::source(
+            report
+                .result_of_holds_test("ASmallHolds")
+                .unwrap()
+                .as_ref()
+                .unwrap_err(),
+        );
+        assert!(matches!(
+            source.and_then(|source| source.downcast_ref::<Error>()),
+            Some(Error::HoldsTestViolated { .. })
+        ));
+        assert!(!report.no_test_failed());
+    }
+
+    /// Fails as soon as `a` reaches 3.
+    #[derive(Debug, Clone, Default)]
+    struct AtMostTwo;
+
+    impl StepRunner<NumbersStep> for AtMostTwo {
+        fn initial_step(&mut self, step: NumbersStep) -> Result<(), String> {
+            self.next_step(step)
+        }
+
+        fn next_step(&mut self, step: NumbersStep) -> Result<(), String> {
+            assert!(step.a < 3, "a = {}", step.a);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        let tla_tests_file = "tests/integration/resource/NumbersHoldsTests.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = crate::ModelatorRuntime::default().fail_fast(true);
+        let report = runtime
+            .run_tla_steps(tla_tests_file, tla_config_file, &mut AtMostTwo)
+            .unwrap();
+
+        // the failing trace is reported, and the next tests are skipped
+        let results = report.result_of_test("AMaxBMinTest").unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(TestError::FailedTest(_))));
+        assert_eq!(
+            report.outcome("ASmallHolds"),
+            Some(crate::TestOutcome::Skipped)
+        );
+        assert_eq!(
+            report.outcome("InvHolds"),
+            Some(crate::TestOutcome::Skipped)
+        );
+        assert!(!report.no_test_failed());
+    }
+
+    #[test]
+    fn test_shrink() {
+        let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = crate::ModelatorRuntime::default().shrink(true);
+        let report = runtime
+            .run_tla_steps(tla_tests_file, tla_config_file, &mut AtMostTwo)
+            .unwrap();
+
+        let results = report.result_of_test("AMaxBMinTest").unwrap();
+        assert!(!results.is_empty());
+        for result in results {
+            match result {
+                Err(TestError::ShrunkFailedTest {
+                    error,
+                    prefix_length,
+                    trace_length,
+                    prefix,
+                }) => {
+                    assert!(matches!(**error, TestError::FailedTest(_)));
+                    // the prefix ends with the first step where `a` is 3
+                    let steps: Vec<_> = prefix.clone().into_iter().collect();
+                    assert_eq!(steps.len(), *prefix_length);
+                    assert!(prefix_length <= trace_length);
+                    assert_eq!(steps[prefix_length - 1]["a"], 3);
+                    assert!(steps[..prefix_length - 1].iter().all(|step| step["a"] != 3));
+                }
+                result => panic!("expected a shrunk failure, got {:?}", result),
+            }
+        }
+        // the failing step is the last one of the shortest failing prefix
+        let step = report.failed_step("AMaxBMinTest", 0).unwrap();
+        assert_eq!(step.value.as_ref().unwrap()["a"], 3);
+        match &results[0] {
+            Err(TestError::ShrunkFailedTest { prefix_length, .. }) => {
+                assert_eq!(step.index + 1, *prefix_length)
+            }
+            result => panic!("expected a shrunk failure, got {:?}", result),
+        }
+    }
+
+    /// Step lacking the `action` and `actionOutcome` variables.
+    #[derive(Debug, Clone, Deserialize)]
+    struct PartialStep {
+        a: u64,
+        b: u64,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct PartialSystem {
+        sum: u64,
+    }
+
+    impl StepRunner<PartialStep> for PartialSystem {
+        fn initial_step(&mut self, step: PartialStep) -> Result<(), String> {
+            self.sum = step.a + step.b;
+            Ok(())
+        }
+
+        fn next_step(&mut self, step: PartialStep) -> Result<(), String> {
+            self.initial_step(step)
+        }
+    }
+
+    #[test]
+    fn test_strict_fields() {
+        let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let run = |runtime: crate::ModelatorRuntime| {
+            runtime
+                .run_tla_steps(
+                    tla_tests_file,
+                    tla_config_file,
+                    &mut PartialSystem::default(),
+                )
+                .unwrap()
+        };
+
+        // the missing fields are ignored by default
+        assert!(run(crate::ModelatorRuntime::default()).no_test_failed());
+
+        let report = run(crate::ModelatorRuntime::default().strict_fields(true));
+        match &report.result_of_test("AMaxBMinTest").unwrap()[0] {
+            Err(TestError::MissingStepFields {
+                step_type,
+                variables,
+            }) => {
+                assert!(step_type.ends_with("PartialStep"));
+                assert_eq!(variables, &["action", "actionOutcome"]);
+            }
+            result => panic!("expected missing step fields, got {:?}", result),
+        }
+
+        // all the variables are deserialized by `NumbersStep`
+        let report = crate::ModelatorRuntime::default()
+            .strict_fields(true)
+            .run_tla_steps(
+                tla_tests_file,
+                tla_config_file,
+                &mut NumberSystem::default(),
+            )
+            .unwrap();
+        assert!(report.no_test_failed());
+    }
+
+    #[test]
+    fn test_record_execution() {
+        let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = crate::ModelatorRuntime::default()
+            .record_execution(true)
+            .execution_log_cap(2);
+        let report = runtime
+            .run_tla_steps(tla_tests_file, tla_config_file, &mut AtMostTwo)
+            .unwrap();
+
+        let execution_log = match &report.result_of_test("AMaxBMinTest").unwrap()[0] {
+            Err(TestError::FailedTest(failure)) if failure.execution_log.is_some() => {
+                failure.execution_log.as_ref().unwrap()
+            }
+            result => panic!("expected a recorded failure, got {:?}", result),
+        };
+        // only the last steps are kept, the failing one being the last
+        assert_eq!(execution_log.entries.len(), 2);
+        let last = execution_log.entries.last().unwrap();
+        assert_eq!(last.step["a"], 3);
+        assert_eq!(last.system, "AtMostTwo");
+        assert_eq!(last.outcome, "panicked: a = 3");
+        assert_eq!(report.execution_log("AMaxBMinTest", 0), Some(execution_log));
+
+        // the failure is attached the id of its trace
+        let error = report.result_of_test("AMaxBMinTest").unwrap()[0]
+            .as_ref()
+            .unwrap_err();
+        let trace_id = report.trace_id("AMaxBMinTest", 0).unwrap();
+        assert!(matches!(
+            error,
+            TestError::FailedTest(failure) if failure.trace_id.as_deref() == Some(trace_id)
+        ));
+        // followed by the final state of the system
+        assert!(error
+            .to_string()
+            .ends_with(&format!(" (trace {})\n   system: AtMostTwo", trace_id)));
+    }
+
+    #[test]
+    fn test_invariants() {
+        fn a_is_small(system: &NumberSystem) -> Result<(), String> {
+            if system.a < 3 {
+                Ok(())
+            } else {
+                Err(format!("a = {}", system.a))
+            }
+        }
+        fn b_is_even(system: &NumberSystem) -> Result<(), String> {
+            if system.b % 2 == 0 {
+                Ok(())
+            } else {
+                Err(format!("b = {}", system.b))
+            }
+        }
+
+        let tla_tests_file = "tests/integration/resource/NumbersAMaxBMinTest.tla";
+        let tla_config_file = "tests/integration/resource/Numbers.cfg";
+        let runtime = crate::ModelatorRuntime::default();
+
+        let report = runtime
+            .run_tla_steps_with_invariants(
+                tla_tests_file,
+                tla_config_file,
+                &mut NumberSystem::default(),
+                &[("BIsEven", b_is_even)],
+            )
+            .unwrap();
+        assert!(report.no_test_failed());
+
+        let report = runtime
+            .run_tla_steps_with_invariants(
+                tla_tests_file,
+                tla_config_file,
+                &mut NumberSystem::default(),
+                &[("BIsEven", b_is_even), ("AIsSmall", a_is_small)],
+            )
+            .unwrap();
+        for result in report.result_of_test("AMaxBMinTest").unwrap() {
+            match result {
+                Err(TestError::FailedTest(failure)) => {
+                    assert_eq!(failure.message, "invariant 'AIsSmall' violated: a = 3");
+                    assert!(failure
+                        .location
+                        .starts_with("test 'AMaxBMinTest', trace 0, step "));
+                }
+                result => panic!("expected AIsSmall to be violated, got {:?}", result),
+            }
+        }
+    }
 }
@@ -1,11 +1,16 @@
+use crate::artifact::JsonTrace;
 use crate::Error;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value as JsonValue;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::path::Path;
 use std::{
     any::Any,
     panic::{self, AssertUnwindSafe, UnwindSafe},
-    sync::{Arc, Mutex},
+    sync::Once,
 };
 
 /// Result of executing a test or a set of tests.
@@ -102,10 +107,10 @@ type SystemTest<State> = Box<dyn FnMut(&mut State, &dyn Any) -> TestResult>;
 /// `SystemTester` is similar to [`SimpleTester`], but allows to
 /// supply test functions that accept also modifiable system state.
 pub struct SystemTester<State> {
-    tests: Vec<SystemTest<State>>,
+    tests: Vec<(&'static str, SystemTest<State>)>,
 }
 
-impl<'a, State> Default for SystemTester<State> {
+impl<State> Default for SystemTester<State> {
     fn default() -> Self {
         Self::new()
     }
@@ -128,7 +133,8 @@ impl<State> SystemTester<State> {
             Some(test_case) => capture_test(|| test(state, test_case.clone())),
             None => TestResult::Unhandled,
         };
-        self.tests.push(Box::new(test_fn));
+        self.tests
+            .push((std::any::type_name::<T>(), Box::new(test_fn)));
     }
 
     /// Add to the tester a test function that can accept closures as input.
@@ -142,7 +148,8 @@ impl<State> SystemTester<State> {
             Some(test_case) => capture_test(|| test(state, test_case.clone())),
             None => TestResult::Unhandled,
         };
-        self.tests.push(Box::new(test_fn));
+        self.tests
+            .push((std::any::type_name::<T>(), Box::new(test_fn)));
     }
 
     /// Run the test functions on the provided system and input.
@@ -150,55 +157,315 @@ impl<State> SystemTester<State> {
     /// will produce the result. If none of the defined test functions is
     /// able to handle the input, the `unhandled` result will be returned.
     pub fn test(&mut self, state: &mut State, input: &dyn Any) -> TestResult {
+        self.test_reporting_handlers(state, input).0
+    }
+
+    /// Same as [`SystemTester::test`], but also returns the names of the input
+    /// types of the test functions that handled the input, in the order
+    /// they ran. On failure, the failing test function is the last one.
+    pub fn test_reporting_handlers(
+        &mut self,
+        state: &mut State,
+        input: &dyn Any,
+    ) -> (TestResult, Vec<&'static str>) {
         let mut last = TestResult::Unhandled;
-        for test in &mut self.tests {
+        let mut handlers = vec![];
+        for (name, test) in &mut self.tests {
             let res = test(state, input);
+            if res != TestResult::Unhandled {
+                handlers.push(*name);
+            }
             match (&last, res) {
                 // On failure return immediately
-                (_, res @ TestResult::Failure { .. }) => return res,
+                (_, res @ TestResult::Failure { .. }) => return (res, handlers),
                 // If previously unhandled -> update
                 (TestResult::Unhandled, res) => last = res,
                 // All other cases (Success, Unhandled), (Success, Success) -> do nothing
                 _ => (),
             };
         }
-        last
+        (last, handlers)
     }
 }
 
+/// A named test, returning why it skipped the input if it can't handle it.
+type NamedTest = (
+    String,
+    Box<dyn Fn(&JsonValue) -> Result<TestResult, String> + Send + Sync>,
+);
+
+/// `Tester` represents a collection of named test functions, run on Json
+/// inputs, possibly in parallel. Unlike [`SimpleTester`], each test function
+/// is run on each input, and the results are collected in a [`TesterReport`].
+#[derive(Default)]
+pub struct Tester {
+    tests: Vec<NamedTest>,
+    filter: Option<String>,
+}
+
+impl Tester {
+    /// Create a new tester.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a named test function to the tester. The test function handles
+    /// the inputs that can be deserialized as T, and skips the others.
+    pub fn add<T, F, R>(&mut self, name: impl Into<String>, test: F)
+    where
+        T: 'static + DeserializeOwned,
+        F: Fn(T) -> R + Send + Sync + 'static,
+        R: 'static + Serialize,
+    {
+        let test_fn = move |input: &JsonValue| {
+            let test_case = parse_from_value::<T>(input.clone()).map_err(|e| e.to_string())?;
+            let mut test_case = Some(test_case);
+            Ok(capture_test(|| {
+                test(test_case.take().expect("test runs once"))
+            }))
+        };
+        self.tests.push((name.into(), Box::new(test_fn)));
+    }
+
+    /// Add a named test function on traces to the tester. The test function
+    /// handles the traces whose states can all be deserialized as Step, and
+    /// skips the others, reporting why the first state that can't be was
+    /// skipped (see [`TesterReport::skip_reason`]).
+    pub fn add_trace_test<Step, F>(&mut self, name: impl Into<String>, test: F)
+    where
+        Step: 'static + DeserializeOwned,
+        F: Fn(Vec<Step>) -> TestResult + Send + Sync + 'static,
+    {
+        let test_fn = move |input: &JsonValue| {
+            let states = input
+                .as_array()
+                .ok_or_else(|| "not a trace: expected an array of states".to_owned())?;
+            let steps = states
+                .iter()
+                .enumerate()
+                .map(|(index, state)| {
+                    serde_json::from_value::<Step>(state.clone())
+                        .map_err(|e| format!("state {}: {}", index, e))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut steps = Some(steps);
+            Ok(
+                capture_panic(|| test(steps.take().expect("test runs once")))
+                    .unwrap_or_else(|failure| failure),
+            )
+        };
+        self.tests.push((name.into(), Box::new(test_fn)));
+    }
+
+    /// Only run the test functions whose name contains `pattern`.
+    pub fn filter(mut self, pattern: impl Into<String>) -> Self {
+        self.filter = Some(pattern.into());
+        self
+    }
+
+    /// Run each test function on each input, one after the other.
+    pub fn run(&self, inputs: &[JsonValue]) -> TesterReport {
+        self.report(
+            self.pairs(inputs.len())
+                .map(|(test, input)| (test, input, (self.tests[test].1)(&inputs[input])))
+                .collect(),
+        )
+    }
+
+    /// Run each test function on each input in parallel. A test function
+    /// that panics on an input only fails on that input.
+    pub fn run_parallel(&self, inputs: &[JsonValue]) -> TesterReport {
+        let pairs: Vec<_> = self.pairs(inputs.len()).collect();
+        self.report(
+            pairs
+                .into_par_iter()
+                .map(|(test, input)| (test, input, (self.tests[test].1)(&inputs[input])))
+                .collect(),
+        )
+    }
+
+    /// Run each test function on each trace in parallel, e.g. on the
+    /// traces returned by [`crate::ModelatorRuntime::traces`].
+    pub fn run_traces(&self, traces: &[JsonTrace]) -> TesterReport {
+        let inputs: Vec<_> = traces.iter().map(|trace| trace.states.clone()).collect();
+        self.run_parallel(&inputs)
+    }
+
+    /// Run each test function in parallel on the traces read from a Json
+    /// file (see [`JsonTrace::read_json`]), or from each Json file of a
+    /// directory, in the order of their names.
+    pub fn run_trace_files(&self, path: impl AsRef<Path>) -> Result<TesterReport, Error> {
+        let path = path.as_ref();
+        let traces = if path.is_dir() {
+            let mut paths = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            paths.retain(|path| path.extension().map_or(false, |e| e == "json"));
+            paths.sort();
+            paths
+                .iter()
+                .map(JsonTrace::read_json)
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![JsonTrace::read_json(path)?]
+        };
+        Ok(self.run_traces(&traces))
+    }
+
+    /// Returns the (test function, input) index pairs to run, filtering
+    /// the test functions by name.
+    fn pairs(&self, input_count: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.tests
+            .iter()
+            .enumerate()
+            .filter(move |(_, (name, _))| {
+                self.filter
+                    .as_ref()
+                    .map_or(true, |pattern| name.contains(pattern.as_str()))
+            })
+            .flat_map(move |(test, _)| (0..input_count).map(move |input| (test, input)))
+    }
+
+    fn report(&self, results: Vec<(usize, usize, Result<TestResult, String>)>) -> TesterReport {
+        let mut report = TesterReport::default();
+        for (test, input, result) in results {
+            let name = &self.tests[test].0;
+            let result = result.unwrap_or_else(|reason| {
+                report.skip_reasons.insert((name.clone(), input), reason);
+                TestResult::Unhandled
+            });
+            report
+                .test_name_to_input_results
+                .entry(name.clone())
+                .or_default()
+                .push(result);
+        }
+        report
+    }
+}
+
+/// Wraps the results of running a [`Tester`], allowing more convenient
+/// access to them. The results of each test function are in the order of
+/// the inputs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TesterReport {
+    test_name_to_input_results: BTreeMap<String, Vec<TestResult>>,
+    skip_reasons: BTreeMap<(String, usize), String>,
+}
+
+impl TesterReport {
+    /// Returns true iff no test function failed on any input.
+    pub fn no_test_failed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Get the results of a single test function, one per input.
+    pub fn result_of_test(&self, name: &str) -> Option<&Vec<TestResult>> {
+        self.test_name_to_input_results.get(name)
+    }
+
+    /// Get why a single test function skipped the input with the given
+    /// index, e.g. the error deserializing it, if it did.
+    pub fn skip_reason(&self, name: &str, input: usize) -> Option<&str> {
+        self.skip_reasons
+            .get(&(name.to_owned(), input))
+            .map(String::as_str)
+    }
+
+    /// Returns the failures, as the name of the test function, the index
+    /// of the input, and the failure message.
+    pub fn failures(&self) -> impl Iterator<Item = (&str, usize, &str)> {
+        self.test_name_to_input_results
+            .iter()
+            .flat_map(|(name, results)| {
+                results
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(input, result)| match result {
+                        TestResult::Failure { message, .. } => {
+                            Some((name.as_str(), input, message.as_str()))
+                        }
+                        _ => None,
+                    })
+            })
+    }
+
+    /// Returns the number of (test function, input) pairs that
+    /// succeeded, failed, and were skipped, in this order.
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let results = self.test_name_to_input_results.values().flatten();
+        results.fold(
+            (0, 0, 0),
+            |(passed, failed, skipped), result| match result {
+                TestResult::Success(_) => (passed + 1, failed, skipped),
+                TestResult::Failure { .. } => (passed, failed + 1, skipped),
+                TestResult::Unhandled => (passed, failed, skipped + 1),
+            },
+        )
+    }
+}
+
+thread_local! {
+    /// Whether the panics of the current thread are captured by [`capture_test`].
+    static CAPTURING: Cell<bool> = Cell::new(false);
+    /// Location of the last panic captured in the current thread.
+    static PANIC_LOCATION: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Installs, once, a panic hook recording the location of the panics in the
+/// threads running [`capture_test`], and delegating the others to the
+/// previous hook. Swapping hooks for each test would race with the tests
+/// running in other threads.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CAPTURING.with(Cell::get) {
+                let location = info.location().map(ToString::to_string);
+                PANIC_LOCATION.with(|l| *l.borrow_mut() = location);
+            } else {
+                previous_hook(info);
+            }
+        }));
+    });
+}
+
 fn capture_test<'a, F, R>(test: F) -> TestResult
 where
     F: FnMut() -> R + 'a,
     R: Serialize,
 {
-    let test_result = Arc::new(Mutex::new(TestResult::Unhandled));
-    let old_hook = panic::take_hook();
-    panic::set_hook({
-        let result = test_result.clone();
-        Box::new(move |info| {
-            let mut result = result.lock().unwrap();
-            let message = match info.payload().downcast_ref::<&'static str>() {
-                Some(&s) => s.to_string(),
-                None => match info.payload().downcast_ref::<String>() {
-                    Some(s) => s.clone(),
-                    None => "Unknown error".to_string(),
-                },
-            };
-            let location = match info.location() {
-                Some(l) => l.to_string(),
-                None => "".to_string(),
-            };
-            *result = TestResult::Failure { message, location };
-        })
-    });
-    let result = panic::catch_unwind(AssertUnwindSafe(test));
-    panic::set_hook(old_hook);
-    match result {
+    match capture_panic(test) {
         Ok(res) => TestResult::Success(serde_json::to_string_pretty(&res).unwrap()),
-        Err(_) => (*test_result.lock().unwrap()).clone(),
+        Err(failure) => failure,
     }
 }
 
+/// Runs the test, returning its panic as a [`TestResult::Failure`].
+fn capture_panic<'a, F, R>(test: F) -> Result<R, TestResult>
+where
+    F: FnMut() -> R + 'a,
+{
+    install_panic_hook();
+    let capturing = CAPTURING.with(|c| c.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(test));
+    CAPTURING.with(|c| c.set(capturing));
+    result.map_err(|payload| {
+        let message = match payload.downcast_ref::<&'static str>() {
+            Some(&s) => s.to_string(),
+            None => match payload.downcast_ref::<String>() {
+                Some(s) => s.clone(),
+                None => "Unknown error".to_string(),
+            },
+        };
+        let location = PANIC_LOCATION
+            .with(|l| l.borrow_mut().take())
+            .unwrap_or_default();
+        TestResult::Failure { message, location }
+    })
+}
+
 fn convert_to<T>(input: &dyn Any) -> Option<T>
 where
     T: 'static + DeserializeOwned + Clone,
@@ -212,7 +479,7 @@ where
     } else if let Some(input) = input.downcast_ref::<Box<T>>() {
         Some((**input).clone())
     } else if let Some(input) = input.downcast_ref::<Box<String>>() {
-        parse_from_str::<T>(&**input).ok()
+        parse_from_str::<T>(input).ok()
     } else if let Some(input) = input.downcast_ref::<Box<JsonValue>>() {
         parse_from_value::<T>((**input).clone()).ok()
     } else if let Some(input) = input.downcast_ref::<Box<dyn Any>>() {
@@ -239,12 +506,12 @@ where
 
 /// Tries to parse a string as the given type
 fn parse_from_str<T: DeserializeOwned>(input: &str) -> Result<T, Error> {
-    serde_json::from_str(input).map_err(|e| Error::JsonParseError(e.to_string()))
+    serde_json::from_str(input).map_err(Error::from)
 }
 
 /// Tries to parse a Json Value as the given type
 fn parse_from_value<T: DeserializeOwned>(input: serde_json::Value) -> Result<T, Error> {
-    serde_json::from_value(input).map_err(|e| Error::JsonParseError(e.to_string()))
+    serde_json::from_value(input).map_err(Error::from)
 }
 
 #[cfg(test)]
@@ -291,15 +558,11 @@ mod tests {
 
         let data = String::from("{\"name\": \"test\"}");
         let res = tester.test(&data);
-        assert!(
-            matches!(
-                res,
-                TestResult::Failure {
-                    message: _,
-                    location: _
-                }
-            ) // if message == "got test")
-        );
+        assert!(matches!(
+            res,
+            TestResult::Failure { message, location }
+                if message == "got test" && location.contains("tester.rs")
+        ));
 
         let data = MyTest {
             name: "my_test".to_string(),
@@ -320,4 +583,124 @@ mod tests {
         tester.add(MyState::test1);
         tester.add(MyState::test2);
     }
+
+    #[test]
+    fn test_run() {
+        let mut tester = Tester::new();
+        tester.add("fails", fails);
+        tester.add("succeeds_if_my_test", succeeds_if_my_test);
+        let inputs = [
+            JsonValue::String("".to_string()),
+            serde_json::json!({"name": "test"}),
+            serde_json::json!({"name": "my_test"}),
+        ];
+        let report = tester.run(&inputs);
+        assert!(!report.no_test_failed());
+        assert_eq!(
+            report.result_of_test("fails").unwrap(),
+            &vec![TestResult::Unhandled; 3]
+        );
+        let results = report.result_of_test("succeeds_if_my_test").unwrap();
+        assert_eq!(results[0], TestResult::Unhandled);
+        assert!(matches!(
+            &results[1],
+            TestResult::Failure { message, location }
+                if message == "got test" && location.contains("tester.rs")
+        ));
+        assert!(matches!(results[2], TestResult::Success(_)));
+        assert_eq!(report.counts(), (1, 1, 4));
+    }
+
+    #[test]
+    fn test_run_parallel() {
+        let mut tester = Tester::new();
+        tester.add("fails", fails);
+        tester.add("succeeds_if_my_test", succeeds_if_my_test);
+        tester.add("my_test2", |t: MyTest2| t.id);
+        let inputs: Vec<JsonValue> = (0..100)
+            .map(|id| match id % 2 {
+                0 => serde_json::json!({ "id": id }),
+                _ => serde_json::json!({ "name": format!("test{}", id) }),
+            })
+            .collect();
+
+        // each failing input is reported, along with its panic message
+        let report = tester.run_parallel(&inputs);
+        assert_eq!(report, tester.run(&inputs));
+        let failures: Vec<_> = report.failures().collect();
+        assert_eq!(failures.len(), 100);
+        assert_eq!(
+            failures[0],
+            ("fails", 0, "internal error: entered unreachable code")
+        );
+        assert_eq!(failures[99], ("succeeds_if_my_test", 99, "got test99"));
+        assert_eq!(
+            report.result_of_test("my_test2").unwrap()[2],
+            TestResult::Success("2".to_string())
+        );
+
+        let report = tester.filter("my_test").run_parallel(&inputs);
+        assert!(report.result_of_test("fails").is_none());
+        assert_eq!(report.counts(), (50, 50, 100));
+    }
+
+    #[derive(Deserialize)]
+    struct NumbersStep {
+        a: u64,
+        b: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct CountStep {
+        count: u64,
+    }
+
+    #[test]
+    fn test_trace_tests() {
+        let mut tester = Tester::new();
+        tester.add_trace_test("b_grows", |steps: Vec<NumbersStep>| {
+            assert!(steps.windows(2).all(|w| w[0].b <= w[1].b));
+            TestResult::Success(steps.last().map_or(0, |step| step.a).to_string())
+        });
+        tester.add_trace_test("counts", |steps: Vec<CountStep>| {
+            TestResult::Success(steps.len().to_string())
+        });
+
+        let itf_path = "tests/integration/resource/NumbersTrace.itf.json";
+        let trace = JsonTrace::read_json(itf_path).unwrap();
+        let report = tester.run_traces(&[trace]);
+        assert_eq!(
+            report.result_of_test("b_grows").unwrap(),
+            &vec![TestResult::Success("1".to_string())]
+        );
+        assert_eq!(
+            report.result_of_test("counts").unwrap(),
+            &vec![TestResult::Unhandled]
+        );
+        assert_eq!(
+            report.skip_reason("counts", 0),
+            Some("state 0: missing field `count`")
+        );
+        assert_eq!(report, tester.run_trace_files(itf_path).unwrap());
+
+        // the traces of a directory are run in the order of their names
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.json"), r#"[{ "count": 1 }]"#).unwrap();
+        std::fs::copy(itf_path, dir.path().join("a.itf.json")).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a trace").unwrap();
+        let report = tester.run_trace_files(dir.path()).unwrap();
+        assert_eq!(report.counts(), (2, 0, 2));
+        assert_eq!(
+            report.result_of_test("counts").unwrap()[1],
+            TestResult::Success("1".to_string())
+        );
+        assert!(report.skip_reason("b_grows", 1).is_some());
+        assert!(report.skip_reason("b_grows", 0).is_none());
+
+        std::fs::write(dir.path().join("c.json"), "42").unwrap();
+        assert!(matches!(
+            tester.run_trace_files(dir.path()),
+            Err(Error::InvalidTraceFile { .. })
+        ));
+    }
 }
@@ -0,0 +1,254 @@
+use crate::artifact::JsonTrace;
+use crate::event::json_value_mismatches;
+use crate::TestError;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value as JsonValue;
+
+/// A `TraceMonitor` checks the states observed from a running system against
+/// a trace (e.g. obtained with [`crate::ModelatorRuntime::traces`]), instead of
+/// driving the system with it.
+///
+/// The observed states must be a stuttering-equivalent refinement of the
+/// trace: an observed state may be repeated, and the steps of the trace that
+/// don't change what is observed may be skipped.
+#[derive(Debug, Clone)]
+pub struct TraceMonitor {
+    trace: JsonTrace,
+}
+
+impl TraceMonitor {
+    /// Create a monitor for the given trace.
+    pub const fn new(trace: JsonTrace) -> Self {
+        Self { trace }
+    }
+
+    /// Check the observed states, given as Json objects, against the trace.
+    /// Only the fields of the trace states that are observed are compared.
+    ///
+    /// Returns a [`TestError::TraceDivergence`] describing the first divergence.
+    pub fn check_json<I>(&self, observed: I) -> Result<(), TestError>
+    where
+        I: IntoIterator<Item = JsonValue>,
+    {
+        self.check_with(observed.into_iter(), |state, observed| {
+            let projected = match (state, observed) {
+                (JsonValue::Object(state), JsonValue::Object(observed)) => JsonValue::Object(
+                    state
+                        .iter()
+                        .filter(|(field, _)| observed.contains_key(*field))
+                        .map(|(field, value)| (field.clone(), value.clone()))
+                        .collect(),
+                ),
+                (state, _) => state.clone(),
+            };
+            json_value_mismatches(&projected, observed, false)
+        })
+    }
+
+    /// Check the observed states against the trace, reading each state of the
+    /// trace as an abstract `State`, as a [`crate::StateHandler`] would.
+    ///
+    /// Returns a [`TestError::TraceDivergence`] describing the first divergence.
+    pub fn check<State, I>(&self, observed: I) -> Result<(), TestError>
+    where
+        State: Serialize + DeserializeOwned,
+        I: IntoIterator<Item = State>,
+    {
+        let observed = observed.into_iter().map(|state| {
+            serde_json::to_value(state)
+                .expect("[modelator] an observed state should be serializable")
+        });
+        self.check_with(
+            observed,
+            |state, observed| match serde_json::from_value::<State>(state.clone())
+                .map(serde_json::to_value)
+            {
+                Ok(Ok(state)) => json_value_mismatches(&state, observed, false),
+                Ok(Err(e)) | Err(e) => vec![format!("cannot read the state of the trace: {}", e)],
+            },
+        )
+    }
+
+    fn check_with<F>(
+        &self,
+        observed: impl Iterator<Item = JsonValue>,
+        mismatches: F,
+    ) -> Result<(), TestError>
+    where
+        F: Fn(&JsonValue, &JsonValue) -> Vec<String>,
+    {
+        let states = self.trace.states();
+        let divergence = |observed_index, observed, trace_index: usize, mismatches| {
+            Err(TestError::TraceDivergence {
+                observed_index,
+                observed,
+                trace_index,
+                expected: states.get(trace_index).cloned().unwrap_or_default(),
+                mismatches,
+            })
+        };
+
+        // index of the state of the trace matching the last observed state
+        let mut index = 0;
+        let mut last_observed: Option<JsonValue> = None;
+        let mut observed_count = 0;
+        for (observed_index, observed) in observed.enumerate() {
+            observed_count += 1;
+            let last = match &last_observed {
+                None if states.is_empty() => {
+                    return divergence(
+                        observed_index,
+                        Some(observed),
+                        0,
+                        vec!["the trace has no state".to_string()],
+                    )
+                }
+                None => {
+                    let first_mismatches = mismatches(&states[0], &observed);
+                    if !first_mismatches.is_empty() {
+                        return divergence(observed_index, Some(observed), 0, first_mismatches);
+                    }
+                    last_observed = Some(observed);
+                    continue;
+                }
+                Some(last) => last,
+            };
+
+            // the observed state is repeated
+            if mismatches(&states[index], &observed).is_empty() {
+                last_observed = Some(observed);
+                continue;
+            }
+
+            // skip the steps of the trace that can't be observed
+            let mut next = index + 1;
+            while next < states.len() && mismatches(&states[next], last).is_empty() {
+                next += 1;
+            }
+            let next = next.min(states.len() - 1);
+            if next == index {
+                return divergence(
+                    observed_index,
+                    Some(observed),
+                    index,
+                    vec!["the trace ended before the observed states".to_string()],
+                );
+            }
+            let next_mismatches = mismatches(&states[next], &observed);
+            if !next_mismatches.is_empty() {
+                return divergence(observed_index, Some(observed), next, next_mismatches);
+            }
+            index = next;
+            last_observed = Some(observed);
+        }
+
+        match last_observed {
+            None if states.is_empty() => Ok(()),
+            None => divergence(0, None, 0, vec!["no state was observed".to_string()]),
+            Some(last) => match (index + 1..states.len())
+                .find(|&next| !mismatches(&states[next], &last).is_empty())
+            {
+                Some(next) => divergence(
+                    observed_count,
+                    None,
+                    next,
+                    vec!["the observed states ended before the trace".to_string()],
+                ),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    fn numbers_trace() -> JsonTrace {
+        let state = |a: u64, b: u64, action: &str| json!({ "a": a, "b": b, "action": action });
+        vec![
+            state(0, 0, "None"),
+            state(0, 0, "None"),
+            state(1, 0, "IncreaseA"),
+            state(1, 2, "IncreaseB"),
+            state(1, 2, "None"),
+        ]
+        .into()
+    }
+
+    fn observed(states: &[(u64, u64)]) -> Vec<JsonValue> {
+        states
+            .iter()
+            .map(|(a, b)| json!({ "a": a, "b": b }))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_json() {
+        let monitor = TraceMonitor::new(numbers_trace());
+
+        // repeated and skipped unobservable steps
+        assert!(monitor
+            .check_json(observed(&[(0, 0), (1, 0), (1, 2)]))
+            .is_ok());
+        assert!(monitor
+            .check_json(observed(&[(0, 0), (0, 0), (0, 0), (1, 0), (1, 2), (1, 2)]))
+            .is_ok());
+
+        // an observable step is skipped
+        match monitor.check_json(observed(&[(0, 0), (1, 2)])) {
+            Err(TestError::TraceDivergence {
+                observed_index,
+                observed,
+                trace_index,
+                expected,
+                mismatches,
+            }) => {
+                assert_eq!((observed_index, trace_index), (1, 2));
+                assert_eq!(observed, Some(json!({ "a": 1, "b": 2 })));
+                assert_eq!(expected, json!({ "a": 1, "b": 0, "action": "IncreaseA" }));
+                assert_eq!(mismatches, vec!["b: expected 0, got 2"]);
+            }
+            result => panic!("expected a divergence, got {:?}", result),
+        }
+
+        // the observed states end early
+        assert!(matches!(
+            monitor.check_json(observed(&[(0, 0), (1, 0)])),
+            Err(TestError::TraceDivergence {
+                observed_index: 2,
+                observed: None,
+                trace_index: 3,
+                ..
+            })
+        ));
+
+        // the observed states go on
+        assert!(matches!(
+            monitor.check_json(observed(&[(0, 0), (1, 0), (1, 2), (2, 2)])),
+            Err(TestError::TraceDivergence {
+                observed_index: 3,
+                trace_index: 4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_check() {
+        #[derive(Serialize, Deserialize)]
+        struct A {
+            a: u64,
+        }
+
+        let monitor = TraceMonitor::new(numbers_trace());
+        let observed = |values: &[u64]| values.iter().map(|&a| A { a }).collect::<Vec<_>>();
+        assert!(monitor.check(observed(&[0, 1])).is_ok());
+        assert!(matches!(
+            monitor.check(observed(&[0, 2])),
+            Err(TestError::TraceDivergence { mismatches, .. }) if mismatches == vec!["a: expected 1, got 2"]
+        ));
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::HashSet;
 use std::fs::{copy, read_to_string};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Condvar, Mutex};
 
 pub(crate) fn cmd_output_to_string(output: &[u8]) -> String {
     String::from_utf8_lossy(output).to_string()
@@ -30,6 +31,44 @@ pub(crate) fn check_file_existence<P: AsRef<Path>>(path: P) -> Result<(), Error>
     }
 }
 
+/// Checks that Java is installed, with at least the given version.
+pub(crate) fn check_java_version(min: usize) -> Result<(), Error> {
+    let mut cmd = Command::new("java");
+    cmd.arg("-version");
+    // show command being run
+    tracing::debug!("{}", cmd_show(&cmd));
+
+    let output = cmd.output().map_err(|err| {
+        tracing::debug!("error checking Java version: {}", err);
+        Error::MissingJava
+    })?;
+    // `java -version` writes to stderr
+    let stderr = cmd_output_to_string(&output.stderr);
+    match parse_java_version(&stderr) {
+        Some(version) if version < min => Err(Error::MinimumJavaVersion(version, min)),
+        Some(_) => Ok(()),
+        None => {
+            tracing::warn!("unable to parse the Java version from:\n{}", stderr);
+            Ok(())
+        }
+    }
+}
+
+/// Parses the major Java version from the output of `java -version`, which
+/// looks like `openjdk version "17.0.2" 2022-01-18` or, before Java 9,
+/// `java version "1.8.0_292"`.
+fn parse_java_version(java_version_output: &str) -> Option<usize> {
+    let line = java_version_output
+        .lines()
+        .find(|line| line.contains(" version \""))?;
+    let version = line.split('"').nth(1)?;
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()? {
+        "1" => parts.next()?.parse().ok(),
+        major => major.parse().ok(),
+    }
+}
+
 pub(crate) fn absolute_path<P: AsRef<Path>>(path: P) -> String {
     match path.as_ref().canonicalize() {
         Ok(path) => path.to_string_lossy().to_string(),
@@ -96,8 +135,8 @@ pub(crate) fn copy_files_into<P: AsRef<Path>, Q: AsRef<Path>>(
     let files = list_files(ext, &file)?;
     let dir = PathBuf::from(dir.as_ref());
     if !dir.is_dir() || !dir.exists() {
-        return Err(Error::IO(
-            "Can't copy files: destination directory doesn't exist".to_string(),
+        return Err(Error::io(
+            "Can't copy files: destination directory doesn't exist",
         ));
     }
     for file in files {
@@ -121,7 +160,7 @@ fn list_files<P: AsRef<Path>>(ext: &str, file: P) -> Result<Vec<PathBuf>, Error>
     };
     let file = file.as_ref();
     if !file.exists() || !is_ext(file) {
-        return Err(Error::IO(format!("File doesn't exist: {}", file.display())));
+        return Err(Error::io(format!("File doesn't exist: {}", file.display())));
     }
     // The parent directory of the file
     let dir = file.parent().map_or(PathBuf::from("./"), |p| {
@@ -145,3 +184,91 @@ fn list_files<P: AsRef<Path>>(ext: &str, file: P) -> Result<Vec<PathBuf>, Error>
         .collect();
     Ok(files)
 }
+
+/// Limits the number of threads running a section at once.
+pub(crate) struct Semaphore {
+    permits: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Permit returned by [`Semaphore::acquire`], released when dropped.
+pub(crate) struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    pub(crate) fn new(permits: usize) -> Self {
+        Self {
+            permits: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Waits until a permit is available, and takes it.
+    pub(crate) fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut permits = self.permits.lock().expect("a semaphore can't be poisoned");
+        while *permits == 0 {
+            permits = self
+                .released
+                .wait(permits)
+                .expect("a semaphore can't be poisoned");
+        }
+        *permits -= 1;
+        SemaphorePermit { semaphore: self }
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        *self
+            .semaphore
+            .permits
+            .lock()
+            .expect("a semaphore can't be poisoned") += 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semaphore() {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let semaphore = Semaphore::new(3);
+        let (running, max_running) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(8)
+            .build()
+            .unwrap()
+            .install(|| {
+                (0..32).into_par_iter().for_each(|_| {
+                    // a stub of a model checker run
+                    let _permit = semaphore.acquire();
+                    let count = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(count, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            });
+        assert_eq!(max_running.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_parse_java_version() {
+        let parse = |first_line: &str| {
+            parse_java_version(&format!(
+                "{}\nOpenJDK Runtime Environment (build ...)\n",
+                first_line
+            ))
+        };
+        assert_eq!(parse(r#"openjdk version "17.0.2" 2022-01-18"#), Some(17));
+        assert_eq!(parse(r#"java version "1.8.0_292""#), Some(8));
+        assert_eq!(parse(r#"openjdk version "11" 2018-09-25"#), Some(11));
+        assert_eq!(parse(r#"openjdk version "21-ea" 2023-09-19"#), Some(21));
+        assert_eq!(parse("Picked up _JAVA_OPTIONS: -Xmx1g"), None);
+    }
+}
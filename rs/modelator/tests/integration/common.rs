@@ -34,15 +34,21 @@ pub struct ModelCheckerRuntimeConfig {
     model_checker: ModelChecker,
     workers: String,
     traces_per_test: String,
+    #[serde(default)]
+    cinit: Option<String>,
 }
 
 impl ModelCheckerRuntimeConfig {
     pub fn to_model_checker_runtime(&self) -> ModelCheckerRuntime {
         use std::str::FromStr;
-        ModelCheckerRuntime::default()
+        let runtime = ModelCheckerRuntime::default()
             .workers(ModelCheckerWorkers::from_str(&self.workers).unwrap())
             .model_checker(self.model_checker)
-            .traces_per_test(self.traces_per_test.parse::<usize>().unwrap())
+            .traces_per_test(self.traces_per_test.parse::<usize>().unwrap());
+        match &self.cinit {
+            Some(cinit) => runtime.cinit(cinit),
+            None => runtime,
+        }
     }
 }
 
@@ -43,6 +43,7 @@ fn test_batch_resources() -> Vec<TestBatchResourceBundle> {
 
 #[test]
 /// This is the single, master, integration test
+#[allow(clippy::const_is_empty, clippy::result_large_err)]
 fn integration_test() {
     // We follow the approach proposed in the following link for integration tests:
     // https://matklad.github.io/2021/02/27/delete-cargo-integration-tests.html
@@ -0,0 +1,7 @@
+------------------------- MODULE 2PossibleTracesNoViewTests ---------------------------
+
+EXTENDS 2PossibleTraces
+
+TestIsThree == x = 3
+
+===============================================================================
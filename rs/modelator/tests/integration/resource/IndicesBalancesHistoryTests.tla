@@ -1,4 +1,4 @@
------------------------------- MODULE IndicesHistoryTests ------------------------------
+------------------------------ MODULE IndicesBalancesHistoryTests ------------------------------
 \* The Model-based tests with history tracking for Substrate Frame Indices.
 \* They are meant to replace the hand-written tests from
 \* https://github.com/paritytech/substrate/blob/master/frame/indices/src/tests.rs
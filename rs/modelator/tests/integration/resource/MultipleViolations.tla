@@ -0,0 +1,20 @@
+------------------------- MODULE MultipleViolations ---------------------------
+(*
+TLC reports two violations of ExceedsOneTest when asked to continue:
+0 -> 2
+0 -> 1 -> 3
+*)
+
+EXTENDS Integers
+
+VARIABLES
+    \* @type: Int;
+    x
+
+Init == x = 0
+
+Next == IF x < 2 THEN x' \in {x + 1, x + 2} ELSE UNCHANGED x
+
+ExceedsOneTest == x > 1
+
+===============================================================================
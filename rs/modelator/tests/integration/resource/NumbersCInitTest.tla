@@ -0,0 +1,13 @@
+--------------------------- MODULE NumbersCInitTest ---------------------------
+EXTENDS Numbers
+-------------------------------------------------------------------------------
+
+\* Initializes the constants, which aren't assigned in the config file
+ConstInit ==
+    MaxNumber = 4
+
+AMaxBMaxTest ==
+    /\ a = MaxNumber
+    /\ b = MaxNumber
+
+===============================================================================
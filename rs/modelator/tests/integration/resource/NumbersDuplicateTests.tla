@@ -0,0 +1,13 @@
+------------------------ MODULE NumbersDuplicateTests -------------------------
+EXTENDS Numbers
+-------------------------------------------------------------------------------
+
+AMaxBMinTest ==
+    /\ a = MaxNumber
+    /\ b = 0
+
+BMinAMaxTest ==
+    /\ b = 0
+    /\ a = MaxNumber
+
+===============================================================================
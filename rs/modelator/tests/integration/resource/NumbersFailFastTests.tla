@@ -0,0 +1,11 @@
+------------------------ MODULE NumbersFailFastTests --------------------------
+EXTENDS Numbers
+-------------------------------------------------------------------------------
+
+\* @modelator:expect-holds
+ASmallHolds == a < 3
+
+\* @modelator:expect-holds
+InvHolds == Inv
+
+===============================================================================
@@ -0,0 +1,15 @@
+-------------------------- MODULE NumbersHoldsTests ---------------------------
+EXTENDS Numbers
+-------------------------------------------------------------------------------
+
+AMaxBMinTest ==
+    /\ a = MaxNumber
+    /\ b = 0
+
+\* @modelator:expect-holds
+InvHolds == Inv
+
+\* @modelator:expect-holds
+ASmallHolds == a < 3
+
+===============================================================================
@@ -0,0 +1,9 @@
+----------------------- MODULE NumbersInitialStateTest ------------------------
+EXTENDS Numbers
+-------------------------------------------------------------------------------
+
+InitialStateTest ==
+    /\ a = 0
+    /\ b = 0
+
+===============================================================================
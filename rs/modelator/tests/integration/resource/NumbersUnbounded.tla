@@ -0,0 +1,21 @@
+-------------------------- MODULE NumbersUnbounded ---------------------------
+EXTENDS Integers
+-------------------------------------------------------------------------------
+
+VARIABLE
+\* @type: Int;
+    a,
+\* @type: Int;
+    b
+
+Init ==
+    /\ a = 0
+    /\ b = 0
+
+Next ==
+    \/ /\ a' = a + 1
+       /\ UNCHANGED <<b>>
+    \/ /\ b' = b + 2
+       /\ UNCHANGED <<a>>
+
+===============================================================================
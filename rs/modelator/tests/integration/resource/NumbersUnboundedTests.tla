@@ -0,0 +1,8 @@
+------------------------ MODULE NumbersUnboundedTests -------------------------
+EXTENDS NumbersUnbounded
+-------------------------------------------------------------------------------
+
+\* @modelator:expect-holds
+BIsEvenHolds == b % 2 = 0
+
+===============================================================================
@@ -0,0 +1,7 @@
+------------------------------ MODULE ParseError ------------------------------
+VARIABLE x
+
+Init == x =
+
+Next == x' = x
+===============================================================================